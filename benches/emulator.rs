@@ -0,0 +1,88 @@
+#[macro_use]
+extern crate criterion;
+extern crate curses_game_wrapper as cgw;
+
+use cgw::Emulator;
+use criterion::Criterion;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+const LINES: usize = 24;
+const COLUMNS: usize = 80;
+
+// Draw whole screen char by char with cursor moves, like rogue redrawing map
+fn print_heavy_input() -> Vec<u8> {
+    let mut res = Vec::new();
+    for frame in 0..20 {
+        for y in 0..LINES {
+            res.extend_from_slice(format!("\x1b[{};1H", y + 1).as_bytes());
+            for x in 0..COLUMNS {
+                res.push(b"|.#@-+%"[(x + y + frame) % 7]);
+            }
+        }
+    }
+    res
+}
+
+// Print lines on the bottom of the screen, like message logs
+fn scroll_heavy_input() -> Vec<u8> {
+    let mut res = Vec::new();
+    res.extend_from_slice(format!("\x1b[{};1H", LINES).as_bytes());
+    for i in 0..2000 {
+        res.extend_from_slice(format!("You hit the kobold. ({})\r\n", i).as_bytes());
+    }
+    res
+}
+
+// Clear screen and lines repeatedly, like menus and status line updates
+fn clear_heavy_input() -> Vec<u8> {
+    let mut res = Vec::new();
+    for i in 0..500 {
+        res.extend_from_slice(b"\x1b[H\x1b[2J");
+        res.extend_from_slice(format!("\x1b[{};1HHp: {}\x1b[K", LINES, i).as_bytes());
+        res.extend_from_slice(b"\x1b[12;40H\x1b[1K\x1b[J");
+    }
+    res
+}
+
+fn captured_input() -> Vec<u8> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/transcripts/rogue_moves.cgwt");
+    let records = cgw::read_transcript(BufReader::new(File::open(path).unwrap())).unwrap();
+    let mut res = Vec::new();
+    for _ in 0..100 {
+        for rec in records.iter().filter(|r| r.kind == cgw::IoKind::Read) {
+            res.extend_from_slice(&rec.bytes);
+        }
+    }
+    res
+}
+
+fn bench_input(c: &mut Criterion, name: &str, input: Vec<u8>) {
+    c.bench_function(name, move |b| {
+        b.iter(|| {
+            let mut emu = Emulator::new(LINES, COLUMNS);
+            emu.feed(&input);
+            emu
+        })
+    });
+}
+
+fn print_heavy(c: &mut Criterion) {
+    bench_input(c, "print_heavy", print_heavy_input());
+}
+
+fn scroll_heavy(c: &mut Criterion) {
+    bench_input(c, "scroll_heavy", scroll_heavy_input());
+}
+
+fn clear_heavy(c: &mut Criterion) {
+    bench_input(c, "clear_heavy", clear_heavy_input());
+}
+
+fn captured(c: &mut Criterion) {
+    bench_input(c, "captured_rogue", captured_input());
+}
+
+criterion_group!(benches, print_heavy, scroll_heavy, clear_heavy, captured);
+criterion_main!(benches);
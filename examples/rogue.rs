@@ -33,7 +33,7 @@ fn main() {
         .debug_file("debug.txt")
         .max_loop(loopnum + 1)
         .draw_on(Duration::from_millis(100));
-    let game = gs.build();
+    let mut game = gs.build().unwrap();
     let mut ai = EmptyAI { loopnum: loopnum };
     game.play(&mut ai);
 }
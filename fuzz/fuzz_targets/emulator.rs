@@ -0,0 +1,15 @@
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate curses_game_wrapper as cgw;
+
+const LINES: usize = 24;
+const COLUMNS: usize = 80;
+
+fuzz_target!(|data: &[u8]| {
+    let mut emu = cgw::Emulator::new(LINES, COLUMNS);
+    emu.feed(data);
+    let screen = emu.screen();
+    assert_eq!(screen.len(), LINES);
+    assert!(screen.iter().all(|line| line.len() == COLUMNS));
+});
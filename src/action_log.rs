@@ -0,0 +1,133 @@
+use std::ascii;
+use std::io::{self, Write};
+use std::time::Duration;
+
+/// Format of action log.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ActionLogFormat {
+    /// Comma separated values with header line
+    Csv,
+    /// One JSON object per line
+    Jsonl,
+}
+
+// What happened in one turn
+pub struct TurnRecord<'a> {
+    pub turn: usize,
+    pub result: &'static str,
+    pub action: Option<&'a [u8]>,
+    pub bells: usize,
+    pub elapsed: Duration,
+    pub action_time: Duration,
+}
+
+pub struct ActionLogger<W: Write> {
+    inner: W,
+    format: ActionLogFormat,
+}
+
+impl<W: Write> ActionLogger<W> {
+    pub fn new(mut inner: W, format: ActionLogFormat) -> io::Result<ActionLogger<W>> {
+        if format == ActionLogFormat::Csv {
+            writeln!(inner, "turn,result,action,bells,elapsed_ms,action_ms")?;
+        }
+        Ok(ActionLogger {
+            inner: inner,
+            format: format,
+        })
+    }
+    pub fn record(&mut self, rec: &TurnRecord) -> io::Result<()> {
+        let action = rec.action.map(escape_bytes);
+        let elapsed = millis(rec.elapsed);
+        let action_time = millis(rec.action_time);
+        match self.format {
+            ActionLogFormat::Csv => writeln!(
+                self.inner,
+                "{},{},{},{},{:.3},{:.3}",
+                rec.turn,
+                rec.result,
+                action.map_or_else(String::new, |a| csv_quote(&a)),
+                rec.bells,
+                elapsed,
+                action_time
+            ),
+            ActionLogFormat::Jsonl => writeln!(
+                self.inner,
+                "{{\"turn\":{},\"result\":\"{}\",\"action\":{},\"bells\":{},\
+                 \"elapsed_ms\":{:.3},\"action_ms\":{:.3}}}",
+                rec.turn,
+                rec.result,
+                action.map_or_else(|| "null".to_owned(), |a| json_quote(&a)),
+                rec.bells,
+                elapsed,
+                action_time
+            ),
+        }
+    }
+}
+
+pub(crate) fn millis(d: Duration) -> f64 {
+    d.as_secs() as f64 * 1000.0 + f64::from(d.subsec_nanos()) / 1_000_000.0
+}
+
+// bytes -> printable ascii string (e.g. ESC -> \x1b)
+pub(crate) fn escape_bytes(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .flat_map(|b| ascii::escape_default(*b))
+        .map(|b| b as char)
+        .collect()
+}
+
+fn csv_quote(s: &str) -> String {
+    if s.contains(|c| c == ',' || c == '"') {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
+
+pub(crate) fn json_quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    fn record() -> TurnRecord<'static> {
+        TurnRecord {
+            turn: 3,
+            result: "Changed",
+            action: Some(b"\x1b,\""),
+            bells: 1,
+            elapsed: Duration::from_millis(1500),
+            action_time: Duration::new(0, 250_000),
+        }
+    }
+    #[test]
+    fn test_csv() {
+        let mut buf = Vec::new();
+        {
+            let mut logger = ActionLogger::new(&mut buf, ActionLogFormat::Csv).unwrap();
+            logger.record(&record()).unwrap();
+        }
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "turn,result,action,bells,elapsed_ms,action_ms\n\
+             3,Changed,\"\\x1b,\\\"\"\",1,1500.000,0.250\n"
+        );
+    }
+    #[test]
+    fn test_jsonl() {
+        let mut buf = Vec::new();
+        {
+            let mut logger = ActionLogger::new(&mut buf, ActionLogFormat::Jsonl).unwrap();
+            logger.record(&record()).unwrap();
+        }
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "{\"turn\":3,\"result\":\"Changed\",\"action\":\"\\\\x1b,\\\\\\\"\",\"bells\":1,\
+             \"elapsed_ms\":1500.000,\"action_ms\":0.250}\n"
+        );
+    }
+}
@@ -0,0 +1,359 @@
+//! Utilities to write AIs.
+
+use games::GameAdapter;
+use screen::Screen;
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+use std::hash::Hash;
+use std::ops::Range;
+use std::sync::Arc;
+
+/// Tells ```MapMemory``` which level the screen shows, and where the map region
+/// is in the level for games scrolling the map.
+///
+/// Closures ```Fn(&Screen) -> Option<K>``` implement it, for games which don't
+/// scroll the map.
+pub trait MapHook<K>: Send + Sync {
+    /// Returns the level shown in ```screen```, or ```None``` if the map isn't
+    /// shown(e.g. in menus), which skips the screen.
+    fn level(&self, screen: &Screen) -> Option<K>;
+    /// Position of the top-left corner of the map region in the level as
+    /// ```(y, x)```(Default: ```(0, 0)```).
+    fn offset(&self, _screen: &Screen) -> (isize, isize) {
+        (0, 0)
+    }
+}
+
+impl<K, F: Fn(&Screen) -> Option<K> + Send + Sync> MapHook<K> for F {
+    fn level(&self, screen: &Screen) -> Option<K> {
+        self(screen)
+    }
+}
+
+/// Explored map of a level kept by ```MapMemory```.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LevelMap {
+    cells: HashMap<(isize, isize), u8>,
+}
+
+impl LevelMap {
+    /// Returns the byte seen last time at ```(y, x)``` of the level, or ```None```
+    /// if it's never seen.
+    pub fn get(&self, y: isize, x: isize) -> Option<u8> {
+        self.cells.get(&(y, x)).cloned()
+    }
+    /// Returns the number of cells seen.
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+    /// Returns the ranges of y and x covering all cells seen.
+    pub fn bounds(&self) -> Option<(Range<isize>, Range<isize>)> {
+        let mut keys = self.cells.keys();
+        let &(y, x) = keys.next()?;
+        let init = (y..y + 1, x..x + 1);
+        Some(keys.fold(init, |(ys, xs), &(y, x)| {
+            (ys.start.min(y)..ys.end.max(y + 1), xs.start.min(x)..xs.end.max(x + 1))
+        }))
+    }
+    /// Draw the cells in ```lines``` x ```columns``` from ```(0, 0)``` of the level,
+    /// with spaces for cells never seen.
+    pub fn to_screen(&self, lines: usize, columns: usize) -> Screen {
+        let buf = (0..lines as isize)
+            .map(|y| {
+                (0..columns as isize)
+                    .map(|x| self.get(y, x).unwrap_or(b' '))
+                    .collect()
+            })
+            .collect();
+        Screen::new(buf)
+    }
+}
+
+/// Explored map merged from the map region of successive screens, which keeps
+/// what's out of sight.
+///
+/// Cells with ```blank``` in the screen don't erase what was seen there.
+/// Levels are told apart by ```MapHook```, and the map of each level is kept
+/// when the AI goes to another level and comes back.
+/// # Example
+/// ```
+/// extern crate curses_game_wrapper as cgw;
+/// use cgw::Screen;
+/// use cgw::ai_utils::MapMemory;
+/// fn main() {
+///     let screen = |lines: &[&str]| {
+///         Screen::new(lines.iter().map(|l| l.as_bytes().to_vec()).collect())
+///     };
+///     // the map is in line 1 and 2, and the level is in line 3
+///     let mut memory = MapMemory::new(1..3, |s: &Screen| s.trimmed_line(3).parse::<u32>().ok());
+///     // a dark room is shown only around the player
+///     memory.update(&screen(&["", "|..", "", "1"]));
+///     memory.update(&screen(&["", "|  .@", "", "1"]));
+///     assert_eq!(memory.level(), Some(&1));
+///     let map = memory.current().unwrap();
+///     assert_eq!(map.to_screen(1, 5).trimmed_line(0), "|...@");
+/// }
+/// ```
+#[derive(Clone)]
+pub struct MapMemory<K = u32> {
+    map: Range<usize>,
+    blank: u8,
+    hook: Arc<dyn MapHook<K>>,
+    levels: HashMap<K, LevelMap>,
+    current: Option<K>,
+}
+
+impl<K: Hash + Eq + Clone> MapMemory<K> {
+    /// Remember the lines ```map``` of screens, told apart by ```hook```.
+    pub fn new<H: MapHook<K> + 'static>(map: Range<usize>, hook: H) -> MapMemory<K> {
+        MapMemory {
+            map: map,
+            blank: b' ',
+            hook: Arc::new(hook),
+            levels: HashMap::new(),
+            current: None,
+        }
+    }
+    /// Byte of cells not seen in the screen(Default: space).
+    pub fn blank(mut self, blank: u8) -> MapMemory<K> {
+        self.blank = blank;
+        self
+    }
+    /// Merge the map region of ```screen``` into the map of its level.
+    ///
+    /// Returns false if the screen is skipped by ```MapHook::level```.
+    pub fn update(&mut self, screen: &Screen) -> bool {
+        let level = match self.hook.level(screen) {
+            Some(level) => level,
+            None => return false,
+        };
+        let (dy, dx) = self.hook.offset(screen);
+        let map = self.levels.entry(level.clone()).or_insert_with(LevelMap::default);
+        let (start, end) = (self.map.start, self.map.end);
+        for (y, x, b) in screen.iter_cells() {
+            if y >= start && y < end && b != self.blank && b != 0 {
+                map.cells.insert(((y - start) as isize + dy, x as isize + dx), b);
+            }
+        }
+        self.current = Some(level);
+        true
+    }
+    /// Returns the level of the last screen merged.
+    pub fn level(&self) -> Option<&K> {
+        self.current.as_ref()
+    }
+    /// Returns the map of the level of the last screen merged.
+    pub fn current(&self) -> Option<&LevelMap> {
+        self.current.as_ref().and_then(|l| self.levels.get(l))
+    }
+    /// Returns the map of ```level```, if it's seen.
+    pub fn get_level(&self, level: &K) -> Option<&LevelMap> {
+        self.levels.get(level)
+    }
+    /// Returns levels seen so far.
+    pub fn levels<'a>(&'a self) -> impl Iterator<Item = &'a K> + 'a {
+        self.levels.keys()
+    }
+    /// Forget all levels, e.g. for a new game.
+    pub fn clear(&mut self) {
+        self.levels.clear();
+        self.current = None;
+    }
+}
+
+impl MapMemory<u32> {
+    /// Remember the map region of ```GameAdapter::layout``` for screens with
+    /// ```lines``` lines, telling levels apart by ```Status::level```.
+    pub fn from_adapter<A: GameAdapter + 'static>(adapter: A, lines: usize) -> MapMemory<u32> {
+        let map = adapter.layout(lines).map;
+        MapMemory::new(map, move |screen: &Screen| {
+            adapter.parse_status(screen).and_then(|s| s.level)
+        })
+    }
+}
+
+impl<K: Debug> Debug for MapMemory<K> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let levels: Vec<_> = self.levels.keys().collect();
+        write!(f, "MapMemory({:?}, {:?}, current: {:?})", self.map, levels, self.current)
+    }
+}
+
+/// Tags cells of the screen with classes you define(like walls, floors,
+/// monsters and items), making a grid of them alongside the screen.
+///
+/// Cells are told apart by the byte and whether it's shown in reverse video,
+/// as the emulator doesn't keep colors. The first rule matching the cell wins.
+/// # Example
+/// ```
+/// extern crate curses_game_wrapper as cgw;
+/// use cgw::Screen;
+/// use cgw::ai_utils::TileTagger;
+/// fn main() {
+///     #[derive(Clone, Debug, PartialEq)]
+///     enum Tile {
+///         Wall,
+///         Floor,
+///         Monster,
+///         Unknown,
+///     }
+///     let tagger = TileTagger::new()
+///         .tag(b"|-", Tile::Wall)
+///         .tag(b".", Tile::Floor)
+///         .tag(b"abcdefghijklmnopqrstuvwxyz", Tile::Monster)
+///         .default_class(Tile::Unknown);
+///     let grid = tagger.grid(&Screen::new(vec![b"|.k?".to_vec()]));
+///     assert_eq!(grid[0], vec![Tile::Wall, Tile::Floor, Tile::Monster, Tile::Unknown]);
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct TileTagger<C> {
+    // indexed by byte * 2 + reverse
+    table: Vec<Option<C>>,
+    default: Option<C>,
+}
+
+impl<C: Clone> TileTagger<C> {
+    pub fn new() -> TileTagger<C> {
+        TileTagger {
+            table: vec![None; 512],
+            default: None,
+        }
+    }
+    /// Tag cells showing any of ```bytes``` as ```class```.
+    pub fn tag(self, bytes: &[u8], class: C) -> TileTagger<C> {
+        self.tag_reverse(bytes, false, class.clone())
+            .tag_reverse(bytes, true, class)
+    }
+    /// Tag cells showing any of ```bytes``` as ```class```, only if they're shown
+    /// in reverse video(```reverse = true```) or not.
+    /// E.g. NetHack can show pets and detected monsters in reverse video.
+    pub fn tag_reverse(mut self, bytes: &[u8], reverse: bool, class: C) -> TileTagger<C> {
+        for &b in bytes {
+            let entry = &mut self.table[b as usize * 2 + reverse as usize];
+            if entry.is_none() {
+                *entry = Some(class.clone());
+            }
+        }
+        self
+    }
+    /// Class of cells matching no rule.
+    pub fn default_class(mut self, class: C) -> TileTagger<C> {
+        self.default = Some(class);
+        self
+    }
+    /// Returns the class of the cell, or ```None``` if it matches no rule and
+    /// ```default_class``` isn't set.
+    pub fn classify(&self, byte: u8, reverse: bool) -> Option<C> {
+        self.table[byte as usize * 2 + reverse as usize]
+            .as_ref()
+            .or_else(|| self.default.as_ref())
+            .cloned()
+    }
+    /// Returns the class of each cell of ```screen```, with ```default_class```
+    /// for cells matching no rule.
+    ///
+    /// # Panics
+    /// Panics if ```default_class``` isn't set and some cell matches no rule.
+    /// Use ```grid_opt``` to tag such cells as ```None``` instead.
+    pub fn grid(&self, screen: &Screen) -> Vec<Vec<C>> {
+        self.grid_opt(screen)
+            .into_iter()
+            .map(|line| {
+                line.into_iter()
+                    .map(|c| c.expect("TileTagger: cell matching no rule without default_class"))
+                    .collect()
+            })
+            .collect()
+    }
+    /// Returns the class of each cell of ```screen```, or ```None``` for cells
+    /// matching no rule without ```default_class```.
+    pub fn grid_opt(&self, screen: &Screen) -> Vec<Vec<Option<C>>> {
+        let mut res = vec![Vec::new(); screen.lines()];
+        for (y, x, b) in screen.iter_cells() {
+            res[y].push(self.classify(b, screen.is_reverse(y, x)));
+        }
+        res
+    }
+}
+
+impl<C: Clone> Default for TileTagger<C> {
+    fn default() -> TileTagger<C> {
+        TileTagger::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use emulator::Emulator;
+    use games::rogue::Rogue;
+    fn screen(lines: &[&str]) -> Screen {
+        Screen::new(lines.iter().map(|l| l.as_bytes().to_vec()).collect())
+    }
+    #[test]
+    fn test_levels() {
+        let mut memory = MapMemory::from_adapter(Rogue, 4);
+        let status = |level: u32| {
+            format!("Level: {}  Gold: 0      Hp: 12(12)  Str: 16(16)  Arm: 4  Exp: 1/0", level)
+        };
+        assert!(memory.update(&screen(&["", "-+-", "|@.", &status(1)])));
+        assert!(memory.update(&screen(&["msg", "   ", " @", &status(1)])));
+        assert!(!memory.update(&screen(&["a) some food", "", "", "--Press space--"])));
+        assert_eq!(memory.current().unwrap().to_screen(2, 3).trimmed_line(1), "|@.");
+        assert!(memory.update(&screen(&["", "  %", "  @", &status(2)])));
+        assert_eq!(memory.level(), Some(&2));
+        assert_eq!(memory.current().unwrap().len(), 2);
+        let first = memory.get_level(&1).unwrap();
+        assert_eq!(first.get(0, 1), Some(b'+'));
+        assert_eq!(first.get(0, 3), None);
+        assert_eq!(first.bounds(), Some((0..2, 0..3)));
+        let mut levels: Vec<_> = memory.levels().cloned().collect();
+        levels.sort();
+        assert_eq!(levels, vec![1, 2]);
+        memory.clear();
+        assert!(memory.current().is_none());
+    }
+    #[test]
+    fn test_tile_tagger() {
+        let mut emu = Emulator::new(2, 4);
+        // a pet in reverse video
+        emu.feed(b"-\x1b[7md\x1b[0md?");
+        let tagger = TileTagger::new()
+            .tag_reverse(b"d", true, "pet")
+            .tag(b"d", "monster")
+            .tag(b"d-", "wall");
+        assert_eq!(tagger.classify(b'-', true), Some("wall"));
+        let grid = tagger.grid_opt(&emu.screen());
+        assert_eq!(grid[0], vec![Some("wall"), Some("pet"), Some("monster"), None]);
+        assert_eq!(grid[1], vec![None; 4]);
+        let grid = tagger.default_class("other").grid(&emu.screen());
+        assert_eq!(grid[0][3], "other");
+    }
+    #[test]
+    fn test_scroll() {
+        struct Scroll;
+        impl MapHook<()> for Scroll {
+            fn level(&self, _screen: &Screen) -> Option<()> {
+                Some(())
+            }
+            // the first line shows the column of the left edge
+            fn offset(&self, screen: &Screen) -> (isize, isize) {
+                (0, screen.trimmed_line(0).parse().unwrap())
+            }
+        }
+        let mut memory = MapMemory::new(1..2, Scroll).blank(b'.');
+        memory.update(&screen(&["0", "ab.."]));
+        memory.update(&screen(&["-2", "..cd"]));
+        let map = memory.current().unwrap();
+        assert_eq!(map.bounds(), Some((0..1, 0..2)));
+        assert_eq!((map.get(0, 0), map.get(0, 1)), (Some(b'c'), Some(b'd')));
+        memory.update(&screen(&["3", "e..."]));
+        let map = memory.current().unwrap();
+        assert_eq!(map.bounds(), Some((0..1, 0..4)));
+        assert_eq!(map.to_screen(1, 4).trimmed_line(0), "cd e");
+    }
+}
@@ -0,0 +1,328 @@
+//! Play back transcripts(```GameSetting::transcript_file```) and ttyrec files
+//! in the terminal, or dump frames as text.
+//!
+//! While playing, ```Space``` pauses, ```+```/```-``` change the speed,
+//! ```.```/```,``` pause and step one frame forward/backward,
+//! ```Left```/```Right``` seek 10 frames, ```Home``` goes back to the start
+//! and ```q``` quits.
+extern crate curses_game_wrapper as cgw;
+extern crate termion;
+
+use cgw::{read_transcript, read_ttyrec, Replay, Screen, TranscriptRecord};
+use termion::event::Key;
+use termion::input::TermRead;
+use termion::raw::IntoRawMode;
+use std::env;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Write};
+use std::process;
+use std::thread;
+use std::time::Duration;
+
+const USAGE: &str = "\
+usage: cgw-replay [OPTIONS] FILE
+
+options:
+    -s, --speed X        playback speed(default 1.0)
+    -f, --from N         start playback from frame N
+    -m, --max-delay MS   cap the delay between frames
+    -i, --skip-idle SECS show the next frame at once after idle gaps longer than SECS
+    -d, --dump N         print frame N as text and exit
+    -l, --lines N        lines of the screen(default 24)
+    -c, --columns N      columns of the screen(default 80)";
+
+// frames skipped by Left/Right
+const SEEK_FRAMES: usize = 10;
+// interval to check keys while waiting
+const POLL_MS: u64 = 20;
+
+struct Options {
+    speed: f64,
+    from: usize,
+    max_delay: Option<Duration>,
+    skip_idle: Option<Duration>,
+    dump: Option<usize>,
+    lines: usize,
+    columns: usize,
+    file: String,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut opts = Options {
+        speed: 1.0,
+        from: 0,
+        max_delay: None,
+        skip_idle: None,
+        dump: None,
+        lines: 24,
+        columns: 80,
+        file: String::new(),
+    };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if !arg.starts_with('-') {
+            opts.file = arg;
+            continue;
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{} needs a value", arg))?;
+        let invalid = |e: &dyn ToString| format!("invalid value for {}: {}", arg, e.to_string());
+        let num = || value.parse::<usize>().map_err(|e| invalid(&e));
+        match &arg[..] {
+            "-s" | "--speed" => {
+                opts.speed = value.parse::<f64>().map_err(|e| invalid(&e))?;
+                if !(opts.speed > 0.0) {
+                    return Err("speed must be positive".to_owned());
+                }
+            }
+            "-f" | "--from" => opts.from = num()?,
+            "-m" | "--max-delay" => opts.max_delay = Some(Duration::from_millis(num()? as u64)),
+            "-i" | "--skip-idle" => {
+                let secs = value.parse::<f64>().map_err(|e| invalid(&e))?;
+                if !(secs >= 0.0) {
+                    return Err("skip-idle must not be negative".to_owned());
+                }
+                opts.skip_idle = Some(Duration::from_nanos((secs * 1e9) as u64));
+            }
+            "-d" | "--dump" => opts.dump = Some(num()?),
+            "-l" | "--lines" => opts.lines = num()?,
+            "-c" | "--columns" => opts.columns = num()?,
+            _ => return Err(format!("unknown option {}", arg)),
+        }
+    }
+    if opts.file.is_empty() {
+        return Err("no file given".to_owned());
+    }
+    Ok(opts)
+}
+
+// read transcript or ttyrec
+fn load(path: &str) -> io::Result<Vec<TranscriptRecord>> {
+    let mut buf = Vec::new();
+    BufReader::new(File::open(path)?).read_to_end(&mut buf)?;
+    read_transcript(&buf[..]).or_else(|_| read_ttyrec(&buf[..]))
+}
+
+fn dump(replay: &mut Replay, n: usize) -> Result<(), String> {
+    if n >= replay.len() {
+        return Err(format!("frame {} not found, the file has {} frames", n, replay.len()));
+    }
+    for line in replay.seek(n + 1).trimmed_lines() {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+struct Player<W: Write> {
+    replay: Replay,
+    out: W,
+    paused: bool,
+    max_delay: Option<Duration>,
+}
+
+impl<W: Write> Player<W> {
+    fn draw(&mut self, screen: &Screen) -> io::Result<()> {
+        self.out.write_all(b"\x1b[H")?;
+        for (y, line) in screen.iter().enumerate() {
+            if y > 0 {
+                self.out.write_all(b"\r\n")?;
+            }
+            self.out.write_all(line)?;
+        }
+        let (y, x) = screen.cursor();
+        write!(self.out, "\x1b[{};{}H", y + 1, x + 1)?;
+        self.out.flush()
+    }
+    fn seek(&mut self, pos: usize) -> io::Result<()> {
+        let screen = self.replay.seek(pos);
+        self.draw(&screen)
+    }
+    fn delay(&self) -> Duration {
+        let d = self.replay.delay();
+        self.max_delay.map_or(d, |max| d.min(max))
+    }
+    // returns false when the user quits
+    fn handle_key(&mut self, key: Key) -> io::Result<bool> {
+        match key {
+            Key::Char('q') | Key::Ctrl('c') => return Ok(false),
+            Key::Char(' ') => self.paused = !self.paused,
+            Key::Char('+') => {
+                let speed = self.replay.speed() * 2.0;
+                self.replay.set_speed(speed)
+            }
+            Key::Char('-') => {
+                let speed = self.replay.speed() / 2.0;
+                self.replay.set_speed(speed)
+            }
+            Key::Char('.') => {
+                self.paused = true;
+                if let Some(screen) = self.replay.step() {
+                    self.draw(&screen)?;
+                }
+            }
+            Key::Char(',') => {
+                self.paused = true;
+                if let Some(screen) = self.replay.step_back() {
+                    self.draw(&screen)?;
+                }
+            }
+            Key::Right => {
+                let pos = self.replay.pos() + SEEK_FRAMES;
+                self.seek(pos)?
+            }
+            Key::Left => {
+                let pos = self.replay.pos().saturating_sub(SEEK_FRAMES);
+                self.seek(pos)?
+            }
+            Key::Home => self.seek(0)?,
+            _ => {}
+        }
+        Ok(true)
+    }
+    fn play<I: Iterator<Item = io::Result<Key>>>(&mut self, mut keys: I) -> io::Result<()> {
+        let mut waited = Duration::from_millis(0);
+        loop {
+            while let Some(key) = keys.next() {
+                if !self.handle_key(key?)? {
+                    return Ok(());
+                }
+            }
+            if self.paused || self.replay.pos() >= self.replay.len() {
+                // stay at the last frame so that the user can seek back
+                thread::sleep(Duration::from_millis(POLL_MS));
+                continue;
+            }
+            let delay = self.delay();
+            if waited < delay {
+                let poll = Duration::from_millis(POLL_MS).min(delay - waited);
+                thread::sleep(poll);
+                waited += poll;
+                continue;
+            }
+            waited = Duration::from_millis(0);
+            if let Some(screen) = self.replay.step() {
+                self.draw(&screen)?;
+            }
+        }
+    }
+}
+
+fn main() {
+    let opts = parse_args().unwrap_or_else(|e| {
+        eprintln!("cgw-replay: {}\n{}", e, USAGE);
+        process::exit(1);
+    });
+    let records = load(&opts.file).unwrap_or_else(|e| {
+        eprintln!("cgw-replay: can't read {}: {}", opts.file, e);
+        process::exit(1);
+    });
+    let mut replay = Replay::new(records, opts.lines, opts.columns);
+    replay.set_speed(opts.speed);
+    replay.set_skip_idle(opts.skip_idle);
+    if let Some(n) = opts.dump {
+        if let Err(e) = dump(&mut replay, n) {
+            eprintln!("cgw-replay: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
+    let res = io::stdout().into_raw_mode().and_then(|out| {
+        let mut player = Player {
+            replay: replay,
+            out: out,
+            paused: false,
+            max_delay: opts.max_delay,
+        };
+        player.out.write_all(b"\x1bc")?;
+        player.seek(opts.from)?;
+        player.play(termion::async_stdin().keys())?;
+        player.out.write_all(b"\x1bc")
+    });
+    if let Err(e) = res {
+        eprintln!("cgw-replay: {}", e);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cgw::IoKind;
+    fn player() -> Player<Vec<u8>> {
+        let records = (0..30)
+            .map(|i| TranscriptRecord {
+                time: Duration::from_millis(i * 100),
+                kind: IoKind::Read,
+                bytes: format!("{}\r\n", i).into_bytes(),
+            })
+            .collect();
+        Player {
+            replay: Replay::new(records, 2, 3),
+            out: Vec::new(),
+            paused: false,
+            max_delay: None,
+        }
+    }
+    #[test]
+    fn test_seek() {
+        let mut p = player();
+        p.handle_key(Key::Right).unwrap();
+        assert_eq!(p.replay.pos(), SEEK_FRAMES);
+        assert_eq!(p.out, b"\x1b[H9  \r\n   \x1b[2;1H".to_vec());
+        p.handle_key(Key::Left).unwrap();
+        p.handle_key(Key::Left).unwrap();
+        assert_eq!(p.replay.pos(), 0);
+        p.seek(100).unwrap();
+        assert_eq!(p.replay.pos(), p.replay.len());
+        assert!(!p.handle_key(Key::Char('q')).unwrap());
+    }
+    #[test]
+    fn test_delay() {
+        let mut p = player();
+        assert_eq!(p.delay(), Duration::from_millis(0));
+        p.seek(5).unwrap();
+        assert_eq!(p.delay(), Duration::from_millis(100));
+        p.handle_key(Key::Char('+')).unwrap();
+        assert_eq!(p.delay(), Duration::from_millis(50));
+        p.max_delay = Some(Duration::from_millis(10));
+        assert_eq!(p.delay(), Duration::from_millis(10));
+        p.replay.set_skip_idle(Some(Duration::from_millis(99)));
+        assert_eq!(p.delay(), Duration::from_millis(0));
+    }
+    #[test]
+    fn test_step() {
+        let mut p = player();
+        p.seek(2).unwrap();
+        p.out.clear();
+        p.handle_key(Key::Char('.')).unwrap();
+        assert!(p.paused);
+        assert_eq!(p.replay.pos(), 3);
+        assert_eq!(p.out, b"\x1b[H2  \r\n   \x1b[2;1H".to_vec());
+        p.out.clear();
+        p.handle_key(Key::Char(',')).unwrap();
+        assert_eq!(p.replay.pos(), 2);
+        assert_eq!(p.out, b"\x1b[H1  \r\n   \x1b[2;1H".to_vec());
+    }
+    // returns no key ```n``` times, then ```q```
+    struct QuitAfter(usize);
+    impl Iterator for QuitAfter {
+        type Item = io::Result<Key>;
+        fn next(&mut self) -> Option<io::Result<Key>> {
+            if self.0 == 0 {
+                return Some(Ok(Key::Char('q')));
+            }
+            self.0 -= 1;
+            None
+        }
+    }
+    #[test]
+    fn test_play() {
+        let mut p = player();
+        p.replay.set_speed(100.0);
+        p.replay.seek(27);
+        p.play(QuitAfter(10)).unwrap();
+        assert_eq!(p.replay.pos(), 30);
+        assert!(p.out.ends_with(b"\x1b[H29 \r\n   \x1b[2;1H"));
+    }
+}
@@ -0,0 +1,467 @@
+//! Run games headlessly by TOML config, for shell scripts and job schedulers.
+//!
+//! ```toml
+//! episodes = 3
+//!
+//! [game]
+//! command = "rogue"
+//! env = { ROGUEUSER = "ai{episode}" }
+//! lines = 24
+//! columns = 80
+//! max_loop = 1000
+//! # end the episode when the regex matches the screen
+//! stop_on = { stuck = "Really attack" }
+//! # built-in game over detector, "rogue" or "nethack"
+//! game_over = "rogue"
+//! # "normal", "fast_forward", "adaptive"(fast forward with adaptive waits)
+//! # or "real_time"(with fps and key_delay_ms)
+//! pacing = "fast_forward"
+//! # translate keys sent by the reactor, in the syntax of parse_keys
+//! key_map = { y = "z", z = "y" }
+//! # replay the last game in the key log before the first episode, to resume it
+//! # after a crash(see GameEnv::restore)
+//! restore = "crashed.keys"
+//! verify_restore = true
+//!
+//! [record]
+//! transcript = "rogue.transcript"
+//! # keys written to the game, for replaying games by GameHandle::replay_keys
+//! key_log = "rogue.keys"
+//! # screens, actions and rewards of each turn, read by read_dataset
+//! dataset = "rogue.dataset"
+//! action_log = "rogue.csv"
+//! # summary of each episode, "jsonl" or "sqlite"(with feature sqlite)
+//! results = "results.jsonl"
+//! results_format = "jsonl"
+//!
+//! [reactor]
+//! keys = ["<Esc>", "hjkl", "Q", "y"]
+//! ```
+//! Instead of ```keys```, ```library = "libmy_ai.so"``` loads a Reactor from a
+//! dynamic library, which exports ```cgw_reactor``` like
+//! ```ignore
+//! #[no_mangle]
+//! pub fn cgw_reactor() -> Box<dyn Reactor> {
+//!     Box::new(MyAI::new())
+//! }
+//! ```
+//! The library must be built by the same compiler and version of this crate.
+extern crate curses_game_wrapper as cgw;
+extern crate libloading;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate toml;
+
+use cgw::{games, parse_keys, read_key_log, ActionLogFormat, ActionResult, GameOver, GameSetting,
+          IdleStreak, KeyMap, MetaEncoding, Pacing, Reactor, ResultFormat, TermMode, TerminalEvent,
+          TurnStats, UnhandledSeq};
+use libloading::Library;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::process;
+use std::time::Duration;
+
+// symbol of the Reactor constructor in dynamic libraries
+const REACTOR_SYMBOL: &[u8] = b"cgw_reactor";
+// real_time pacing without fps and key_delay_ms
+const DEFAULT_FPS: u32 = 10;
+const DEFAULT_KEY_DELAY_MS: u64 = 100;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    #[serde(default = "default_episodes")]
+    episodes: usize,
+    game: GameConfig,
+    #[serde(default)]
+    record: RecordConfig,
+    #[serde(default)]
+    reactor: ReactorConfig,
+}
+
+fn default_episodes() -> usize {
+    1
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GameConfig {
+    command: String,
+    #[serde(default)]
+    args: Vec<String>,
+    #[serde(default)]
+    env: BTreeMap<String, String>,
+    lines: Option<usize>,
+    columns: Option<usize>,
+    timeout_ms: Option<u64>,
+    settle_ms: Option<u64>,
+    max_loop: Option<usize>,
+    seed: Option<u64>,
+    // reason -> regex
+    #[serde(default)]
+    stop_on: BTreeMap<String, String>,
+    game_over: Option<String>,
+    pacing: Option<String>,
+    // for real_time pacing
+    fps: Option<u32>,
+    key_delay_ms: Option<u64>,
+    // key sent by the reactor -> key sent to the game
+    #[serde(default)]
+    key_map: BTreeMap<String, String>,
+    // key log replayed before the first episode
+    restore: Option<String>,
+    #[serde(default)]
+    verify_restore: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RecordConfig {
+    transcript: Option<String>,
+    key_log: Option<String>,
+    dataset: Option<String>,
+    action_log: Option<String>,
+    // "csv" or "jsonl"
+    action_log_format: Option<String>,
+    results: Option<String>,
+    // "jsonl" or "sqlite"
+    results_format: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ReactorConfig {
+    // sent one per turn, in the syntax of parse_keys
+    #[serde(default)]
+    keys: Vec<String>,
+    // start the script again after the last keys
+    #[serde(default)]
+    repeat: bool,
+    library: Option<String>,
+}
+
+impl Config {
+    fn parse(s: &str) -> Result<Config, String> {
+        let config: Config = toml::from_str(s).map_err(|e| e.to_string())?;
+        if config.reactor.library.is_some() && !config.reactor.keys.is_empty() {
+            return Err("reactor can't have both keys and library".to_owned());
+        }
+        config.action_log_format()?;
+        config.game_over()?;
+        config.key_map()?;
+        config.pacing()?;
+        config.results_format()?;
+        config.script()?;
+        Ok(config)
+    }
+    fn action_log_format(&self) -> Result<ActionLogFormat, String> {
+        match self.record.action_log_format.as_ref().map(|s| &s[..]) {
+            None | Some("csv") => Ok(ActionLogFormat::Csv),
+            Some("jsonl") => Ok(ActionLogFormat::Jsonl),
+            Some(f) => Err(format!("unknown action_log_format {}", f)),
+        }
+    }
+    fn results_format(&self) -> Result<ResultFormat, String> {
+        match self.record.results_format.as_ref().map(|s| &s[..]) {
+            None | Some("jsonl") => Ok(ResultFormat::Jsonl),
+            #[cfg(feature = "sqlite")]
+            Some("sqlite") => Ok(ResultFormat::Sqlite),
+            Some(f) => Err(format!("unknown results_format {}", f)),
+        }
+    }
+    fn game_over(&self) -> Result<Option<GameOver>, String> {
+        match self.game.game_over.as_ref().map(|s| &s[..]) {
+            None => Ok(None),
+            Some("rogue") => Ok(Some(games::rogue::game_over())),
+            Some("nethack") => Ok(Some(games::nethack::game_over())),
+            Some(g) => Err(format!("unknown game_over {}", g)),
+        }
+    }
+    fn key_map(&self) -> Result<KeyMap, String> {
+        let parse = |k: &str| {
+            parse_keys(k, MetaEncoding::default()).map_err(|e| format!("can't parse key {}", e))
+        };
+        let mut map = KeyMap::new();
+        for (from, to) in &self.game.key_map {
+            map = map.map(&parse(from)?, &parse(to)?);
+        }
+        Ok(map)
+    }
+    fn pacing(&self) -> Result<Pacing, String> {
+        match self.game.pacing.as_ref().map(|s| &s[..]) {
+            None | Some("normal") => Ok(Pacing::Normal),
+            Some("fast_forward") => Ok(Pacing::FastForward { adaptive: false }),
+            Some("adaptive") => Ok(Pacing::FastForward { adaptive: true }),
+            Some("real_time") => {
+                let delay = self.game.key_delay_ms.unwrap_or(DEFAULT_KEY_DELAY_MS);
+                Ok(Pacing::RealTime {
+                    fps: self.game.fps.unwrap_or(DEFAULT_FPS),
+                    key_delay: Duration::from_millis(delay),
+                })
+            }
+            Some(p) => Err(format!("unknown pacing {}", p)),
+        }
+    }
+    fn script(&self) -> Result<Script, String> {
+        let keys = self.reactor
+            .keys
+            .iter()
+            .map(|k| {
+                parse_keys(k, MetaEncoding::default()).map_err(|e| format!("can't parse key {}", e))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Script {
+            keys: keys,
+            repeat: self.reactor.repeat,
+            next: 0,
+        })
+    }
+    fn setting(&self) -> Result<GameSetting, String> {
+        let game = &self.game;
+        let mut gs = GameSetting::new(&game.command).args(game.args.iter().map(|s| &s[..]));
+        for (name, value) in &game.env {
+            gs = gs.env(name, value);
+        }
+        if let Some(lines) = game.lines {
+            gs = gs.lines(lines);
+        }
+        if let Some(columns) = game.columns {
+            gs = gs.columns(columns);
+        }
+        if let Some(ms) = game.timeout_ms {
+            gs = gs.timeout(Duration::from_millis(ms));
+        }
+        if let Some(ms) = game.settle_ms {
+            gs = gs.settle(Duration::from_millis(ms));
+        }
+        gs = gs.pacing(self.pacing()?);
+        gs = gs.key_map(self.key_map()?);
+        if let Some(max_loop) = game.max_loop {
+            gs = gs.max_loop(max_loop);
+        }
+        if let Some(seed) = game.seed {
+            gs = gs.seed(seed);
+        }
+        for (reason, pattern) in &game.stop_on {
+            gs = gs.stop_on(reason, pattern);
+        }
+        if let Some(detector) = self.game_over()? {
+            gs = gs.game_over(detector);
+        }
+        if let Some(ref path) = self.record.transcript {
+            gs = gs.transcript_file(path);
+        }
+        if let Some(ref path) = self.record.key_log {
+            gs = gs.key_log_file(path);
+        }
+        if let Some(ref path) = self.record.dataset {
+            gs = gs.dataset_file(path);
+        }
+        if let Some(ref path) = self.record.action_log {
+            gs = gs.action_log(path, self.action_log_format()?);
+        }
+        if let Some(ref path) = self.record.results {
+            gs = gs.results_file(path, self.results_format()?);
+        }
+        Ok(gs)
+    }
+}
+
+// Sends scripted keys, one per turn.
+struct Script {
+    keys: Vec<Vec<u8>>,
+    repeat: bool,
+    next: usize,
+}
+
+impl Reactor for Script {
+    fn action(&mut self, result: ActionResult, _turn: usize) -> Option<Vec<u8>> {
+        if let ActionResult::GameEnded = result {
+            self.next = 0;
+            return None;
+        }
+        if self.repeat && !self.keys.is_empty() {
+            self.next %= self.keys.len();
+        }
+        let res = self.keys.get(self.next).cloned();
+        self.next += 1;
+        res
+    }
+}
+
+// Reactor loaded from a dynamic library.
+struct Dylib {
+    // dropped before the library
+    ai: Box<dyn Reactor>,
+    _lib: Library,
+}
+
+impl Dylib {
+    fn load(path: &str) -> Result<Dylib, String> {
+        let lib = Library::new(path).map_err(|e| format!("can't load {}: {}", path, e))?;
+        let ai = unsafe {
+            let ctor = lib.get::<fn() -> Box<dyn Reactor>>(REACTOR_SYMBOL)
+                .map_err(|e| format!("can't find cgw_reactor in {}: {}", path, e))?;
+            ctor()
+        };
+        Ok(Dylib { ai: ai, _lib: lib })
+    }
+}
+
+impl Reactor for Dylib {
+    fn action(&mut self, result: ActionResult, turn: usize) -> Option<Vec<u8>> {
+        self.ai.action(result, turn)
+    }
+    fn unhandled(&mut self, seq: &UnhandledSeq) {
+        self.ai.unhandled(seq)
+    }
+    fn on_turn_stats(&mut self, stats: TurnStats) {
+        self.ai.on_turn_stats(stats)
+    }
+    fn on_reward(&mut self, reward: f64) {
+        self.ai.on_reward(reward)
+    }
+    fn on_idle(&mut self, idle: IdleStreak) {
+        self.ai.on_idle(idle)
+    }
+    fn on_term_mode(&mut self, mode: TermMode) {
+        self.ai.on_term_mode(mode)
+    }
+    fn on_scrollback(&mut self, lines: &[Vec<u8>]) {
+        self.ai.on_scrollback(lines)
+    }
+    fn on_terminal_event(&mut self, event: &TerminalEvent) {
+        self.ai.on_terminal_event(event)
+    }
+}
+
+fn load_config(path: &str) -> Result<Config, String> {
+    let mut s = String::new();
+    File::open(path)
+        .and_then(|mut f| f.read_to_string(&mut s))
+        .map_err(|e| format!("can't read {}: {}", path, e))?;
+    Config::parse(&s).map_err(|e| format!("invalid config {}: {}", path, e))
+}
+
+fn run(config: &Config) -> Result<(), String> {
+    match config.reactor.library {
+        Some(ref path) => play(config, &mut Dylib::load(path)?),
+        None => play(config, &mut config.script()?),
+    }
+}
+
+fn play<R: Reactor>(config: &Config, ai: &mut R) -> Result<(), String> {
+    // read it before the key log of this run overwrites it
+    let restore = match config.game.restore {
+        Some(ref path) => File::open(path)
+            .and_then(read_key_log)
+            .map_err(|e| format!("can't read key log {}: {}", path, e))?
+            .pop(),
+        None => None,
+    };
+    let mut game = config.setting()?.build().map_err(|e| e.to_string())?;
+    if let Some(records) = restore {
+        game.restore(records, config.game.verify_restore);
+    }
+    for _ in 0..config.episodes {
+        let episode = game.episode();
+        let m = game.play(ai);
+        println!(
+            "episode {}: turns {}, frames {}, bytes read {}, bytes written {}, restarts {}{}{}",
+            episode,
+            m.turns,
+            m.frames,
+            m.bytes_read,
+            m.bytes_written,
+            m.restarts,
+            if m.hung { ", hung" } else { "" },
+            m.stop_reason
+                .map_or(String::new(), |r| format!(", stopped by {}", r))
+        );
+    }
+    Ok(())
+}
+
+fn main() {
+    let path = match env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: cgw-run CONFIG");
+            process::exit(1);
+        }
+    };
+    if let Err(e) = load_config(&path).and_then(|config| run(&config)) {
+        eprintln!("cgw-run: {}", e);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_config() {
+        let config = Config::parse(
+            r#"
+            episodes = 2
+            [game]
+            command = "cat"
+            env = { A = "{episode}" }
+            max_loop = 10
+            stop_on = { died = "You die" }
+            game_over = "nethack"
+            pacing = "adaptive"
+            key_map = { N = "<Up>", y = "z" }
+            [record]
+            action_log = "a.jsonl"
+            action_log_format = "jsonl"
+            results = "results.jsonl"
+            [reactor]
+            keys = ["<Up>", "q"]
+            "#,
+        ).unwrap();
+        assert_eq!(config.episodes, 2);
+        assert_eq!(config.game.env["A"], "{episode}");
+        assert_eq!(config.game.stop_on["died"], "You die");
+        assert_eq!(config.action_log_format(), Ok(ActionLogFormat::Jsonl));
+        assert_eq!(config.script().unwrap().keys, vec![b"\x1b[A".to_vec(), b"q".to_vec()]);
+        assert!(config.game_over().unwrap().is_some());
+        assert_eq!(config.pacing(), Ok(Pacing::FastForward { adaptive: true }));
+        let key_map = KeyMap::new().map(b"N", b"\x1b[A").map(b"y", b"z");
+        assert_eq!(config.key_map(), Ok(key_map));
+        assert_eq!(config.results_format(), Ok(ResultFormat::Jsonl));
+        assert!(Config::parse("[game]\ncommand = \"cat\"\nfoo = 1").is_err());
+        assert!(Config::parse("[game]\ncommand = \"cat\"\ngame_over = \"angband\"").is_err());
+        assert!(Config::parse("[game]\ncommand = \"cat\"\npacing = \"slow\"").is_err());
+        assert!(Config::parse("[game]\ncommand = \"cat\"\nkey_map = { a = \"<Foo>\" }").is_err());
+        let real_time = Config::parse("[game]\ncommand = \"cat\"\npacing = \"real_time\"\nfps = 5");
+        assert_eq!(
+            real_time.unwrap().pacing(),
+            Ok(Pacing::RealTime {
+                fps: 5,
+                key_delay: Duration::from_millis(DEFAULT_KEY_DELAY_MS)
+            })
+        );
+        assert!(Config::parse("[game]\ncommand = \"cat\"\n[reactor]\nkeys = [\"<Foo>\"]").is_err());
+        let both = "[game]\ncommand = \"cat\"\n[reactor]\nkeys = [\"a\"]\nlibrary = \"a.so\"";
+        assert!(Config::parse(both).is_err());
+    }
+    #[test]
+    fn test_script() {
+        let mut script = Script {
+            keys: vec![b"a".to_vec(), b"b".to_vec()],
+            repeat: true,
+            next: 0,
+        };
+        let keys: Vec<_> = (1..4)
+            .map(|t| script.action(ActionResult::NotChanged, t))
+            .collect();
+        assert_eq!(keys, vec![Some(b"a".to_vec()), Some(b"b".to_vec()), Some(b"a".to_vec())]);
+        script.repeat = false;
+        assert_eq!(script.action(ActionResult::NotChanged, 4), Some(b"b".to_vec()));
+        assert_eq!(script.action(ActionResult::NotChanged, 5), None);
+    }
+}
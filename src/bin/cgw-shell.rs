@@ -0,0 +1,183 @@
+//! REPL to play a game by key sequences and see the emulated screen.
+//!
+//! Useful to find out which bytes your AI has to send.
+//! ```shell
+//! cargo run --bin cgw-shell -- --env ROGUEUSER=shell rogue
+//! ```
+extern crate curses_game_wrapper as cgw;
+
+use cgw::{parse_keys, write_golden, ActionResult, GameHandle, GameSetting, MetaEncoding};
+use std::ascii;
+use std::env;
+use std::fs::File;
+use std::io::{self, BufRead, Write};
+use std::process;
+use std::time::Duration;
+
+const USAGE: &str = "\
+usage: cgw-shell [OPTIONS] COMMAND [ARGS...]
+
+options:
+    -l, --lines N        lines of the screen(default 24)
+    -c, --columns N      columns of the screen(default 80)
+    -e, --env NAME=VAL   set environment variable of the game
+    -w, --wait MS        time to wait for the game output(default 500)";
+
+const HELP: &str = "\
+Type keys and press Enter to send them, like 2h<Esc>i<C-r><M-p><Enter>.
+Names of keys(<Up>, <F1>, ...), <C-x>, <M-x> and <lt> are available.
+commands:
+    :show         show the screen again
+    :wait [MS]    wait for the game output
+    :save FILE    save the screen as a golden frame
+    :help         show this help
+    :quit         kill the game and exit";
+
+struct Options {
+    lines: usize,
+    columns: usize,
+    envs: Vec<(String, String)>,
+    wait: Duration,
+    command: Vec<String>,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut opts = Options {
+        lines: 24,
+        columns: 80,
+        envs: Vec::new(),
+        wait: Duration::from_millis(500),
+        command: Vec::new(),
+    };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if !arg.starts_with('-') {
+            opts.command.push(arg);
+            opts.command.extend(args);
+            break;
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{} needs a value", arg))?;
+        let num = || {
+            value
+                .parse::<usize>()
+                .map_err(|e| format!("invalid value for {}: {}", arg, e))
+        };
+        match &arg[..] {
+            "-l" | "--lines" => opts.lines = num()?,
+            "-c" | "--columns" => opts.columns = num()?,
+            "-w" | "--wait" => opts.wait = Duration::from_millis(num()? as u64),
+            "-e" | "--env" => {
+                let eq = value
+                    .find('=')
+                    .ok_or_else(|| format!("{} needs NAME=VALUE", arg))?;
+                opts.envs
+                    .push((value[..eq].to_owned(), value[eq + 1..].to_owned()));
+            }
+            _ => return Err(format!("unknown option {}", arg)),
+        }
+    }
+    if opts.command.is_empty() {
+        return Err("no command given".to_owned());
+    }
+    Ok(opts)
+}
+
+fn escape(bytes: &[u8]) -> String {
+    let escaped: Vec<u8> = bytes
+        .iter()
+        .flat_map(|&b| ascii::escape_default(b))
+        .collect();
+    String::from_utf8(escaped).unwrap()
+}
+
+fn show(handle: &GameHandle) {
+    let ruled = handle.screen().to_ruled_string(Some(handle.cursor()));
+    if ruled.ends_with('\n') {
+        print!("{}", ruled);
+    } else {
+        println!("{}", ruled);
+    }
+}
+
+// wait for the game output and show the screen
+fn wait(handle: &mut GameHandle, timeout: Duration) {
+    match handle.next_screen(timeout) {
+        ActionResult::GameEnded => println!("game ended"),
+        ActionResult::NotChanged => println!("(no output)"),
+        _ => {}
+    }
+    show(handle);
+}
+
+fn save(handle: &GameHandle, path: &str) -> io::Result<()> {
+    let file = File::create(path)?;
+    write_golden(file, &[handle.screen()])
+}
+
+fn main() {
+    let opts = parse_args().unwrap_or_else(|e| {
+        eprintln!("cgw-shell: {}\n{}", e, USAGE);
+        process::exit(1);
+    });
+    let setting = opts.envs.iter().fold(
+        GameSetting::new(&opts.command[0])
+            .args(opts.command[1..].iter().map(|s| &s[..]))
+            .lines(opts.lines)
+            .columns(opts.columns),
+        |s, &(ref name, ref value)| s.env(name, value),
+    );
+    let mut env = setting.build().unwrap_or_else(|e| {
+        eprintln!("cgw-shell: {}", e);
+        process::exit(1);
+    });
+    let mut handle = env.handle();
+    wait(&mut handle, opts.wait);
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("> ");
+        io::stdout().flush().ok();
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => break,
+        };
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some(":q") | Some(":quit") => break,
+            Some(":h") | Some(":help") => println!("{}", HELP),
+            Some(":show") => show(&handle),
+            Some(":wait") => {
+                let timeout = words
+                    .next()
+                    .and_then(|ms| ms.parse().ok())
+                    .map_or(opts.wait, Duration::from_millis);
+                wait(&mut handle, timeout);
+            }
+            Some(":save") => match words.next() {
+                Some(path) => match save(&handle, path) {
+                    Ok(()) => println!("saved to {}", path),
+                    Err(e) => println!("can't save: {}", e),
+                },
+                None => println!("usage: :save FILE"),
+            },
+            Some(cmd) if cmd.starts_with(':') => println!("unknown command {}, see :help", cmd),
+            _ => match parse_keys(&line, MetaEncoding::default()) {
+                Ok(ref bytes) if bytes.is_empty() => {}
+                Ok(bytes) => {
+                    println!("sent \"{}\"", escape(&bytes));
+                    if let Err(e) = handle.send(&bytes) {
+                        println!("can't send: {}", e);
+                        break;
+                    }
+                    wait(&mut handle, opts.wait);
+                }
+                Err(part) => println!("can't parse {}", part),
+            },
+        }
+        if handle.is_ended() {
+            break;
+        }
+    }
+}
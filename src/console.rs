@@ -0,0 +1,242 @@
+//! Debug console showing the emulated screen with parsed sequences and AI actions.
+use game_env::{GameViewer, Handle, ThreadHandle, ViewerError};
+use emulator::Emulator;
+use term_type::TermType;
+use termion::{clear, cursor, terminal_size};
+use termion::raw::IntoRawMode;
+use vte::{Parser, Perform};
+use std::ascii;
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::mem;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+
+// events kept for the right pane
+const MAX_EVENTS: usize = 1000;
+
+enum Message {
+    Output(Handle<Vec<u8>>),
+    Action(usize, Vec<u8>),
+}
+
+/// Viewer drawing the emulated screen on the left and the log of parsed
+/// escape sequences and AI actions on the right.
+pub struct ConsoleViewer {
+    tx: Sender<Message>,
+    rx: Option<Receiver<Message>>,
+    sleep_time: Duration,
+    stopped: Arc<AtomicBool>,
+    lines: usize,
+    columns: usize,
+    term: TermType,
+}
+
+impl ConsoleViewer {
+    pub fn new(d: Duration, lines: usize, columns: usize, term: TermType) -> ConsoleViewer {
+        let (tx, rx) = mpsc::channel();
+        ConsoleViewer {
+            tx: tx,
+            rx: Some(rx),
+            sleep_time: d,
+            stopped: Arc::new(AtomicBool::new(false)),
+            lines: lines,
+            columns: columns,
+            term: term,
+        }
+    }
+}
+
+impl GameViewer for ConsoleViewer {
+    fn run(&mut self) -> ThreadHandle<Result<(), ViewerError>> {
+        let rx = self.rx.take().expect("ConsoleViewer::run is called twice");
+        let sleep = self.sleep_time;
+        let stopped = Arc::clone(&self.stopped);
+        let mut emu = Emulator::with_term(self.lines, self.columns, self.term);
+        ThreadHandle::spawn(move || {
+            let mut stdout = io::stdout()
+                .into_raw_mode()
+                .map_err(|e| ViewerError(format!("couldn't get raw stdout: {}", e)))?;
+            let mut parser = Parser::new();
+            let mut log = EventLog::default();
+            while let Ok(msg) = rx.recv() {
+                match msg {
+                    Message::Output(Handle::Valid(bytes)) => {
+                        emu.feed(&bytes);
+                        for c in &bytes {
+                            parser.advance(&mut log, *c);
+                        }
+                        log.flush_text();
+                    }
+                    Message::Output(Handle::Zero) | Message::Output(Handle::Error(_)) => break,
+                    Message::Action(turn, bytes) => {
+                        let text = format!("turn {}: AI sent \"{}\"", turn, escape(&bytes));
+                        log.push(text);
+                    }
+                }
+                draw(&mut stdout, &emu, &log.events)
+                    .map_err(|e| ViewerError(format!("couldn't draw console: {}", e)))?;
+                if !stopped.load(Ordering::Relaxed) {
+                    thread::sleep(sleep);
+                }
+            }
+            write!(stdout, "{}{}", cursor::Show, cursor::Goto(1, 1)).ok();
+            stdout.flush().ok();
+            Ok(())
+        })
+    }
+    fn send_bytes(&mut self, b: Handle<&[u8]>) -> Result<(), ViewerError> {
+        let msg = match b {
+            Handle::Zero => Handle::Zero,
+            Handle::Error(e) => Handle::Error(e),
+            Handle::Valid(b) => Handle::Valid(b.to_owned()),
+        };
+        self.tx
+            .send(Message::Output(msg))
+            .map_err(|e| ViewerError(format!("{}", e)))
+    }
+    fn send_action(&mut self, turn: usize, bytes: &[u8]) {
+        self.tx.send(Message::Action(turn, bytes.to_owned())).ok();
+    }
+    fn shutdown(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        self.tx.send(Message::Output(Handle::Zero)).ok();
+    }
+}
+
+fn draw<W: Write>(out: &mut W, emu: &Emulator, events: &VecDeque<String>) -> io::Result<()> {
+    let (cols, rows) = terminal_size().unwrap_or((160, 48));
+    let (cols, rows) = (cols as usize, rows as usize);
+    let screen = emu.screen();
+    // the screen takes at most 2/3 of the terminal
+    let left = screen.columns().min(cols * 2 / 3);
+    let right = cols.saturating_sub(left + 3);
+    let skip = events.len().saturating_sub(rows);
+    write!(out, "{}{}", cursor::Hide, clear::All)?;
+    for y in 0..rows {
+        write!(out, "{}", cursor::Goto(1, y as u16 + 1))?;
+        if y < screen.lines() {
+            let line: String = screen[y][..left]
+                .iter()
+                .map(|&c| if c < 0x20 || c == 0x7f { ' ' } else { char::from(c) })
+                .collect();
+            write!(out, "{}", line)?;
+        }
+        write!(out, "{} | ", cursor::Goto(left as u16 + 1, y as u16 + 1))?;
+        if let Some(e) = events.iter().skip(skip).nth(y) {
+            let e: String = e.chars().take(right).collect();
+            write!(out, "{}", e)?;
+        }
+    }
+    let (cy, cx) = emu.cursor();
+    if cy < rows && cx < left {
+        write!(out, "{}{}", cursor::Goto(cx as u16 + 1, cy as u16 + 1), cursor::Show)?;
+    }
+    out.flush()
+}
+
+fn escape(bytes: &[u8]) -> String {
+    let escaped: Vec<u8> = bytes
+        .iter()
+        .flat_map(|&b| ascii::escape_default(b))
+        .collect();
+    String::from_utf8(escaped).unwrap()
+}
+
+fn join(params: &[i64]) -> String {
+    let params: Vec<_> = params.iter().map(|p| p.to_string()).collect();
+    params.join(";")
+}
+
+// Describes each sequence the game wrote in a line.
+#[derive(Default)]
+struct EventLog {
+    events: VecDeque<String>,
+    // printed characters not pushed yet
+    text: String,
+}
+
+impl EventLog {
+    fn push(&mut self, event: String) {
+        self.flush_text();
+        if self.events.len() >= MAX_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+    fn flush_text(&mut self) {
+        if !self.text.is_empty() {
+            let text = mem::replace(&mut self.text, String::new());
+            self.push(format!("print \"{}\"", text));
+        }
+    }
+}
+
+impl Perform for EventLog {
+    fn print(&mut self, c: char) {
+        self.text.push(c);
+    }
+    fn execute(&mut self, byte: u8) {
+        let name = match byte {
+            0x07 => "BEL".to_owned(),
+            0x08 => "BS".to_owned(),
+            0x09 => "HT".to_owned(),
+            0x0a => "LF".to_owned(),
+            0x0d => "CR".to_owned(),
+            b => format!("{:02x}", b),
+        };
+        self.push(format!("execute {}", name));
+    }
+    fn hook(&mut self, params: &[i64], intermediates: &[u8], _ignore: bool) {
+        let ints = String::from_utf8_lossy(intermediates);
+        self.push(format!("DCS {}{}", ints, join(params)));
+    }
+    fn put(&mut self, _byte: u8) {}
+    fn unhook(&mut self) {}
+    fn osc_dispatch(&mut self, params: &[&[u8]]) {
+        let params: Vec<_> = params.iter().map(|p| String::from_utf8_lossy(p)).collect();
+        self.push(format!("OSC {}", params.join(";")));
+    }
+    fn csi_dispatch(&mut self, params: &[i64], intermediates: &[u8], _ignore: bool, c: char) {
+        let ints = String::from_utf8_lossy(intermediates);
+        self.push(format!("CSI {}{}{}", ints, join(params), c));
+    }
+    fn esc_dispatch(&mut self, _params: &[i64], intermediates: &[u8], _ignore: bool, byte: u8) {
+        let ints = String::from_utf8_lossy(intermediates);
+        self.push(format!("ESC {}{}", ints, char::from(byte)));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_event_log() {
+        let mut log = EventLog::default();
+        let mut parser = Parser::new();
+        for c in b"ab\x1b[?1;2h\r\n\x1b#8\x1b]0;t\x07c" {
+            parser.advance(&mut log, *c);
+        }
+        log.flush_text();
+        let events: Vec<_> = log.events.iter().map(|s| &s[..]).collect();
+        assert_eq!(
+            events,
+            vec![
+                "print \"ab\"",
+                "CSI ?1;2h",
+                "execute CR",
+                "execute LF",
+                "ESC #8",
+                "OSC 0;t",
+                "print \"c\"",
+            ]
+        );
+    }
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape(b"h\x1b[A\r"), "h\\x1b[A\\r");
+    }
+}
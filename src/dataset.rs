@@ -0,0 +1,406 @@
+use keylog::{next_byte, read_varint};
+#[cfg(any(test, feature = "process"))]
+use keylog::write_varint;
+use rle::decode_line;
+#[cfg(any(test, feature = "process"))]
+use rle::encode_line;
+use screen::{LineAttr, Screen};
+use std::io::{self, Read};
+#[cfg(any(test, feature = "process"))]
+use std::io::Write;
+use std::time::Duration;
+#[cfg(any(test, feature = "process"))]
+use std::time::Instant;
+use super::ActionResult;
+
+const MAGIC: &[u8; 5] = b"CGWD\x01";
+
+// tags of records
+const GAME_START: u8 = b'S';
+const TURN: u8 = b'T';
+
+// results of turns
+const CHANGED: u8 = b'C';
+const NOT_CHANGED: u8 = b'N';
+const GAME_ENDED: u8 = b'E';
+
+// how screens are stored
+const FULL: u8 = 0;
+const DIFF: u8 = 1;
+// added to FULL or DIFF if styles follow lines
+const STYLED: u8 = 2;
+
+/// One turn delivered to the AI, recorded by ```GameSetting::dataset_file```.
+/// ```time``` is elapsed time from the start of the game.
+#[derive(Clone, Debug)]
+pub struct DatasetRecord {
+    pub time: Duration,
+    pub turn: usize,
+    /// What the AI received, with the whole screen in ```Changed```
+    pub result: ActionResult,
+    /// Keys the AI returned
+    pub action: Option<Vec<u8>>,
+    /// Sum of rewards of ```GameSetting::reward```(```None``` for turns with no
+    /// new frame, or if no reward function is set)
+    pub reward: Option<f64>,
+}
+
+// Each game starts with GAME_START, followed by records written as
+// TURN | micro secs since last record(varint) | turn(varint) | result
+//      | screen(only for CHANGED) | action length + 1(varint, 0 for None) | action
+//      | 0, or 1 and reward(f64 LE)
+// where screen is
+// FULL | cursor | number of lines(varint) | lines | styles(only with STYLED)
+// or DIFF | cursor | number of changed lines(varint) | (line number(varint) | line)...
+//      | styles(only with STYLED)
+// cursor is y(varint) | x(varint) | visible(0 or 1), and lines are run-length
+// encoded(see rle.rs), as deltas from the last screen in DIFF.
+// styles are number of lines(varint) | (line number(varint) | LineAttr
+//      | reverse video flags(run-length encoded, 0 or 1 per character))...
+// which have reverse video or LineAttr other than Normal in FULL, and those
+// whose styles are changed in DIFF(flags are deltas as lines).
+// DIFF is used when the number of lines is same as the last screen of the game.
+#[cfg(any(test, feature = "process"))]
+pub struct DatasetWriter<W: Write> {
+    inner: W,
+    start: Instant,
+    // elapsed micro secs at the last record
+    last: u64,
+    // the last screen recorded in this game
+    prev: Option<Screen>,
+}
+
+#[cfg(any(test, feature = "process"))]
+impl<W: Write> DatasetWriter<W> {
+    pub fn new(mut inner: W) -> io::Result<DatasetWriter<W>> {
+        inner.write_all(MAGIC)?;
+        Ok(DatasetWriter {
+            inner: inner,
+            start: Instant::now(),
+            last: 0,
+            prev: None,
+        })
+    }
+    pub fn start_game(&mut self) -> io::Result<()> {
+        self.start = Instant::now();
+        self.last = 0;
+        self.prev = None;
+        self.inner.write_all(&[GAME_START])?;
+        self.inner.flush()
+    }
+    // appended and flushed each turn, so that the dataset survives crashes
+    pub fn record(
+        &mut self,
+        turn: usize,
+        result: &ActionResult,
+        action: Option<&[u8]>,
+        reward: Option<f64>,
+    ) -> io::Result<()> {
+        let elapsed = self.start.elapsed();
+        let micros = elapsed.as_secs() * 1_000_000 + u64::from(elapsed.subsec_nanos() / 1000);
+        let mut buf = vec![TURN];
+        write_varint(&mut buf, micros - self.last);
+        write_varint(&mut buf, turn as u64);
+        match *result {
+            ActionResult::Changed(ref screen) => {
+                buf.push(CHANGED);
+                self.write_screen(&mut buf, screen);
+                self.prev = Some(screen.clone());
+            }
+            ActionResult::NotChanged => buf.push(NOT_CHANGED),
+            ActionResult::GameEnded => buf.push(GAME_ENDED),
+        }
+        match action {
+            Some(bytes) => {
+                write_varint(&mut buf, bytes.len() as u64 + 1);
+                buf.extend_from_slice(bytes);
+            }
+            None => buf.push(0),
+        }
+        match reward {
+            Some(r) => {
+                buf.push(1);
+                let bits = r.to_bits();
+                buf.extend((0..8).map(|i| (bits >> (i * 8)) as u8));
+            }
+            None => buf.push(0),
+        }
+        self.last = micros;
+        self.inner.write_all(&buf)?;
+        self.inner.flush()
+    }
+    fn write_screen(&self, buf: &mut Vec<u8>, screen: &Screen) {
+        let prev = self.prev.as_ref().filter(|p| p.lines() == screen.lines());
+        let styles: Vec<_> = (0..screen.lines())
+            .map(|y| (y, screen.line_attr(y), reverse_flags(screen, y)))
+            .filter(|&(y, attr, ref flags)| match prev {
+                Some(prev) => attr != prev.line_attr(y) || *flags != reverse_flags(prev, y),
+                None => attr != LineAttr::Normal || flags.contains(&1),
+            })
+            .collect();
+        let kind = if prev.is_some() { DIFF } else { FULL };
+        buf.push(if styles.is_empty() { kind } else { kind + STYLED });
+        let (y, x) = screen.cursor();
+        write_varint(buf, y as u64);
+        write_varint(buf, x as u64);
+        buf.push(screen.is_cursor_visible() as u8);
+        match prev {
+            Some(prev) => {
+                let changed: Vec<_> = (0..screen.lines())
+                    .filter(|&y| prev[y] != screen[y])
+                    .collect();
+                write_varint(buf, changed.len() as u64);
+                for y in changed {
+                    write_varint(buf, y as u64);
+                    encode_line(buf, &screen[y], Some(&prev[y]));
+                }
+            }
+            None => {
+                write_varint(buf, screen.lines() as u64);
+                for line in screen.iter() {
+                    encode_line(buf, line, None);
+                }
+            }
+        }
+        if styles.is_empty() {
+            return;
+        }
+        write_varint(buf, styles.len() as u64);
+        for (y, attr, flags) in styles {
+            write_varint(buf, y as u64);
+            buf.push(match attr {
+                LineAttr::Normal => 0,
+                LineAttr::DoubleWidth => 1,
+                LineAttr::DoubleHeightTop => 2,
+                LineAttr::DoubleHeightBottom => 3,
+            });
+            let prev_flags = prev.map(|p| reverse_flags(p, y));
+            encode_line(buf, &flags, prev_flags.as_ref().map(|f| &f[..]));
+        }
+    }
+}
+
+#[cfg(any(test, feature = "process"))]
+fn reverse_flags(screen: &Screen, y: usize) -> Vec<u8> {
+    (0..screen[y].len())
+        .map(|x| screen.is_reverse(y, x) as u8)
+        .collect()
+}
+
+/// Read all records from dataset made by ```GameSetting::dataset_file```,
+/// as a list of records per game.
+///
+/// Screens stored as differences are restored to the whole screens.
+pub fn read_dataset<R: Read>(reader: R) -> io::Result<Vec<Vec<DatasetRecord>>> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_owned());
+    let mut bytes = reader.bytes();
+    let mut magic = [0u8; 5];
+    for b in magic.iter_mut() {
+        *b = next_byte(&mut bytes)?;
+    }
+    if &magic != MAGIC {
+        return Err(invalid("not a dataset file"));
+    }
+    let mut res: Vec<Vec<DatasetRecord>> = Vec::new();
+    let mut micros = 0;
+    // lines, LineAttrs and reverse video flags of the last screen in this game
+    let mut prev: Vec<Vec<u8>> = Vec::new();
+    let mut attrs: Vec<LineAttr> = Vec::new();
+    let mut reverse: Vec<Vec<u8>> = Vec::new();
+    while let Some(tag) = bytes.next() {
+        match tag? {
+            GAME_START => {
+                res.push(Vec::new());
+                micros = 0;
+                prev.clear();
+                attrs.clear();
+                reverse.clear();
+                continue;
+            }
+            TURN => {}
+            _ => return Err(invalid("invalid record kind")),
+        }
+        micros += read_varint(&mut bytes)?;
+        let turn = read_varint(&mut bytes)? as usize;
+        let result = match next_byte(&mut bytes)? {
+            CHANGED => {
+                let kind = next_byte(&mut bytes)?;
+                let cursor = (
+                    read_varint(&mut bytes)? as usize,
+                    read_varint(&mut bytes)? as usize,
+                );
+                let visible = next_byte(&mut bytes)? != 0;
+                let num = read_varint(&mut bytes)?;
+                match kind & !STYLED {
+                    FULL => {
+                        prev = (0..num)
+                            .map(|_| decode_line(&mut bytes, None))
+                            .collect::<io::Result<_>>()?;
+                        attrs = vec![LineAttr::Normal; prev.len()];
+                        reverse = prev.iter().map(|l| vec![0; l.len()]).collect();
+                    }
+                    DIFF => for _ in 0..num {
+                        let y = read_varint(&mut bytes)? as usize;
+                        let line = prev
+                            .get_mut(y)
+                            .ok_or_else(|| invalid("difference out of the screen"))?;
+                        *line = decode_line(&mut bytes, Some(&line[..]))?;
+                    },
+                    _ => return Err(invalid("invalid screen kind")),
+                }
+                let styles = if kind & STYLED != 0 { read_varint(&mut bytes)? } else { 0 };
+                for _ in 0..styles {
+                    let y = read_varint(&mut bytes)? as usize;
+                    if y >= prev.len() {
+                        return Err(invalid("style out of the screen"));
+                    }
+                    attrs[y] = match next_byte(&mut bytes)? {
+                        0 => LineAttr::Normal,
+                        1 => LineAttr::DoubleWidth,
+                        2 => LineAttr::DoubleHeightTop,
+                        3 => LineAttr::DoubleHeightBottom,
+                        _ => return Err(invalid("invalid line attribute")),
+                    };
+                    let flags = decode_line(&mut bytes, Some(&reverse[y][..]))?;
+                    reverse[y] = flags;
+                }
+                let screen = Screen::new(prev.clone())
+                    .with_line_attrs(attrs.clone())
+                    .with_reverse(
+                        reverse
+                            .iter()
+                            .map(|l| l.iter().map(|&f| f != 0).collect())
+                            .collect(),
+                    )
+                    .with_cursor(cursor, visible);
+                ActionResult::Changed(screen)
+            }
+            NOT_CHANGED => ActionResult::NotChanged,
+            GAME_ENDED => ActionResult::GameEnded,
+            _ => return Err(invalid("invalid result")),
+        };
+        let action = match read_varint(&mut bytes)? {
+            0 => None,
+            len => Some(
+                (1..len)
+                    .map(|_| next_byte(&mut bytes))
+                    .collect::<io::Result<Vec<u8>>>()?,
+            ),
+        };
+        let reward = match next_byte(&mut bytes)? {
+            0 => None,
+            _ => {
+                let mut bits = 0;
+                for i in 0..8 {
+                    bits |= u64::from(next_byte(&mut bytes)?) << (i * 8);
+                }
+                Some(f64::from_bits(bits))
+            }
+        };
+        let game = res.last_mut()
+            .ok_or_else(|| invalid("record before the start of game"))?;
+        game.push(DatasetRecord {
+            time: Duration::new(micros / 1_000_000, (micros % 1_000_000) as u32 * 1000),
+            turn: turn,
+            result: result,
+            action: action,
+            reward: reward,
+        });
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    fn screen(lines: &[&[u8]]) -> Screen {
+        Screen::new(lines.iter().map(|l| l.to_vec()).collect())
+    }
+    #[test]
+    fn test_roundtrip() {
+        let first = screen(&[b"Hello   ", b"  @     ", b"Gold: 0 "]).with_cursor((1, 2), true);
+        let second = screen(&[b"        ", b"   @    ", b"Gold: 0 "]).with_cursor((1, 3), false);
+        let resized = screen(&[b"  ", b"  "]);
+        let mut rev = vec![vec![false; 8]; 3];
+        rev[0][..5].iter_mut().for_each(|r| *r = true);
+        rev[2][7] = true;
+        let reversed = first
+            .clone()
+            .with_reverse(rev)
+            .with_line_attrs(vec![LineAttr::DoubleWidth, LineAttr::Normal, LineAttr::Normal]);
+        let mut buf = Vec::new();
+        {
+            let mut writer = DatasetWriter::new(&mut buf).unwrap();
+            writer.start_game().unwrap();
+            let changed = ActionResult::Changed(first.clone());
+            writer.record(1, &changed, Some(b"l"), Some(0.5)).unwrap();
+            writer.record(2, &ActionResult::NotChanged, None, None).unwrap();
+            let changed = ActionResult::Changed(second.clone());
+            writer.record(3, &changed, Some(b""), Some(-1.0)).unwrap();
+            let changed = ActionResult::Changed(resized.clone());
+            writer.record(4, &changed, Some(b"\x1b"), None).unwrap();
+            writer.record(5, &ActionResult::GameEnded, None, None).unwrap();
+            writer.start_game().unwrap();
+            writer.record(1, &ActionResult::Changed(reversed.clone()), None, None).unwrap();
+            writer.record(2, &ActionResult::Changed(first.clone()), None, None).unwrap();
+            writer.record(3, &ActionResult::Changed(reversed.clone()), None, None).unwrap();
+        }
+        let games = read_dataset(&buf[..]).unwrap();
+        assert_eq!(games.len(), 2);
+        let game = &games[0];
+        assert_eq!(game.len(), 5);
+        let changed = |rec: &DatasetRecord| match rec.result {
+            ActionResult::Changed(ref s) => s.clone(),
+            ref r => panic!("unexpected result {:?}", r),
+        };
+        assert_eq!(changed(&game[0]), first);
+        assert_eq!(changed(&game[0]).cursor(), (1, 2));
+        assert_eq!(game[0].action, Some(b"l".to_vec()));
+        assert_eq!(game[0].reward, Some(0.5));
+        assert_eq!(game[1].turn, 2);
+        assert_eq!(game[1].result.name(), "NotChanged");
+        assert_eq!(game[1].action, None);
+        assert_eq!(game[1].reward, None);
+        assert!(game[1].time >= game[0].time);
+        assert_eq!(changed(&game[2]), second);
+        assert!(!changed(&game[2]).is_cursor_visible());
+        assert_eq!(game[2].action, Some(Vec::new()));
+        assert_eq!(game[2].reward, Some(-1.0));
+        assert_eq!(changed(&game[3]), resized);
+        assert_eq!(game[4].result.name(), "GameEnded");
+        // screens which differ only in reverse video and line attributes
+        assert_eq!(changed(&games[1][0]), reversed);
+        assert!(changed(&games[1][0]).is_reverse(0, 4));
+        assert_eq!(changed(&games[1][0]).line_attr(0), LineAttr::DoubleWidth);
+        assert_eq!(changed(&games[1][1]), first);
+        assert_eq!(changed(&games[1][2]), reversed);
+        assert!(read_dataset(&buf[..buf.len() - 1]).is_err());
+        assert!(read_dataset(&b"CGWD\x01T\x00\x01N\x00\x00"[..]).is_err());
+        assert!(read_dataset(&b"hello"[..]).is_err());
+        // a line claiming ~2^63 bytes
+        let broken = b"CGWD\x01ST\x00\x01C\x00\x00\x00\x01\x01\
+                       \xff\xff\xff\xff\xff\xff\xff\xff\x7f\x80 ";
+        assert!(read_dataset(&broken[..]).is_err());
+    }
+    #[test]
+    fn test_diff() {
+        // sizes of screens only, as time since the last record varies
+        let size = |writer: &mut DatasetWriter<Vec<u8>>, screen: Screen| {
+            let mut buf = Vec::new();
+            writer.write_screen(&mut buf, &screen);
+            writer.prev = Some(screen);
+            buf.len()
+        };
+        let mut lines = vec![vec![b' '; 80]; 24];
+        let mut writer = DatasetWriter::new(Vec::new()).unwrap();
+        // blanks are run-length encoded
+        assert!(size(&mut writer, Screen::new(lines.clone())) < 100);
+        lines[3][5] = b'@';
+        // only the changed cell is stored
+        assert!(size(&mut writer, Screen::new(lines.clone())) < 15);
+        let mut rev = vec![vec![false; 80]; 24];
+        rev[3][5] = true;
+        let screen = Screen::new(lines).with_reverse(rev);
+        assert!(size(&mut writer, screen) < 20);
+    }
+}
@@ -0,0 +1,180 @@
+use screen::Screen;
+use std::ops::Range;
+
+/// Post-processor of screens, which runs before the screen reaches the AI.
+///
+/// Register it by ```GameSetting::decoder```, or by ```GameSetting::filter``` to
+/// run it before frames are compared. Decoders run in registered order.
+/// Closures ```Fn(&mut Screen)``` implement it.
+pub trait ScreenDecoder: Send + Sync {
+    fn decode(&self, screen: &mut Screen);
+}
+
+impl<F: Fn(&mut Screen) + Send + Sync> ScreenDecoder for F {
+    fn decode(&self, screen: &mut Screen) {
+        self(screen)
+    }
+}
+
+/// Clears reverse video and line attributes, so that screens differing only
+/// in highlights are equal.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StripAttributes;
+
+impl ScreenDecoder for StripAttributes {
+    fn decode(&self, screen: &mut Screen) {
+        screen.clear_attributes();
+    }
+}
+
+/// Replaces control and non-ASCII characters with ```replacement```,
+/// and characters registered by ```map```(e.g. DECgraphics letters to ASCII).
+///
+/// ```0```, which can be blank(see ```GameSetting::blank```), is kept.
+#[derive(Clone, Debug)]
+pub struct AsciiGraphics {
+    table: Vec<u8>,
+}
+
+impl AsciiGraphics {
+    pub fn new(replacement: u8) -> AsciiGraphics {
+        let table = (0..=255u8)
+            .map(|c| match c {
+                0 | 0x20..=0x7e => c,
+                _ => replacement,
+            })
+            .collect();
+        AsciiGraphics { table: table }
+    }
+    /// Show ```from``` as ```to```.
+    pub fn map(mut self, from: u8, to: u8) -> AsciiGraphics {
+        self.table[usize::from(from)] = to;
+        self
+    }
+}
+
+impl Default for AsciiGraphics {
+    fn default() -> AsciiGraphics {
+        AsciiGraphics::new(b'?')
+    }
+}
+
+impl ScreenDecoder for AsciiGraphics {
+    fn decode(&self, screen: &mut Screen) {
+        for y in 0..screen.lines() {
+            for c in screen[y].iter_mut() {
+                *c = self.table[usize::from(*c)];
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Lines {
+    Range(Range<usize>),
+    Last(usize),
+}
+
+/// Fills lines with blank, e.g. to hide the status area that changes every turn.
+#[derive(Clone, Debug)]
+pub struct MaskLines {
+    lines: Lines,
+    // all columns if None
+    columns: Option<Range<usize>>,
+    blank: u8,
+}
+
+impl MaskLines {
+    /// Mask lines in ```range```.
+    pub fn new(range: Range<usize>) -> MaskLines {
+        MaskLines {
+            lines: Lines::Range(range),
+            columns: None,
+            blank: b' ',
+        }
+    }
+    /// Mask last ```n``` lines, like status lines of NetHack(```MaskLines::last(2)```).
+    pub fn last(n: usize) -> MaskLines {
+        MaskLines {
+            lines: Lines::Last(n),
+            columns: None,
+            blank: b' ',
+        }
+    }
+    /// Mask only columns in ```range``` of the lines, e.g. to hide a clock.
+    pub fn columns(mut self, range: Range<usize>) -> MaskLines {
+        self.columns = Some(range);
+        self
+    }
+    /// Set the character to fill with(Default: space).
+    pub fn blank(mut self, b: u8) -> MaskLines {
+        self.blank = b;
+        self
+    }
+}
+
+impl ScreenDecoder for MaskLines {
+    fn decode(&self, screen: &mut Screen) {
+        let height = screen.lines();
+        let (start, end) = match self.lines {
+            Lines::Range(ref r) => (r.start.min(height), r.end.min(height)),
+            Lines::Last(n) => (height.saturating_sub(n), height),
+        };
+        for y in start..end {
+            let (left, right) = match self.columns {
+                Some(ref r) => (r.start, r.end),
+                None => (0, usize::max_value()),
+            };
+            for c in screen[y].iter_mut().take(right).skip(left) {
+                *c = self.blank;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use emulator::Emulator;
+    fn screen(lines: &[&[u8]]) -> Screen {
+        Screen::new(lines.iter().map(|l| l.to_vec()).collect())
+    }
+    #[test]
+    fn test_strip_attributes() {
+        let mut emu = Emulator::new(2, 4);
+        emu.feed(b"\x1b#6\x1b[7mab");
+        let mut s = emu.screen();
+        StripAttributes.decode(&mut s);
+        assert!(!s.is_reverse(0, 0));
+        assert_eq!(s.highlighted_lines().count(), 0);
+        assert!(!s.line_attr(0).is_double_width());
+        assert_eq!(s.trimmed_line(0), "ab");
+    }
+    #[test]
+    fn test_ascii_graphics() {
+        let mut s = screen(&[b"a\x01q\xb7", b"xx\x00\x7f"]);
+        AsciiGraphics::default()
+            .map(b'q', b'-')
+            .map(b'x', b'|')
+            .decode(&mut s);
+        assert_eq!(s.into_inner(), vec![b"a?-?".to_vec(), b"||\x00?".to_vec()]);
+    }
+    #[test]
+    fn test_mask_lines() {
+        let mut s = screen(&[b"ab", b"cd", b"ef"]);
+        MaskLines::new(1..10).decode(&mut s);
+        assert_eq!(s.to_trimmed_string(), "ab");
+        let mut s = screen(&[b"ab", b"cd", b"ef"]);
+        MaskLines::last(1).blank(b'#').decode(&mut s);
+        let first_column = |s: &mut Screen| {
+            for y in 0..s.lines() {
+                s[y][0] = b'*';
+            }
+        };
+        first_column.decode(&mut s);
+        assert_eq!(s.into_inner(), vec![b"*b".to_vec(), b"*d".to_vec(), b"*#".to_vec()]);
+        let mut s = screen(&[b"abcd", b"efgh"]);
+        MaskLines::new(0..1).columns(2..10).decode(&mut s);
+        assert_eq!(s.into_inner(), vec![b"ab  ".to_vec(), b"efgh".to_vec()]);
+    }
+}
@@ -0,0 +1,136 @@
+use keys::CursorKeyMode;
+use mouse::MouseMode;
+use screen::Screen;
+use term_data::{Parser, TermData, TermMode, TerminalEvent};
+use term_type::TermType;
+
+/// vt100 emulator used inside ```GameEnv```.
+///
+/// You can use it directly to replay recorded game outputs.
+/// # Example
+/// ```
+/// extern crate curses_game_wrapper as cgw;
+/// use cgw::Emulator;
+/// fn main() {
+///     let mut emu = Emulator::new(24, 80);
+///     emu.feed(b"\x1b[2;3Hhello");
+///     assert_eq!(&emu.screen()[1][2..7], b"hello");
+/// }
+/// ```
+pub struct Emulator {
+    parser: Parser,
+    term_data: TermData,
+}
+
+impl Emulator {
+    /// Make emulator with blank screen of given size.
+    /// # Panics
+    /// Panics if ```lines``` or ```columns``` is 0.
+    pub fn new(lines: usize, columns: usize) -> Emulator {
+        Emulator::with_blank(lines, columns, b' ')
+    }
+    /// Make emulator which fills blank cells with ```blank```.
+    /// See ```GameSetting::blank``` for detail.
+    /// # Panics
+    /// Panics if ```lines``` or ```columns``` is 0.
+    pub fn with_blank(lines: usize, columns: usize, blank: u8) -> Emulator {
+        Emulator {
+            parser: Parser::new(),
+            term_data: TermData::with_size(lines, columns, blank),
+        }
+    }
+    /// Make emulator behaving as ```term```.
+    ///
+    /// Emulators made by ```new``` and ```with_blank``` accept xterm extensions.
+    /// # Panics
+    /// Panics if ```lines``` or ```columns``` is 0.
+    pub fn with_term(lines: usize, columns: usize, term: TermType) -> Emulator {
+        let mut res = Emulator::new(lines, columns);
+        res.term_data.set_term(term);
+        res
+    }
+    /// Keep at most ```depth``` lines scrolled off the top of the screen(0 to disable).
+    pub fn set_scrollback(&mut self, depth: usize) {
+        self.term_data.set_scrollback(depth);
+    }
+    /// Returns lines scrolled off the screen, oldest first.
+    pub fn scrollback(&self) -> Vec<Vec<u8>> {
+        self.term_data.scrollback()
+    }
+    /// Record ```TerminalEvent```s from now on(Default: false), to receive them by
+    /// ```take_events```. Events not taken yet are dropped when it's turned off.
+    pub fn record_events(&mut self, on: bool) {
+        self.term_data.set_record_events(on);
+    }
+    /// Returns events interpreted from the game output since the last call.
+    /// # Example
+    /// ```
+    /// extern crate curses_game_wrapper as cgw;
+    /// use cgw::{Emulator, TerminalEvent};
+    /// fn main() {
+    ///     let mut emu = Emulator::new(24, 80);
+    ///     emu.record_events(true);
+    ///     emu.feed(b"\x1b[2;3Hhello");
+    ///     assert_eq!(
+    ///         emu.take_events(),
+    ///         vec![
+    ///             TerminalEvent::CursorMove { y: 1, x: 2 },
+    ///             TerminalEvent::Print { y: 1, x: 2, text: b"hello".to_vec() },
+    ///         ]
+    ///     );
+    /// }
+    /// ```
+    pub fn take_events(&mut self) -> Vec<TerminalEvent> {
+        self.term_data.take_events()
+    }
+    /// Process bytes written by the game.
+    pub fn feed(&mut self, bytes: &[u8]) {
+        for c in bytes {
+            self.parser.advance(&mut self.term_data, *c);
+        }
+    }
+    /// Returns replies to queries of the game(e.g. ```DECRQSS```) since the last
+    /// call, which should be sent back to the game.
+    ///
+    /// DCS queries(```DECRQSS``` and ```XTGETTCAP```) are replied only with xterm
+    /// terminal types(```TermType::Xterm``` and ```TermType::Xterm256Color```).
+    pub fn take_responses(&mut self) -> Vec<u8> {
+        self.term_data.take_responses()
+    }
+    /// Returns current screen.
+    pub fn screen(&self) -> Screen {
+        self.term_data.ret_screen()
+    }
+    /// Returns terminal modes set by the game.
+    pub fn mode(&self) -> TermMode {
+        self.term_data.mode()
+    }
+    /// Returns true if the game enabled bracketed paste mode.
+    pub fn bracketed_paste(&self) -> bool {
+        self.term_data.bracketed_paste()
+    }
+    /// Returns mouse reporting modes set by the game.
+    pub fn mouse_mode(&self) -> MouseMode {
+        self.term_data.mouse_mode()
+    }
+    /// Returns cursor position as ```(y, x)```.
+    pub fn cursor(&self) -> (usize, usize) {
+        self.term_data.cursor()
+    }
+    /// Returns true if the cursor is visible.
+    pub fn cursor_visible(&self) -> bool {
+        self.term_data.cursor_visible()
+    }
+    /// Returns cursor key mode set by the game.
+    pub fn cursor_key_mode(&self) -> CursorKeyMode {
+        self.term_data.cursor_key_mode()
+    }
+    /// Change the screen size, keeping the top-left of the screen.
+    ///
+    /// Scroll region is reset to the whole screen and cursors are moved into the screen.
+    /// # Panics
+    /// Panics if ```lines``` or ```columns``` is 0.
+    pub fn resize(&mut self, lines: usize, columns: usize) {
+        self.term_data.resize(lines, columns);
+    }
+}
@@ -0,0 +1,46 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// Error returned by ```GameSetting::build```, or kept as ```GameEnv::error```
+/// when the game failed.
+#[derive(Debug)]
+pub enum CgwError {
+    /// Invalid value of the setting, like zero lines
+    InvalidSetting(String),
+    /// The command isn't found in ```PATH``` or isn't executable
+    CommandNotFound(String),
+    /// Setup command exited with failure
+    SetupFailed(String),
+    /// I/O error with what the crate was doing
+    Io(String, io::Error),
+    /// Reading the game output failed
+    ChildIo(io::Error),
+    /// Drawing the game(```GameSetting::draw_on``` or ```debug_console```) failed
+    Viewer(String),
+    /// The game replayed by ```GameEnv::restore``` didn't behave as recorded
+    ReplayDiverged(String),
+}
+
+impl fmt::Display for CgwError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CgwError::InvalidSetting(ref s) => write!(f, "invalid setting: {}", s),
+            CgwError::CommandNotFound(ref s) => write!(f, "command not found: {}", s),
+            CgwError::SetupFailed(ref s) => write!(f, "setup command failed: {}", s),
+            CgwError::Io(ref s, ref e) => write!(f, "{}: {}", s, e),
+            CgwError::ChildIo(ref e) => write!(f, "couldn't read the game output: {}", e),
+            CgwError::Viewer(ref s) => write!(f, "viewer failed: {}", s),
+            CgwError::ReplayDiverged(ref s) => write!(f, "replay diverged: {}", s),
+        }
+    }
+}
+
+impl Error for CgwError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            CgwError::Io(_, ref e) | CgwError::ChildIo(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
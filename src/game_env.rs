@@ -0,0 +1,4717 @@
+use action_log::{ActionLogFormat, ActionLogger, TurnRecord};
+#[cfg(feature = "debug-console")]
+use console;
+use dataset::DatasetWriter;
+use decoder::ScreenDecoder;
+use error::CgwError;
+use games::GameAdapter;
+use keylog::{screen_hash, KeyLogWriter, KeyRecord};
+use keys::{meta, split_keys, wrap_paste, CursorKeyMode, InputSanitizer, Key, KeyMap, MetaEncoding,
+           CTRL_C, CTRL_Q, CTRL_S};
+use logging::Severity;
+use logging::Logger;
+use metrics::{IdleStreak, Metrics};
+use mock::MockGame;
+use mouse::{MouseEvent, MouseMode};
+use observation::{MappedReactor, ObservationMapper};
+use pacing::{AdaptiveWait, FrameLimiter, Pacing};
+use pause::PauseHandle;
+use regex::Regex;
+use results::{EpisodeRecord, ResultFormat, ResultSink};
+use reward::RewardFn;
+use screen::Screen;
+use stop::{GameOver, GameResult, Outcome, StopCondition};
+use term_data::{Parser, TermData, TermMode, TerminalEvent};
+use term_type::TermType;
+#[cfg(feature = "terminfo")]
+use terminfo::TermKeys;
+use termion::async_stdin;
+use termion::raw::IntoRawMode;
+use transcript::{IoKind, TranscriptWriter};
+use super::{ActionResult, Reactor, TryReactor};
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt::{self, Debug, Formatter};
+use std::io;
+use std::fs::File;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::env;
+use std::ffi::OsString;
+use std::fs::OpenOptions;
+use std::io::{BufWriter, Read, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::mem;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
+use std::os::unix::net::UnixStream;
+use std::os::unix::process::CommandExt;
+use std::str;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+
+#[derive(Clone, Debug)]
+pub(crate) struct LogInfo {
+    pub(crate) fname: String,
+    pub(crate) sev: Severity,
+    #[cfg(feature = "slog-logger")]
+    pub(crate) logger: Option<Logger>,
+}
+
+impl Default for LogInfo {
+    fn default() -> LogInfo {
+        LogInfo {
+            fname: String::new(),
+            sev: Severity::Debug,
+            #[cfg(feature = "slog-logger")]
+            logger: None,
+        }
+    }
+}
+
+// when and how to respawn crashed game
+#[derive(Copy, Clone, Debug)]
+struct RestartPolicy {
+    max_restarts: usize,
+    min_turns: usize,
+    backoff: Duration,
+}
+
+/// What to do when the game seems to hang.
+/// See ```GameSetting::watchdog```.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WatchdogAction {
+    /// Send the bytes(e.g. ```ESC``` to close a prompt) to wake up the game
+    Send(Vec<u8>),
+    /// Kill the game and set ```Metrics::hung```
+    Kill,
+}
+
+/// Input of the game set by ```GameSetting::stdin_from```.
+///
+/// The game reads it instead of the keys AI sends.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StdinSource {
+    /// Redirect stdin from the file
+    File(PathBuf),
+    /// Write the bytes to stdin and close it
+    Bytes(Vec<u8>),
+}
+
+impl StdinSource {
+    /// Read all bytes from ```reader```, which are given to each episode.
+    pub fn from_reader<R: Read>(mut reader: R) -> io::Result<StdinSource> {
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf)?;
+        Ok(StdinSource::Bytes(buf))
+    }
+}
+
+impl<'a> From<&'a Path> for StdinSource {
+    fn from(path: &'a Path) -> StdinSource {
+        StdinSource::File(path.to_owned())
+    }
+}
+
+impl From<PathBuf> for StdinSource {
+    fn from(path: PathBuf) -> StdinSource {
+        StdinSource::File(path)
+    }
+}
+
+impl<'a> From<&'a [u8]> for StdinSource {
+    fn from(bytes: &'a [u8]) -> StdinSource {
+        StdinSource::Bytes(bytes.to_owned())
+    }
+}
+
+impl From<Vec<u8>> for StdinSource {
+    fn from(bytes: Vec<u8>) -> StdinSource {
+        StdinSource::Bytes(bytes)
+    }
+}
+
+/// Pre-existing channel to the game set by ```GameSetting::connect```, for games
+/// run by another harness instead of spawned as a child process.
+///
+/// It's opened again for each episode, and the game ends when the other side
+/// closes it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GameIo {
+    /// Named pipes: keys are written to ```input``` and output is read from ```output```
+    Fifo { input: PathBuf, output: PathBuf },
+    /// Unix domain socket used for both directions
+    UnixSocket(PathBuf),
+}
+
+impl GameIo {
+    fn open(&self) -> io::Result<Connection> {
+        match *self {
+            GameIo::Fifo {
+                ref input,
+                ref output,
+            } => {
+                // don't wait for the game to open the other end here, or we would
+                // deadlock if the game opens input first
+                let reader = OpenOptions::new()
+                    .read(true)
+                    .custom_flags(libc::O_NONBLOCK)
+                    .open(output)?;
+                set_nonblocking(reader.as_raw_fd(), false)?;
+                let writer = OpenOptions::new().write(true).open(input)?;
+                Ok(Connection {
+                    writer: writer,
+                    reader: Some(reader),
+                })
+            }
+            GameIo::UnixSocket(ref path) => {
+                let stream = UnixStream::connect(path)?;
+                let reader = stream.try_clone()?;
+                unsafe {
+                    Ok(Connection {
+                        writer: File::from_raw_fd(stream.into_raw_fd()),
+                        reader: Some(File::from_raw_fd(reader.into_raw_fd())),
+                    })
+                }
+            }
+        }
+    }
+}
+
+// opened GameIo
+struct Connection {
+    writer: File,
+    // taken by the reader thread
+    reader: Option<File>,
+}
+
+// ScreenDecoders in registered order
+#[derive(Clone, Default)]
+struct Decoders(Vec<Arc<dyn ScreenDecoder>>);
+
+impl Decoders {
+    fn decode(&self, mut screen: Screen) -> Screen {
+        for d in &self.0 {
+            d.decode(&mut screen);
+        }
+        screen
+    }
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl Debug for Decoders {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Decoders({})", self.0.len())
+    }
+}
+
+// RewardFns, whose rewards are summed
+#[derive(Clone, Default)]
+struct Rewards(Vec<Arc<dyn RewardFn>>);
+
+impl Rewards {
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    fn reward(&self, prev: Option<&Screen>, current: &Screen) -> f64 {
+        self.0.iter().map(|r| r.reward(prev, current)).sum()
+    }
+}
+
+impl Debug for Rewards {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Rewards({})", self.0.len())
+    }
+}
+
+// GameOver detector and StopConditions with their reasons
+#[derive(Clone, Default)]
+struct Stops {
+    game_over: Option<GameOver>,
+    conds: Vec<(String, Arc<dyn StopCondition>)>,
+}
+
+impl Stops {
+    // returns the reason and outcome, checking the detector first and then
+    // conditions in registered order
+    fn check(&self, screen: &Screen) -> Option<(String, Option<Outcome>)> {
+        if let Some(outcome) = self.game_over.as_ref().and_then(|g| g.detect(screen)) {
+            return Some((outcome.to_string(), Some(outcome)));
+        }
+        self.conds
+            .iter()
+            .find(|&&(_, ref cond)| cond.is_met(screen))
+            .map(|&(ref reason, _)| (reason.clone(), None))
+    }
+    // keep the last frames for the parser of GameOver
+    fn keep(&self, frames: &mut Vec<Screen>, screen: &Screen) {
+        if self.game_over.is_none() {
+            return;
+        }
+        if frames.len() >= RESULT_FRAMES {
+            frames.remove(0);
+        }
+        frames.push(screen.clone());
+    }
+    fn parse_result(&self, frames: &[Screen]) -> Option<GameResult> {
+        self.game_over.as_ref().and_then(|g| g.parse_result(frames))
+    }
+}
+
+impl Debug for Stops {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let reasons: Vec<_> = self.conds.iter().map(|&(ref reason, _)| reason).collect();
+        write!(f, "Stops({:?}, {:?})", self.game_over, reasons)
+    }
+}
+
+// GameAdapter given to GameSetting::adapter
+#[derive(Clone)]
+struct Adapter(Arc<dyn GameAdapter>);
+
+impl Debug for Adapter {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Adapter({})", self.0.name())
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+enum DrawType {
+    Terminal(Duration),
+    #[cfg(feature = "debug-console")]
+    Console(Duration),
+    Null,
+}
+
+/// Game process builder, providing control over how a new process
+/// should be spawned.
+///
+/// Like ```std::process::Command```, A default configuration can be
+/// generated using Gamesetting::new(command name) and other settings
+/// can be added by builder methods.
+/// # Example
+/// ```no_run
+/// extern crate curses_game_wrapper as cgw;
+/// use cgw::{Reactor, ActionResult, AsciiChar, GameSetting, Severity};
+/// use std::time::Duration;
+/// fn main() {
+///     let loopnum = 50;
+///     let gs = GameSetting::new("rogue")
+///         .env("ROGUEUSER", "EmptyAI")
+///         .lines(24)
+///         .columns(80)
+///         .debug_file("debug.txt")
+///         .max_loop(loopnum + 1)
+///         .draw_on(Duration::from_millis(200));
+///     let game = gs.build().unwrap();
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct GameSetting<'a> {
+    cmdname: String,
+    pub(crate) lines: usize,
+    pub(crate) columns: usize,
+    envs: Vec<(&'a str, &'a str)>,
+    args: Vec<&'a str>,
+    pub(crate) log_info: LogInfo,
+    timeout: Duration,
+    settle: Option<Duration>,
+    pacing: Pacing,
+    draw_type: DrawType,
+    max_loop: usize,
+    transcript: Option<String>,
+    key_log: Option<String>,
+    dataset: Option<String>,
+    action_log: Option<(String, ActionLogFormat)>,
+    results: Option<(String, ResultFormat)>,
+    pub(crate) blank: u8,
+    pub(crate) scrollback: usize,
+    pub(crate) terminal_events: bool,
+    seed: u64,
+    setup: Vec<&'a str>,
+    teardown: Vec<&'a str>,
+    aux: Vec<(&'a str, Duration)>,
+    restart: Option<RestartPolicy>,
+    watchdog: Option<(Duration, WatchdogAction)>,
+    meta: MetaEncoding,
+    key_map: KeyMap,
+    sanitizer: Option<InputSanitizer>,
+    pub(crate) flow_control: bool,
+    nice: Option<i32>,
+    cpus: Vec<usize>,
+    env_clear: bool,
+    env_allow: Vec<&'a str>,
+    pub(crate) term: TermType,
+    stdin: Option<StdinSource>,
+    mock: Option<MockGame>,
+    connect: Option<GameIo>,
+    decoders: Decoders,
+    filters: Decoders,
+    rewards: Rewards,
+    stops: Stops,
+    adapter: Option<Adapter>,
+    // patterns given to stop_on which aren't valid regex
+    invalid_patterns: Vec<String>,
+    #[cfg(feature = "terminfo")]
+    terminfo: bool,
+}
+impl<'a> GameSetting<'a> {
+    /// Build GameSetting object with command name(like ```rogue```).
+    pub fn new(command_name: &str) -> Self {
+        GameSetting {
+            cmdname: String::from(command_name),
+            lines: 24,
+            columns: 80,
+            envs: Vec::new(),
+            args: Vec::new(),
+            log_info: LogInfo::default(),
+            timeout: Duration::from_millis(100),
+            settle: None,
+            pacing: Pacing::default(),
+            draw_type: DrawType::Null,
+            max_loop: 100,
+            transcript: None,
+            key_log: None,
+            dataset: None,
+            action_log: None,
+            results: None,
+            blank: b' ',
+            scrollback: 0,
+            terminal_events: false,
+            seed: 0,
+            setup: Vec::new(),
+            teardown: Vec::new(),
+            aux: Vec::new(),
+            restart: None,
+            watchdog: None,
+            meta: MetaEncoding::default(),
+            key_map: KeyMap::default(),
+            sanitizer: None,
+            flow_control: false,
+            nice: None,
+            cpus: Vec::new(),
+            env_clear: false,
+            env_allow: Vec::new(),
+            term: TermType::default(),
+            stdin: None,
+            mock: None,
+            connect: None,
+            decoders: Decoders::default(),
+            filters: Decoders::default(),
+            rewards: Rewards::default(),
+            stops: Stops::default(),
+            adapter: None,
+            invalid_patterns: Vec::new(),
+            #[cfg(feature = "terminfo")]
+            terminfo: false,
+        }
+    }
+    /// Build GameSetting object with fake game, instead of command.
+    /// # Example
+    /// ```
+    /// extern crate curses_game_wrapper as cgw;
+    /// use cgw::{ActionResult, GameSetting, MockGame, Reactor};
+    /// use std::time::Duration;
+    /// fn main() {
+    ///     struct QuitAI;
+    ///     impl Reactor for QuitAI {
+    ///         fn action(&mut self, _screen: ActionResult, _turn: usize) -> Option<Vec<u8>> {
+    ///             Some(b"q".to_vec())
+    ///         }
+    ///     }
+    ///     let game = MockGame::new(|keys: &[u8]| match keys {
+    ///         b"q" => None,
+    ///         _ => Some(b"\x1b[2J@".to_vec()),
+    ///     });
+    ///     let mut env = GameSetting::mock(game)
+    ///         .timeout(Duration::from_millis(10))
+    ///         .build()
+    ///         .unwrap();
+    ///     assert_eq!(env.play(&mut QuitAI).turns, 2);
+    /// }
+    /// ```
+    pub fn mock(game: MockGame) -> Self {
+        let mut setting = GameSetting::new("mock");
+        setting.mock = Some(game);
+        setting
+    }
+    /// Build GameSetting object playing the game through ```io```, instead of
+    /// spawning a command.
+    ///
+    /// Settings of the process(e.g. ```args```, ```env``` and ```nice```) are ignored,
+    /// but ```setup``` and ```aux_process``` run as usual, e.g. to start the harness.
+    /// # Example
+    /// ```no_run
+    /// extern crate curses_game_wrapper as cgw;
+    /// use cgw::{GameIo, GameSetting};
+    /// fn main() {
+    ///     let io = GameIo::UnixSocket("/tmp/rogue.sock".into());
+    ///     let game = GameSetting::connect(io).lines(24).columns(80).build().unwrap();
+    /// }
+    /// ```
+    pub fn connect(io: GameIo) -> Self {
+        let mut setting = GameSetting::new("connect");
+        setting.connect = Some(io);
+        setting
+    }
+    /// Set screen width of curses widow
+    pub fn columns(mut self, u: usize) -> Self {
+        self.columns = u;
+        self
+    }
+    /// Set screen height of curses window
+    pub fn lines(mut self, u: usize) -> Self {
+        self.lines = u;
+        self
+    }
+    /// Set terminal type(Default: ```TermType::Vt100```).
+    ///
+    /// It sets ```TERM``` of the game and makes the emulator behave as the terminal.
+    /// If you set ```TERM``` by ```env``` instead, terminal type is guessed from it.
+    pub fn term(mut self, t: TermType) -> Self {
+        self.term = t;
+        self
+    }
+    /// Set the byte to fill cells never drawn or cleared by the game(Default: b' ').
+    ///
+    /// If you set it to e.g. 0, you can distinguish unexplored cells from spaces
+    /// drawn by the game.
+    pub fn blank(mut self, b: u8) -> Self {
+        self.blank = b;
+        self
+    }
+    /// Keep at most ```depth``` lines scrolled off the top of the screen(Default: 0),
+    /// so that long messages aren't lost. See ```Reactor::on_scrollback```.
+    ///
+    /// The game can clear them by ```CSI 3 J```.
+    pub fn scrollback(mut self, depth: usize) -> Self {
+        self.scrollback = depth;
+        self
+    }
+    /// Pass events interpreted from the game output to
+    /// ```Reactor::on_terminal_event```(Default: false).
+    ///
+    /// It's off by default, since recording events slows down parsing a bit.
+    pub fn terminal_events(mut self, on: bool) -> Self {
+        self.terminal_events = on;
+        self
+    }
+    /// Add command line argument
+    ///
+    /// ```{episode}``` and ```{seed}``` in arguments and values of environmental variables
+    /// are replaced by the number of game and its seed, each time the game is spawned.
+    /// See ```seed``` for detail.
+    pub fn arg(mut self, s: &'a str) -> Self {
+        self.args.push(s);
+        self
+    }
+    /// Set environmental variable
+    ///
+    /// ```{episode}``` and ```{seed}``` in the value are replaced like ```arg```.
+    pub fn env(mut self, s: &'a str, t: &'a str) -> Self {
+        self.envs.push((s, t));
+        self
+    }
+    /// Don't inherit environmental variables of this process, except ones allowed by
+    /// ```env_allow```, so that the game runs the same way on any machine.
+    ///
+    /// ```LINES```, ```COLUMNS```, ```TERM``` and ones set by ```env``` are still set.
+    /// # Example
+    /// ```no_run
+    /// extern crate curses_game_wrapper as cgw;
+    /// use cgw::GameSetting;
+    /// fn main() {
+    ///     let gs = GameSetting::new("rogue")
+    ///         .env_clear()
+    ///         .env_allow("PATH")
+    ///         .env("ROGUEUSER", "EmptyAI");
+    /// }
+    /// ```
+    pub fn env_clear(mut self) -> Self {
+        self.env_clear = true;
+        self
+    }
+    /// Pass environmental variable of this process to the game, with ```env_clear```.
+    pub fn env_allow(mut self, name: &'a str) -> Self {
+        self.env_allow.push(name);
+        self
+    }
+    /// Set base seed used for ```{seed}``` in arguments(Default: 0).
+    ///
+    /// In n-th game(starting from 0), ```{seed}``` is replaced by ```seed + n```.
+    /// # Example
+    /// ```no_run
+    /// extern crate curses_game_wrapper as cgw;
+    /// use cgw::GameSetting;
+    /// fn main() {
+    ///     let gs = GameSetting::new("nethack")
+    ///         .env("NETHACKOPTIONS", "name:ai{episode}")
+    ///         .env("SEED", "{seed}")
+    ///         .seed(42);
+    /// }
+    /// ```
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+    /// Add shell command run before each game is spawned,
+    /// e.g. to delete old save files.
+    ///
+    /// ```{episode}``` and ```{seed}``` are replaced like ```arg```.
+    /// If the command fails, ```build``` returns ```CgwError::SetupFailed```, and later
+    /// games end at once(the AI receives ```GameEnded```) with the error kept as
+    /// ```GameEnv::error```.
+    pub fn setup_command(mut self, s: &'a str) -> Self {
+        self.setup.push(s);
+        self
+    }
+    /// Add shell command run after each game ends, e.g. to archive score files.
+    ///
+    /// ```{episode}``` and ```{seed}``` are replaced like ```arg```.
+    /// If the command fails, it's only logged.
+    pub fn teardown_command(mut self, s: &'a str) -> Self {
+        self.teardown.push(s);
+        self
+    }
+    /// Add shell command running in background while each game, e.g. a server
+    /// which the game connects to.
+    ///
+    /// It's spawned after setup commands, and the game is spawned ```startup```
+    /// later. It's terminated with its children when the game ends(also when the
+    /// game is restarted). It has environment variables set by ```env```, and
+    /// ```{episode}``` and ```{seed}``` are replaced like ```arg```.
+    /// If it exits before ```startup```, spawning the game fails with
+    /// ```CgwError::SetupFailed```, like ```setup_command```.
+    pub fn aux_process(mut self, s: &'a str, startup: Duration) -> Self {
+        self.aux.push((s, startup));
+        self
+    }
+    /// Set multiple command line arguments
+    pub fn args<I>(mut self, i: I) -> Self
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let v: Vec<_> = i.into_iter().collect();
+        self.args = v;
+        self
+    }
+    /// Set multiple environmental variables
+    pub fn envs<I>(mut self, i: I) -> Self
+    where
+        I: IntoIterator<Item = (&'a str, &'a str)>,
+    {
+        let v: Vec<_> = i.into_iter().collect();
+        self.envs = v;
+        self
+    }
+    /// Draw game on terminal(Default: off).
+    /// You hanve to set duration of drawing.
+    pub fn draw_on(mut self, d: Duration) -> Self {
+        self.draw_type = DrawType::Terminal(d);
+        self
+    }
+    /// Draw emulated screen with the log of parsed escape sequences and AI actions,
+    /// instead of raw game output. You have to set duration of drawing.
+    ///
+    /// It's a debugger for wrappers and AIs, so slower than ```draw_on```.
+    #[cfg(feature = "debug-console")]
+    pub fn debug_console(mut self, d: Duration) -> Self {
+        self.draw_type = DrawType::Console(d);
+        self
+    }
+    /// You can set debug file of this crate.
+    /// This is mainly for developper of this crate:)
+    ///
+    /// Without feature ```slog-logger```, it's ignored and all logs go to ```log``` crate.
+    pub fn debug_file(mut self, s: &str) -> Self {
+        self.log_info.fname = s.to_owned();
+        self
+    }
+    /// You can set debug level of this crate.
+    /// This is mainly for developper of this crate:)
+    pub fn debug_level(mut self, s: Severity) -> Self {
+        self.log_info.sev = s;
+        self
+    }
+    /// Use your own logger instead of the one made by ```debug_file```.
+    /// Then ```debug_file``` and ```debug_level``` are ignored.
+    #[cfg(feature = "slog-logger")]
+    pub fn logger(mut self, l: Logger) -> Self {
+        self.log_info.logger = Some(l);
+        self
+    }
+    /// You can set timeout to game output.
+    /// It's setted to 0.1s by default.
+    ///
+    /// If the game writes nothing in it, AI receives ```NotChanged```.
+    /// It's also used as ```settle``` window unless ```settle``` is set.
+    pub fn timeout(mut self, d: Duration) -> Self {
+        self.timeout = d;
+        self
+    }
+    /// After the game writes something, the screen is passed to AI when the game
+    /// writes nothing for ```d```(Default: same as ```timeout```).
+    ///
+    /// Short ```timeout``` with longer ```settle``` makes AI react to output quickly
+    /// without receiving half-drawn screens.
+    pub fn settle(mut self, d: Duration) -> Self {
+        self.settle = Some(d);
+        self
+    }
+    /// Set how fast games are played(Default: ```Pacing::Normal```).
+    ///
+    /// It can be switched without touching other settings, e.g. fast forward
+    /// while training and normal for debugging.
+    pub fn pacing(mut self, pacing: Pacing) -> Self {
+        self.pacing = pacing;
+        self
+    }
+    /// You can set max_loop of game.
+    /// It's setted to 100 by default.
+    pub fn max_loop(mut self, t: usize) -> Self {
+        self.max_loop = t;
+        self
+    }
+    /// Record all bytes read from and written to the game with timestamps.
+    /// You can read the file by ```read_transcript```.
+    pub fn transcript_file(mut self, s: &str) -> Self {
+        self.transcript = Some(s.to_owned());
+        self
+    }
+    /// Record bytes written to the game with timestamps, to reproduce games of
+    /// deterministic games(e.g. with fixed ```seed```) by ```GameHandle::replay_keys```.
+    /// You can read the file by ```read_key_log```.
+    ///
+    /// It's much smaller than ```transcript_file```, and flushed for each record
+    /// so that it survives crashes.
+    pub fn key_log_file(mut self, s: &str) -> Self {
+        self.key_log = Some(s.to_owned());
+        self
+    }
+    /// Record each turn delivered to the AI(timestamp, screen, action and reward)
+    /// in one file, as dataset for supervised learning or offline RL.
+    /// You can read the file by ```read_dataset```.
+    ///
+    /// Screens are stored as differences from the last screen, and the file is
+    /// flushed for each turn.
+    pub fn dataset_file(mut self, s: &str) -> Self {
+        self.dataset = Some(s.to_owned());
+        self
+    }
+    /// Write one record per turn(turn number, action, bell count and timings)
+    /// for offline analysis of your AI.
+    pub fn action_log(mut self, s: &str, format: ActionLogFormat) -> Self {
+        self.action_log = Some((s.to_owned(), format));
+        self
+    }
+    /// Append the summary of each game(```Metrics``` with the episode number
+    /// and ```config_hash```) to the file, to keep histories of experiments.
+    ///
+    /// The file is created if it doesn't exist.
+    pub fn results_file(mut self, s: &str, format: ResultFormat) -> Self {
+        self.results = Some((s.to_owned(), format));
+        self
+    }
+    /// Returns hash of the settings which affect the game(command, arguments,
+    /// environment variables, screen size, timings, ```max_loop``` and seed),
+    /// to group results of the same configuration.
+    pub fn config_hash(&self) -> String {
+        let config = format!(
+            "{:?}",
+            (
+                &self.cmdname,
+                &self.args,
+                &self.envs,
+                (self.lines, self.columns, self.term, self.blank),
+                (self.timeout, self.settle, self.max_loop, self.seed),
+            )
+        );
+        // FNV-1a, which is stable unlike DefaultHasher
+        let hash = config.bytes().fold(0xcbf2_9ce4_8422_2325u64, |h, b| {
+            (h ^ u64::from(b)).wrapping_mul(0x100_0000_01b3)
+        });
+        format!("{:016x}", hash)
+    }
+    /// Set niceness of the game process, so that many games running in parallel
+    /// don't starve your training process.
+    pub fn nice(mut self, nice: i32) -> Self {
+        self.nice = Some(nice);
+        self
+    }
+    /// Run the game only on given CPUs. It works only on Linux.
+    pub fn cpu_affinity<I: IntoIterator<Item = usize>>(mut self, cpus: I) -> Self {
+        self.cpus = cpus.into_iter().collect();
+        self
+    }
+    /// Respawn the game when it exits before ```min_turns``` turns
+    /// (e.g. segfault or misconfiguration), up to ```max_restarts``` times in a game.
+    ///
+    /// Before n-th restart it waits ```backoff * 2^(n - 1)```.
+    /// The AI doesn't receive ```GameEnded``` for crashed processes and
+    /// the number of restarts is reported as ```Metrics::restarts```.
+    pub fn restart_on_crash(
+        mut self,
+        max_restarts: usize,
+        min_turns: usize,
+        backoff: Duration,
+    ) -> Self {
+        self.restart = Some(RestartPolicy {
+            max_restarts: max_restarts,
+            min_turns: min_turns,
+            backoff: backoff,
+        });
+        self
+    }
+    /// Take ```action``` when the game produces no output for ```period```,
+    /// instead of wasting remaining turns on ```NotChanged```.
+    ///
+    /// Inputs which don't make the game output anything are ignored,
+    /// so sending keys to a hung game doesn't reset the timer.
+    /// # Example
+    /// ```no_run
+    /// extern crate curses_game_wrapper as cgw;
+    /// use cgw::{GameSetting, WatchdogAction};
+    /// use std::time::Duration;
+    /// fn main() {
+    ///     let gs = GameSetting::new("rogue")
+    ///         .watchdog(Duration::from_secs(5), WatchdogAction::Send(vec![0x1b]));
+    /// }
+    /// ```
+    pub fn watchdog(mut self, period: Duration, action: WatchdogAction) -> Self {
+        self.watchdog = Some((period, action));
+        self
+    }
+    /// Plug in knowledge of the game, setting ```game_over``` and ```meta_encoding```
+    /// by ```adapter```. You can get it back by ```GameEnv::adapter```.
+    ///
+    /// Builder methods called later override them.
+    /// # Example
+    /// ```no_run
+    /// extern crate curses_game_wrapper as cgw;
+    /// use cgw::{ActionResult, GameSetting, Reactor};
+    /// use cgw::games::GameAdapter;
+    /// use cgw::games::nethack::NetHack;
+    /// fn main() {
+    ///     struct StatusAI(NetHack);
+    ///     impl Reactor for StatusAI {
+    ///         fn action(&mut self, screen: ActionResult, _turn: usize) -> Option<Vec<u8>> {
+    ///             if let ActionResult::Changed(screen) = screen {
+    ///                 println!("{:?}", self.0.parse_status(&screen));
+    ///             }
+    ///             None
+    ///         }
+    ///     }
+    ///     let nethack = NetHack::new();
+    ///     let mut game = GameSetting::new("nethack").adapter(nethack).build().unwrap();
+    ///     game.play(&mut StatusAI(nethack));
+    /// }
+    /// ```
+    pub fn adapter<A: GameAdapter + 'static>(mut self, adapter: A) -> Self {
+        self.meta = adapter.meta_encoding();
+        self.stops.game_over = Some(adapter.game_over());
+        self.adapter = Some(Adapter(Arc::new(adapter)));
+        self
+    }
+    /// Set how the game receives Alt-modified keys(Default: ```MetaEncoding::Esc```).
+    /// It's used by ```GameHandle::send_meta```.
+    pub fn meta_encoding(mut self, encoding: MetaEncoding) -> Self {
+        self.meta = encoding;
+        self
+    }
+    /// Translate keys sent by the AI by ```map``` before they're written to the
+    /// game, e.g. to play a game with other key bindings by the same AI.
+    ///
+    /// Keys sent by ```GameHandle::send``` are translated too, but replies to
+    /// queries of the game and keys of ```GameHandle::send_key``` etc. aren't.
+    pub fn key_map(mut self, map: KeyMap) -> Self {
+        self.key_map = map;
+        self
+    }
+    /// Check keys sent by the AI by ```sanitizer``` before they're written to the
+    /// game, after ```key_map```.
+    ///
+    /// Rejected keys aren't sent and counted as ```Metrics::rejected```, and
+    /// ```GameHandle::send``` returns ```InvalidInput``` for them.
+    pub fn sanitize_input(mut self, sanitizer: InputSanitizer) -> Self {
+        self.sanitizer = Some(sanitizer);
+        self
+    }
+    /// Honor software flow control(Default: false).
+    ///
+    /// If the game writes XOFF(```Ctrl-S```), keys are held until it writes
+    /// XON(```Ctrl-Q```), and recorded to the transcript and the key log when
+    /// they're sent. Otherwise they're passed to ```Reactor::unhandled```.
+    pub fn flow_control(mut self, b: bool) -> Self {
+        self.flow_control = b;
+        self
+    }
+    /// Encode special keys by the terminfo entry of ```TERM```, instead of
+    /// built-in sequences. It's used by ```GameEnv::key``` and ```GameHandle::send_key```.
+    ///
+    /// If the entry isn't found, built-in sequences are used.
+    #[cfg(feature = "terminfo")]
+    pub fn terminfo_keys(mut self) -> Self {
+        self.terminfo = true;
+        self
+    }
+    /// Give the game precomputed input from a file or bytes, for batch-style runs.
+    ///
+    /// The screens are still emulated and recorded, but keys AI returns are
+    /// not sent to the game.
+    /// # Example
+    /// ```no_run
+    /// extern crate curses_game_wrapper as cgw;
+    /// use cgw::{GameSetting, StdinSource};
+    /// use std::path::Path;
+    /// fn main() {
+    ///     let gs = GameSetting::new("rogue").stdin_from(Path::new("keys.txt"));
+    ///     let gs = GameSetting::new("rogue").stdin_from(&b"hjkl"[..]);
+    ///     let input = StdinSource::from_reader(std::io::stdin()).unwrap();
+    ///     let gs = GameSetting::new("rogue").stdin_from(input);
+    /// }
+    /// ```
+    pub fn stdin_from<S: Into<StdinSource>>(mut self, source: S) -> Self {
+        self.stdin = Some(source.into());
+        self
+    }
+    // check the setting before creating files and spawning the game
+    fn validate(&self) -> Result<(), CgwError> {
+        let max = usize::from(u16::max_value());
+        for &(name, n) in &[("lines", self.lines), ("columns", self.columns)] {
+            if n == 0 || n > max {
+                return Err(CgwError::InvalidSetting(format!(
+                    "{} must be in 1..={}, but {}",
+                    name, max, n
+                )));
+            }
+        }
+        let path = match self.envs.iter().rev().find(|&&(k, _)| k == "PATH") {
+            Some(&(_, path)) => Some(path.into()),
+            None => env::var_os("PATH"),
+        };
+        if let Some(e) = self.invalid_patterns.first() {
+            return Err(CgwError::InvalidSetting(e.clone()));
+        }
+        let spawns = self.mock.is_none() && self.connect.is_none();
+        if spawns && !find_command(&self.cmdname, path) {
+            return Err(CgwError::CommandNotFound(self.cmdname.clone()));
+        }
+        if !self.log_info.fname.is_empty() {
+            if let Err(why) = OpenOptions::new()
+                .append(true)
+                .create(true)
+                .open(&self.log_info.fname)
+            {
+                let msg = format!("couldn't open debug file {}", self.log_info.fname);
+                return Err(CgwError::Io(msg, why));
+            }
+        }
+        Ok(())
+    }
+    /// Add post-processor of screens, which runs before screens reach the AI.
+    /// Decoders run in the order they are added.
+    /// # Example
+    /// ```no_run
+    /// extern crate curses_game_wrapper as cgw;
+    /// use cgw::{AsciiGraphics, GameSetting, MaskLines, StripAttributes};
+    /// fn main() {
+    ///     let gs = GameSetting::new("nethack")
+    ///         .decoder(StripAttributes)
+    ///         .decoder(AsciiGraphics::default())
+    ///         // hide status lines
+    ///         .decoder(MaskLines::last(2));
+    /// }
+    /// ```
+    pub fn decoder<D: ScreenDecoder + 'static>(mut self, d: D) -> Self {
+        self.decoders.0.push(Arc::new(d));
+        self
+    }
+    /// Add filter of frames, which runs on every frame read from the game
+    /// before frames are coalesced and delivered.
+    ///
+    /// Unlike ```decoder```, a frame equal to the last delivered one after
+    /// filters is seen as ```NotChanged```. So you can keep e.g. a clock
+    /// updated every second from waking up the AI, by masking it.
+    /// Filters run in the order they are added, before decoders.
+    /// # Example
+    /// ```no_run
+    /// extern crate curses_game_wrapper as cgw;
+    /// use cgw::{GameSetting, MaskLines};
+    /// fn main() {
+    ///     // hide the clock at the top right corner
+    ///     let gs = GameSetting::new("nethack").filter(MaskLines::new(0..1).columns(72..80));
+    /// }
+    /// ```
+    pub fn filter<D: ScreenDecoder + 'static>(mut self, d: D) -> Self {
+        self.filters.0.push(Arc::new(d));
+        self
+    }
+    /// Add reward function, called for every frame delivered to the AI.
+    /// Rewards of all functions are summed.
+    /// # Example
+    /// ```no_run
+    /// extern crate curses_game_wrapper as cgw;
+    /// use cgw::{CounterDelta, GameSetting, Screen};
+    /// fn main() {
+    ///     let gs = GameSetting::new("rogue")
+    ///         .reward(CounterDelta::new("Gold:"))
+    ///         // penalty for each frame
+    ///         .reward(|_: Option<&Screen>, _: &Screen| -0.01);
+    /// }
+    /// ```
+    pub fn reward<F: RewardFn + 'static>(mut self, f: F) -> Self {
+        self.rewards.0.push(Arc::new(f));
+        self
+    }
+    /// End the game when ```cond``` is met by a frame delivered to the AI,
+    /// instead of waiting for ```max_loop``` on e.g. the tombstone.
+    ///
+    /// The AI receives the frame and then ```GameEnded```, and ```reason``` is
+    /// reported as ```Metrics::stop_reason```.
+    /// Conditions are checked in the order they are added.
+    /// # Example
+    /// ```no_run
+    /// extern crate curses_game_wrapper as cgw;
+    /// use cgw::{GameSetting, Screen};
+    /// fn main() {
+    ///     let gs = GameSetting::new("rogue")
+    ///         .stop_when("no hp", |s: &Screen| s.trimmed_lines().any(|l| l.contains("Hp: 0(")));
+    /// }
+    /// ```
+    pub fn stop_when<C: StopCondition + 'static>(mut self, reason: &str, cond: C) -> Self {
+        self.stops.conds.push((reason.to_owned(), Arc::new(cond)));
+        self
+    }
+    /// Same as ```stop_when``` with regex ```pattern```, which is matched against
+    /// ```Screen::to_trimmed_string```.
+    ///
+    /// If the pattern is invalid, ```build``` returns error.
+    /// # Example
+    /// ```no_run
+    /// extern crate curses_game_wrapper as cgw;
+    /// use cgw::GameSetting;
+    /// fn main() {
+    ///     let gs = GameSetting::new("nethack")
+    ///         .stop_on("died", "You die")
+    ///         .stop_on("died", "Do you want your possessions identified");
+    /// }
+    /// ```
+    pub fn stop_on(mut self, reason: &str, pattern: &str) -> Self {
+        match Regex::new(pattern) {
+            Ok(re) => self.stops.conds.push((reason.to_owned(), Arc::new(re))),
+            Err(e) => self
+                .invalid_patterns
+                .push(format!("invalid pattern {}: {}", pattern, e)),
+        }
+        self
+    }
+    /// End the game when ```detector``` finds the game over screen, reporting
+    /// the outcome as ```Metrics::outcome``` and ```Metrics::stop_reason```,
+    /// and the result parsed from the last frames as ```Metrics::result```.
+    ///
+    /// It's checked before conditions of ```stop_when```.
+    /// # Example
+    /// ```no_run
+    /// extern crate curses_game_wrapper as cgw;
+    /// use cgw::GameSetting;
+    /// use cgw::games::rogue;
+    /// fn main() {
+    ///     let gs = GameSetting::new("rogue").game_over(rogue::game_over());
+    /// }
+    /// ```
+    pub fn game_over(mut self, detector: GameOver) -> Self {
+        self.stops.game_over = Some(detector);
+        self
+    }
+    /// Consume game setting and build GameEnv, spawning the game.
+    ///
+    /// Returns error if the setting is invalid, the command is not found,
+    /// files for logs and recordings can't be created, or the game can't be spawned.
+    pub fn build(mut self) -> Result<GameEnv, CgwError> {
+        self.validate()?;
+        // keep TERM and emulation consistent
+        if let Some(&(_, name)) = self.envs.iter().rev().find(|&&(k, _)| k == "TERM") {
+            self.term = TermType::from_name(name).unwrap_or(TermType::Xterm);
+        }
+        let dat = TermData::from_setting(&self);
+        let t = self.timeout;
+        let settle = self.settle.unwrap_or(t);
+        let m = self.max_loop;
+        let d = self.draw_type;
+        let transcript = match self.transcript {
+            Some(ref fname) => {
+                let msg = || format!("couldn't create transcript file {}", fname);
+                let file = File::create(fname).map_err(|e| CgwError::Io(msg(), e))?;
+                let writer = TranscriptWriter::new(BufWriter::new(file))
+                    .map_err(|e| CgwError::Io(msg(), e))?;
+                Some(writer)
+            }
+            None => None,
+        };
+        let key_log = match self.key_log {
+            Some(ref fname) => {
+                let msg = || format!("couldn't create key log file {}", fname);
+                let file = File::create(fname).map_err(|e| CgwError::Io(msg(), e))?;
+                let writer = KeyLogWriter::new(BufWriter::new(file))
+                    .map_err(|e| CgwError::Io(msg(), e))?;
+                Some(writer)
+            }
+            None => None,
+        };
+        let dataset = match self.dataset {
+            Some(ref fname) => {
+                let msg = || format!("couldn't create dataset file {}", fname);
+                let file = File::create(fname).map_err(|e| CgwError::Io(msg(), e))?;
+                let writer = DatasetWriter::new(BufWriter::new(file))
+                    .map_err(|e| CgwError::Io(msg(), e))?;
+                Some(writer)
+            }
+            None => None,
+        };
+        let action_log = match self.action_log {
+            Some((ref fname, format)) => {
+                let msg = || format!("couldn't create action log file {}", fname);
+                let file = File::create(fname).map_err(|e| CgwError::Io(msg(), e))?;
+                let logger = ActionLogger::new(BufWriter::new(file), format)
+                    .map_err(|e| CgwError::Io(msg(), e))?;
+                Some(logger)
+            }
+            None => None,
+        };
+        let results = match self.results {
+            Some((ref fname, format)) => {
+                let sink = ResultSink::open(fname, format).map_err(|e| {
+                    CgwError::Io(format!("couldn't open results file {}", fname), e)
+                })?;
+                Some(sink)
+            }
+            None => None,
+        };
+        let config_hash = self.config_hash();
+        let proc_setting = ProcSetting {
+            cmdname: self.cmdname.clone(),
+            args: self.args.iter().map(|s| s.to_string()).collect(),
+            envs: self.envs
+                .iter()
+                .map(|&(k, v)| (k.to_owned(), v.to_owned()))
+                .collect(),
+            lines: self.lines,
+            columns: self.columns,
+            seed: self.seed,
+            setup: self.setup.iter().map(|s| s.to_string()).collect(),
+            teardown: self.teardown.iter().map(|s| s.to_string()).collect(),
+            aux: self.aux.iter().map(|&(s, d)| (s.to_owned(), d)).collect(),
+            pause: PauseHandle::default(),
+            nice: self.nice,
+            cpus: self.cpus.clone(),
+            env_clear: self.env_clear,
+            env_allow: self.env_allow.iter().map(|s| s.to_string()).collect(),
+            term: self.term,
+            stdin: self.stdin.clone(),
+            mock: self.mock.clone(),
+            connect: self.connect.clone(),
+            logger: dat.logger.clone(),
+        };
+        let process = ProcHandler::spawn(&proc_setting, 0)?;
+        Ok(GameEnv {
+            process: Some(process),
+            proc_setting: proc_setting,
+            episode: 0,
+            metrics: Metrics::default(),
+            error: None,
+            term_data: dat,
+            timeout: t,
+            settle: settle,
+            pacing: self.pacing,
+            adaptive: match self.pacing {
+                Pacing::FastForward { adaptive: true } => Some(AdaptiveWait::default()),
+                _ => None,
+            },
+            decoders: self.decoders.clone(),
+            filters: self.filters.clone(),
+            rewards: self.rewards.clone(),
+            stops: self.stops.clone(),
+            adapter: self.adapter.as_ref().map(|a| Arc::clone(&a.0)),
+            max_loop: m,
+            draw_type: d,
+            transcript: transcript,
+            key_log: key_log,
+            dataset: dataset,
+            restore: None,
+            action_log: action_log,
+            results: results,
+            config_hash: config_hash,
+            restart: self.restart,
+            watchdog: self.watchdog,
+            meta: self.meta,
+            key_map: self.key_map.clone(),
+            sanitizer: self.sanitizer.clone(),
+            #[cfg(feature = "terminfo")]
+            keys: if self.terminfo {
+                TermKeys::load(self.term).ok()
+            } else {
+                None
+            },
+        })
+    }
+}
+
+/// This is for spawning curses game as child process.
+///
+/// It stores inputs from the game and sends result to AI when its input handler timeouts.
+///
+/// The only usage is
+/// # Example
+/// ```no_run
+/// extern crate curses_game_wrapper as cgw;
+/// use cgw::{Reactor, ActionResult, AsciiChar, GameSetting};
+/// use std::time::Duration;
+/// fn main() {
+///     struct EmptyAI;
+///     impl Reactor for EmptyAI {
+///         fn action(&mut self, _screen: ActionResult, _turn: usize) -> Option<Vec<u8>> {
+///              None
+///         }
+///     }
+///     let gs = GameSetting::new("rogue")
+///         .env("ROGUEUSER", "EmptyAI")
+///         .lines(24)
+///         .columns(80)
+///         .debug_file("debug.txt")
+///         .max_loop(10)
+///         .draw_on(Duration::from_millis(200));
+///     let mut game = gs.build().unwrap();
+///     let mut ai = EmptyAI { };
+///     game.play(&mut ai);
+/// }
+/// ```
+pub struct GameEnv {
+    // process spawned for the next game
+    process: Option<ProcHandler>,
+    proc_setting: ProcSetting,
+    // number of games already played
+    episode: usize,
+    metrics: Metrics,
+    // failure which ended the last game
+    error: Option<CgwError>,
+    term_data: TermData,
+    timeout: Duration,
+    // quiet period to consider the screen settled
+    settle: Duration,
+    pacing: Pacing,
+    // shrinks waits when Pacing::FastForward is adaptive
+    adaptive: Option<AdaptiveWait>,
+    decoders: Decoders,
+    filters: Decoders,
+    rewards: Rewards,
+    stops: Stops,
+    adapter: Option<Arc<dyn GameAdapter>>,
+    max_loop: usize,
+    draw_type: DrawType,
+    transcript: Option<TranscriptWriter<BufWriter<File>>>,
+    key_log: Option<KeyLogWriter<BufWriter<File>>>,
+    // keys replayed at the start of the next game, and whether to verify screens
+    restore: Option<(Vec<KeyRecord>, bool)>,
+    dataset: Option<DatasetWriter<BufWriter<File>>>,
+    action_log: Option<ActionLogger<BufWriter<File>>>,
+    results: Option<ResultSink>,
+    config_hash: String,
+    restart: Option<RestartPolicy>,
+    watchdog: Option<(Duration, WatchdogAction)>,
+    meta: MetaEncoding,
+    key_map: KeyMap,
+    sanitizer: Option<InputSanitizer>,
+    #[cfg(feature = "terminfo")]
+    keys: Option<TermKeys>,
+}
+impl GameEnv {
+    /// Start process and run AI.
+    ///
+    /// Returns runtime statistics of the game.
+    /// You can call it again to play next game, which spawns new process.
+    pub fn play<R: Reactor>(&mut self, ai: &mut R) -> Metrics {
+        match self.try_play(ai) {
+            Ok(metrics) => metrics,
+            Err(e) => match e {},
+        }
+    }
+    /// Start process and run fallible AI.
+    ///
+    /// If ```TryReactor::try_action``` fails, kills the game and returns the error.
+    pub fn try_play<R: TryReactor>(&mut self, ai: &mut R) -> Result<Metrics, R::Error> {
+        use std::sync::mpsc::RecvTimeoutError;
+        macro_rules! send_or {
+            ($to:expr, $handle:expr) => (
+                if let Err(why) = $to.send_bytes($handle) {
+                    debug!(
+                        self.term_data.logger,
+                        concat!("can't send to ", stringify!($to), ": {}"),
+                        why.description()
+                    );
+                }
+            )
+        }
+        macro_rules! record {
+            ($kind:expr, $bytes:expr) => (
+                if let Some(ref mut transcript) = self.transcript {
+                    if let Err(why) = transcript.record($kind, $bytes) {
+                        debug!(self.term_data.logger, "can't write transcript: {}", why);
+                    }
+                }
+            )
+        }
+        let mut process = match self.process.take() {
+            Some(p) => p,
+            None => match ProcHandler::spawn(&self.proc_setting, self.episode) {
+                Ok(p) => p,
+                Err(why) => return self.spawn_failed(ai, why),
+            },
+        };
+        self.term_data.reset();
+        self.start_key_log();
+        self.start_dataset();
+        let mut proc_handle = process.run();
+        let draw_type = if self.pacing.is_fast_forward() {
+            DrawType::Null
+        } else {
+            self.draw_type
+        };
+        let mut viewer: Box<dyn GameViewer> = match draw_type {
+            DrawType::Terminal(d) => Box::new(TerminalViewer::new(d)),
+            #[cfg(feature = "debug-console")]
+            DrawType::Console(d) => {
+                let s = &self.proc_setting;
+                Box::new(console::ConsoleViewer::new(d, s.lines, s.columns, s.term))
+            }
+            DrawType::Null => Box::new(EmptyViewer {}),
+        };
+        let viewer_handle = viewer.run();
+        let join_timeout = Duration::from_millis(JOIN_TIMEOUT_MS);
+        let mut stdin = async_stdin().bytes();
+        let mut ctrl_c = false;
+
+        let mut parser = Parser::new();
+        let mut proc_dead = false;
+        let mut stored_map = None;
+        // last frame delivered to the AI, kept for reward functions
+        let mut prev_screen: Option<Screen> = None;
+        // reward of the frame being delivered, recorded in the dataset
+        let mut frame_reward = None;
+        // last frames delivered to the AI, kept for GameOver
+        let mut last_frames = Vec::new();
+        // last frame delivered to the AI before decoders, if filters are set
+        let mut last_filtered: Option<Screen> = None;
+        let mut cnt = 0;
+        let mut metrics = Metrics::default();
+        let mut prev_metrics = Metrics::default();
+        let mut error = None;
+        // failure of the reader or the viewer, which ends the game
+        let mut failure = None;
+        // turn when current process was spawned
+        let mut spawned_turn = 0;
+        // when the game wrote something last time
+        let mut last_output = Instant::now();
+        // consecutive NotChanged turns, and when Changed was delivered last time
+        let mut idle_turns = 0;
+        let mut last_change = Instant::now();
+        // terminal modes passed to on_term_mode last time
+        let mut last_mode = None;
+        // keys held while the game stopped us by XOFF
+        let mut held = Vec::new();
+        // when keys were written with no output after them yet, and the latency
+        // measured since last turn
+        let mut sent_at: Option<Instant> = None;
+        let mut latency = None;
+        let (mut frame_limiter, key_delay) = match self.pacing {
+            Pacing::RealTime { fps, key_delay } => (Some(FrameLimiter::new(fps)), Some(key_delay)),
+            _ => (None, None),
+        };
+        // bring the game to the state recorded for GameEnv::restore
+        if let Some((records, verify)) = self.restore.take() {
+            match self.fast_replay(&mut process, &mut parser, &records, verify) {
+                Ok(()) => stored_map = Some(self.filters.decode(self.term_data.ret_screen())),
+                Err(why) => failure = Some(why),
+            }
+            last_output = Instant::now();
+        }
+        let start = Instant::now();
+        while failure.is_none() && cnt < self.max_loop {
+            // freeze turns while paused
+            if self.proc_setting.pause.is_paused() {
+                thread::sleep(self.timeout);
+                last_output = Instant::now();
+                match stdin.next() {
+                    Some(Ok(CTRL_C)) => {
+                        ctrl_c = true;
+                        break;
+                    }
+                    Some(Ok(CTRL_Q)) => if let Err(why) = self.resume() {
+                        warn!(self.term_data.logger, "couldn't resume game: {}", why);
+                    },
+                    _ => {}
+                }
+                continue;
+            }
+            macro_rules! do_action {
+                ($act:expr) => {{
+                    cnt += 1;
+                    let act = $act;
+                    let result = act.name();
+                    if let Some(ref mut limiter) = frame_limiter {
+                        limiter.wait();
+                    }
+                    let reward = frame_reward.take();
+                    let observed = self.dataset.as_ref().map(|_| act.clone());
+                    let action_start = Instant::now();
+                    let action = match ai.try_action(act, cnt) {
+                        Ok(a) => a,
+                        Err(e) => {
+                            debug!(self.term_data.logger, "AI failed in turn {}", cnt);
+                            error = Some(e);
+                            break;
+                        }
+                    };
+                    let action_time = action_start.elapsed();
+                    metrics.turns += 1;
+                    metrics.action_time += action_time;
+                    let outgoing = action.as_ref().and_then(|keys| {
+                        let res = self.outgoing(keys);
+                        if res.is_none() {
+                            let logger = &self.term_data.logger;
+                            warn!(logger, "rejected keys {:?} in turn {}", keys, cnt);
+                            metrics.rejected += 1;
+                        }
+                        res
+                    });
+                    if let Some(ref bytes) = outgoing {
+                        if sent_at.is_none() && !bytes.is_empty() {
+                            sent_at = Some(Instant::now());
+                        }
+                        viewer.send_action(cnt, bytes);
+                        if !self.term_data.xoff() {
+                            metrics.bytes_written += bytes.len();
+                            record!(IoKind::Write, bytes);
+                            log_keys(&mut self.key_log, &self.term_data, false, bytes);
+                        }
+                        match key_delay {
+                            // recorded when they're sent
+                            _ if self.term_data.xoff() => held.extend_from_slice(bytes),
+                            Some(delay) => {
+                                for (i, key) in split_keys(bytes).into_iter().enumerate() {
+                                    if i > 0 {
+                                        thread::sleep(delay);
+                                    }
+                                    send_or!(process, key);
+                                }
+                            }
+                            None => send_or!(process, bytes),
+                        }
+                    }
+                    let bells = self.term_data.take_bells();
+                    ai.on_turn_stats(metrics.turn_stats(&prev_metrics, bells, latency.take()));
+                    prev_metrics = metrics.clone();
+                    if let Some(ref mut logger) = self.action_log {
+                        let rec = TurnRecord {
+                            turn: cnt,
+                            result: result,
+                            action: action.as_ref().map(|a| &a[..]),
+                            bells: bells,
+                            elapsed: start.elapsed(),
+                            action_time: action_time,
+                        };
+                        if let Err(why) = logger.record(&rec) {
+                            debug!(self.term_data.logger, "can't write action log: {}", why);
+                        }
+                    }
+                    if let (Some(dataset), Some(observed)) = (self.dataset.as_mut(), observed) {
+                        let action = action.as_ref().map(|a| &a[..]);
+                        if let Err(why) = dataset.record(cnt, &observed, action, reward) {
+                            debug!(self.term_data.logger, "can't write dataset: {}", why);
+                        }
+                    }
+                }}
+            }
+            let wait_start = Instant::now();
+            // wait shorter once the game started drawing
+            let wait = if stored_map.is_some() {
+                self.settle
+            } else {
+                self.timeout
+            };
+            let wait = self.adaptive.as_ref().map_or(wait, |a| a.wait(wait));
+            let received = process.rx.recv_timeout(wait);
+            metrics.wait_time += wait_start.elapsed();
+            let action_res = match received {
+                Ok(rec) => match rec {
+                    Handle::Error(why) => {
+                        warn!(self.term_data.logger, "couldn't read the game output: {}", why);
+                        failure = Some(CgwError::ChildIo(why));
+                        break;
+                    }
+                    Handle::Zero => {
+                        debug!(self.term_data.logger, "read zero bytes");
+                        let restarts = metrics.restarts;
+                        let restart = self.restart.filter(|p| {
+                            restarts < p.max_restarts && cnt - spawned_turn < p.min_turns
+                        });
+                        if let Some(policy) = restart {
+                            let wait = policy.backoff * (1u32 << restarts.min(16));
+                            warn!(
+                                self.term_data.logger,
+                                "game crashed in turn {}, restarting after {:?}", cnt, wait
+                            );
+                            if !self.pacing.is_fast_forward() {
+                                thread::sleep(wait);
+                            }
+                            let new_process =
+                                match ProcHandler::spawn(&self.proc_setting, self.episode) {
+                                    Ok(p) => p,
+                                    Err(why) => {
+                                        failure = Some(why);
+                                        break;
+                                    }
+                                };
+                            drop(mem::replace(&mut process, new_process));
+                            let old_handle = mem::replace(&mut proc_handle, process.run());
+                            if let Some(Err(_)) = old_handle.join_timeout(join_timeout) {
+                                warn!(self.term_data.logger, "reader thread panicked");
+                            }
+                            self.term_data.reset();
+                            self.start_key_log();
+                            self.start_dataset();
+                            parser = Parser::new();
+                            stored_map = None;
+                            prev_screen = None;
+                            last_frames.clear();
+                            last_filtered = None;
+                            spawned_turn = cnt;
+                            last_output = Instant::now();
+                            idle_turns = 0;
+                            last_change = Instant::now();
+                            last_mode = None;
+                            held.clear();
+                            sent_at = None;
+                            metrics.restarts += 1;
+                            continue;
+                        }
+                        send_or!(viewer, Handle::Zero);
+                        proc_dead = true;
+                        ActionResult::GameEnded
+                    }
+                    Handle::Valid(ref r) => {
+                        record!(IoKind::Read, r);
+                        send_or!(viewer, Handle::Valid(r));
+                        if let Some(ref mut adaptive) = self.adaptive {
+                            adaptive.observe(wait_start.elapsed());
+                        }
+                        last_output = Instant::now();
+                        if let Some(t) = sent_at.take() {
+                            latency = Some(t.elapsed());
+                        }
+                        metrics.bytes_read += r.len();
+                        let parse_start = Instant::now();
+                        for c in r {
+                            parser.advance(&mut self.term_data, *c);
+                        }
+                        metrics.parse_time += parse_start.elapsed();
+                        for seq in self.term_data.take_unhandled() {
+                            ai.unhandled(&seq);
+                        }
+                        for event in self.term_data.take_events() {
+                            ai.on_terminal_event(&event);
+                        }
+                        let responses = self.term_data.take_responses();
+                        if !responses.is_empty() {
+                            metrics.bytes_written += responses.len();
+                            record!(IoKind::Write, &responses);
+                            log_keys(&mut self.key_log, &self.term_data, true, &responses);
+                            send_or!(process, &responses);
+                        }
+                        if !held.is_empty() && !self.term_data.xoff() {
+                            metrics.bytes_written += held.len();
+                            record!(IoKind::Write, &held);
+                            log_keys(&mut self.key_log, &self.term_data, false, &held);
+                            send_or!(process, &held);
+                            held.clear();
+                        }
+                        ActionResult::Changed(self.filters.decode(self.term_data.ret_screen()))
+                    }
+                },
+                Err(err) => match err {
+                    RecvTimeoutError::Timeout => ActionResult::NotChanged,
+                    RecvTimeoutError::Disconnected => {
+                        failure = Some(reader_stopped());
+                        break;
+                    }
+                },
+            };
+            trace!(self.term_data.logger, "{:?}, turn: {}", action_res, cnt);
+            match action_res {
+                ActionResult::GameEnded => do_action!(ActionResult::GameEnded),
+                // store inputs until timeout occurs
+                ActionResult::Changed(map) => stored_map = Some(map),
+                ActionResult::NotChanged => if let Some(map) = stored_map
+                    .take()
+                    .filter(|map| self.filters.is_empty() || last_filtered.as_ref() != Some(map))
+                {
+                    if !self.filters.is_empty() {
+                        last_filtered = Some(map.clone());
+                    }
+                    metrics.frames += 1;
+                    let screen = self.decoders.decode(map);
+                    if !self.rewards.is_empty() {
+                        let reward = self.rewards.reward(prev_screen.as_ref(), &screen);
+                        metrics.reward += reward;
+                        ai.on_reward(reward);
+                        frame_reward = Some(reward);
+                        prev_screen = Some(screen.clone());
+                    }
+                    self.stops.keep(&mut last_frames, &screen);
+                    let stop = self.stops.check(&screen);
+                    idle_turns = 0;
+                    last_change = Instant::now();
+                    let mode = self.term_data.mode();
+                    if last_mode != Some(mode) {
+                        ai.on_term_mode(mode);
+                        last_mode = Some(mode);
+                    }
+                    let scrolled = self.term_data.take_scrolled();
+                    if !scrolled.is_empty() {
+                        ai.on_scrollback(&scrolled);
+                    }
+                    do_action!(ActionResult::Changed(screen));
+                    if let Some((reason, outcome)) = stop {
+                        debug!(self.term_data.logger, "stopped by {} in turn {}", reason, cnt);
+                        metrics.stop_reason = Some(reason);
+                        if outcome.is_some() {
+                            metrics.outcome = outcome;
+                            metrics.result = self.stops.parse_result(&last_frames);
+                        }
+                        break;
+                    }
+                } else {
+                    idle_turns += 1;
+                    ai.on_idle(IdleStreak {
+                        turns: idle_turns,
+                        since_change: last_change.elapsed(),
+                    });
+                    do_action!(ActionResult::NotChanged);
+                },
+            }
+            if proc_dead {
+                trace!(self.term_data.logger, "Game ended in turn {}", cnt);
+                break;
+            }
+            if let Some((period, ref action)) = self.watchdog {
+                if last_output.elapsed() >= period {
+                    warn!(self.term_data.logger, "no output for {:?} in turn {}", period, cnt);
+                    match *action {
+                        WatchdogAction::Send(ref bytes) => {
+                            metrics.bytes_written += bytes.len();
+                            record!(IoKind::Write, bytes);
+                            log_keys(&mut self.key_log, &self.term_data, false, bytes);
+                            send_or!(process, bytes);
+                            last_output = Instant::now();
+                        }
+                        WatchdogAction::Kill => {
+                            metrics.hung = true;
+                            break;
+                        }
+                    }
+                }
+            }
+            match stdin.next() {
+                Some(Ok(CTRL_C)) => {
+                    ctrl_c = true;
+                    break;
+                }
+                // Ctrl-S and Ctrl-Q in the viewer pause and resume the game
+                Some(Ok(CTRL_S)) => if let Err(why) = self.pause() {
+                    warn!(self.term_data.logger, "couldn't pause game: {}", why);
+                },
+                _ => {}
+            }
+        }
+        if !proc_dead {
+            debug!(
+                self.term_data.logger,
+                "Game not ended and killed process forcibly"
+            );
+            process.kill();
+            send_or!(viewer, Handle::Zero);
+            if error.is_none() {
+                if let Err(e) = ai.try_action(ActionResult::GameEnded, self.max_loop) {
+                    error = Some(e);
+                }
+            }
+        }
+        viewer.shutdown();
+        if !ctrl_c {
+            match proc_handle.join_timeout(join_timeout) {
+                Some(Ok(())) => {}
+                Some(Err(_)) => {
+                    failure.get_or_insert_with(reader_stopped);
+                }
+                None => warn!(self.term_data.logger, "reader thread didn't stop"),
+            }
+            match viewer_handle.join_timeout(join_timeout) {
+                Some(Ok(Ok(()))) => {}
+                Some(Ok(Err(why))) => {
+                    failure.get_or_insert(CgwError::Viewer(why.0));
+                }
+                Some(Err(_)) => {
+                    failure.get_or_insert(CgwError::Viewer("viewer thread panicked".to_owned()));
+                }
+                None => warn!(self.term_data.logger, "viewer thread didn't stop"),
+            }
+        }
+        if let Some(ref why) = failure {
+            warn!(self.term_data.logger, "game failed in turn {}: {}", cnt, why);
+            if metrics.stop_reason.is_none() {
+                metrics.stop_reason = Some(why.to_string());
+            }
+        }
+        self.error = failure;
+        drop(process);
+        if let Err(why) = self.proc_setting
+            .run_commands(&self.proc_setting.teardown, self.episode)
+        {
+            warn!(self.term_data.logger, "teardown command failed: {}", why);
+        }
+        if let Some(ref mut sink) = self.results {
+            let rec = EpisodeRecord::new(self.episode, &self.config_hash, &metrics);
+            if let Err(why) = sink.record(&rec) {
+                warn!(self.term_data.logger, "can't write results: {}", why);
+            }
+        }
+        self.metrics = metrics.clone();
+        self.episode += 1;
+        match error {
+            Some(e) => Err(e),
+            None => Ok(metrics),
+        }
+    }
+    // end the game which couldn't be spawned at once, keeping the error
+    fn spawn_failed<R: TryReactor>(
+        &mut self,
+        ai: &mut R,
+        why: CgwError,
+    ) -> Result<Metrics, R::Error> {
+        warn!(self.term_data.logger, "couldn't spawn game: {}", why);
+        let metrics = Metrics {
+            stop_reason: Some(why.to_string()),
+            ..Metrics::default()
+        };
+        self.error = Some(why);
+        if let Some(ref mut sink) = self.results {
+            let rec = EpisodeRecord::new(self.episode, &self.config_hash, &metrics);
+            if let Err(why) = sink.record(&rec) {
+                warn!(self.term_data.logger, "can't write results: {}", why);
+            }
+        }
+        self.metrics = metrics.clone();
+        self.episode += 1;
+        ai.try_action(ActionResult::GameEnded, 1)?;
+        Ok(metrics)
+    }
+    /// Start process and run AI receiving observations made by ```mapper```
+    /// from each screen.
+    /// # Example
+    /// ```no_run
+    /// extern crate curses_game_wrapper as cgw;
+    /// use cgw::{ActionResult, GameSetting, Reactor, Screen};
+    /// fn main() {
+    ///     // receives the position of '@'
+    ///     struct PlayerAI;
+    ///     impl Reactor<Option<(usize, usize)>> for PlayerAI {
+    ///         fn action(
+    ///             &mut self,
+    ///             res: ActionResult<Option<(usize, usize)>>,
+    ///             _turn: usize,
+    ///         ) -> Option<Vec<u8>> {
+    ///             match res {
+    ///                 ActionResult::Changed(Some((_y, x))) if x > 40 => Some(b"h".to_vec()),
+    ///                 _ => Some(b"l".to_vec()),
+    ///             }
+    ///         }
+    ///     }
+    ///     let mut game = GameSetting::new("rogue").build().unwrap();
+    ///     let mut find_player = |s: &Screen| {
+    ///         s.iter_cells().find(|&(_, _, c)| c == b'@').map(|(y, x, _)| (y, x))
+    ///     };
+    ///     game.play_mapped(&mut find_player, &mut PlayerAI);
+    /// }
+    /// ```
+    pub fn play_mapped<M, R>(&mut self, mapper: &mut M, ai: &mut R) -> Metrics
+    where
+        M: ObservationMapper,
+        R: Reactor<M::Observation>,
+    {
+        self.play(&mut MappedReactor::new(mapper, ai))
+    }
+    /// Returns the number of games already played.
+    pub fn episode(&self) -> usize {
+        self.episode
+    }
+    /// Returns ```GameSetting::config_hash``` of the setting.
+    pub fn config_hash(&self) -> &str {
+        &self.config_hash
+    }
+    /// Returns ```GameAdapter``` given to ```GameSetting::adapter```.
+    pub fn adapter(&self) -> Option<&dyn GameAdapter> {
+        self.adapter.as_ref().map(|a| &**a)
+    }
+    /// Returns runtime statistics of the last game.
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+    /// Returns the error which ended the last game, if reading the game output
+    /// (```CgwError::ChildIo```), drawing it(```CgwError::Viewer```) or
+    /// ```GameEnv::restore```(```CgwError::ReplayDiverged```) failed.
+    ///
+    /// The game is ended as ```GameEnded``` then, with the error as
+    /// ```Metrics::stop_reason```.
+    pub fn error(&self) -> Option<&CgwError> {
+        self.error.as_ref()
+    }
+    /// Stop the game with ```SIGSTOP```, and stop calling the AI.
+    /// See ```PauseHandle``` for detail.
+    pub fn pause(&self) -> io::Result<()> {
+        self.proc_setting.pause.pause()
+    }
+    /// Continue the game paused by ```pause```.
+    pub fn resume(&self) -> io::Result<()> {
+        self.proc_setting.pause.resume()
+    }
+    /// Returns the bytes of special key the game expects.
+    ///
+    /// With ```GameSetting::terminfo_keys```, they come from the terminfo entry of ```TERM```.
+    pub fn key(&self, key: Key) -> &[u8] {
+        #[cfg(feature = "terminfo")]
+        {
+            if let Some(ref keys) = self.keys {
+                return keys.encode(key);
+            }
+        }
+        key.as_bytes()
+    }
+    // keys written to the game for the AI's keys, or None if the sanitizer rejects them
+    fn outgoing<'b>(&self, bytes: &'b [u8]) -> Option<Cow<'b, [u8]>> {
+        let mapped = self.key_map.apply(bytes);
+        match self.sanitizer {
+            Some(ref s) => match mapped {
+                Cow::Borrowed(b) => s.apply(b),
+                Cow::Owned(v) => s.apply(&v).map(|b| Cow::Owned(b.into_owned())),
+            },
+            None => Some(mapped),
+        }
+    }
+    // send keys of GameEnv::restore as soon as the game stops writing
+    fn fast_replay(
+        &mut self,
+        process: &mut ProcHandler,
+        parser: &mut Parser,
+        records: &[KeyRecord],
+        verify: bool,
+    ) -> Result<(), CgwError> {
+        let keys: Vec<_> = records.iter().filter(|r| !r.response).collect();
+        for (i, rec) in keys.iter().enumerate() {
+            self.replay_output(process, parser)?;
+            if verify && rec.screen_hash != Some(screen_hash(&self.term_data.ret_screen())) {
+                let msg = format!("screen differs before keys {} of {}", i + 1, keys.len());
+                return Err(CgwError::ReplayDiverged(msg));
+            }
+            log_keys(&mut self.key_log, &self.term_data, false, &rec.bytes);
+            process.send_bytes(&rec.bytes).map_err(|e| {
+                let e = io::Error::new(io::ErrorKind::Other, e);
+                CgwError::Io("couldn't send replayed keys".to_owned(), e)
+            })?;
+        }
+        self.replay_output(process, parser)
+    }
+    // parse the game output until the game stops writing, without delivering it
+    fn replay_output(
+        &mut self,
+        process: &mut ProcHandler,
+        parser: &mut Parser,
+    ) -> Result<(), CgwError> {
+        use std::sync::mpsc::RecvTimeoutError;
+        let mut wait = self.timeout;
+        loop {
+            match process.rx.recv_timeout(wait) {
+                Ok(Handle::Valid(bytes)) => {
+                    for c in &bytes {
+                        parser.advance(&mut self.term_data, *c);
+                    }
+                    let responses = self.term_data.take_responses();
+                    if !responses.is_empty() {
+                        log_keys(&mut self.key_log, &self.term_data, true, &responses);
+                        if let Err(why) = process.send_bytes(&responses) {
+                            debug!(self.term_data.logger, "can't send responses: {}", why);
+                        }
+                    }
+                    wait = self.settle;
+                }
+                Ok(Handle::Zero) => {
+                    let msg = "game ended during replay".to_owned();
+                    return Err(CgwError::ReplayDiverged(msg));
+                }
+                Ok(Handle::Error(why)) => return Err(CgwError::ChildIo(why)),
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return Err(reader_stopped()),
+            }
+        }
+        self.term_data.take_unhandled();
+        self.term_data.take_events();
+        self.term_data.take_bells();
+        self.term_data.take_scrolled();
+        Ok(())
+    }
+    // mark the start of a game in GameSetting::key_log_file
+    fn start_key_log(&mut self) {
+        if let Some(ref mut key_log) = self.key_log {
+            if let Err(why) = key_log.start_game() {
+                debug!(self.term_data.logger, "can't write key log: {}", why);
+            }
+        }
+    }
+    // mark the start of a game in GameSetting::dataset_file
+    fn start_dataset(&mut self) {
+        if let Some(ref mut dataset) = self.dataset {
+            if let Err(why) = dataset.start_game() {
+                debug!(self.term_data.logger, "can't write dataset: {}", why);
+            }
+        }
+    }
+    /// Replay ```records``` of ```GameSetting::key_log_file``` at the start of the
+    /// next game, to resume a long game after crashes.
+    ///
+    /// The game is respawned as usual, and each recorded key is sent as soon as
+    /// the game stops writing for ```settle```, without waiting the recorded time.
+    /// The output is parsed but not drawn or delivered to the AI, and then the
+    /// AI plays from the screen reached, starting from turn 1.
+    /// It works only if the game behaves the same way for the same keys(e.g. with
+    /// fixed ```GameSetting::seed```). If ```verify``` is true, the screen before
+    /// each key is checked against the recorded one, which requires that the AI
+    /// sent the keys after the game output settled.
+    /// If the replay fails, the game ends with ```CgwError::ReplayDiverged``` as
+    /// ```GameEnv::error```. Replayed keys are recorded again to the key log.
+    /// # Example
+    /// ```no_run
+    /// extern crate curses_game_wrapper as cgw;
+    /// use cgw::{read_key_log, ActionResult, GameSetting, Reactor};
+    /// use std::fs::File;
+    /// fn main() {
+    ///     struct EmptyAI;
+    ///     impl Reactor for EmptyAI {
+    ///         fn action(&mut self, _screen: ActionResult, _turn: usize) -> Option<Vec<u8>> {
+    ///              None
+    ///         }
+    ///     }
+    ///     let mut games = read_key_log(File::open("nethack.keys").unwrap()).unwrap();
+    ///     let mut game = GameSetting::new("nethack")
+    ///         .key_log_file("nethack-resumed.keys")
+    ///         .build()
+    ///         .unwrap();
+    ///     game.restore(games.pop().unwrap(), true);
+    ///     game.play(&mut EmptyAI);
+    /// }
+    /// ```
+    pub fn restore(&mut self, records: Vec<KeyRecord>, verify: bool) {
+        self.restore = Some((records, verify));
+    }
+    /// Returns ```PauseHandle``` to pause the game from other threads,
+    /// e.g. while ```play``` runs.
+    pub fn pause_handle(&self) -> PauseHandle {
+        self.proc_setting.pause.clone()
+    }
+    /// Start process and returns ```GameHandle``` to control the game by yourself,
+    /// instead of implementing ```Reactor```.
+    ///
+    /// If the game can't be spawned, the handle is already ended, and the error is
+    /// kept as ```GameEnv::error```.
+    /// # Example
+    /// ```no_run
+    /// extern crate curses_game_wrapper as cgw;
+    /// use cgw::{ActionResult, GameSetting};
+    /// use std::time::Duration;
+    /// fn main() {
+    ///     let mut game = GameSetting::new("rogue").build().unwrap();
+    ///     let mut handle = game.handle();
+    ///     handle.send(b"j").unwrap();
+    ///     if let ActionResult::Changed(screen) = handle.next_screen(Duration::from_secs(1)) {
+    ///         println!("{}", screen);
+    ///     }
+    /// }
+    /// ```
+    pub fn handle(&mut self) -> GameHandle {
+        self.start_handle(true)
+    }
+    /// Start process and returns ```GameHandle``` without spawning threads to read
+    /// the game output, to embed the game in your event loop.
+    ///
+    /// Drive the game by ```GameHandle::poll```, which reads the output without
+    /// blocking. ```GameHandle::output_fd``` can be registered to your poller to
+    /// know when the game writes something.
+    /// ```next_screen``` and ```EmulatedSession``` don't work with this handle,
+    /// except for ```GameSetting::mock```.
+    /// # Example
+    /// ```no_run
+    /// extern crate curses_game_wrapper as cgw;
+    /// use cgw::{ActionResult, GameSetting};
+    /// use std::thread;
+    /// use std::time::Duration;
+    /// fn main() {
+    ///     let mut game = GameSetting::new("rogue").build().unwrap();
+    ///     let mut handle = game.poll_handle();
+    ///     loop {
+    ///         match handle.poll() {
+    ///             ActionResult::Changed(screen) => println!("{}", screen),
+    ///             ActionResult::NotChanged => thread::sleep(Duration::from_millis(10)),
+    ///             ActionResult::GameEnded => break,
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn poll_handle(&mut self) -> GameHandle {
+        self.start_handle(false)
+    }
+    fn start_handle(&mut self, threaded: bool) -> GameHandle {
+        let process = match self.process.take() {
+            Some(p) => Ok(p),
+            None => ProcHandler::spawn(&self.proc_setting, self.episode),
+        };
+        self.term_data.reset();
+        self.error = None;
+        let mut process = match process {
+            Ok(p) => p,
+            // the handle of the game which ended at once
+            Err(why) => {
+                warn!(self.term_data.logger, "couldn't spawn game: {}", why);
+                let metrics = Metrics {
+                    stop_reason: Some(why.to_string()),
+                    ..Metrics::default()
+                };
+                self.error = Some(why);
+                return GameHandle {
+                    env: self,
+                    process: None,
+                    reader: None,
+                    output: None,
+                    pending: None,
+                    last_filtered: None,
+                    parser: Parser::new(),
+                    metrics: metrics,
+                    ended: true,
+                    unread: Vec::new(),
+                    held: Vec::new(),
+                    started: Instant::now(),
+                };
+            }
+        };
+        self.start_key_log();
+        let (reader, output) = if threaded {
+            (Some(process.run()), None)
+        } else {
+            let output = process.take_output();
+            if let Some(ref out) = output {
+                if let Err(why) = set_nonblocking(out.as_raw_fd(), true) {
+                    warn!(self.term_data.logger, "can't make game output nonblocking: {}", why);
+                }
+            }
+            (None, output)
+        };
+        GameHandle {
+            env: self,
+            process: Some(process),
+            reader: reader,
+            output: output,
+            pending: None,
+            last_filtered: None,
+            parser: Parser::new(),
+            metrics: Metrics::default(),
+            ended: false,
+            unread: Vec::new(),
+            held: Vec::new(),
+            started: Instant::now(),
+        }
+    }
+    /// Start process and returns iterator of ```(turn, ActionResult)```,
+    /// so that you can write AI as a loop.
+    ///
+    /// Like ```play```, it yields ```NotChanged``` when the game writes nothing in
+    /// ```GameSetting::timeout```, and ends after ```GameEnded``` or ```max_loop``` turns.
+    /// # Example
+    /// ```no_run
+    /// extern crate curses_game_wrapper as cgw;
+    /// use cgw::{ActionResult, GameSetting};
+    /// fn main() {
+    ///     let mut game = GameSetting::new("rogue").max_loop(100).build().unwrap();
+    ///     let mut iter = game.play_iter();
+    ///     while let Some((turn, result)) = iter.next() {
+    ///         if let ActionResult::Changed(_) = result {
+    ///             iter.send(if turn % 2 == 0 { b"h" } else { b"l" }).unwrap();
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn play_iter(&mut self) -> PlayIter {
+        let max_loop = self.max_loop;
+        PlayIter {
+            handle: self.handle(),
+            turn: 0,
+            max_loop: max_loop,
+            done: false,
+        }
+    }
+}
+
+/// Iterator of ```(turn, ActionResult)```, made by ```GameEnv::play_iter```.
+///
+/// The game is killed when it's dropped.
+pub struct PlayIter<'a> {
+    handle: GameHandle<'a>,
+    turn: usize,
+    max_loop: usize,
+    done: bool,
+}
+
+impl<'a> PlayIter<'a> {
+    /// Send keys to the game.
+    pub fn send(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.handle.send(bytes)
+    }
+    /// Returns runtime statistics of the game so far.
+    pub fn metrics(&self) -> &Metrics {
+        self.handle.metrics()
+    }
+}
+
+impl<'a> Iterator for PlayIter<'a> {
+    type Item = (usize, ActionResult);
+    fn next(&mut self) -> Option<(usize, ActionResult)> {
+        if self.done {
+            return None;
+        }
+        if self.turn >= self.max_loop {
+            self.done = true;
+            return Some((self.max_loop, ActionResult::GameEnded));
+        }
+        self.turn += 1;
+        let timeout = self.handle.env.timeout;
+        let res = self.handle.next_screen(timeout);
+        if let ActionResult::GameEnded = res {
+            self.done = true;
+        }
+        self.handle.metrics.turns += 1;
+        Some((self.turn, res))
+    }
+}
+
+/// Imperative interface to the game, made by ```GameEnv::handle```.
+///
+/// Useful for small scripts and experiments.
+/// The game is killed when the handle is dropped.
+pub struct GameHandle<'a> {
+    env: &'a mut GameEnv,
+    // None if the game couldn't be spawned
+    pub(crate) process: Option<ProcHandler>,
+    // None for GameEnv::poll_handle
+    reader: Option<ThreadHandle>,
+    // game output read by poll, instead of the reader
+    output: Option<File>,
+    // when poll read the last output not delivered yet
+    pending: Option<Instant>,
+    // last frame delivered before decoders, if filters are set
+    last_filtered: Option<Screen>,
+    parser: Parser,
+    pub(crate) metrics: Metrics,
+    pub(crate) ended: bool,
+    // bytes not consumed by Read yet
+    pub(crate) unread: Vec<u8>,
+    // keys(and whether they're responses) held while the game stopped us by XOFF
+    held: Vec<(Vec<u8>, bool)>,
+    // when the game was spawned
+    started: Instant,
+}
+
+impl<'a> GameHandle<'a> {
+    /// Send keys to the game, translated by ```GameSetting::key_map```.
+    ///
+    /// If the game stopped us by XOFF(see ```GameSetting::flow_control```), they're
+    /// held and sent when the game writes XON.
+    pub fn send(&mut self, bytes: &[u8]) -> io::Result<()> {
+        match self.env.outgoing(bytes) {
+            Some(b) => self.send_raw(&b),
+            None => {
+                self.metrics.rejected += 1;
+                Err(io::Error::new(io::ErrorKind::InvalidInput, "keys rejected by sanitizer"))
+            }
+        }
+    }
+    // send bytes as they are
+    fn send_raw(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.write(bytes, false)
+    }
+    // write keys(or responses to queries of the game) to the game
+    fn write(&mut self, bytes: &[u8], response: bool) -> io::Result<()> {
+        // recorded when they're sent
+        if self.env.term_data.xoff() {
+            self.held.push((bytes.to_owned(), response));
+            return Ok(());
+        }
+        if let Some(ref mut transcript) = self.env.transcript {
+            if let Err(why) = transcript.record(IoKind::Write, bytes) {
+                debug!(self.env.term_data.logger, "can't write transcript: {}", why);
+            }
+        }
+        log_keys(&mut self.env.key_log, &self.env.term_data, response, bytes);
+        self.metrics.bytes_written += bytes.len();
+        match self.process {
+            Some(ref mut process) => process
+                .send_bytes(bytes)
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e)),
+            None => Err(io::Error::new(io::ErrorKind::BrokenPipe, "game isn't running")),
+        }
+    }
+    /// Send keys recorded by ```GameSetting::key_log_file``` to the game at the
+    /// recorded time since the game started, reading the game output meanwhile.
+    ///
+    /// Use it with a freshly started handle, and a game which behaves the same
+    /// way for the same keys(e.g. with fixed ```GameSetting::seed```).
+    /// Responses to queries of the game are skipped, because the emulator
+    /// answers them again. Replayed keys are recorded again to the key log.
+    /// Returns an error of ```UnexpectedEof``` if the game ends before all
+    /// keys are sent. Like ```next_screen```, it doesn't work with
+    /// ```GameEnv::poll_handle```.
+    /// # Example
+    /// ```no_run
+    /// extern crate curses_game_wrapper as cgw;
+    /// use cgw::{read_key_log, GameSetting};
+    /// use std::fs::File;
+    /// use std::time::Duration;
+    /// fn main() {
+    ///     let games = read_key_log(File::open("rogue.keys").unwrap()).unwrap();
+    ///     let mut game = GameSetting::new("rogue").seed(42).build().unwrap();
+    ///     let mut handle = game.handle();
+    ///     handle.replay_keys(&games[0]).unwrap();
+    ///     handle.next_screen(Duration::from_secs(1));
+    ///     println!("{}", handle.screen());
+    /// }
+    /// ```
+    pub fn replay_keys(&mut self, records: &[KeyRecord]) -> io::Result<()> {
+        let ended = || io::Error::new(io::ErrorKind::UnexpectedEof, "game ended during replay");
+        for rec in records.iter().filter(|r| !r.response) {
+            let due = self.started + rec.time;
+            loop {
+                let now = Instant::now();
+                if self.ended {
+                    return Err(ended());
+                }
+                if now >= due {
+                    break;
+                }
+                self.next_screen(due - now);
+            }
+            self.send_raw(&rec.bytes)?;
+        }
+        Ok(())
+    }
+    /// Wait for the game output up to ```timeout```, and read it until
+    /// the game stops writing for ```GameSetting::settle```.
+    ///
+    /// Returns ```NotChanged``` if the game writes nothing in ```timeout```,
+    /// and ```GameEnded``` if the game exited.
+    pub fn next_screen(&mut self, timeout: Duration) -> ActionResult {
+        use std::sync::mpsc::RecvTimeoutError;
+        if self.ended {
+            return ActionResult::GameEnded;
+        }
+        let mut changed = false;
+        let mut wait = timeout;
+        loop {
+            let wait_start = Instant::now();
+            let received = self.process.as_ref().unwrap().rx.recv_timeout(wait);
+            self.metrics.wait_time += wait_start.elapsed();
+            match received {
+                Ok(Handle::Valid(bytes)) => {
+                    self.feed(&bytes);
+                    changed = true;
+                    wait = self.env.settle;
+                }
+                Ok(Handle::Zero) => {
+                    debug!(self.env.term_data.logger, "read zero bytes");
+                    self.ended = true;
+                    return ActionResult::GameEnded;
+                }
+                Ok(Handle::Error(why)) => {
+                    self.fail(CgwError::ChildIo(why));
+                    return ActionResult::GameEnded;
+                }
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.fail(reader_stopped());
+                    return ActionResult::GameEnded;
+                }
+            }
+        }
+        if changed {
+            self.frame()
+        } else {
+            ActionResult::NotChanged
+        }
+    }
+    /// Read the game output written so far without blocking, and returns
+    /// ```Changed``` once the game has stopped writing for ```GameSetting::settle```.
+    ///
+    /// Returns ```NotChanged``` if no frame is due yet, and ```GameEnded``` if the
+    /// game exited(after the last frame). Mainly for handles made by
+    /// ```GameEnv::poll_handle```.
+    pub fn poll(&mut self) -> ActionResult {
+        use std::sync::mpsc::TryRecvError;
+        if self.ended {
+            return ActionResult::GameEnded;
+        }
+        let mut buf = [0u8; 4096];
+        loop {
+            let received = match self.output {
+                Some(ref mut out) => match out.read(&mut buf) {
+                    Ok(0) => Ok(Handle::Zero),
+                    Ok(n) => Ok(Handle::Valid(buf[..n].to_vec())),
+                    Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => Err(TryRecvError::Empty),
+                    Err(why) => Ok(Handle::Error(why)),
+                },
+                None => self.process.as_ref().unwrap().rx.try_recv(),
+            };
+            match received {
+                Ok(Handle::Valid(bytes)) => {
+                    self.feed(&bytes);
+                    self.pending = Some(Instant::now());
+                }
+                Ok(Handle::Zero) => {
+                    debug!(self.env.term_data.logger, "read zero bytes");
+                    self.ended = true;
+                    break;
+                }
+                Ok(Handle::Error(why)) => {
+                    self.fail(CgwError::ChildIo(why));
+                    break;
+                }
+                Err(TryRecvError::Disconnected) => {
+                    self.fail(reader_stopped());
+                    break;
+                }
+                Err(TryRecvError::Empty) => break,
+            }
+        }
+        // deliver the last frame before GameEnded
+        match self.pending {
+            Some(last) if self.ended || last.elapsed() >= self.env.settle => {
+                self.pending = None;
+                self.frame()
+            }
+            _ if self.ended => ActionResult::GameEnded,
+            _ => ActionResult::NotChanged,
+        }
+    }
+    // current screen as a new frame, unless it's the same as the last one
+    // after GameSetting::filter
+    fn frame(&mut self) -> ActionResult {
+        let filtered = self.env.filters.decode(self.env.term_data.ret_screen());
+        if !self.env.filters.is_empty() {
+            if self.last_filtered.as_ref() == Some(&filtered) {
+                return ActionResult::NotChanged;
+            }
+            self.last_filtered = Some(filtered.clone());
+        }
+        self.metrics.frames += 1;
+        ActionResult::Changed(self.env.decoders.decode(filtered))
+    }
+    /// Returns the file descriptor of the game output read by ```poll```, which
+    /// becomes readable when the game writes something.
+    ///
+    /// Returns ```None``` unless the handle is made by ```GameEnv::poll_handle```,
+    /// or for ```GameSetting::mock```.
+    pub fn output_fd(&self) -> Option<RawFd> {
+        self.output.as_ref().map(|out| out.as_raw_fd())
+    }
+    // end the game by the failure, kept as GameEnv::error
+    fn fail(&mut self, why: CgwError) {
+        warn!(self.env.term_data.logger, "game failed: {}", why);
+        if let Some(ref mut process) = self.process {
+            process.kill();
+        }
+        self.ended = true;
+        if self.metrics.stop_reason.is_none() {
+            self.metrics.stop_reason = Some(why.to_string());
+        }
+        self.env.error = Some(why);
+    }
+    // emulate game output
+    pub(crate) fn feed(&mut self, bytes: &[u8]) {
+        if let Some(ref mut transcript) = self.env.transcript {
+            if let Err(why) = transcript.record(IoKind::Read, bytes) {
+                debug!(self.env.term_data.logger, "can't write transcript: {}", why);
+            }
+        }
+        self.metrics.bytes_read += bytes.len();
+        let parse_start = Instant::now();
+        for c in bytes {
+            self.parser.advance(&mut self.env.term_data, *c);
+        }
+        self.metrics.parse_time += parse_start.elapsed();
+        self.env.term_data.take_unhandled();
+        let responses = self.env.term_data.take_responses();
+        if !responses.is_empty() {
+            if let Err(why) = self.write(&responses, true) {
+                debug!(self.env.term_data.logger, "can't send responses: {}", why);
+            }
+        }
+        if !self.env.term_data.xoff() {
+            for (bytes, response) in mem::replace(&mut self.held, Vec::new()) {
+                if let Err(why) = self.write(&bytes, response) {
+                    debug!(self.env.term_data.logger, "can't send held keys: {}", why);
+                }
+            }
+        }
+    }
+    /// Returns current screen, processed by ```GameSetting::filter```s and
+    /// ```GameSetting::decoder```s.
+    pub fn screen(&self) -> Screen {
+        let filtered = self.env.filters.decode(self.env.term_data.ret_screen());
+        self.env.decoders.decode(filtered)
+    }
+    /// Returns cursor position as ```(y, x)```, e.g. for ```Screen::to_ruled_string```.
+    pub fn cursor(&self) -> (usize, usize) {
+        self.env.term_data.cursor()
+    }
+    /// Returns true if the cursor is visible. Games usually hide it while
+    /// showing menus or prompts.
+    pub fn cursor_visible(&self) -> bool {
+        self.env.term_data.cursor_visible()
+    }
+    /// Returns lines scrolled off the screen(oldest first), kept by
+    /// ```GameSetting::scrollback```.
+    pub fn scrollback(&self) -> Vec<Vec<u8>> {
+        self.env.term_data.scrollback()
+    }
+    /// Returns events interpreted from the game output since the last call,
+    /// if ```GameSetting::terminal_events``` is set.
+    pub fn take_events(&mut self) -> Vec<TerminalEvent> {
+        self.env.term_data.take_events()
+    }
+    /// Returns cursor key mode set by the game, for ```Direction::as_arrow_seq```.
+    pub fn cursor_key_mode(&self) -> CursorKeyMode {
+        self.env.term_data.cursor_key_mode()
+    }
+    /// Send special key, encoded as ```GameEnv::key```.
+    pub fn send_key(&mut self, key: Key) -> io::Result<()> {
+        let bytes = self.env.key(key).to_owned();
+        self.send_raw(&bytes)
+    }
+    /// Send Alt + ```c```, encoded as ```GameSetting::meta_encoding```.
+    pub fn send_meta(&mut self, c: u8) -> io::Result<()> {
+        let bytes = meta(c, self.env.meta);
+        self.send_raw(&bytes)
+    }
+    /// Send text as pasted, wrapped by ```wrap_paste``` if the game enabled
+    /// bracketed paste mode.
+    pub fn paste(&mut self, text: &[u8]) -> io::Result<()> {
+        let bytes = wrap_paste(text, self.env.term_data.bracketed_paste());
+        self.send_raw(&bytes)
+    }
+    /// Returns terminal modes set by the game.
+    pub fn mode(&self) -> TermMode {
+        self.env.term_data.mode()
+    }
+    /// Returns mouse reporting modes set by the game.
+    pub fn mouse_mode(&self) -> MouseMode {
+        self.env.term_data.mouse_mode()
+    }
+    /// Send mouse event encoded for current mouse mode.
+    ///
+    /// Returns ```false``` and sends nothing if the game doesn't accept the event.
+    pub fn send_mouse(&mut self, event: MouseEvent) -> io::Result<bool> {
+        match event.encode(self.mouse_mode()) {
+            Some(bytes) => self.send_raw(&bytes).map(|_| true),
+            None => Ok(false),
+        }
+    }
+    /// Send ```GameAdapter::quit_keys``` of ```GameSetting::adapter```.
+    ///
+    /// Returns an error of ```InvalidInput``` if the adapter isn't set.
+    pub fn quit(&mut self) -> io::Result<()> {
+        let keys = match self.env.adapter {
+            Some(ref a) => a.quit_keys(),
+            None => {
+                let msg = "quitting needs GameSetting::adapter";
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+            }
+        };
+        self.send_raw(&keys)
+    }
+    /// Returns true if the game exited.
+    pub fn is_ended(&self) -> bool {
+        self.ended
+    }
+    /// Returns runtime statistics of the game so far.
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+}
+
+impl<'a> Drop for GameHandle<'a> {
+    fn drop(&mut self) {
+        if let Some(mut process) = self.process.take() {
+            if !self.ended {
+                process.kill();
+            }
+            if let Some(reader) = self.reader.take() {
+                match reader.join_timeout(Duration::from_millis(JOIN_TIMEOUT_MS)) {
+                    Some(Err(_)) => warn!(self.env.term_data.logger, "reader thread panicked"),
+                    Some(Ok(())) => {}
+                    None => warn!(self.env.term_data.logger, "reader thread didn't stop"),
+                }
+            }
+            drop(process);
+            let env = &mut *self.env;
+            if let Err(why) = env.proc_setting
+                .run_commands(&env.proc_setting.teardown, env.episode)
+            {
+                warn!(env.term_data.logger, "teardown command failed: {}", why);
+            }
+        }
+        let env = &mut *self.env;
+        env.metrics = self.metrics.clone();
+        env.episode += 1;
+    }
+}
+
+// record bytes written to the game in GameSetting::key_log_file, with the
+// screen the keys are sent for
+fn log_keys(
+    key_log: &mut Option<KeyLogWriter<BufWriter<File>>>,
+    term_data: &TermData,
+    response: bool,
+    bytes: &[u8],
+) {
+    if let Some(ref mut key_log) = *key_log {
+        let hash = if response {
+            None
+        } else {
+            Some(screen_hash(&term_data.ret_screen()))
+        };
+        if let Err(why) = key_log.record(hash, bytes) {
+            debug!(term_data.logger, "can't write key log: {}", why);
+        }
+    }
+}
+
+// the reader thread stopped without telling why
+fn reader_stopped() -> CgwError {
+    let e = io::Error::new(io::ErrorKind::BrokenPipe, "reader thread stopped");
+    CgwError::ChildIo(e)
+}
+
+// how long to wait for threads to stop after the game ends
+const JOIN_TIMEOUT_MS: u64 = 1000;
+// number of frames kept for GameOver::parse_result
+const RESULT_FRAMES: usize = 16;
+
+// JoinHandle which can be joined with timeout
+pub(crate) struct ThreadHandle<T = ()> {
+    handle: JoinHandle<T>,
+    // disconnected when the thread ends(or panics)
+    done: Receiver<()>,
+}
+
+impl<T: Send + 'static> ThreadHandle<T> {
+    pub(crate) fn spawn<F: FnOnce() -> T + Send + 'static>(f: F) -> ThreadHandle<T> {
+        let (tx, rx) = mpsc::channel();
+        let handle = thread::spawn(move || {
+            let _done: Sender<()> = tx;
+            f()
+        });
+        ThreadHandle {
+            handle: handle,
+            done: rx,
+        }
+    }
+    // Returns None and detaches the thread if it doesn't end in time
+    fn join_timeout(self, timeout: Duration) -> Option<thread::Result<T>> {
+        match self.done.recv_timeout(timeout) {
+            Err(mpsc::RecvTimeoutError::Timeout) => None,
+            _ => Some(self.handle.join()),
+        }
+    }
+}
+
+// handles Sender and Reciever
+pub(crate) enum Handle<T> {
+    Error(io::Error), // couldn't read the game output
+    Zero,             // read 0 bytes (probably game ended)
+    Valid(T),         // read 1 or more bytes
+}
+
+pub(crate) trait GameViewer {
+    // the thread returns the error which stopped drawing
+    fn run(&mut self) -> ThreadHandle<Result<(), ViewerError>>;
+    fn send_bytes(&mut self, bytes: Handle<&[u8]>) -> Result<(), ViewerError>;
+    // bytes AI sent in the turn
+    fn send_action(&mut self, _turn: usize, _bytes: &[u8]) {}
+    // stop waiting between drawings and quit when reaches Zero
+    fn shutdown(&mut self) {}
+}
+
+#[derive(Debug)]
+pub(crate) struct ViewerError(pub(crate) String);
+impl fmt::Display for ViewerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+impl Error for ViewerError {
+    fn description(&self) -> &str {
+        &self.0
+    }
+}
+impl From<mpsc::SendError<Handle<Vec<u8>>>> for ViewerError {
+    fn from(e: mpsc::SendError<Handle<Vec<u8>>>) -> Self {
+        ViewerError(e.description().to_owned())
+    }
+}
+
+struct EmptyViewer {}
+
+impl GameViewer for EmptyViewer {
+    fn run(&mut self) -> ThreadHandle<Result<(), ViewerError>> {
+        ThreadHandle::spawn(move || Ok(()))
+    }
+    fn send_bytes(&mut self, _bytes: Handle<&[u8]>) -> Result<(), ViewerError> {
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct TerminalViewer {
+    tx: Sender<Handle<Vec<u8>>>,
+    // moved into the thread by run
+    rx: Option<Receiver<Handle<Vec<u8>>>>,
+    sleep_time: Duration,
+    stopped: Arc<AtomicBool>,
+}
+
+impl TerminalViewer {
+    fn new(d: Duration) -> Self {
+        let (tx, rx) = mpsc::channel();
+        TerminalViewer {
+            tx: tx,
+            rx: Some(rx),
+            sleep_time: d,
+            stopped: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
+impl GameViewer for TerminalViewer {
+    fn run(&mut self) -> ThreadHandle<Result<(), ViewerError>> {
+        let rx = self.rx.take().expect("TerminalViewer::run is called twice");
+        let sleep = self.sleep_time;
+        let stopped = Arc::clone(&self.stopped);
+        env::set_var("TERM", "vt100");
+        ThreadHandle::spawn(move || {
+            let mut stdout = io::stdout()
+                .into_raw_mode()
+                .map_err(|e| ViewerError(format!("couldn't get raw stdout: {}", e)))?;
+            while let Ok(game_input) = rx.recv() {
+                match game_input {
+                    Handle::Valid(ref bytes) => {
+                        stdout
+                            .write_all(bytes)
+                            .and_then(|_| stdout.flush())
+                            .map_err(|e| ViewerError(format!("couldn't write to stdout: {}", e)))?;
+                    }
+                    Handle::Zero | Handle::Error(_) => break,
+                }
+                if !stopped.load(Ordering::Relaxed) {
+                    thread::sleep(sleep);
+                }
+            }
+            Ok(())
+        })
+    }
+    fn send_bytes(&mut self, b: Handle<&[u8]>) -> Result<(), ViewerError> {
+        let res = match b {
+            Handle::Zero => Handle::Zero,
+            Handle::Error(e) => Handle::Error(e),
+            Handle::Valid(b) => Handle::Valid(b.to_owned()),
+        };
+        self.tx.send(res)?;
+        Ok(())
+    }
+    fn shutdown(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        self.tx.send(Handle::Zero).ok();
+    }
+}
+
+#[derive(Debug)]
+struct ProcessError(String);
+
+impl fmt::Display for ProcessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for ProcessError {
+    fn description(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<io::Error> for ProcessError {
+    fn from(why: io::Error) -> Self {
+        ProcessError(why.description().to_owned())
+    }
+}
+
+// Settings to spawn game process, owned by GameEnv
+#[derive(Clone, Debug)]
+struct ProcSetting {
+    cmdname: String,
+    args: Vec<String>,
+    envs: Vec<(String, String)>,
+    lines: usize,
+    columns: usize,
+    seed: u64,
+    setup: Vec<String>,
+    teardown: Vec<String>,
+    // commands and startup time of auxiliary processes
+    aux: Vec<(String, Duration)>,
+    pause: PauseHandle,
+    nice: Option<i32>,
+    cpus: Vec<usize>,
+    env_clear: bool,
+    env_allow: Vec<String>,
+    term: TermType,
+    stdin: Option<StdinSource>,
+    mock: Option<MockGame>,
+    connect: Option<GameIo>,
+    logger: Logger,
+}
+
+impl ProcSetting {
+    // replace {episode} and {seed}
+    fn expand(&self, s: &str, episode: usize) -> String {
+        let seed = self.seed.wrapping_add(episode as u64);
+        s.replace("{episode}", &episode.to_string())
+            .replace("{seed}", &seed.to_string())
+    }
+    // run shell commands and check their exit status
+    fn run_commands(&self, commands: &[String], episode: usize) -> Result<(), ProcessError> {
+        for c in commands {
+            let c = self.expand(c, episode);
+            let status = Command::new("sh").arg("-c").arg(&c).status()?;
+            if !status.success() {
+                return Err(ProcessError(format!("`{}` failed with {}", c, status)));
+            }
+        }
+        Ok(())
+    }
+    fn spawn_aux(&self, episode: usize) -> Result<Vec<AuxProcess>, ProcessError> {
+        let mut res = Vec::new();
+        for &(ref c, startup) in &self.aux {
+            let c = self.expand(c, episode);
+            let mut cmd = Command::new("sh");
+            cmd.arg("-c")
+                .arg(&c)
+                .envs(self.envs.iter().map(|&(ref k, ref v)| (k, self.expand(v, episode))))
+                .stdin(Stdio::null())
+                .stdout(Stdio::null());
+            let cmd = unsafe {
+                // make new process group, to terminate it with its children
+                cmd.pre_exec(|| {
+                    if libc::setpgid(0, 0) < 0 {
+                        return Err(io::Error::last_os_error());
+                    }
+                    Ok(())
+                })
+            };
+            let mut aux = AuxProcess(cmd.spawn()?);
+            thread::sleep(startup);
+            if let Some(status) = aux.0.try_wait()? {
+                return Err(ProcessError(format!("`{}` exited with {}", c, status)));
+            }
+            res.push(aux);
+        }
+        Ok(res)
+    }
+}
+
+// time to wait auxiliary processes to exit by SIGTERM
+const AUX_TERM_TIMEOUT_MS: u64 = 1000;
+
+// auxiliary process, terminated with its process group when dropped
+struct AuxProcess(Child);
+
+impl Drop for AuxProcess {
+    fn drop(&mut self) {
+        let pgid = -(self.0.id() as libc::pid_t);
+        unsafe {
+            libc::kill(pgid, libc::SIGTERM);
+        }
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_millis(AUX_TERM_TIMEOUT_MS) {
+            match self.0.try_wait() {
+                Ok(None) => thread::sleep(Duration::from_millis(POLL_INTERVAL_MS as u64)),
+                _ => return,
+            }
+        }
+        unsafe {
+            libc::kill(pgid, libc::SIGKILL);
+        }
+        self.0.wait().ok();
+    }
+}
+
+// exec process
+pub(crate) struct ProcHandler {
+    // None for MockGame
+    pub(crate) my_proc: Option<Child>,
+    mock: Option<MockGame>,
+    conn: Option<Connection>,
+    aux: Vec<AuxProcess>,
+    tx: Sender<Handle<Vec<u8>>>,
+    // note : Reciever blocks until some bytes wrote
+    pub(crate) rx: Receiver<Handle<Vec<u8>>>,
+    killed: Arc<AtomicBool>,
+    // wakes up the reader when killed(None for MockGame)
+    waker: Option<Arc<Waker>>,
+    pause: PauseHandle,
+    logger: Logger,
+    // stdin of the game shared with GameSink, in GameEnv::stream
+    pub(crate) stdin: Option<Arc<Mutex<ChildStdin>>>,
+}
+
+impl ProcHandler {
+    fn spawn(g: &ProcSetting, episode: usize) -> Result<ProcHandler, CgwError> {
+        if let Err(why) = g.run_commands(&g.setup, episode) {
+            return Err(CgwError::SetupFailed(why.0));
+        }
+        let aux = g.spawn_aux(episode)
+            .map_err(|why| CgwError::SetupFailed(why.0))?;
+        if let Some(ref mock) = g.mock {
+            let (tx, rx) = mpsc::channel();
+            let mut handler = ProcHandler {
+                my_proc: None,
+                mock: Some(mock.clone()),
+                conn: None,
+                aux: aux,
+                tx: tx,
+                rx: rx,
+                killed: Arc::new(AtomicBool::new(false)),
+                waker: None,
+                pause: g.pause.clone(),
+                logger: g.logger.clone(),
+                stdin: None,
+            };
+            handler.respond(&[]);
+            return Ok(handler);
+        }
+        if let Some(ref io) = g.connect {
+            let conn = io.open()
+                .map_err(|e| CgwError::Io(format!("couldn't connect to {:?}", io), e))?;
+            let waker = Waker::new()
+                .map_err(|e| CgwError::Io("couldn't make pipe".to_owned(), e))?;
+            let (tx, rx) = mpsc::channel();
+            return Ok(ProcHandler {
+                my_proc: None,
+                mock: None,
+                conn: Some(conn),
+                aux: aux,
+                tx: tx,
+                rx: rx,
+                killed: Arc::new(AtomicBool::new(false)),
+                waker: Some(Arc::new(waker)),
+                pause: g.pause.clone(),
+                logger: g.logger.clone(),
+                stdin: None,
+            });
+        }
+        let mut cmd = Command::new(&g.cmdname);
+        if g.env_clear {
+            cmd.env_clear();
+            for name in &g.env_allow {
+                if let Some(val) = env::var_os(name) {
+                    cmd.env(name, val);
+                }
+            }
+        }
+        let cmd = cmd.args(g.args.iter().map(|a| g.expand(a, episode)));
+        let cmd = cmd.env("LINES", format!("{}", g.lines));
+        let cmd = cmd.env("COLUMNS", format!("{}", g.columns));
+        let cmd = cmd.env("TERM", g.term.name());
+        let cmd = cmd.envs(g.envs.iter().map(|&(ref k, ref v)| (k, g.expand(v, episode))));
+        let stdin = match g.stdin {
+            Some(StdinSource::File(ref path)) => match File::open(path) {
+                Ok(f) => Stdio::from(f),
+                Err(why) => {
+                    let msg = format!("couldn't open stdin file {}", path.display());
+                    return Err(CgwError::Io(msg, why));
+                }
+            },
+            _ => Stdio::piped(),
+        };
+        let cmd = cmd.stdin(stdin).stdout(Stdio::piped());
+        let nice = g.nice;
+        let cpus = g.cpus.clone();
+        let cmd = unsafe {
+            cmd.pre_exec(move || {
+                // make new process group, to pause the game with its children
+                if libc::setpgid(0, 0) < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                if let Some(nice) = nice {
+                    if libc::setpriority(libc::PRIO_PROCESS as _, 0, nice) < 0 {
+                        return Err(io::Error::last_os_error());
+                    }
+                }
+                set_cpu_affinity(&cpus)
+            })
+        };
+        let mut process = cmd.spawn()
+            .map_err(|e| CgwError::Io(format!("couldn't spawn {}", g.cmdname), e))?;
+        if let Some(StdinSource::Bytes(ref bytes)) = g.stdin {
+            let mut stdin = process.stdin.take().unwrap();
+            let bytes = bytes.clone();
+            // the game may exit without reading all, so ignore errors
+            thread::spawn(move || stdin.write_all(&bytes));
+        }
+        if let Err(why) = g.pause.attach(process.id()) {
+            kill_and_wait(&mut process, &g.logger);
+            return Err(CgwError::Io("couldn't pause game".to_owned(), why));
+        }
+        let waker = match Waker::new() {
+            Ok(w) => w,
+            Err(why) => {
+                kill_and_wait(&mut process, &g.logger);
+                return Err(CgwError::Io("couldn't make pipe".to_owned(), why));
+            }
+        };
+        let (tx, rx) = mpsc::channel();
+        Ok(ProcHandler {
+            my_proc: Some(process),
+            mock: None,
+            conn: None,
+            aux: aux,
+            tx: tx,
+            rx: rx,
+            killed: Arc::new(AtomicBool::new(false)),
+            waker: Some(Arc::new(waker)),
+            pause: g.pause.clone(),
+            logger: g.logger.clone(),
+            stdin: None,
+        })
+    }
+
+    // pass output of MockGame as if the reader thread read it
+    fn respond(&mut self, keys: &[u8]) {
+        if self.killed.load(Ordering::Relaxed) {
+            return;
+        }
+        let res = match self.mock {
+            Some(ref mock) => mock.respond(keys),
+            None => return,
+        };
+        match res {
+            Some(ref out) if out.is_empty() => {}
+            Some(out) => {
+                self.tx.send(Handle::Valid(out)).ok();
+            }
+            None => {
+                self.killed.store(true, Ordering::Relaxed);
+                self.tx.send(Handle::Zero).ok();
+            }
+        }
+    }
+
+    // output of the game, None for MockGame
+    fn take_output(&mut self) -> Option<File> {
+        match (self.my_proc.as_mut(), self.conn.as_mut()) {
+            (Some(p), _) => p.stdout
+                .take()
+                .map(|out| unsafe { File::from_raw_fd(out.into_raw_fd()) }),
+            (None, Some(conn)) => conn.reader.take(),
+            (None, None) => None,
+        }
+    }
+
+    fn run(&mut self) -> ThreadHandle {
+        let mut proc_out = match self.take_output() {
+            Some(out) => out,
+            None => return ThreadHandle::spawn(|| {}),
+        };
+        let txclone = self.tx.clone();
+        let ac = Arc::clone(&self.killed);
+        let waker = Arc::clone(self.waker.as_ref().unwrap());
+        ThreadHandle::spawn(move || {
+            const BUFSIZE: usize = 4096;
+            let mut readbuf = vec![0u8; BUFSIZE];
+            let fd = proc_out.as_raw_fd();
+            while !ac.load(Ordering::Relaxed) {
+                // don't block in read, to stop as soon as killed
+                match poll_readable(fd, waker.fd()) {
+                    Ok(true) => {}
+                    Ok(false) => continue,
+                    Err(why) => {
+                        txclone.send(Handle::Error(why)).ok();
+                        break;
+                    }
+                }
+                match proc_out.read(&mut readbuf) {
+                    Err(why) => {
+                        txclone.send(Handle::Error(why)).ok();
+                        break;
+                    }
+                    Ok(0) => {
+                        txclone.send(Handle::Zero).ok();
+                        break;
+                    }
+                    // the rest is read in the next loop
+                    Ok(n) => {
+                        txclone.send(Handle::Valid(readbuf[0..n].to_owned())).ok();
+                    }
+                }
+            }
+        })
+    }
+
+    fn send_bytes(&mut self, buf: &[u8]) -> Result<(), ProcessError> {
+        if self.mock.is_some() {
+            self.respond(buf);
+            return Ok(());
+        }
+        if let Some(ref mut conn) = self.conn {
+            conn.writer.write_all(buf)?;
+            return Ok(());
+        }
+        if let Some(ref stdin) = self.stdin {
+            // the sink panicked while writing, but the pipe is still usable
+            let mut stdin = stdin.lock().unwrap_or_else(|e| e.into_inner());
+            stdin.write_all(buf)?;
+            return Ok(());
+        }
+        match self.my_proc.as_mut().and_then(|p| p.stdin.as_mut()) {
+            Some(stdin) => stdin.write_all(buf)?,
+            None => return Err(ProcessError("stdin of the game is redirected".to_owned())),
+        }
+        Ok(())
+    }
+
+    fn kill(&mut self) {
+        if let Some(ref mut p) = self.my_proc {
+            kill_and_wait(p, &self.logger);
+        }
+        self.stop_reader();
+    }
+
+    // stop the reader, even if children of the game keep stdout open
+    fn stop_reader(&self) {
+        self.killed.store(true, Ordering::Relaxed);
+        if let Some(ref waker) = self.waker {
+            waker.wake();
+        }
+    }
+}
+
+// kill the game and reap it, so that it doesn't remain as a zombie
+fn kill_and_wait(p: &mut Child, logger: &Logger) {
+    // already reaped
+    if let Ok(Some(_)) = p.try_wait() {
+        return;
+    }
+    if let Err(why) = p.kill() {
+        debug!(logger, "can't kill game: {}", why);
+    }
+    if let Err(why) = p.wait() {
+        warn!(logger, "can't wait for game: {}", why);
+    }
+}
+
+// search executable command like shells
+fn find_command(cmd: &str, path: Option<OsString>) -> bool {
+    fn is_executable(p: &Path) -> bool {
+        p.metadata()
+            .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+            .unwrap_or(false)
+    }
+    if cmd.contains('/') {
+        return is_executable(Path::new(cmd));
+    }
+    path.map_or(false, |path| {
+        env::split_paths(&path).any(|dir| is_executable(&dir.join(cmd)))
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn set_cpu_affinity(cpus: &[usize]) -> io::Result<()> {
+    if cpus.is_empty() {
+        return Ok(());
+    }
+    unsafe {
+        let mut set: libc::cpu_set_t = mem::zeroed();
+        for &cpu in cpus {
+            libc::CPU_SET(cpu, &mut set);
+        }
+        if libc::sched_setaffinity(0, mem::size_of::<libc::cpu_set_t>(), &set) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_cpu_affinity(_cpus: &[usize]) -> io::Result<()> {
+    Ok(())
+}
+
+// interval to check if processes exited
+const POLL_INTERVAL_MS: i32 = 20;
+
+// pipe to wake up the reader blocking in poll
+struct Waker {
+    rx: File,
+    tx: File,
+}
+
+impl Waker {
+    fn new() -> io::Result<Waker> {
+        let mut fds = [0; 2];
+        unsafe {
+            if libc::pipe(fds.as_mut_ptr()) < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let (rx, tx) = (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1]));
+            // the game must not inherit them
+            for &fd in &fds {
+                if libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            Ok(Waker { rx: rx, tx: tx })
+        }
+    }
+    fn fd(&self) -> RawFd {
+        self.rx.as_raw_fd()
+    }
+    // the pipe stays readable after this
+    fn wake(&self) {
+        (&self.tx).write_all(&[0]).ok();
+    }
+}
+
+fn set_nonblocking(fd: RawFd, nonblocking: bool) -> io::Result<()> {
+    unsafe {
+        let flags = libc::fcntl(fd, libc::F_GETFL);
+        if flags < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let flags = if nonblocking {
+            flags | libc::O_NONBLOCK
+        } else {
+            flags & !libc::O_NONBLOCK
+        };
+        if libc::fcntl(fd, libc::F_SETFL, flags) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+// wait until fd becomes readable(or closed), returns false if woken up by waker
+fn poll_readable(fd: RawFd, waker: RawFd) -> io::Result<bool> {
+    let pollfd = |fd| libc::pollfd {
+        fd: fd,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    let mut pfds = [pollfd(fd), pollfd(waker)];
+    let res = unsafe { libc::poll(pfds.as_mut_ptr(), 2, -1) };
+    if res < 0 {
+        let err = io::Error::last_os_error();
+        if err.kind() == io::ErrorKind::Interrupted {
+            return Ok(false);
+        }
+        return Err(err);
+    }
+    Ok(pfds[1].revents == 0 && pfds[0].revents != 0)
+}
+
+// Destractor (kill proc)
+impl Drop for ProcHandler {
+    fn drop(&mut self) {
+        if let Some(ref mut p) = self.my_proc {
+            self.pause.detach(p.id());
+            kill_and_wait(p, &self.logger);
+        }
+        self.stop_reader();
+        // terminate auxiliary processes after the game
+        self.aux.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+    use test_utils::{EmptyAI, RecordAI};
+    #[test]
+    #[ignore]
+    fn test_gameplay() {
+        use super::*;
+        struct EmptyAI {
+            loopnum: usize,
+        };
+        impl Reactor for EmptyAI {
+            fn action(&mut self, _screen: ActionResult, turn: usize) -> Option<Vec<u8>> {
+                let mut res = Vec::new();
+                match turn {
+                    val if val == self.loopnum - 1 => res.push(AsciiChar::CarriageReturn.as_byte()),
+                    val if val == self.loopnum - 2 => res.push(b'y'),
+                    val if val == self.loopnum - 3 => res.push(b'Q'),
+                    _ => res.push(b'j'),
+                };
+                Some(res)
+            }
+        }
+        let loopnum = 10;
+        let gs = GameSetting::new("rogue")
+            .env("ROGUEUSER", "EmptyAI")
+            .lines(24)
+            .columns(80)
+            .debug_file("debug.txt")
+            .debug_level(Severity::Trace)
+            .max_loop(loopnum + 1)
+            .draw_on(Duration::from_millis(100));
+        let mut game = gs.build().unwrap();
+        let mut ai = EmptyAI { loopnum: loopnum };
+        game.play(&mut ai);
+    }
+    #[test]
+    fn test_try_play_error() {
+        use super::*;
+        struct FailingAI;
+        impl TryReactor for FailingAI {
+            type Error = String;
+            fn try_action(
+                &mut self,
+                _screen: ActionResult,
+                turn: usize,
+            ) -> Result<Option<Vec<u8>>, String> {
+                if turn == 2 {
+                    Err(format!("failed in turn {}", turn))
+                } else {
+                    Ok(None)
+                }
+            }
+        }
+        let gs = GameSetting::new("sh")
+            .args(vec!["-c", "printf hello; exec sleep 10"])
+            .max_loop(10);
+        let res = gs.build().unwrap().try_play(&mut FailingAI);
+        assert_eq!(res, Err("failed in turn 2".to_owned()));
+    }
+    #[test]
+    fn test_reuse_env() {
+        use super::*;
+        struct CountAI {
+            ended: usize,
+        }
+        impl Reactor for CountAI {
+            fn action(&mut self, screen: ActionResult, _turn: usize) -> Option<Vec<u8>> {
+                if let ActionResult::GameEnded = screen {
+                    self.ended += 1;
+                }
+                None
+            }
+        }
+        let mut game = GameSetting::new("sh")
+            .args(vec!["-c", "printf hello"])
+            .max_loop(10)
+            .build()
+            .unwrap();
+        let mut ai = CountAI { ended: 0 };
+        for _ in 0..2 {
+            let metrics = game.play(&mut ai);
+            assert_eq!(metrics.bytes_read, 5);
+            assert_eq!(game.metrics(), &metrics);
+        }
+        assert_eq!(ai.ended, 2);
+    }
+    #[test]
+    fn test_episode_template() {
+        use super::*;
+        let mut game = GameSetting::new("sh")
+            .args(vec!["-c", "printf $EP-{episode}; exec sleep 10"])
+            .env("EP", "{seed}")
+            .seed(100)
+            .max_loop(3)
+            .build()
+            .unwrap();
+        let mut ai = RecordAI { lines: Vec::new() };
+        game.play(&mut ai);
+        game.play(&mut ai);
+        assert_eq!(game.episode(), 2);
+        assert_eq!(ai.lines, vec!["100-0", "101-1"]);
+    }
+    #[test]
+    fn test_stdin_from() {
+        use super::*;
+        struct IgnoringAI {
+            lines: Vec<String>,
+        }
+        impl Reactor for IgnoringAI {
+            fn action(&mut self, screen: ActionResult, _turn: usize) -> Option<Vec<u8>> {
+                if let ActionResult::Changed(screen) = screen {
+                    self.lines.push(screen.trimmed_line(0).into_owned());
+                }
+                // ignored
+                Some(b"x".to_vec())
+            }
+        }
+        let mut game = GameSetting::new("sh")
+            .args(vec!["-c", "read a; printf $a; exec sleep 10"])
+            .stdin_from(&b"bytes\n"[..])
+            .max_loop(3)
+            .build()
+            .unwrap();
+        let mut ai = IgnoringAI { lines: Vec::new() };
+        game.play(&mut ai);
+        game.play(&mut ai);
+        assert_eq!(ai.lines, vec!["bytes", "bytes"]);
+        let path = env::temp_dir().join(format!("cgw-stdin-{}", std::process::id()));
+        File::create(&path).unwrap().write_all(b"file\n").unwrap();
+        let mut game = GameSetting::new("sh")
+            .args(vec!["-c", "read a; printf $a; exec sleep 10"])
+            .stdin_from(path.clone())
+            .max_loop(3)
+            .build()
+            .unwrap();
+        let mut ai = IgnoringAI { lines: Vec::new() };
+        game.play(&mut ai);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(ai.lines, vec!["file"]);
+    }
+    #[test]
+    fn test_build_error() {
+        use super::*;
+        let err = |gs: GameSetting| gs.build().err().expect("build must fail");
+        match err(GameSetting::new("cgw-no-such-command")) {
+            CgwError::CommandNotFound(ref c) => assert_eq!(c, "cgw-no-such-command"),
+            e => panic!("unexpected error: {}", e),
+        }
+        match err(GameSetting::new("cat").env("PATH", "/cgw-no-such-dir")) {
+            CgwError::CommandNotFound(_) => {}
+            e => panic!("unexpected error: {}", e),
+        }
+        match err(GameSetting::new("cat").lines(0)) {
+            CgwError::InvalidSetting(_) => {}
+            e => panic!("unexpected error: {}", e),
+        }
+        match err(GameSetting::new("cat").columns(100_000)) {
+            CgwError::InvalidSetting(_) => {}
+            e => panic!("unexpected error: {}", e),
+        }
+        match err(GameSetting::new("cat").stop_on("died", "You die(")) {
+            CgwError::InvalidSetting(_) => {}
+            e => panic!("unexpected error: {}", e),
+        }
+        match err(GameSetting::new("cat").debug_file("/cgw-no-such-dir/debug.txt")) {
+            CgwError::Io(..) => {}
+            e => panic!("unexpected error: {}", e),
+        }
+        match err(GameSetting::new("cat").transcript_file("/cgw-no-such-dir/t")) {
+            CgwError::Io(..) => {}
+            e => panic!("unexpected error: {}", e),
+        }
+        match err(GameSetting::new("cat").setup_command("exit 1")) {
+            CgwError::SetupFailed(_) => {}
+            e => panic!("unexpected error: {}", e),
+        }
+        assert!(GameSetting::new("/bin/cat").build().is_ok());
+    }
+    #[test]
+    fn test_settle() {
+        use super::*;
+        let play = |settle: u64| {
+            let mut game = GameSetting::new("sh")
+                .args(vec!["-c", "printf a; sleep 0.2; printf b; exec sleep 10"])
+                .timeout(Duration::from_millis(500))
+                .settle(Duration::from_millis(settle))
+                .max_loop(3)
+                .build()
+                .unwrap();
+            let mut ai = RecordAI { lines: Vec::new() };
+            game.play(&mut ai);
+            ai.lines
+        };
+        assert_eq!(play(20), vec!["a", "ab"]);
+        assert_eq!(play(400), vec!["ab"]);
+    }
+    #[test]
+    fn test_fast_forward() {
+        use super::*;
+        let mut game = GameSetting::new("sh")
+            .args(vec!["-c", "printf a; exec sleep 10"])
+            .timeout(Duration::from_secs(1))
+            .draw_on(Duration::from_secs(1))
+            .pacing(Pacing::FastForward { adaptive: true })
+            .max_loop(6)
+            .build()
+            .unwrap();
+        let start = Instant::now();
+        let metrics = game.play(&mut EmptyAI);
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert_eq!(metrics.turns, 6);
+        assert_eq!(metrics.frames, 1);
+    }
+    #[test]
+    fn test_viewer_error() {
+        use super::*;
+        // the viewer can't make stdout raw unless it's a terminal
+        if unsafe { libc::isatty(1) } != 0 {
+            return;
+        }
+        let mut game = GameSetting::new("sh")
+            .args(vec!["-c", "printf a; exec sleep 10"])
+            .timeout(Duration::from_millis(50))
+            .draw_on(Duration::from_millis(1))
+            .max_loop(3)
+            .build()
+            .unwrap();
+        let metrics = game.play(&mut EmptyAI);
+        assert_eq!(metrics.frames, 1);
+        match game.error() {
+            Some(&CgwError::Viewer(ref s)) => assert!(s.contains("raw stdout")),
+            e => panic!("unexpected error: {:?}", e),
+        }
+        assert!(metrics.stop_reason.unwrap().starts_with("viewer failed"));
+        let mut game = GameSetting::new("sh")
+            .args(vec!["-c", "printf a; exec sleep 10"])
+            .timeout(Duration::from_millis(50))
+            .max_loop(3)
+            .build()
+            .unwrap();
+        game.play(&mut EmptyAI);
+        assert!(game.error().is_none());
+    }
+    #[test]
+    fn test_real_time() {
+        use super::*;
+        use std::sync::Mutex;
+        let keys = Arc::new(Mutex::new(Vec::new()));
+        let received = Arc::clone(&keys);
+        let game = MockGame::new(move |k: &[u8]| {
+            received.lock().unwrap().push((k.to_vec(), Instant::now()));
+            Some(b"@".to_vec())
+        });
+        struct ArrowAI;
+        impl Reactor for ArrowAI {
+            fn action(&mut self, _screen: ActionResult, turn: usize) -> Option<Vec<u8>> {
+                if turn == 1 {
+                    Some(b"2\x1b[Ah".to_vec())
+                } else {
+                    None
+                }
+            }
+        }
+        let mut game = GameSetting::mock(game)
+            .timeout(Duration::from_millis(1))
+            .pacing(Pacing::RealTime {
+                fps: 20,
+                key_delay: Duration::from_millis(30),
+            })
+            .max_loop(6)
+            .build()
+            .unwrap();
+        let start = Instant::now();
+        game.play(&mut ArrowAI);
+        assert!(start.elapsed() >= Duration::from_millis(250));
+        let keys = keys.lock().unwrap();
+        let sent: Vec<_> = keys.iter().map(|k| &k.0[..]).collect();
+        let expected: Vec<&[u8]> = vec![b"", b"2", b"\x1b[A", b"h"];
+        assert_eq!(sent, expected);
+        for w in keys[1..].windows(2) {
+            assert!(w[1].1 - w[0].1 >= Duration::from_millis(30));
+        }
+    }
+    #[test]
+    fn test_play_mapped() {
+        use super::*;
+        struct LenAI {
+            lens: Vec<usize>,
+        }
+        impl Reactor<usize> for LenAI {
+            fn action(&mut self, res: ActionResult<usize>, _turn: usize) -> Option<Vec<u8>> {
+                if let ActionResult::Changed(len) = res {
+                    self.lens.push(len);
+                }
+                None
+            }
+        }
+        let mut game = GameSetting::new("sh")
+            .args(vec!["-c", "printf hello; exec sleep 10"])
+            .max_loop(3)
+            .build()
+            .unwrap();
+        let mut ai = LenAI { lens: Vec::new() };
+        let mut first_line_len = |s: &Screen| s.trimmed_line(0).len();
+        game.play_mapped(&mut first_line_len, &mut ai);
+        assert_eq!(ai.lens, vec![5]);
+    }
+    #[test]
+    fn test_decoder() {
+        use super::*;
+        let mut game = GameSetting::new("sh")
+            .args(vec!["-c", "printf 'HP:10\\r\\nab'; exec sleep 10"])
+            .lines(3)
+            .decoder(MaskLines::new(0..1))
+            .decoder(|s: &mut Screen| s[1][0] = b'*')
+            .build()
+            .unwrap();
+        let mut handle = game.handle();
+        handle.next_screen(Duration::from_secs(1));
+        assert_eq!(handle.screen().to_trimmed_string(), "\n*b");
+    }
+    #[test]
+    fn test_filter() {
+        use super::*;
+        struct ResultAI {
+            results: Vec<&'static str>,
+        }
+        impl Reactor for ResultAI {
+            fn action(&mut self, res: ActionResult, turn: usize) -> Option<Vec<u8>> {
+                self.results.push(res.name());
+                match turn {
+                    1 => Some(b"w".to_vec()),
+                    2 => Some(b"k".to_vec()),
+                    _ => None,
+                }
+            }
+        }
+        // 'w' only ticks the clock
+        let game = MockGame::new(|keys: &[u8]| {
+            Some(match keys {
+                b"" => &b"@ 00"[..],
+                b"w" => b"\x1b[1;3H01",
+                _ => b"\x1b[2;1H.",
+            }.to_vec())
+        });
+        let mut game = GameSetting::mock(game)
+            .lines(2)
+            .columns(4)
+            .filter(MaskLines::new(0..1).columns(2..4))
+            .decoder(|s: &mut Screen| s[0][1] = b'*')
+            .timeout(Duration::from_millis(10))
+            .max_loop(4)
+            .build()
+            .unwrap();
+        let mut ai = ResultAI { results: Vec::new() };
+        let metrics = game.play(&mut ai);
+        let expected = vec!["Changed", "NotChanged", "Changed", "NotChanged", "GameEnded"];
+        assert_eq!(ai.results, expected);
+        assert_eq!(metrics.frames, 2);
+        let mut handle = game.handle();
+        let timeout = Duration::from_millis(100);
+        match handle.next_screen(timeout) {
+            ActionResult::Changed(screen) => assert_eq!(screen.to_trimmed_string(), "@*"),
+            res => panic!("unexpected {:?}", res),
+        }
+        handle.send(b"w").unwrap();
+        assert_eq!(handle.next_screen(timeout).name(), "NotChanged");
+        handle.send(b"k").unwrap();
+        assert_eq!(handle.next_screen(timeout).name(), "Changed");
+    }
+    #[test]
+    fn test_key_map() {
+        use super::*;
+        use std::sync::Mutex;
+        struct NorthAI;
+        impl Reactor for NorthAI {
+            fn action(&mut self, _res: ActionResult, turn: usize) -> Option<Vec<u8>> {
+                if turn == 1 {
+                    Some(b"NyN".to_vec())
+                } else {
+                    None
+                }
+            }
+        }
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let game = {
+            let received = Arc::clone(&received);
+            MockGame::new(move |keys: &[u8]| {
+                received.lock().unwrap().push(keys.to_vec());
+                Some(b"@".to_vec())
+            })
+        };
+        let mut game = GameSetting::mock(game)
+            .key_map(KeyMap::new().map(b"N", b"k").swap(b"y", b"z"))
+            .timeout(Duration::from_millis(10))
+            .max_loop(2)
+            .build()
+            .unwrap();
+        game.play(&mut NorthAI);
+        {
+            let mut handle = game.handle();
+            handle.send(b"N").unwrap();
+            handle.send_key(Key::Up).unwrap();
+            handle.next_screen(Duration::from_millis(100));
+        }
+        let expected: Vec<&[u8]> = vec![b"", b"kzk", b"", b"k", b"\x1b[A"];
+        assert_eq!(*received.lock().unwrap(), expected);
+    }
+    #[test]
+    fn test_key_log() {
+        use super::*;
+        use keylog::read_key_log;
+        use std::sync::Mutex;
+        struct TwoKeysAI;
+        impl Reactor for TwoKeysAI {
+            fn action(&mut self, _res: ActionResult, turn: usize) -> Option<Vec<u8>> {
+                match turn {
+                    1 => Some(b"a".to_vec()),
+                    3 => Some(b"bc".to_vec()),
+                    _ => None,
+                }
+            }
+        }
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let game = {
+            let received = Arc::clone(&received);
+            MockGame::new(move |keys: &[u8]| {
+                received.lock().unwrap().push(keys.to_vec());
+                Some(keys.to_vec())
+            })
+        };
+        let path = env::temp_dir().join(format!("cgw-keys-{}", std::process::id()));
+        let mut game = GameSetting::mock(game)
+            .key_log_file(path.to_str().unwrap())
+            .timeout(Duration::from_millis(10))
+            .max_loop(4)
+            .build()
+            .unwrap();
+        game.play(&mut TwoKeysAI);
+        let games = read_key_log(File::open(&path).unwrap()).unwrap();
+        assert_eq!(games.len(), 1);
+        let keys: Vec<_> = games[0].iter().map(|r| &r.bytes[..]).collect();
+        assert_eq!(keys, vec![&b"a"[..], b"bc"]);
+        assert!(games[0][1].time >= Duration::from_millis(20));
+        {
+            let mut handle = game.handle();
+            let start = Instant::now();
+            handle.replay_keys(&games[0]).unwrap();
+            assert!(start.elapsed() >= games[0][1].time);
+            handle.next_screen(Duration::from_millis(100));
+            assert!(handle.screen().to_string().starts_with("abc"));
+        }
+        let expected: Vec<&[u8]> = vec![b"", b"a", b"bc", b"", b"a", b"bc"];
+        assert_eq!(*received.lock().unwrap(), expected);
+        let replayed = read_key_log(File::open(&path).unwrap()).unwrap();
+        assert_eq!(replayed.len(), 2);
+        assert_eq!(replayed[1].len(), 2);
+        assert_eq!(replayed[1][1].bytes, b"bc".to_vec());
+        std::fs::remove_file(&path).unwrap();
+    }
+    #[test]
+    fn test_dataset() {
+        use super::*;
+        use dataset::read_dataset;
+        struct TypeAI;
+        impl Reactor for TypeAI {
+            fn action(&mut self, res: ActionResult, _turn: usize) -> Option<Vec<u8>> {
+                match res {
+                    ActionResult::Changed(_) => Some(b"a".to_vec()),
+                    _ => None,
+                }
+            }
+        }
+        let path = env::temp_dir().join(format!("cgw-dataset-{}", std::process::id()));
+        // prints "x" when it starts, and echoes keys
+        let game = MockGame::new(|keys: &[u8]| match keys {
+            b"" => Some(b"x".to_vec()),
+            _ => Some(keys.to_vec()),
+        });
+        let mut game = GameSetting::mock(game)
+            .dataset_file(path.to_str().unwrap())
+            .reward(|_: Option<&Screen>, _: &Screen| 1.0)
+            .timeout(Duration::from_millis(10))
+            .max_loop(6)
+            .build()
+            .unwrap();
+        game.play(&mut TypeAI);
+        game.play(&mut TypeAI);
+        let games = read_dataset(File::open(&path).unwrap()).unwrap();
+        assert_eq!(games.len(), 2);
+        let turns: Vec<_> = games[0].iter().map(|r| r.turn).collect();
+        assert_eq!(turns, (1..7).collect::<Vec<_>>());
+        let screens: Vec<_> = games[0]
+            .iter()
+            .filter_map(|r| match r.result {
+                ActionResult::Changed(ref s) => Some(s.trimmed_line(0).into_owned()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(screens[..2], ["x".to_owned(), "xa".to_owned()]);
+        for rec in &games[0] {
+            match rec.result {
+                ActionResult::Changed(_) => {
+                    assert_eq!(rec.action, Some(b"a".to_vec()));
+                    assert_eq!(rec.reward, Some(1.0));
+                }
+                _ => {
+                    assert_eq!(rec.action, None);
+                    assert_eq!(rec.reward, None);
+                }
+            }
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+    #[test]
+    fn test_restore() {
+        use super::*;
+        use keylog::read_key_log;
+        use std::sync::Mutex;
+        // types "ab", remembering the first screen
+        struct TypeAI {
+            first: Option<(usize, String)>,
+        }
+        impl Reactor for TypeAI {
+            fn action(&mut self, res: ActionResult, turn: usize) -> Option<Vec<u8>> {
+                let line = match res {
+                    ActionResult::Changed(screen) => screen.trimmed_line(0).into_owned(),
+                    _ => return None,
+                };
+                if self.first.is_none() {
+                    self.first = Some((turn, line.clone()));
+                }
+                match &line[..] {
+                    ">" => Some(b"a".to_vec()),
+                    ">a" => Some(b"b".to_vec()),
+                    _ => None,
+                }
+            }
+        }
+        let typed = Arc::new(Mutex::new(Vec::new()));
+        let game = {
+            let typed = Arc::clone(&typed);
+            MockGame::new(move |keys: &[u8]| {
+                let mut typed = typed.lock().unwrap();
+                if keys.is_empty() {
+                    typed.clear();
+                }
+                typed.extend_from_slice(keys);
+                Some([&b"\x1b[H>"[..], &typed].concat())
+            })
+        };
+        let path = env::temp_dir().join(format!("cgw-restore-{}", std::process::id()));
+        let mut game = GameSetting::mock(game)
+            .key_log_file(path.to_str().unwrap())
+            .timeout(Duration::from_millis(20))
+            .max_loop(6)
+            .build()
+            .unwrap();
+        let mut ai = TypeAI { first: None };
+        game.play(&mut ai);
+        assert_eq!(ai.first, Some((1, ">".to_owned())));
+        let mut records = read_key_log(File::open(&path).unwrap()).unwrap().remove(0);
+        assert_eq!(records.len(), 2);
+        game.restore(records.clone(), true);
+        let mut ai = TypeAI { first: None };
+        let metrics = game.play(&mut ai);
+        assert!(game.error().is_none());
+        assert_eq!(ai.first, Some((1, ">ab".to_owned())));
+        assert_eq!(metrics.turns, 6);
+        // replayed keys are recorded again
+        let games = read_key_log(File::open(&path).unwrap()).unwrap();
+        assert_eq!(games[1].len(), 2);
+        assert_eq!(games[1][1].bytes, b"b".to_vec());
+        assert_eq!(games[1][1].screen_hash, records[1].screen_hash);
+        records[1].screen_hash = Some(0);
+        game.restore(records, true);
+        let mut ai = TypeAI { first: None };
+        game.play(&mut ai);
+        assert_eq!(ai.first, None);
+        match game.error() {
+            Some(&CgwError::ReplayDiverged(ref msg)) => assert!(msg.contains("keys 2 of 2")),
+            e => panic!("unexpected error {:?}", e),
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+    #[test]
+    fn test_adapter() {
+        use super::*;
+        use games::nethack::NetHack;
+        use games::rogue::Rogue;
+        use std::sync::Mutex;
+        struct QuitAI;
+        impl Reactor for QuitAI {
+            fn action(&mut self, _res: ActionResult, turn: usize) -> Option<Vec<u8>> {
+                if turn == 1 {
+                    Some(Rogue.quit_keys())
+                } else {
+                    None
+                }
+            }
+        }
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let game = {
+            let received = Arc::clone(&received);
+            MockGame::new(move |keys: &[u8]| {
+                received.lock().unwrap().push(keys.to_vec());
+                match keys {
+                    b"Qy" => Some(b"\x1b[2JYou quit with 35 gold pieces".to_vec()),
+                    _ => Some(b"@".to_vec()),
+                }
+            })
+        };
+        let setting = GameSetting::mock(game)
+            .timeout(Duration::from_millis(10))
+            .max_loop(5);
+        let mut game = setting.clone().adapter(Rogue).build().unwrap();
+        assert_eq!(game.adapter().map(|a| a.name()), Some("rogue"));
+        let metrics = game.play(&mut QuitAI);
+        assert_eq!(metrics.outcome, Some(Outcome::Quit));
+        assert_eq!(metrics.result.and_then(|r| r.gold), Some(35));
+        {
+            let mut handle = game.handle();
+            handle.quit().unwrap();
+        }
+        let mut game = setting.clone().adapter(NetHack::new()).build().unwrap();
+        {
+            let mut handle = game.handle();
+            handle.send_meta(b'x').unwrap();
+            handle.quit().unwrap();
+        }
+        let expected: Vec<&[u8]> = vec![b"", b"Qy", b"", b"Qy", b"", b"\xf8", b"#quit\ny"];
+        assert_eq!(*received.lock().unwrap(), expected);
+        let mut game = setting.build().unwrap();
+        assert!(game.adapter().is_none());
+        assert!(game.handle().quit().is_err());
+    }
+    #[test]
+    fn test_sanitize_input() {
+        use super::*;
+        use std::sync::Mutex;
+        struct BuggyAI;
+        impl Reactor for BuggyAI {
+            fn action(&mut self, _res: ActionResult, turn: usize) -> Option<Vec<u8>> {
+                match turn {
+                    1 => Some(b"a\0b".to_vec()),
+                    2 => Some(b"\x1b[A".to_vec()),
+                    3 => Some(b"hjkl".to_vec()),
+                    _ => None,
+                }
+            }
+        }
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let game = {
+            let received = Arc::clone(&received);
+            MockGame::new(move |keys: &[u8]| {
+                received.lock().unwrap().push(keys.to_vec());
+                Some(keys.to_vec())
+            })
+        };
+        let sanitizer = InputSanitizer::new().max_len(2).reject_esc(true);
+        let mut game = GameSetting::mock(game)
+            .sanitize_input(sanitizer)
+            .timeout(Duration::from_millis(10))
+            .max_loop(4)
+            .build()
+            .unwrap();
+        let metrics = game.play(&mut BuggyAI);
+        assert_eq!(metrics.rejected, 1);
+        assert_eq!(metrics.bytes_written, 4);
+        let expected: Vec<&[u8]> = vec![b"", b"ab", b"hj"];
+        assert_eq!(*received.lock().unwrap(), expected);
+        let mut handle = game.handle();
+        let err = handle.send(b"\x1bOP").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(handle.metrics().rejected, 1);
+    }
+    #[test]
+    fn test_reward() {
+        use super::*;
+        struct RewardAI {
+            rewards: Vec<f64>,
+        }
+        impl Reactor for RewardAI {
+            fn action(&mut self, _screen: ActionResult, _turn: usize) -> Option<Vec<u8>> {
+                None
+            }
+            fn on_reward(&mut self, reward: f64) {
+                self.rewards.push(reward);
+            }
+        }
+        let mut game = GameSetting::new("sh")
+            .args(vec!["-c", "printf 'Gold: 3'; sleep 0.3; printf '\\rGold: 10'; exec sleep 10"])
+            .settle(Duration::from_millis(20))
+            .timeout(Duration::from_millis(500))
+            .max_loop(3)
+            .reward(CounterDelta::new("Gold:"))
+            .reward(|_: Option<&Screen>, _: &Screen| -1.0)
+            .build()
+            .unwrap();
+        let mut ai = RewardAI { rewards: Vec::new() };
+        let metrics = game.play(&mut ai);
+        assert_eq!(ai.rewards, vec![-1.0, 6.0]);
+        assert_eq!(metrics.reward, 5.0);
+    }
+    #[test]
+    fn test_idle() {
+        use super::*;
+        // escalates by 'l' after 3 idle turns
+        struct IdleAI {
+            streaks: Vec<IdleStreak>,
+        }
+        impl Reactor for IdleAI {
+            fn action(&mut self, res: ActionResult, _turn: usize) -> Option<Vec<u8>> {
+                match res {
+                    ActionResult::NotChanged if self.streaks.last().unwrap().turns == 3 => {
+                        Some(b"l".to_vec())
+                    }
+                    _ => None,
+                }
+            }
+            fn on_idle(&mut self, idle: IdleStreak) {
+                self.streaks.push(idle);
+            }
+        }
+        let game = MockGame::new(|keys: &[u8]| {
+            Some(if keys.is_empty() { b"a" } else { b"b" }.to_vec())
+        });
+        let mut game = GameSetting::mock(game)
+            .timeout(Duration::from_millis(10))
+            .max_loop(7)
+            .build()
+            .unwrap();
+        let mut ai = IdleAI { streaks: Vec::new() };
+        game.play(&mut ai);
+        let turns: Vec<_> = ai.streaks.iter().map(|s| s.turns).collect();
+        assert_eq!(turns, vec![1, 2, 3, 1, 2]);
+        assert!(ai.streaks[2].since_change > ai.streaks[0].since_change);
+        assert!(ai.streaks[3].since_change < ai.streaks[2].since_change);
+    }
+    #[test]
+    fn test_term_mode() {
+        use super::*;
+        struct ModeAI {
+            modes: Vec<TermMode>,
+        }
+        impl Reactor for ModeAI {
+            fn action(&mut self, _res: ActionResult, _turn: usize) -> Option<Vec<u8>> {
+                Some(b"k".to_vec())
+            }
+            fn on_term_mode(&mut self, mode: TermMode) {
+                self.modes.push(mode);
+            }
+        }
+        // a new frame for each key, changing modes only by the second one
+        let mut count = 0;
+        let game = MockGame::new(move |_keys: &[u8]| {
+            count += 1;
+            Some(match count {
+                2 => format!("{}\x1b[?1h\x1b[?2004h\x1b[?25l", count),
+                _ => count.to_string(),
+            }.into_bytes())
+        });
+        let mut game = GameSetting::mock(game)
+            .term(TermType::Xterm)
+            .timeout(Duration::from_millis(10))
+            .max_loop(8)
+            .build()
+            .unwrap();
+        let mut ai = ModeAI { modes: Vec::new() };
+        game.play(&mut ai);
+        let changed = TermMode::APP_CURSOR | TermMode::BRACKETED_PASTE | TermMode::LINE_WRAP;
+        assert_eq!(ai.modes, vec![TermMode::default(), changed]);
+    }
+    #[test]
+    fn test_scrollback() {
+        use super::*;
+        struct ScrollAI {
+            lines: Vec<Vec<Vec<u8>>>,
+        }
+        impl Reactor for ScrollAI {
+            fn action(&mut self, _res: ActionResult, turn: usize) -> Option<Vec<u8>> {
+                if turn == 1 {
+                    Some(b"k".to_vec())
+                } else {
+                    None
+                }
+            }
+            fn on_scrollback(&mut self, lines: &[Vec<u8>]) {
+                self.lines.push(lines.to_vec());
+            }
+        }
+        let game = MockGame::new(|keys: &[u8]| {
+            Some(if keys.is_empty() { &b"a\r\nb"[..] } else { b"\r\nc\r\nd\r\ne" }.to_vec())
+        });
+        let mut game = GameSetting::mock(game)
+            .lines(2)
+            .columns(2)
+            .scrollback(2)
+            .timeout(Duration::from_millis(10))
+            .max_loop(4)
+            .build()
+            .unwrap();
+        let mut ai = ScrollAI { lines: Vec::new() };
+        game.play(&mut ai);
+        // only the last 2 lines are kept
+        assert_eq!(ai.lines, vec![vec![b"b ".to_vec(), b"c ".to_vec()]]);
+    }
+    #[test]
+    fn test_terminal_events() {
+        use super::*;
+        struct EventAI {
+            events: Vec<TerminalEvent>,
+        }
+        impl Reactor for EventAI {
+            fn action(&mut self, _res: ActionResult, turn: usize) -> Option<Vec<u8>> {
+                if turn == 1 {
+                    Some(b"k".to_vec())
+                } else {
+                    None
+                }
+            }
+            fn on_terminal_event(&mut self, event: &TerminalEvent) {
+                self.events.push(event.clone());
+            }
+        }
+        let game = MockGame::new(|keys: &[u8]| {
+            Some(if keys.is_empty() { &b"@"[..] } else { b"\x1b[2;1H." }.to_vec())
+        });
+        let mut game = GameSetting::mock(game)
+            .terminal_events(true)
+            .timeout(Duration::from_millis(10))
+            .max_loop(4)
+            .build()
+            .unwrap();
+        let mut ai = EventAI { events: Vec::new() };
+        game.play(&mut ai);
+        assert_eq!(
+            ai.events,
+            vec![
+                TerminalEvent::Print { y: 0, x: 0, text: b"@".to_vec() },
+                TerminalEvent::CursorMove { y: 1, x: 0 },
+                TerminalEvent::Print { y: 1, x: 0, text: b".".to_vec() },
+            ]
+        );
+    }
+    #[test]
+    fn test_latency() {
+        use super::*;
+        struct LatencyAI {
+            latencies: Vec<Option<Duration>>,
+        }
+        impl Reactor for LatencyAI {
+            fn action(&mut self, _res: ActionResult, turn: usize) -> Option<Vec<u8>> {
+                Some(if turn == 1 { b"k" } else { b"n" }.to_vec())
+            }
+            fn on_turn_stats(&mut self, stats: TurnStats) {
+                self.latencies.push(stats.latency);
+            }
+        }
+        // responds to 'k' after a while, and ignores 'n'
+        let game = MockGame::new(|keys: &[u8]| match keys {
+            b"n" => Some(Vec::new()),
+            b"k" => {
+                thread::sleep(Duration::from_millis(30));
+                Some(b"k".to_vec())
+            }
+            _ => Some(b"@".to_vec()),
+        });
+        let mut game = GameSetting::mock(game)
+            .timeout(Duration::from_millis(10))
+            .max_loop(3)
+            .build()
+            .unwrap();
+        let mut ai = LatencyAI { latencies: Vec::new() };
+        game.play(&mut ai);
+        assert_eq!(ai.latencies.len(), 3);
+        assert_eq!(ai.latencies[0], None);
+        assert!(ai.latencies[1].unwrap() >= Duration::from_millis(30));
+        assert_eq!(ai.latencies[2], None);
+    }
+    #[test]
+    fn test_flow_control() {
+        use super::*;
+        // the game echoes a key after XON
+        let script = "printf 'a\\023'; sleep 0.3; printf '\\021'; head -c 1; exec sleep 10";
+        let mut game = GameSetting::new("sh")
+            .args(vec!["-c", script])
+            .flow_control(true)
+            .settle(Duration::from_millis(20))
+            .build()
+            .unwrap();
+        let mut handle = game.handle();
+        handle.next_screen(Duration::from_secs(1));
+        handle.send(b"x").unwrap();
+        assert_eq!(handle.held, vec![(b"x".to_vec(), false)]);
+        // not written yet
+        assert_eq!(handle.metrics.bytes_written, 0);
+        handle.next_screen(Duration::from_secs(1));
+        assert!(handle.held.is_empty());
+        assert_eq!(handle.metrics.bytes_written, 1);
+        handle.next_screen(Duration::from_secs(1));
+        assert_eq!(handle.screen().trimmed_line(0), "ax");
+    }
+    #[test]
+    fn test_stop() {
+        use super::*;
+        struct ResultsAI {
+            results: Vec<String>,
+        }
+        impl Reactor for ResultsAI {
+            fn action(&mut self, res: ActionResult, _turn: usize) -> Option<Vec<u8>> {
+                self.results.push(match res {
+                    ActionResult::Changed(screen) => screen.trimmed_line(0).into_owned(),
+                    res => res.name().to_owned(),
+                });
+                None
+            }
+        }
+        let mut game = GameSetting::new("sh")
+            .args(vec!["-c", "printf 'You die...'; exec sleep 10"])
+            .max_loop(100)
+            .stop_when("never", |_: &Screen| false)
+            .stop_on("died", "^You die")
+            .build()
+            .unwrap();
+        let mut ai = ResultsAI { results: Vec::new() };
+        let metrics = game.play(&mut ai);
+        assert_eq!(metrics.stop_reason, Some("died".to_owned()));
+        assert_eq!(ai.results.last().map(|s| &s[..]), Some("GameEnded"));
+        assert_eq!(ai.results[ai.results.len() - 2], "You die...");
+        assert!(metrics.turns < 100);
+        assert_eq!(metrics.outcome, None);
+        let mut game = GameSetting::new("sh")
+            .args(vec!["-c", "printf 'You die...'; exec sleep 10"])
+            .max_loop(100)
+            .stop_on("died", "^You die")
+            .game_over(games::nethack::game_over())
+            .build()
+            .unwrap();
+        let metrics = game.play(&mut ai);
+        assert_eq!(metrics.stop_reason, Some("died".to_owned()));
+        assert_eq!(metrics.outcome, Some(Outcome::Died));
+        assert_eq!(metrics.result, None);
+        let mut game = GameSetting::new("sh")
+            .args(vec!["-c", "printf 'Killed by a bat with 3 gold'; exec sleep 10"])
+            .max_loop(100)
+            .game_over(games::rogue::game_over())
+            .stop_when("never", |_: &Screen| false)
+            .build()
+            .unwrap();
+        let metrics = game.play(&mut ai);
+        assert_eq!(metrics.stop_reason, Some("died".to_owned()));
+        assert_eq!(metrics.result.and_then(|r| r.cause), Some("killed by a bat".to_owned()));
+    }
+    #[test]
+    fn test_results_file() {
+        use super::*;
+        let path = env::temp_dir().join(format!("cgw-results-{}.jsonl", std::process::id()));
+        let path = path.to_str().unwrap();
+        let setting = || {
+            GameSetting::new("sh")
+                .args(vec!["-c", "printf 'You die...'; exec sleep 10"])
+                .max_loop(5)
+                .stop_on("died", "You die")
+                .results_file(path, ResultFormat::Jsonl)
+        };
+        let hash = setting().config_hash();
+        assert_eq!(hash, setting().config_hash());
+        assert_ne!(hash, setting().seed(1).config_hash());
+        for _ in 0..2 {
+            let mut game = setting().build().unwrap();
+            assert_eq!(game.config_hash(), hash);
+            game.play(&mut EmptyAI);
+        }
+        let mut lines = String::new();
+        File::open(path).unwrap().read_to_string(&mut lines).unwrap();
+        std::fs::remove_file(path).unwrap();
+        let lines: Vec<_> = lines.lines().collect();
+        assert_eq!(lines.len(), 2);
+        for line in lines {
+            assert!(line.contains(&format!("\"episode\":0,\"config_hash\":\"{}\"", hash)));
+            assert!(line.contains("\"stop_reason\":\"died\""));
+        }
+    }
+    #[test]
+    fn test_mock_game() {
+        use super::*;
+        // moves @ right by 'l', and exits by 'q'
+        let mut x = 0;
+        let game = MockGame::new(move |keys: &[u8]| {
+            if keys.is_empty() {
+                x = 0;
+            }
+            x += keys.iter().filter(|&&k| k == b'l').count();
+            if keys.contains(&b'q') {
+                return None;
+            }
+            Some(format!("\x1b[2J\x1b[1;{}H@", x + 1).into_bytes())
+        });
+        struct RightAI {
+            xs: Vec<usize>,
+        }
+        impl Reactor for RightAI {
+            fn action(&mut self, res: ActionResult, _turn: usize) -> Option<Vec<u8>> {
+                match res {
+                    ActionResult::Changed(screen) => {
+                        self.xs.push(screen.trimmed_line(0).len() - 1);
+                        Some(if self.xs.len() < 3 { b"l" } else { b"q" }.to_vec())
+                    }
+                    _ => None,
+                }
+            }
+        }
+        let mut env = GameSetting::mock(game)
+            .timeout(Duration::from_millis(5))
+            .max_loop(100)
+            .build()
+            .unwrap();
+        for _ in 0..2 {
+            let mut ai = RightAI { xs: Vec::new() };
+            let metrics = env.play(&mut ai);
+            assert_eq!(ai.xs, vec![0, 1, 2]);
+            assert_eq!(metrics.turns, 4);
+        }
+    }
+    #[test]
+    fn test_connect() {
+        use super::*;
+        use std::ffi::CString;
+        use std::fs;
+        use std::os::unix::net::UnixListener;
+        use std::process;
+        // prints "hello", and echoes a key
+        fn harness<R: Read, W: Write>(mut r: R, mut w: W) {
+            w.write_all(b"hello").unwrap();
+            let mut key = [0; 1];
+            r.read_exact(&mut key).unwrap();
+            w.write_all(&key).unwrap();
+            // until cgw closes the connection
+            r.read_to_end(&mut Vec::new()).ok();
+        }
+        struct EchoAI {
+            lines: Vec<String>,
+        }
+        impl Reactor for EchoAI {
+            fn action(&mut self, res: ActionResult, _turn: usize) -> Option<Vec<u8>> {
+                match res {
+                    ActionResult::Changed(screen) => {
+                        let line = screen.trimmed_line(0).into_owned();
+                        let done = line == "hellox";
+                        self.lines.push(line);
+                        if done {
+                            None
+                        } else {
+                            Some(b"x".to_vec())
+                        }
+                    }
+                    _ => Some(Vec::new()),
+                }
+            }
+        }
+        let play = |io: GameIo| {
+            let mut ai = EchoAI { lines: Vec::new() };
+            GameSetting::connect(io)
+                .timeout(Duration::from_millis(50))
+                .max_loop(20)
+                .build()
+                .unwrap()
+                .play(&mut ai);
+            assert_eq!(ai.lines.last().map(|s| &s[..]), Some("hellox"));
+            assert!(ai.lines.iter().all(|l| l == "hello" || l == "hellox"));
+        };
+        let dir = env::temp_dir();
+        let id = process::id();
+        let sock = dir.join(format!("cgw-connect-{}.sock", id));
+        let listener = UnixListener::bind(&sock).unwrap();
+        let th = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            harness(stream.try_clone().unwrap(), stream);
+        });
+        play(GameIo::UnixSocket(sock.clone()));
+        th.join().unwrap();
+        fs::remove_file(&sock).unwrap();
+        let input = dir.join(format!("cgw-connect-{}.in", id));
+        let output = dir.join(format!("cgw-connect-{}.out", id));
+        for path in &[&input, &output] {
+            let cpath = CString::new(path.to_str().unwrap()).unwrap();
+            assert_eq!(unsafe { libc::mkfifo(cpath.as_ptr(), 0o600) }, 0);
+        }
+        let (i, o) = (input.clone(), output.clone());
+        let th = thread::spawn(move || {
+            let r = File::open(&i).unwrap();
+            harness(r, OpenOptions::new().write(true).open(&o).unwrap());
+        });
+        play(GameIo::Fifo {
+            input: input.clone(),
+            output: output.clone(),
+        });
+        th.join().unwrap();
+        fs::remove_file(&input).unwrap();
+        fs::remove_file(&output).unwrap();
+    }
+    #[test]
+    fn test_scripted_game() {
+        use super::*;
+        let script = ScriptedGame::new(b"\x1b[2J\x1b[1;1H@\x1b[2;1HHP: 10")
+            .step(b"l", b"\x1b[1;1H.@")
+            .step(b"l", b"\x1b[1;2H.@\x1b[2;5H9 ")
+            .exit(b"q");
+        struct StepAI {
+            lines: Vec<String>,
+        }
+        impl Reactor for StepAI {
+            fn action(&mut self, res: ActionResult, _turn: usize) -> Option<Vec<u8>> {
+                match res {
+                    ActionResult::Changed(screen) => {
+                        let lines = format!("{}|{}", screen.trimmed_line(0), screen.trimmed_line(1));
+                        self.lines.push(lines);
+                        Some(if self.lines.len() < 3 { b"l" } else { b"q" }.to_vec())
+                    }
+                    _ => None,
+                }
+            }
+        }
+        let mut env = GameSetting::mock(script.to_mock())
+            .columns(10)
+            .timeout(Duration::from_millis(5))
+            .max_loop(100)
+            .build()
+            .unwrap();
+        for _ in 0..2 {
+            let mut ai = StepAI { lines: Vec::new() };
+            env.play(&mut ai);
+            assert_eq!(script.remaining(), 0);
+            assert_eq!(ai.lines, vec!["@|HP: 10", ".@|HP: 10", "..@|HP: 9"]);
+        }
+    }
+    #[test]
+    #[should_panic(expected = "step 1 expected \"l\", but got \"\\x1b\"")]
+    fn test_scripted_game_mismatch() {
+        use super::*;
+        struct EscAI {
+            sent: usize,
+        }
+        impl Reactor for EscAI {
+            fn action(&mut self, res: ActionResult, _turn: usize) -> Option<Vec<u8>> {
+                match res {
+                    ActionResult::Changed(_) => {
+                        self.sent += 1;
+                        Some(if self.sent == 1 { b"l" } else { b"\x1b" }.to_vec())
+                    }
+                    _ => None,
+                }
+            }
+        }
+        let script = ScriptedGame::new(b"@").step(b"l", b".@").step(b"l", b"..@");
+        let mut env = GameSetting::mock(script.to_mock())
+            .timeout(Duration::from_millis(5))
+            .max_loop(10)
+            .build()
+            .unwrap();
+        env.play(&mut EscAI { sent: 0 });
+    }
+    #[test]
+    fn test_setup_teardown() {
+        use super::*;
+        let path = env::temp_dir().join(format!("cgw-setup-{}", std::process::id()));
+        let path = path.to_str().unwrap();
+        let setup = format!("echo ep{{episode}} > {}", path);
+        let teardown = format!("rm {}", path);
+        let game_cmd = format!("cat {}; exec sleep 10", path);
+        let mut game = GameSetting::new("sh")
+            .args(vec!["-c", &game_cmd])
+            .setup_command(&setup)
+            .teardown_command(&teardown)
+            .max_loop(3)
+            .build()
+            .unwrap();
+        let mut ai = RecordAI { lines: Vec::new() };
+        game.play(&mut ai);
+        assert!(!std::path::Path::new(path).exists());
+        game.play(&mut ai);
+        assert_eq!(ai.lines, vec!["ep0", "ep1"]);
+    }
+    #[test]
+    fn test_aux_process() {
+        use super::*;
+        let path = env::temp_dir().join(format!("cgw-aux-{}", std::process::id()));
+        let path = path.to_str().unwrap();
+        // server ignoring SIGTERM, with a child which leaves a file if it survives
+        let server = format!(
+            "trap '' TERM; echo $$ > {0}-$EP; (sleep 2; touch {0}-$EP-alive) & exec sleep 10",
+            path
+        );
+        let game_cmd = format!("cat {}-$EP; exec sleep 10", path);
+        let mut game = GameSetting::new("sh")
+            .args(vec!["-c", &game_cmd])
+            .env("EP", "{episode}")
+            .aux_process(&server, Duration::from_millis(200))
+            .max_loop(3)
+            .build()
+            .unwrap();
+        for episode in 0..2 {
+            let mut ai = RecordAI { lines: Vec::new() };
+            game.play(&mut ai);
+            assert!(ai.lines[0].parse::<u32>().is_ok());
+            std::fs::remove_file(format!("{}-{}", path, episode)).unwrap();
+        }
+        thread::sleep(Duration::from_millis(1500));
+        for episode in 0..2 {
+            assert!(!Path::new(&format!("{}-{}-alive", path, episode)).exists());
+        }
+        let res = GameSetting::new("sh")
+            .aux_process("exit 3", Duration::from_millis(100))
+            .build();
+        match res {
+            Err(CgwError::SetupFailed(msg)) => assert!(msg.contains("exit 3")),
+            _ => panic!("aux process which exited must be an error"),
+        }
+    }
+    #[test]
+    fn test_reap_game() {
+        use super::*;
+        let mut game = GameSetting::new("cat")
+            .timeout(Duration::from_millis(10))
+            .max_loop(2)
+            .build()
+            .unwrap();
+        let mut pids = Vec::new();
+        for _ in 0..3 {
+            let mut handle = game.handle();
+            pids.push(handle.process.as_ref().unwrap().my_proc.as_ref().unwrap().id());
+            handle.next_screen(Duration::from_millis(10));
+        }
+        // the pid may be reused by other tests, so check only its state
+        for pid in pids {
+            if let Ok(stat) = std::fs::read_to_string(format!("/proc/{}/stat", pid)) {
+                assert!(!stat.contains(") Z "), "game {} is a zombie", pid);
+            }
+        }
+    }
+    #[test]
+    fn test_setup_fails_later() {
+        use super::*;
+        struct EndAI {
+            ended: usize,
+        }
+        impl Reactor for EndAI {
+            fn action(&mut self, res: ActionResult, _turn: usize) -> Option<Vec<u8>> {
+                if let ActionResult::GameEnded = res {
+                    self.ended += 1;
+                }
+                None
+            }
+        }
+        let mut game = GameSetting::mock(MockGame::new(|_: &[u8]| None))
+            .setup_command("test {episode} -lt 1")
+            .timeout(Duration::from_millis(10))
+            .max_loop(2)
+            .build()
+            .unwrap();
+        let mut ai = EndAI { ended: 0 };
+        game.play(&mut ai);
+        assert!(game.error().is_none());
+        let metrics = game.play(&mut ai);
+        assert_eq!(ai.ended, 2);
+        assert_eq!(metrics.turns, 0);
+        assert!(metrics.stop_reason.unwrap().contains("test 1 -lt 1"));
+        match game.error() {
+            Some(&CgwError::SetupFailed(_)) => {}
+            e => panic!("unexpected error {:?}", e),
+        }
+        {
+            let mut handle = game.handle();
+            assert!(handle.is_ended());
+            assert!(handle.send(b"a").is_err());
+            assert_eq!(handle.next_screen(Duration::from_millis(10)).name(), "GameEnded");
+        }
+        assert!(game.error().is_some());
+        assert_eq!(game.episode(), 3);
+    }
+    #[test]
+    fn test_restart_on_crash() {
+        use super::*;
+        struct EndingAI {
+            lines: Vec<String>,
+            ended: usize,
+        }
+        impl Reactor for EndingAI {
+            fn action(&mut self, screen: ActionResult, _turn: usize) -> Option<Vec<u8>> {
+                match screen {
+                    ActionResult::Changed(screen) => {
+                        self.lines.push(screen.trimmed_line(0).into_owned())
+                    }
+                    ActionResult::GameEnded => self.ended += 1,
+                    ActionResult::NotChanged => {}
+                }
+                None
+            }
+        }
+        let path = env::temp_dir().join(format!("cgw-restart-{}", std::process::id()));
+        let path = path.to_str().unwrap();
+        // crashes in the first run
+        let game_cmd = format!(
+            "if [ -e {0} ]; then printf ok; exec sleep 10; else touch {0}; exit 1; fi",
+            path
+        );
+        let teardown = format!("rm {}", path);
+        let mut game = GameSetting::new("sh")
+            .args(vec!["-c", &game_cmd])
+            .teardown_command(&teardown)
+            .restart_on_crash(2, 5, Duration::from_millis(10))
+            .max_loop(5)
+            .build()
+            .unwrap();
+        let mut ai = EndingAI {
+            lines: Vec::new(),
+            ended: 0,
+        };
+        let metrics = game.play(&mut ai);
+        assert_eq!(metrics.restarts, 1);
+        assert_eq!(ai.lines, vec!["ok"]);
+        assert_eq!(ai.ended, 1);
+    }
+    #[test]
+    fn test_watchdog() {
+        use super::*;
+        let mut game = GameSetting::new("sh")
+            .args(vec!["-c", "read x; printf woke; exec sleep 10"])
+            .timeout(Duration::from_millis(10))
+            .watchdog(Duration::from_millis(100), WatchdogAction::Send(b"\n".to_vec()))
+            .max_loop(30)
+            .build()
+            .unwrap();
+        let mut ai = RecordAI { lines: Vec::new() };
+        let metrics = game.play(&mut ai);
+        assert_eq!(ai.lines, vec!["woke"]);
+        assert!(!metrics.hung);
+        let mut game = GameSetting::new("sh")
+            .args(vec!["-c", "exec sleep 10"])
+            .timeout(Duration::from_millis(10))
+            .watchdog(Duration::from_millis(100), WatchdogAction::Kill)
+            .max_loop(1000)
+            .build()
+            .unwrap();
+        let metrics = game.play(&mut ai);
+        assert!(metrics.hung);
+        assert!(metrics.turns < 1000);
+    }
+    #[test]
+    fn test_bounded_shutdown() {
+        use super::*;
+        // background sleep keeps stdout open after the game is killed
+        let mut game = GameSetting::new("sh")
+            .args(vec!["-c", "sleep 3 & exec sleep 10"])
+            .max_loop(2)
+            .build()
+            .unwrap();
+        let start = Instant::now();
+        game.play(&mut EmptyAI);
+        assert!(start.elapsed() < Duration::from_millis(2500));
+    }
+    #[test]
+    fn test_prompt_kill() {
+        use super::*;
+        use games::rogue::Rogue;
+        // the game ignores the quit keys, and a background sleep keeps stdout open
+        let mut game = GameSetting::new("sh")
+            .args(vec!["-c", "sleep 3 & while :; do read x; done"])
+            .adapter(Rogue)
+            .build()
+            .unwrap();
+        let start = Instant::now();
+        {
+            let mut handle = game.handle();
+            handle.quit().unwrap();
+            handle.next_screen(Duration::from_millis(100));
+            assert!(!handle.is_ended());
+        }
+        assert!(start.elapsed() < Duration::from_millis(JOIN_TIMEOUT_MS));
+    }
+    #[test]
+    fn test_large_output() {
+        use super::*;
+        // more than the read buffer at once
+        let mut game = GameSetting::new("sh")
+            .args(vec!["-c", "head -c 10000 /dev/zero | tr '\\0' a; printf b; exec sleep 10"])
+            .build()
+            .unwrap();
+        let mut handle = game.handle();
+        handle.next_screen(Duration::from_secs(1));
+        let screen = handle.screen();
+        assert_eq!(screen.trimmed_line(22), "a".repeat(80));
+        assert_eq!(screen.trimmed_line(23), "b");
+    }
+    #[test]
+    fn test_handle() {
+        use super::*;
+        let mut game = GameSetting::new("cat").build().unwrap();
+        {
+            let mut handle = game.handle();
+            handle.send(b"hello").unwrap();
+            match handle.next_screen(Duration::from_secs(1)) {
+                ActionResult::Changed(screen) => assert_eq!(screen.trimmed_line(0), "hello"),
+                res => panic!("unexpected result: {:?}", res),
+            }
+            match handle.next_screen(Duration::from_millis(10)) {
+                ActionResult::NotChanged => {}
+                res => panic!("unexpected result: {:?}", res),
+            }
+            assert_eq!(handle.screen().trimmed_line(0), "hello");
+            assert_eq!(handle.metrics().bytes_read, 5);
+        }
+        assert_eq!(game.episode(), 1);
+        assert_eq!(game.metrics().frames, 1);
+    }
+    #[test]
+    fn test_poll_handle() {
+        use super::*;
+        // polls until the result isn't NotChanged
+        fn poll_until(handle: &mut GameHandle) -> ActionResult {
+            let start = Instant::now();
+            while start.elapsed() < Duration::from_secs(1) {
+                match handle.poll() {
+                    ActionResult::NotChanged => thread::sleep(Duration::from_millis(1)),
+                    res => return res,
+                }
+            }
+            ActionResult::NotChanged
+        }
+        let mut game = GameSetting::new("cat")
+            .settle(Duration::from_millis(20))
+            .build()
+            .unwrap();
+        {
+            let mut handle = game.poll_handle();
+            assert!(handle.output_fd().is_some());
+            handle.send(b"hello").unwrap();
+            let start = Instant::now();
+            match poll_until(&mut handle) {
+                ActionResult::Changed(screen) => assert_eq!(screen.trimmed_line(0), "hello"),
+                res => panic!("unexpected result: {:?}", res),
+            }
+            assert!(start.elapsed() >= Duration::from_millis(20));
+            match handle.poll() {
+                ActionResult::NotChanged => {}
+                res => panic!("unexpected result: {:?}", res),
+            }
+        }
+        assert_eq!(game.metrics().frames, 1);
+        let mut game = GameSetting::new("sh")
+            .args(vec!["-c", "printf bye"])
+            .settle(Duration::from_millis(0))
+            .build()
+            .unwrap();
+        let mut handle = game.poll_handle();
+        match poll_until(&mut handle) {
+            ActionResult::Changed(screen) => assert_eq!(screen.trimmed_line(0), "bye"),
+            res => panic!("unexpected result: {:?}", res),
+        }
+        match poll_until(&mut handle) {
+            ActionResult::GameEnded => {}
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+    #[test]
+    fn test_play_iter() {
+        use super::*;
+        let mut game = GameSetting::new("cat").max_loop(5).build().unwrap();
+        let mut lines = Vec::new();
+        let mut turns = Vec::new();
+        {
+            let mut iter = game.play_iter();
+            iter.send(b"a").unwrap();
+            while let Some((turn, result)) = iter.next() {
+                turns.push(turn);
+                if let ActionResult::Changed(screen) = result {
+                    lines.push(screen.trimmed_line(0).into_owned());
+                    if turn == 1 {
+                        iter.send(b"b").unwrap();
+                    }
+                }
+            }
+        }
+        assert_eq!(lines, vec!["a", "ab"]);
+        assert_eq!(turns, vec![1, 2, 3, 4, 5, 5]);
+        assert_eq!(game.metrics().turns, 5);
+    }
+    #[test]
+    fn test_pause() {
+        use super::*;
+        let mut game = GameSetting::new("cat").build().unwrap();
+        let pause = game.pause_handle();
+        let mut handle = game.handle();
+        pause.pause().unwrap();
+        assert!(pause.is_paused());
+        handle.send(b"x").unwrap();
+        match handle.next_screen(Duration::from_millis(200)) {
+            ActionResult::NotChanged => {}
+            res => panic!("unexpected result: {:?}", res),
+        }
+        pause.resume().unwrap();
+        match handle.next_screen(Duration::from_secs(1)) {
+            ActionResult::Changed(screen) => assert_eq!(screen.trimmed_line(0), "x"),
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_nice_and_affinity() {
+        use super::*;
+        let mut game = GameSetting::new("sh")
+            .args(vec![
+                "-c",
+                "echo $(nice) $(awk '/Cpus_allowed_list/{print $2}' /proc/self/status); \
+                 exec sleep 10",
+            ])
+            .nice(5)
+            .cpu_affinity(vec![0])
+            .build()
+            .unwrap();
+        let mut handle = game.handle();
+        handle.next_screen(Duration::from_secs(1));
+        assert_eq!(handle.screen().trimmed_line(0), "5 0");
+    }
+    #[test]
+    fn test_env_clear() {
+        use super::*;
+        env::set_var("CGW_TEST_ALLOWED", "allowed");
+        env::set_var("CGW_TEST_DENIED", "denied");
+        let mut game = GameSetting::new("sh")
+            .args(vec![
+                "-c",
+                "echo $CGW_TEST_ALLOWED ${CGW_TEST_DENIED:-none} $FOO; exec sleep 10",
+            ])
+            .env_clear()
+            .env_allow("PATH")
+            .env_allow("CGW_TEST_ALLOWED")
+            .env("FOO", "bar")
+            .build()
+            .unwrap();
+        let mut handle = game.handle();
+        handle.next_screen(Duration::from_secs(1));
+        assert_eq!(handle.screen().trimmed_line(0), "allowed none bar");
+    }
+}
+
@@ -0,0 +1,95 @@
+//! Utilities for specific games.
+
+// ```&'static Regex``` compiled at the first use, as parsers run every frame
+macro_rules! regex {
+    ($re:expr) => {{
+        lazy_static! {
+            static ref REGEX: Regex = Regex::new($re).unwrap();
+        }
+        &*REGEX
+    }};
+}
+
+pub mod nethack;
+pub mod rogue;
+
+use keys::{Direction, MetaEncoding};
+use screen::Screen;
+use std::ops::Range;
+use stop::GameOver;
+
+/// Knowledge of a specific game: the screen layout, the status parser, the
+/// game over detector, how to quit and how the game reads keys.
+///
+/// ```GameSetting::adapter``` plugs it in at once, and your AI can get it back by
+/// ```GameEnv::adapter```. It's implemented for rogue(```rogue::Rogue```) and
+/// NetHack(```nethack::NetHack```).
+/// # Example
+/// ```
+/// extern crate curses_game_wrapper as cgw;
+/// use cgw::Direction;
+/// use cgw::games::GameAdapter;
+/// use cgw::games::nethack::NetHack;
+/// fn main() {
+///     let nethack = NetHack::new().number_pad(true);
+///     assert_eq!(nethack.layout(24).map, 1..22);
+///     assert_eq!(nethack.direction_key(Direction::N), b'8');
+///     assert_eq!(nethack.quit_keys(), b"#quit\ny");
+/// }
+/// ```
+pub trait GameAdapter: Send + Sync {
+    /// Name of the game, like ```rogue```
+    fn name(&self) -> &str;
+    /// Regions of the screen with ```lines``` lines.
+    fn layout(&self, lines: usize) -> Layout;
+    /// Parse the status lines of ```screen```, or returns ```None``` if they
+    /// aren't shown(e.g. in menus).
+    fn parse_status(&self, screen: &Screen) -> Option<Status>;
+    /// Detector passed to ```GameSetting::game_over```.
+    fn game_over(&self) -> GameOver;
+    /// Keys to quit the game, including confirmations.
+    fn quit_keys(&self) -> Vec<u8>;
+    /// Key moving toward ```d```(Default: vi-keys).
+    fn direction_key(&self, d: Direction) -> u8 {
+        d.as_vi_byte()
+    }
+    /// Encoding passed to ```GameSetting::meta_encoding```(Default: ```Esc```).
+    fn meta_encoding(&self) -> MetaEncoding {
+        MetaEncoding::Esc
+    }
+}
+
+/// Lines of each region of the screen, given by ```GameAdapter::layout```.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Layout {
+    pub message: Range<usize>,
+    pub map: Range<usize>,
+    pub status: Range<usize>,
+}
+
+/// Status of the player parsed by ```GameAdapter::parse_status```.
+///
+/// Fields not shown by the game are ```None```.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Status {
+    /// Dungeon level
+    pub level: Option<u32>,
+    pub gold: Option<u64>,
+    pub hp: Option<i64>,
+    pub max_hp: Option<i64>,
+    /// Experience level
+    pub exp_level: Option<u32>,
+    /// Game turns
+    pub turns: Option<u64>,
+}
+
+// lines of the status region, joined
+fn status_text(screen: &Screen, layout: &Layout) -> String {
+    layout
+        .status
+        .clone()
+        .filter(|&y| y < screen.lines())
+        .map(|y| screen.trimmed_line(y).into_owned())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,663 @@
+//! Utilities for NetHack.
+
+use keys::{Direction, MetaEncoding, CTRL_D};
+use regex::Regex;
+use screen::Screen;
+use stop::{GameOver, Outcome};
+use std::fs;
+use std::io;
+use std::path::Path;
+use super::{status_text, GameAdapter, Layout, Status};
+
+// files made by NetHack which can remain after the game is killed
+fn is_stale_file(name: &str) -> bool {
+    // level files and lock of running game, like 1000wizard.0 or 1000wizard
+    name.starts_with(|c: char| c.is_ascii_digit())
+        // lock files of record, logfile, etc.
+        || name.ends_with("_lock")
+        || name.starts_with("xlock.")
+        // bones files, like bonD0.3
+        || name.starts_with("bon")
+}
+
+/// Remove stale lock files, level files, save files and bones in NetHack's
+/// playground directory(e.g. ```/usr/games/lib/nethackdir```).
+///
+/// Stale locks made by killed games prevent next game from starting,
+/// so you should call it before each game.
+/// To run it as setup command, use ```cleanup_command```.
+pub fn clean_playground<P: AsRef<Path>>(dir: P) -> io::Result<()> {
+    let dir = dir.as_ref();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_file() {
+            continue;
+        }
+        if entry.file_name().to_str().map_or(false, is_stale_file) {
+            fs::remove_file(entry.path())?;
+        }
+    }
+    let save_dir = dir.join("save");
+    if save_dir.is_dir() {
+        for entry in fs::read_dir(save_dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                fs::remove_file(entry.path())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns shell command doing the same thing as ```clean_playground```,
+/// which you can pass to ```GameSetting::setup_command```.
+/// # Example
+/// ```no_run
+/// extern crate curses_game_wrapper as cgw;
+/// # #[cfg(feature = "process")]
+/// use cgw::GameSetting;
+/// use cgw::games::nethack::cleanup_command;
+/// # #[cfg(feature = "process")]
+/// fn main() {
+///     let cleanup = cleanup_command("/usr/games/lib/nethackdir");
+///     let gs = GameSetting::new("nethack").setup_command(&cleanup);
+/// }
+/// # #[cfg(not(feature = "process"))]
+/// # fn main() {}
+/// ```
+pub fn cleanup_command<P: AsRef<Path>>(dir: P) -> String {
+    let dir = shell_quote(&dir.as_ref().to_string_lossy());
+    format!(
+        "rm -f {0}/[0-9]* {0}/*_lock {0}/xlock.* {0}/bon* {0}/save/*",
+        dir
+    )
+}
+
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// Commands of NetHack, which render to the exact keys including confirmations.
+///
+/// Items and menu entries are specified by their letters.
+/// # Example
+/// ```
+/// extern crate curses_game_wrapper as cgw;
+/// use cgw::Direction;
+/// use cgw::games::nethack::Command;
+/// fn main() {
+///     assert_eq!(Command::Kick(Direction::W).to_bytes(false), b"\x04h");
+///     assert_eq!(Command::Kick(Direction::W).to_bytes(true), b"\x044");
+///     assert_eq!(Command::Pray.to_bytes(false), b"#pray\ny");
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Command {
+    /// Move one step
+    Move(Direction),
+    /// Move until something interesting is found
+    Run(Direction),
+    /// Search traps and secret doors around
+    Search,
+    /// Rest one turn
+    Rest,
+    Kick(Direction),
+    Open(Direction),
+    Close(Direction),
+    /// Pray, answering ```y``` to "Are you sure you want to pray?"
+    Pray,
+    PickUp,
+    Quaff(u8),
+    Read(u8),
+    Eat(u8),
+    Wield(u8),
+    Wear(u8),
+    Apply(u8),
+    /// Go down stairs
+    Descend,
+    /// Go up stairs
+    Ascend,
+    /// Extended command like ```Extended("enhance".to_owned())```
+    Extended(String),
+    /// Select entries of the menu by their letters and close it
+    Select(Vec<u8>),
+    /// Quit the game, answering ```y``` to "Really quit?"
+    Quit,
+}
+
+impl Command {
+    /// Returns the keys to send.
+    ///
+    /// Set ```number_pad``` if the game runs with option ```number_pad```,
+    /// where directions are digits instead of vi-keys.
+    pub fn to_bytes(&self, number_pad: bool) -> Vec<u8> {
+        let dir = |d: Direction| direction_byte(d, number_pad);
+        match *self {
+            Command::Move(d) => vec![dir(d)],
+            Command::Run(d) if number_pad => vec![b'G', dir(d)],
+            Command::Run(d) => vec![dir(d).to_ascii_uppercase()],
+            Command::Search => b"s".to_vec(),
+            Command::Rest => b".".to_vec(),
+            Command::Kick(d) => vec![CTRL_D, dir(d)],
+            Command::Open(d) => vec![b'o', dir(d)],
+            Command::Close(d) => vec![b'c', dir(d)],
+            Command::Pray => b"#pray\ny".to_vec(),
+            Command::PickUp => b",".to_vec(),
+            Command::Quaff(item) => vec![b'q', item],
+            Command::Read(item) => vec![b'r', item],
+            Command::Eat(item) => vec![b'e', item],
+            Command::Wield(item) => vec![b'w', item],
+            Command::Wear(item) => vec![b'W', item],
+            Command::Apply(item) => vec![b'a', item],
+            Command::Descend => b">".to_vec(),
+            Command::Ascend => b"<".to_vec(),
+            Command::Extended(ref name) => format!("#{}\n", name).into_bytes(),
+            Command::Select(ref items) => {
+                let mut res = items.clone();
+                res.push(b'\r');
+                res
+            }
+            Command::Quit => b"#quit\ny".to_vec(),
+        }
+    }
+}
+
+fn direction_byte(d: Direction, number_pad: bool) -> u8 {
+    if !number_pad {
+        return d.as_vi_byte();
+    }
+    match d {
+        Direction::N => b'8',
+        Direction::S => b'2',
+        Direction::E => b'6',
+        Direction::W => b'4',
+        Direction::NE => b'9',
+        Direction::NW => b'7',
+        Direction::SE => b'3',
+        Direction::SW => b'1',
+    }
+}
+
+/// ```GameAdapter``` of NetHack's tty interface, with messages in the first line
+/// and two status lines at the bottom.
+///
+/// Alt-modified keys are sent with the 8th bit set, unless the game runs with
+/// option ```altmeta```.
+/// # Example
+/// ```no_run
+/// extern crate curses_game_wrapper as cgw;
+/// # #[cfg(feature = "process")]
+/// use cgw::GameSetting;
+/// use cgw::games::nethack::NetHack;
+/// # #[cfg(feature = "process")]
+/// fn main() {
+///     let gs = GameSetting::new("nethack")
+///         .env("NETHACKOPTIONS", "number_pad,time")
+///         .adapter(NetHack::new().number_pad(true));
+/// }
+/// # #[cfg(not(feature = "process"))]
+/// # fn main() {}
+/// ```
+#[derive(Copy, Clone, Debug, Default)]
+pub struct NetHack {
+    number_pad: bool,
+}
+
+impl NetHack {
+    pub fn new() -> NetHack {
+        NetHack::default()
+    }
+    /// Set if the game runs with option ```number_pad```, as ```Command::to_bytes```.
+    pub fn number_pad(mut self, number_pad: bool) -> NetHack {
+        self.number_pad = number_pad;
+        self
+    }
+}
+
+impl GameAdapter for NetHack {
+    fn name(&self) -> &str {
+        "nethack"
+    }
+    fn layout(&self, lines: usize) -> Layout {
+        let status = lines.saturating_sub(2);
+        Layout {
+            message: 0..1,
+            map: 1..status,
+            status: status..lines,
+        }
+    }
+    fn parse_status(&self, screen: &Screen) -> Option<Status> {
+        let text = status_text(screen, &self.layout(screen.lines()));
+        let caps = regex!(r"HP:(-?\d+)\((\d+)\)").captures(&text)?;
+        let field = |re: &Regex| re.captures(&text).map(|caps| caps[1].to_owned());
+        Some(Status {
+            level: field(regex!(r"Dlvl:(\d+)")).and_then(|s| s.parse().ok()),
+            gold: field(regex!(r"\$:(\d+)")).and_then(|s| s.parse().ok()),
+            hp: caps[1].parse().ok(),
+            max_hp: caps[2].parse().ok(),
+            // HD while polymorphed
+            exp_level: field(regex!(r"\b(?:Xp|Exp|HD):(\d+)")).and_then(|s| s.parse().ok()),
+            turns: field(regex!(r"\bT:(\d+)")).and_then(|s| s.parse().ok()),
+        })
+    }
+    fn game_over(&self) -> GameOver {
+        game_over()
+    }
+    fn quit_keys(&self) -> Vec<u8> {
+        Command::Quit.to_bytes(self.number_pad)
+    }
+    fn direction_key(&self, d: Direction) -> u8 {
+        direction_byte(d, self.number_pad)
+    }
+    fn meta_encoding(&self) -> MetaEncoding {
+        MetaEncoding::HighBit
+    }
+}
+
+/// Detector of the end of NetHack: ```You die...``` and the last messages of
+/// ascension, escape and quit.
+///
+/// Deaths are detected before ```Do you want your possessions identified?```,
+/// but other endings are detected when the AI goes through the prompts and
+/// the ```Goodbye``` screen shows.
+/// # Example
+/// ```no_run
+/// extern crate curses_game_wrapper as cgw;
+/// # #[cfg(feature = "process")]
+/// use cgw::GameSetting;
+/// use cgw::games::nethack;
+/// # #[cfg(feature = "process")]
+/// fn main() {
+///     let gs = GameSetting::new("nethack").game_over(nethack::game_over());
+/// }
+/// # #[cfg(not(feature = "process"))]
+/// # fn main() {}
+/// ```
+pub fn game_over() -> GameOver {
+    GameOver::new()
+        .on(r"You die\.\.\.", Outcome::Died)
+        .on("You ascend to the status of|You went to your reward", Outcome::Won)
+        .on("You escaped from the dungeon", Outcome::Escaped)
+        .on(r"You quit (in|with) ", Outcome::Quit)
+}
+
+/// Class of items, shown as headings of the inventory.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ItemClass {
+    Coin,
+    Amulet,
+    Weapon,
+    Armor,
+    Comestible,
+    Scroll,
+    Spellbook,
+    Potion,
+    Ring,
+    Wand,
+    Tool,
+    Gem,
+    /// Boulders and statues
+    Rock,
+    IronBall,
+    Chain,
+    Venom,
+}
+
+impl ItemClass {
+    /// Returns the class of the inventory heading, like ```Weapons```.
+    pub fn from_heading(heading: &str) -> Option<ItemClass> {
+        let class = match heading.trim() {
+            "Coins" => ItemClass::Coin,
+            "Amulets" => ItemClass::Amulet,
+            "Weapons" => ItemClass::Weapon,
+            "Armor" => ItemClass::Armor,
+            "Comestibles" => ItemClass::Comestible,
+            "Scrolls" => ItemClass::Scroll,
+            "Spellbooks" => ItemClass::Spellbook,
+            "Potions" => ItemClass::Potion,
+            "Rings" => ItemClass::Ring,
+            "Wands" => ItemClass::Wand,
+            "Tools" => ItemClass::Tool,
+            "Gems" | "Gems/Stones" => ItemClass::Gem,
+            "Boulders/Statues" => ItemClass::Rock,
+            "Iron balls" => ItemClass::IronBall,
+            "Chains" => ItemClass::Chain,
+            "Venoms" => ItemClass::Venom,
+            _ => return None,
+        };
+        Some(class)
+    }
+}
+
+/// Blessed, uncursed or cursed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Buc {
+    Blessed,
+    Uncursed,
+    Cursed,
+}
+
+/// Item in the inventory, parsed from lines like
+/// ```a - a blessed +1 long sword (weapon in hand)```.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Item {
+    /// Inventory letter
+    pub letter: u8,
+    /// ```None``` if the heading isn't shown, e.g. in the middle of pages
+    pub class: Option<ItemClass>,
+    /// Name without the quantity, BUC status, enchantment and the notes in
+    /// parentheses, like ```long sword```
+    pub name: String,
+    pub quantity: u32,
+    pub buc: Option<Buc>,
+    pub enchantment: Option<i32>,
+    /// Whole description after ```a - ```
+    pub text: String,
+}
+
+impl Item {
+    /// Parse the description after the letter, like ```2 uncursed food rations```.
+    pub fn parse(letter: u8, class: Option<ItemClass>, text: &str) -> Item {
+        let mut words = text.split_whitespace().peekable();
+        let quantity = match words.peek().cloned() {
+            Some("a") | Some("an") | Some("the") => {
+                words.next();
+                1
+            }
+            Some(w) => match w.parse() {
+                Ok(n) => {
+                    words.next();
+                    n
+                }
+                Err(_) => 1,
+            },
+            None => 1,
+        };
+        let mut buc = None;
+        let mut enchantment = None;
+        let mut name: Vec<&str> = Vec::new();
+        for w in words {
+            match w {
+                "blessed" if buc.is_none() && name.is_empty() => buc = Some(Buc::Blessed),
+                "uncursed" if buc.is_none() && name.is_empty() => buc = Some(Buc::Uncursed),
+                "cursed" if buc.is_none() && name.is_empty() => buc = Some(Buc::Cursed),
+                // erosion like "rusty" comes before the enchantment
+                _ if enchantment.is_none() && is_enchantment(w) => {
+                    enchantment = w.parse().ok();
+                    name.clear();
+                }
+                _ => name.push(w),
+            }
+        }
+        let mut name = name.join(" ");
+        // notes like "(weapon in hand)", "(0:4)" or "(lit)"
+        while name.ends_with(')') {
+            match name.rfind(" (") {
+                Some(i) => name.truncate(i),
+                None => break,
+            }
+        }
+        Item {
+            letter: letter,
+            class: class,
+            name: name,
+            quantity: quantity,
+            buc: buc,
+            enchantment: enchantment,
+            text: text.to_owned(),
+        }
+    }
+}
+
+fn is_enchantment(w: &str) -> bool {
+    w.len() > 1
+        && w.starts_with(|c| c == '+' || c == '-')
+        && w[1..].bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Page of the inventory menu parsed by ```parse_inventory_page```.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InventoryPage {
+    pub items: Vec<Item>,
+    /// Current page and the number of pages, like ```(1, 2)``` for ```(1 of 2)```
+    pub page: (usize, usize),
+}
+
+impl InventoryPage {
+    /// Returns true if it's the last page, where ```(end)``` is shown.
+    pub fn is_last(&self) -> bool {
+        self.page.0 >= self.page.1
+    }
+}
+
+/// Parse the inventory menu shown by ```i```, or returns ```None``` if the
+/// screen doesn't show a menu.
+///
+/// The menu is found by ```(end)``` or ```(1 of 2)``` at the bottom, and
+/// read from its column, so that it can be drawn over the map.
+/// Use ```parse_inventory``` for menus of many pages.
+pub fn parse_inventory_page(screen: &Screen) -> Option<InventoryPage> {
+    let footer = regex!(r"^\((?:end|(\d+) of (\d+))\)");
+    let item = regex!(r"^([a-zA-Z$#]) - (.+)$");
+    // columns are found on the raw bytes, as map glyphs(e.g. DECgraphics) left
+    // to the menu aren't single characters once decoded
+    let text = |line: &[u8]| String::from_utf8_lossy(line).trim_end().to_owned();
+    let mut footers = screen.iter().enumerate().rev().filter_map(|(y, line)| {
+        let left = line.iter().rposition(|&b| b == b'(')?;
+        let text = text(&line[left..]);
+        let caps = footer.captures(&text)?;
+        let page = match (caps.get(1), caps.get(2)) {
+            (Some(cur), Some(total)) => (cur.as_str().parse().ok()?, total.as_str().parse().ok()?),
+            _ => (1, 1),
+        };
+        Some((y, left, page))
+    });
+    let (bottom, left, page) = footers.next()?;
+    let mut class = None;
+    let mut items = Vec::new();
+    for line in screen.iter().take(bottom) {
+        let text = text(line.get(left..).unwrap_or(&[]));
+        if let Some(caps) = item.captures(&text) {
+            items.push(Item::parse(caps[1].as_bytes()[0], class, &caps[2]));
+        } else if let Some(c) = ItemClass::from_heading(&text) {
+            class = Some(c);
+        }
+    }
+    Some(InventoryPage {
+        items: items,
+        page: page,
+    })
+}
+
+/// Parse the inventory menu of many pages in ```screens```(each page in order),
+/// where items continued from the last page take the class of its last heading.
+/// # Example
+/// ```
+/// extern crate curses_game_wrapper as cgw;
+/// use cgw::Screen;
+/// use cgw::games::nethack::{parse_inventory, ItemClass};
+/// fn main() {
+///     let screen = |text: &str| {
+///         Screen::new(text.lines().map(|l| l.as_bytes().to_vec()).collect())
+///     };
+///     let first = screen(" Weapons\n a - a +1 long sword (weapon in hand)\n (1 of 2)");
+///     let second = screen(" b - 4 uncursed daggers\n Armor\n c - a ring mail\n (2 of 2)");
+///     let items = parse_inventory(&[first, second]);
+///     assert_eq!(items.len(), 3);
+///     assert_eq!((items[0].name.as_str(), items[0].enchantment), ("long sword", Some(1)));
+///     assert_eq!((items[1].quantity, items[1].class), (4, Some(ItemClass::Weapon)));
+///     assert_eq!(items[2].class, Some(ItemClass::Armor));
+/// }
+/// ```
+pub fn parse_inventory(screens: &[Screen]) -> Vec<Item> {
+    let mut res: Vec<Item> = Vec::new();
+    for page in screens.iter().filter_map(parse_inventory_page) {
+        let last = res.last().and_then(|i| i.class);
+        for mut item in page.items {
+            if item.class.is_none() {
+                item.class = last;
+            }
+            res.push(item);
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+    use std::fs::File;
+    use std::process;
+    fn make_playground(name: &str) -> ::std::path::PathBuf {
+        let dir = env::temp_dir().join(format!("cgw-nethack-{}-{}", name, process::id()));
+        fs::create_dir_all(dir.join("save")).unwrap();
+        for f in &[
+            "1000wizard",
+            "1000wizard.0",
+            "perm_lock",
+            "record_lock",
+            "xlock.1",
+            "bonD0.3",
+            "save/1000wizard.gz",
+            "record",
+            "nhdat",
+        ] {
+            File::create(dir.join(f)).unwrap();
+        }
+        dir
+    }
+    fn remaining(dir: &Path) -> Vec<String> {
+        let mut res: Vec<_> = fs::read_dir(dir)
+            .unwrap()
+            .chain(fs::read_dir(dir.join("save")).unwrap())
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        res.sort();
+        res
+    }
+    #[test]
+    fn test_adapter() {
+        let text = "\n\nAgent the Stripling   St:17 Dx:14 Co:18 In:8 Wi:9 Ch:7 Lawful\n\
+                    Dlvl:3 $:52 HP:-1(16) Pw:1(1) AC:6 Xp:2/31 T:1203 Hungry";
+        let screen = Screen::new(text.lines().map(|l| l.as_bytes().to_vec()).collect());
+        let nethack = NetHack::new();
+        assert_eq!(
+            nethack.parse_status(&screen),
+            Some(Status {
+                level: Some(3),
+                gold: Some(52),
+                hp: Some(-1),
+                max_hp: Some(16),
+                exp_level: Some(2),
+                turns: Some(1203),
+            })
+        );
+        let polymorphed = Screen::new(vec![b"Dlvl:1 $:0 HP:5(5) Pw:1(1) AC:6 HD:4".to_vec()]);
+        let status = nethack.parse_status(&polymorphed).unwrap();
+        assert_eq!((status.exp_level, status.turns), (Some(4), None));
+        assert_eq!(nethack.parse_status(&Screen::new(vec![b"--More--".to_vec()])), None);
+        assert_eq!(nethack.layout(24).status, 22..24);
+        assert_eq!(nethack.direction_key(Direction::W), b'h');
+        assert_eq!(nethack.number_pad(true).quit_keys(), b"#quit\ny");
+    }
+    #[test]
+    fn test_command() {
+        assert_eq!(Command::Move(Direction::NE).to_bytes(false), b"u");
+        assert_eq!(Command::Move(Direction::NE).to_bytes(true), b"9");
+        assert_eq!(Command::Run(Direction::S).to_bytes(false), b"J");
+        assert_eq!(Command::Run(Direction::S).to_bytes(true), b"G2");
+        assert_eq!(Command::Open(Direction::E).to_bytes(true), b"o6");
+        let enhance = Command::Extended("enhance".to_owned());
+        assert_eq!(enhance.to_bytes(false), b"#enhance\n");
+        assert_eq!(Command::Select(b"ab".to_vec()).to_bytes(true), b"ab\r");
+    }
+    #[test]
+    fn test_item() {
+        let item = Item::parse(b'a', None, "a blessed rusty +2 long sword (weapon in hand)");
+        assert_eq!(item.name, "long sword");
+        assert_eq!((item.quantity, item.buc, item.enchantment), (1, Some(Buc::Blessed), Some(2)));
+        let item = Item::parse(b'f', None, "12 uncursed food rations");
+        assert_eq!((item.name.as_str(), item.quantity), ("food rations", 12));
+        let item = Item::parse(b'g', None, "a cursed -1 ring of protection (on right hand)");
+        assert_eq!((item.buc, item.enchantment), (Some(Buc::Cursed), Some(-1)));
+        assert_eq!(item.name, "ring of protection");
+        let item = Item::parse(b'h', None, "a wand of striking (0:4)");
+        assert_eq!(item.name, "wand of striking");
+        assert_eq!((item.buc, item.enchantment), (None, None));
+        assert_eq!(item.text, "a wand of striking (0:4)");
+    }
+    #[test]
+    fn test_inventory() {
+        let text = " You see here a jackal corpse.     Coins
+                                   $ - 11 gold pieces
+ |..........                       Weapons
+ |.....@....                       a - a +1 long sword (weapon in hand)
+ |..........                       b - 4 uncursed +0 daggers (in quiver)
+ -----------                       (1 of 2)";
+        let screen = Screen::new(text.lines().map(|l| l.as_bytes().to_vec()).collect());
+        let page = parse_inventory_page(&screen).unwrap();
+        assert_eq!(page.page, (1, 2));
+        assert!(!page.is_last());
+        let letters: Vec<_> = page.items.iter().map(|i| i.letter).collect();
+        assert_eq!(letters, b"$ab".to_vec());
+        assert_eq!(page.items[0].class, Some(ItemClass::Coin));
+        assert_eq!((page.items[0].quantity, page.items[0].name.as_str()), (11, "gold pieces"));
+        assert_eq!(page.items[2].class, Some(ItemClass::Weapon));
+        let text = " c - an uncursed +0 dagger\n Gems/Stones\n d - a gray stone\n (end)";
+        let last = Screen::new(text.lines().map(|l| l.as_bytes().to_vec()).collect());
+        assert!(parse_inventory_page(&last).unwrap().is_last());
+        let items = parse_inventory(&[screen.clone(), last]);
+        assert_eq!(items.len(), 5);
+        assert_eq!(items[3].class, Some(ItemClass::Weapon));
+        assert_eq!(items[4].class, Some(ItemClass::Gem));
+        let map = Screen::new(vec![b" |.@..(  |".to_vec()]);
+        assert_eq!(parse_inventory_page(&map), None);
+        // IBMgraphics walls(not UTF-8) left to the menu
+        let ibm = Screen::new(
+            screen
+                .iter()
+                .map(|l| l.iter().map(|&b| if b == b'|' { 0xb3 } else { b }).collect())
+                .collect(),
+        );
+        assert_eq!(parse_inventory_page(&ibm), Some(page));
+    }
+    #[test]
+    fn test_game_over() {
+        let screen = |lines: &[&str]| {
+            Screen::new(lines.iter().map(|l| l.as_bytes().to_vec()).collect())
+        };
+        let go = game_over();
+        let died = screen(&["You die...--More--", "", "   @"]);
+        assert_eq!(go.detect(&died), Some(Outcome::Died));
+        let quit = screen(&[
+            "Goodbye agent the Valkyrie...",
+            "",
+            "You quit in The Dungeons of Doom on dungeon level 1 with 0 points,",
+        ]);
+        assert_eq!(go.detect(&quit), Some(Outcome::Quit));
+        let escaped = screen(&["You escaped from the dungeon with 42 points,"]);
+        assert_eq!(go.detect(&escaped), Some(Outcome::Escaped));
+        let play = screen(&["You see here a die.", "Dlvl:1 $:0 HP:16(16)"]);
+        assert_eq!(go.detect(&play), None);
+    }
+    #[test]
+    fn test_clean_playground() {
+        let dir = make_playground("fn");
+        clean_playground(&dir).unwrap();
+        assert_eq!(remaining(&dir), vec!["nhdat", "record", "save"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+    #[test]
+    fn test_cleanup_command() {
+        let dir = make_playground("cmd");
+        let status = process::Command::new("sh")
+            .arg("-c")
+            .arg(cleanup_command(&dir))
+            .status()
+            .unwrap();
+        assert!(status.success());
+        assert_eq!(remaining(&dir), vec!["nhdat", "record", "save"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
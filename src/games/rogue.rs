@@ -0,0 +1,289 @@
+//! Utilities for rogue(5.4).
+
+use keys::Direction;
+use regex::Regex;
+use screen::Screen;
+use stop::{GameOver, GameResult, Outcome};
+use super::{status_text, GameAdapter, Layout, Status};
+
+/// Commands of rogue, which render to the exact keys including confirmations.
+///
+/// Items are specified by their inventory letters.
+/// # Example
+/// ```
+/// extern crate curses_game_wrapper as cgw;
+/// use cgw::Direction;
+/// use cgw::games::rogue::Command;
+/// fn main() {
+///     assert_eq!(Command::Run(Direction::E).to_bytes(), b"L");
+///     assert_eq!(Command::Quit.to_bytes(), b"Qy");
+/// }
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Command {
+    /// Move one step
+    Move(Direction),
+    /// Move until something interesting is found
+    Run(Direction),
+    /// Search traps and secret doors around
+    Search,
+    /// Rest one turn
+    Rest,
+    Quaff(u8),
+    Read(u8),
+    Eat(u8),
+    Wield(u8),
+    Wear(u8),
+    TakeOff,
+    PutOn(u8),
+    Remove(u8),
+    Throw(Direction, u8),
+    Zap(Direction, u8),
+    /// Go down stairs
+    Descend,
+    /// Go up stairs(only with the amulet)
+    Ascend,
+    /// Quit the game, answering ```y``` to "really quit?"
+    Quit,
+}
+
+impl Command {
+    /// Returns the keys to send.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match *self {
+            Command::Move(d) => vec![d.as_vi_byte()],
+            Command::Run(d) => vec![d.as_vi_byte().to_ascii_uppercase()],
+            Command::Search => b"s".to_vec(),
+            Command::Rest => b".".to_vec(),
+            Command::Quaff(item) => vec![b'q', item],
+            Command::Read(item) => vec![b'r', item],
+            Command::Eat(item) => vec![b'e', item],
+            Command::Wield(item) => vec![b'w', item],
+            Command::Wear(item) => vec![b'W', item],
+            Command::TakeOff => b"T".to_vec(),
+            Command::PutOn(item) => vec![b'P', item],
+            Command::Remove(item) => vec![b'R', item],
+            Command::Throw(d, item) => vec![b't', d.as_vi_byte(), item],
+            Command::Zap(d, item) => vec![b'z', d.as_vi_byte(), item],
+            Command::Descend => b">".to_vec(),
+            Command::Ascend => b"<".to_vec(),
+            Command::Quit => b"Qy".to_vec(),
+        }
+    }
+}
+
+/// ```GameAdapter``` of rogue, with the message in the first line and the
+/// status in the last line.
+/// # Example
+/// ```no_run
+/// extern crate curses_game_wrapper as cgw;
+/// # #[cfg(feature = "process")]
+/// use cgw::GameSetting;
+/// use cgw::games::rogue::Rogue;
+/// # #[cfg(feature = "process")]
+/// fn main() {
+///     let gs = GameSetting::new("rogue").adapter(Rogue);
+/// }
+/// # #[cfg(not(feature = "process"))]
+/// # fn main() {}
+/// ```
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Rogue;
+
+impl GameAdapter for Rogue {
+    fn name(&self) -> &str {
+        "rogue"
+    }
+    fn layout(&self, lines: usize) -> Layout {
+        let last = lines.saturating_sub(1);
+        Layout {
+            message: 0..1,
+            map: 1..last,
+            status: last..lines,
+        }
+    }
+    fn parse_status(&self, screen: &Screen) -> Option<Status> {
+        let text = status_text(screen, &self.layout(screen.lines()));
+        let status = regex!(r"Level:\s*(\d+)\s+Gold:\s*(\d+)\s+Hp:\s*(\d+)\((\d+)\)");
+        let caps = status.captures(&text)?;
+        let exp = regex!(r"Exp:\s*(\d+)/");
+        Some(Status {
+            level: caps[1].parse().ok(),
+            gold: caps[2].parse().ok(),
+            hp: caps[3].parse().ok(),
+            max_hp: caps[4].parse().ok(),
+            exp_level: exp.captures(&text).and_then(|c| c[1].parse().ok()),
+            turns: None,
+        })
+    }
+    fn game_over(&self) -> GameOver {
+        game_over()
+    }
+    fn quit_keys(&self) -> Vec<u8> {
+        Command::Quit.to_bytes()
+    }
+}
+
+/// Detector of the end of rogue: the tombstone(or ```Killed by``` line
+/// without it), ```You quit with``` and the message of the total winner.
+/// ```Metrics::result``` is parsed by ```parse_result```.
+/// # Example
+/// ```no_run
+/// extern crate curses_game_wrapper as cgw;
+/// # #[cfg(feature = "process")]
+/// use cgw::GameSetting;
+/// use cgw::games::rogue;
+/// # #[cfg(feature = "process")]
+/// fn main() {
+///     let gs = GameSetting::new("rogue").game_over(rogue::game_over());
+/// }
+/// # #[cfg(not(feature = "process"))]
+/// # fn main() {}
+/// ```
+pub fn game_over() -> GameOver {
+    GameOver::new()
+        .on(r"(?s)REST.*IN.*PEACE|Killed by .+ with \d+ gold", Outcome::Died)
+        .on(r"You quit with \d+ gold", Outcome::Quit)
+        .on("made it to the light of day", Outcome::Won)
+        .result_parser(parse_result)
+}
+
+/// Parse the tombstone, the quit message or the message of the total winner
+/// in ```screens```(oldest first), and the level and gold in the last status line.
+///
+/// ```score``` is the gold, as rogue scores deaths and quits. It's not parsed
+/// for total winners, whose score is the worth of their items.
+pub fn parse_result(screens: &[Screen]) -> GameResult {
+    let mut res = GameResult::default();
+    let status = regex!(r"Level:\s*(\d+)\s+Gold:\s*(\d+)");
+    for line in screens.iter().flat_map(|s| s.trimmed_lines()) {
+        if let Some(caps) = status.captures(&line) {
+            res.level = caps[1].parse().ok();
+            res.gold = caps[2].parse().ok();
+        }
+    }
+    let killed = regex!(r"Killed by (.+) with (\d+) gold");
+    let quit = regex!(r"You quit with (\d+) gold");
+    for screen in screens.iter().rev() {
+        let text = screen.to_trimmed_string();
+        if text.contains("REST") && text.contains("PEACE") {
+            parse_tombstone(screen, &mut res);
+        } else if let Some(caps) = killed.captures(&text) {
+            res.cause = Some(format!("killed by {}", &caps[1]));
+            res.gold = caps[2].parse().ok();
+        } else if let Some(caps) = quit.captures(&text) {
+            res.cause = Some("quit".to_owned());
+            res.gold = caps[1].parse().ok();
+        } else if text.contains("made it to the light of day") {
+            res.cause = Some("total winner".to_owned());
+            return res;
+        } else {
+            continue;
+        }
+        res.score = res.gold;
+        break;
+    }
+    res
+}
+
+// read lines in the tombstone, like "12 Au", "killed by a" and "kestrel"
+fn parse_tombstone(screen: &Screen, res: &mut GameResult) {
+    let inner = regex!(r"\|\s*(.*?)\s*\|");
+    let gold = regex!(r"^(\d+) Au$");
+    let texts: Vec<String> = screen
+        .trimmed_lines()
+        .filter_map(|l| inner.captures(&l).map(|caps| caps[1].to_owned()))
+        .filter(|t| !t.is_empty())
+        .collect();
+    for (i, text) in texts.iter().enumerate() {
+        if let Some(caps) = gold.captures(text) {
+            res.gold = caps[1].parse().ok();
+        } else if text.starts_with("killed by") {
+            let cause = match texts.get(i + 1) {
+                Some(next) if !next.starts_with(|c: char| c.is_ascii_digit()) => {
+                    format!("{} {}", text, next)
+                }
+                _ => text.clone(),
+            };
+            res.cause = Some(cause);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_command() {
+        assert_eq!(Command::Move(Direction::SW).to_bytes(), b"b");
+        assert_eq!(Command::Run(Direction::NE).to_bytes(), b"U");
+        assert_eq!(Command::Quaff(b'c').to_bytes(), b"qc");
+        assert_eq!(Command::Throw(Direction::W, b'f').to_bytes(), b"thf");
+    }
+    fn screen(text: &str) -> Screen {
+        Screen::new(text.lines().map(|l| l.as_bytes().to_vec()).collect())
+    }
+    #[test]
+    fn test_adapter() {
+        let play = "\n\n\nLevel: 2  Gold: 41     Hp: 9(12)   Str: 16(16)  Arm: 4  Exp: 3/25";
+        assert_eq!(
+            Rogue.parse_status(&screen(play)),
+            Some(Status {
+                level: Some(2),
+                gold: Some(41),
+                hp: Some(9),
+                max_hp: Some(12),
+                exp_level: Some(3),
+                turns: None,
+            })
+        );
+        assert_eq!(Rogue.parse_status(&screen("Level: 2  Gold: 41\n")), None);
+        assert_eq!(Rogue.layout(24).status, 23..24);
+        assert_eq!(Rogue.quit_keys(), b"Qy");
+    }
+    #[test]
+    fn test_game_over() {
+        let tomb = "\
+                       __________
+                      /          \\
+                     /    REST    \\
+                    /      IN      \\
+                   /     PEACE      \\
+                  /                  \\
+                  |       ai         |
+                  |      12 Au       |
+                  |   killed by a    |
+                  |      kestrel     |
+                  |       1980       |
+                 *|     *  *  *      | *";
+        let go = game_over();
+        assert_eq!(go.detect(&screen(tomb)), Some(Outcome::Died));
+        let quit = "\n\nYou quit with 35 gold pieces";
+        assert_eq!(go.detect(&screen(quit)), Some(Outcome::Quit));
+        let win = "  Congratulations, you have made it to the light of day!";
+        assert_eq!(go.detect(&screen(win)), Some(Outcome::Won));
+        let play = "Level: 1  Gold: 0      Hp: 12(12)  Str: 16(16)  Arm: 4  Exp: 1/0";
+        assert_eq!(go.detect(&screen(play)), None);
+        let status = "\n\nLevel: 3  Gold: 10     Hp: 1(12)   Str: 16(16)  Arm: 4  Exp: 2/12";
+        let res = parse_result(&[screen(play), screen(status), screen(tomb)]);
+        assert_eq!(
+            res,
+            GameResult {
+                cause: Some("killed by a kestrel".to_owned()),
+                gold: Some(12),
+                level: Some(3),
+                score: Some(12),
+            }
+        );
+        let res = parse_result(&[screen(status), screen(quit)]);
+        assert_eq!(res.cause, Some("quit".to_owned()));
+        assert_eq!((res.gold, res.level, res.score), (Some(35), Some(3), Some(35)));
+        let killed = "Killed by starvation with 20 gold";
+        let res = parse_result(&[screen(killed)]);
+        assert_eq!(res.cause, Some("killed by starvation".to_owned()));
+        assert_eq!((res.gold, res.level), (Some(20), None));
+        let res = parse_result(&[screen(status), screen(win)]);
+        assert_eq!((res.gold, res.score), (Some(10), None));
+        assert_eq!(parse_result(&[screen(play)]).cause, None);
+    }
+}
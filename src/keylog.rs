@@ -0,0 +1,209 @@
+#[cfg(any(test, feature = "process"))]
+use screen::Screen;
+use std::io::{self, Read};
+#[cfg(any(test, feature = "process"))]
+use std::io::Write;
+use std::time::Duration;
+#[cfg(any(test, feature = "process"))]
+use std::time::Instant;
+
+const MAGIC: &[u8; 5] = b"CGWK\x01";
+
+// tags of records
+const GAME_START: u8 = b'S';
+const KEYS: u8 = b'K';
+const RESPONSE: u8 = b'R';
+
+/// Bytes written to the game at once, recorded in key log.
+/// ```time``` is elapsed time from the start of the game.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyRecord {
+    pub time: Duration,
+    /// True for replies to queries of the game(e.g. cursor position reports),
+    /// which are skipped by ```GameHandle::replay_keys``` because the emulator
+    /// answers them again.
+    pub response: bool,
+    /// Hash of the screen text when the keys were sent(```None``` for responses),
+    /// checked by ```GameEnv::restore```
+    pub screen_hash: Option<u64>,
+    pub bytes: Vec<u8>,
+}
+
+// Each game starts with GAME_START, followed by records written as
+// KEYS | micro secs since last record(varint) | screen hash(u64 LE) | length(varint) | bytes
+// or RESPONSE | micro secs since last record(varint) | length(varint) | bytes
+#[cfg(any(test, feature = "process"))]
+pub struct KeyLogWriter<W: Write> {
+    inner: W,
+    start: Instant,
+    // elapsed micro secs at the last record
+    last: u64,
+}
+
+#[cfg(any(test, feature = "process"))]
+impl<W: Write> KeyLogWriter<W> {
+    pub fn new(mut inner: W) -> io::Result<KeyLogWriter<W>> {
+        inner.write_all(MAGIC)?;
+        Ok(KeyLogWriter {
+            inner: inner,
+            start: Instant::now(),
+            last: 0,
+        })
+    }
+    pub fn start_game(&mut self) -> io::Result<()> {
+        self.start = Instant::now();
+        self.last = 0;
+        self.inner.write_all(&[GAME_START])?;
+        self.inner.flush()
+    }
+    // flushed each time, so that the log survives crashes
+    pub fn record(&mut self, screen_hash: Option<u64>, bytes: &[u8]) -> io::Result<()> {
+        let elapsed = self.start.elapsed();
+        let micros = elapsed.as_secs() * 1_000_000 + u64::from(elapsed.subsec_nanos() / 1000);
+        let mut buf = vec![if screen_hash.is_some() { KEYS } else { RESPONSE }];
+        write_varint(&mut buf, micros - self.last);
+        if let Some(hash) = screen_hash {
+            buf.extend((0..8).map(|i| (hash >> (i * 8)) as u8));
+        }
+        write_varint(&mut buf, bytes.len() as u64);
+        buf.extend_from_slice(bytes);
+        self.last = micros;
+        self.inner.write_all(&buf)?;
+        self.inner.flush()
+    }
+}
+
+/// Read all records from key log made by ```GameSetting::key_log_file```,
+/// as a list of records per game.
+pub fn read_key_log<R: Read>(reader: R) -> io::Result<Vec<Vec<KeyRecord>>> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_owned());
+    let mut bytes = reader.bytes();
+    let mut magic = [0u8; 5];
+    for b in magic.iter_mut() {
+        *b = next_byte(&mut bytes)?;
+    }
+    if &magic != MAGIC {
+        return Err(invalid("not a key log file"));
+    }
+    let mut res: Vec<Vec<KeyRecord>> = Vec::new();
+    let mut micros = 0;
+    while let Some(tag) = bytes.next() {
+        let tag = tag?;
+        if tag == GAME_START {
+            res.push(Vec::new());
+            micros = 0;
+            continue;
+        }
+        let response = match tag {
+            KEYS => false,
+            RESPONSE => true,
+            _ => return Err(invalid("invalid record kind")),
+        };
+        micros += read_varint(&mut bytes)?;
+        let screen_hash = if response {
+            None
+        } else {
+            let mut hash = 0;
+            for i in 0..8 {
+                hash |= u64::from(next_byte(&mut bytes)?) << (i * 8);
+            }
+            Some(hash)
+        };
+        let len = read_varint(&mut bytes)?;
+        let data = (0..len)
+            .map(|_| next_byte(&mut bytes))
+            .collect::<io::Result<Vec<u8>>>()?;
+        let game = res.last_mut()
+            .ok_or_else(|| invalid("record before the start of game"))?;
+        game.push(KeyRecord {
+            time: Duration::new(micros / 1_000_000, (micros % 1_000_000) as u32 * 1000),
+            response: response,
+            screen_hash: screen_hash,
+            bytes: data,
+        });
+    }
+    Ok(res)
+}
+
+// FNV-1a hash of the screen text, recorded with keys
+#[cfg(any(test, feature = "process"))]
+pub(crate) fn screen_hash(screen: &Screen) -> u64 {
+    (0..screen.lines())
+        .flat_map(|y| screen.line(y).into_owned().into_bytes().into_iter().chain(Some(b'\n')))
+        .fold(0xcbf2_9ce4_8422_2325u64, |h, b| {
+            (h ^ u64::from(b)).wrapping_mul(0x100_0000_01b3)
+        })
+}
+
+// LEB128
+pub(crate) fn write_varint(buf: &mut Vec<u8>, mut u: u64) {
+    while u >= 0x80 {
+        buf.push(u as u8 | 0x80);
+        u >>= 7;
+    }
+    buf.push(u as u8);
+}
+
+pub(crate) fn read_varint<I: Iterator<Item = io::Result<u8>>>(bytes: &mut I) -> io::Result<u64> {
+    let mut res = 0;
+    for shift in (0..10).map(|i| i * 7) {
+        let b = next_byte(bytes)?;
+        res |= u64::from(b & 0x7f) << shift;
+        if b < 0x80 {
+            return Ok(res);
+        }
+    }
+    Err(io::Error::new(io::ErrorKind::InvalidData, "too long varint"))
+}
+
+pub(crate) fn next_byte<I: Iterator<Item = io::Result<u8>>>(bytes: &mut I) -> io::Result<u8> {
+    bytes.next().unwrap_or_else(|| {
+        Err(io::Error::new(io::ErrorKind::UnexpectedEof, "unexpected end of file"))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_roundtrip() {
+        let mut buf = Vec::new();
+        {
+            let mut writer = KeyLogWriter::new(&mut buf).unwrap();
+            writer.start_game().unwrap();
+            writer.record(Some(u64::max_value()), b"j").unwrap();
+            writer.record(None, b"\x1b[1;1R").unwrap();
+            writer.start_game().unwrap();
+            writer.record(Some(3), &[b'a'; 200]).unwrap();
+        }
+        let games = read_key_log(&buf[..]).unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].len(), 2);
+        assert_eq!(games[0][0].bytes, b"j".to_vec());
+        assert!(!games[0][0].response);
+        assert_eq!(games[0][0].screen_hash, Some(u64::max_value()));
+        assert!(games[0][1].response);
+        assert_eq!(games[0][1].screen_hash, None);
+        assert!(games[0][1].time >= games[0][0].time);
+        assert_eq!(games[1][0].bytes, vec![b'a'; 200]);
+        assert_eq!(games[1][0].screen_hash, Some(3));
+        assert!(read_key_log(&buf[..buf.len() - 1]).is_err());
+        assert!(read_key_log(&b"CGWK\x01R\x00\x00"[..]).is_err());
+        assert!(read_key_log(&b"hello"[..]).is_err());
+    }
+    #[test]
+    fn test_screen_hash() {
+        let screen = |lines: &[&[u8]]| Screen::new(lines.iter().map(|l| l.to_vec()).collect());
+        let hash = screen_hash(&screen(&[b"ab", b"cd"]));
+        assert_eq!(hash, screen_hash(&screen(&[b"ab", b"cd"])));
+        assert_ne!(hash, screen_hash(&screen(&[b"abc", b"d"])));
+    }
+    #[test]
+    fn test_varint() {
+        for &u in &[0, 1, 127, 128, 300, u64::from(u32::max_value()), u64::max_value()] {
+            let mut buf = Vec::new();
+            write_varint(&mut buf, u);
+            assert_eq!(read_varint(&mut buf.into_iter().map(Ok)).unwrap(), u);
+        }
+    }
+}
@@ -0,0 +1,592 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// Named special keys, which you can use in ```keys!```.
+///
+/// Cursor keys are the sequences of normal cursor key mode(```ESC [ A``` etc).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Key {
+    Esc,
+    /// Carriage return, which curses games receive as Enter
+    Enter,
+    Tab,
+    Backspace,
+    Space,
+    Up,
+    Down,
+    Right,
+    Left,
+    Home,
+    End,
+    Insert,
+    Delete,
+    PageUp,
+    PageDown,
+    F1,
+    F2,
+    F3,
+    F4,
+}
+
+impl Key {
+    /// All keys, in declaration order.
+    pub const ALL: [Key; 19] = [
+        Key::Esc,
+        Key::Enter,
+        Key::Tab,
+        Key::Backspace,
+        Key::Space,
+        Key::Up,
+        Key::Down,
+        Key::Right,
+        Key::Left,
+        Key::Home,
+        Key::End,
+        Key::Insert,
+        Key::Delete,
+        Key::PageUp,
+        Key::PageDown,
+        Key::F1,
+        Key::F2,
+        Key::F3,
+        Key::F4,
+    ];
+    /// Parse the name of the key(case insensitive), like ```Key::from_name("pageup")```.
+    pub fn from_name(name: &str) -> Option<Key> {
+        Key::ALL
+            .iter()
+            .find(|k| format!("{:?}", k).eq_ignore_ascii_case(name))
+            .cloned()
+    }
+    /// Returns the bytes sent to the game.
+    pub fn as_bytes(&self) -> &'static [u8] {
+        match *self {
+            Key::Esc => b"\x1b",
+            Key::Enter => b"\r",
+            Key::Tab => b"\t",
+            Key::Backspace => b"\x7f",
+            Key::Space => b" ",
+            Key::Up => b"\x1b[A",
+            Key::Down => b"\x1b[B",
+            Key::Right => b"\x1b[C",
+            Key::Left => b"\x1b[D",
+            Key::Home => b"\x1b[H",
+            Key::End => b"\x1b[F",
+            Key::Insert => b"\x1b[2~",
+            Key::Delete => b"\x1b[3~",
+            Key::PageUp => b"\x1b[5~",
+            Key::PageDown => b"\x1b[6~",
+            Key::F1 => b"\x1bOP",
+            Key::F2 => b"\x1bOQ",
+            Key::F3 => b"\x1bOR",
+            Key::F4 => b"\x1bOS",
+        }
+    }
+}
+
+/// Cursor key mode(DECCKM) of the terminal, which changes the sequences of arrow keys.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum CursorKeyMode {
+    /// Arrow keys send ```ESC [ A``` etc.
+    Normal,
+    /// Arrow keys send ```ESC O A``` etc.
+    Application,
+}
+
+impl Default for CursorKeyMode {
+    fn default() -> CursorKeyMode {
+        CursorKeyMode::Normal
+    }
+}
+
+/// 8 directions, shared by movement logic and key emission.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    N,
+    S,
+    E,
+    W,
+    NE,
+    NW,
+    SE,
+    SW,
+}
+
+impl Direction {
+    /// All directions, clockwise from ```N```.
+    pub const ALL: [Direction; 8] = [
+        Direction::N,
+        Direction::NE,
+        Direction::E,
+        Direction::SE,
+        Direction::S,
+        Direction::SW,
+        Direction::W,
+        Direction::NW,
+    ];
+    /// Returns vi-key(```hjklyubn```) of the direction.
+    pub fn as_vi_byte(&self) -> u8 {
+        match *self {
+            Direction::N => b'k',
+            Direction::S => b'j',
+            Direction::E => b'l',
+            Direction::W => b'h',
+            Direction::NE => b'u',
+            Direction::NW => b'y',
+            Direction::SE => b'n',
+            Direction::SW => b'b',
+        }
+    }
+    /// Parse vi-key.
+    pub fn from_vi_byte(b: u8) -> Option<Direction> {
+        Direction::ALL.iter().find(|d| d.as_vi_byte() == b).cloned()
+    }
+    /// Returns the sequence of cursor key.
+    ///
+    /// Diagonal directions are mapped to keys of numeric keypad without NumLock
+    /// (```Home```, ```PageUp```, ```End``` and ```PageDown```).
+    pub fn as_arrow_seq(&self, mode: CursorKeyMode) -> &'static [u8] {
+        let app = mode == CursorKeyMode::Application;
+        match *self {
+            Direction::N if app => b"\x1bOA",
+            Direction::S if app => b"\x1bOB",
+            Direction::E if app => b"\x1bOC",
+            Direction::W if app => b"\x1bOD",
+            Direction::NW if app => b"\x1bOH",
+            Direction::SW if app => b"\x1bOF",
+            Direction::N => Key::Up.as_bytes(),
+            Direction::S => Key::Down.as_bytes(),
+            Direction::E => Key::Right.as_bytes(),
+            Direction::W => Key::Left.as_bytes(),
+            Direction::NW => Key::Home.as_bytes(),
+            Direction::SW => Key::End.as_bytes(),
+            Direction::NE => Key::PageUp.as_bytes(),
+            Direction::SE => Key::PageDown.as_bytes(),
+        }
+    }
+    /// Returns ```(dx, dy)``` on the screen(y grows downward).
+    pub fn delta(&self) -> (isize, isize) {
+        match *self {
+            Direction::N => (0, -1),
+            Direction::S => (0, 1),
+            Direction::E => (1, 0),
+            Direction::W => (-1, 0),
+            Direction::NE => (1, -1),
+            Direction::NW => (-1, -1),
+            Direction::SE => (1, 1),
+            Direction::SW => (-1, 1),
+        }
+    }
+    /// Returns the direction of ```(dx, dy)```, using only their signs.
+    /// Returns ```None``` for ```(0, 0)```.
+    pub fn from_delta(dx: isize, dy: isize) -> Option<Direction> {
+        let d = (dx.signum(), dy.signum());
+        Direction::ALL.iter().find(|dir| dir.delta() == d).cloned()
+    }
+}
+
+/// Ctrl-C
+pub const CTRL_C: u8 = 0x03;
+/// Ctrl-D(kick in NetHack)
+pub const CTRL_D: u8 = 0x04;
+/// Ctrl-P(previous message in rogue and NetHack)
+pub const CTRL_P: u8 = 0x10;
+/// Ctrl-Q(XON)
+pub const CTRL_Q: u8 = 0x11;
+/// Ctrl-R(redraw screen in rogue and NetHack)
+pub const CTRL_R: u8 = 0x12;
+/// Ctrl-S(XOFF)
+pub const CTRL_S: u8 = 0x13;
+/// Ctrl-X(show attributes in NetHack)
+pub const CTRL_X: u8 = 0x18;
+/// Ctrl-Z
+pub const CTRL_Z: u8 = 0x1a;
+
+/// Returns the control code sent by Ctrl + ```c```, like ```ctrl('r') == 0x12```.
+///
+/// Letters are case insensitive, and ```ctrl('?')``` is DEL.
+/// To use it in ```keys!```, put it in an array like ```[ctrl('r')]```.
+/// # Panics
+/// Panics if ```c``` is not one of ```@A-Z[\]^_a-z?```.
+pub fn ctrl(c: char) -> u8 {
+    match c {
+        '?' => 0x7f,
+        '@'..='_' | 'a'..='z' => c as u8 & 0x1f,
+        _ => panic!("no control code for {:?}", c),
+    }
+}
+
+/// How the game receives Alt(Meta)-modified keys.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum MetaEncoding {
+    /// ```ESC``` followed by the key, like xterm's ```metaSendsEscape```.
+    /// NetHack needs option ```altmeta``` for it.
+    Esc,
+    /// The key with the 8th bit set
+    HighBit,
+}
+
+impl Default for MetaEncoding {
+    fn default() -> MetaEncoding {
+        MetaEncoding::Esc
+    }
+}
+
+/// Returns the bytes sent by Alt + ```c```, like ```meta(b'p', MetaEncoding::Esc)```
+/// which is ```#pray``` in NetHack.
+pub fn meta(c: u8, encoding: MetaEncoding) -> Vec<u8> {
+    match encoding {
+        MetaEncoding::Esc => vec![0x1b, c],
+        MetaEncoding::HighBit => vec![c | 0x80],
+    }
+}
+
+/// Returns the bytes to paste ```text```.
+///
+/// If ```bracketed``` is true(i.e. the game enabled bracketed paste mode, 2004),
+/// the text is wrapped in ```ESC [ 200 ~``` and ```ESC [ 201 ~```, and end markers
+/// inside the text are removed so that it can't end the paste early.
+pub fn wrap_paste(text: &[u8], bracketed: bool) -> Vec<u8> {
+    const START: &[u8] = b"\x1b[200~";
+    const END: &[u8] = b"\x1b[201~";
+    if !bracketed {
+        return text.to_vec();
+    }
+    let mut res = START.to_vec();
+    let mut i = 0;
+    while i < text.len() {
+        if text[i..].starts_with(END) {
+            i += END.len();
+        } else {
+            res.push(text[i]);
+            i += 1;
+        }
+    }
+    res.extend_from_slice(END);
+    res
+}
+
+/// Split keys into what's typed at once: a character(possibly multibyte in
+/// UTF-8), an escape sequence, or a character with Meta as ```ESC```.
+pub(crate) fn split_keys(bytes: &[u8]) -> Vec<&[u8]> {
+    let mut res = Vec::new();
+    let mut rest = bytes;
+    while !rest.is_empty() {
+        let len = match rest[0] {
+            0x1b => match rest.get(1) {
+                // CSI and SS3: until the final byte
+                Some(&b'[') | Some(&b'O') => rest[2..]
+                    .iter()
+                    .position(|&b| b >= 0x40 && b <= 0x7e)
+                    .map_or(rest.len(), |i| i + 3),
+                Some(_) => 2,
+                None => 1,
+            },
+            b if b >= 0xc0 => {
+                1 + rest[1..]
+                    .iter()
+                    .take_while(|&&b| b & 0xc0 == 0x80)
+                    .count()
+            }
+            _ => 1,
+        };
+        res.push(&rest[..len]);
+        rest = &rest[len..];
+    }
+    res
+}
+
+/// Translation of keys sent by the AI, applied before they're written to the
+/// game, so that one AI can play games configured differently.
+/// Register it by ```GameSetting::key_map```.
+///
+/// Keys are split as typed(a character, an escape sequence, or a character with
+/// Meta as ```ESC```), so a mapped character isn't replaced inside escape sequences.
+/// # Example
+/// ```
+/// extern crate curses_game_wrapper as cgw;
+/// use cgw::KeyMap;
+/// fn main() {
+///     // abstract directions, and QWERTZ layout
+///     let map = KeyMap::new().map(b"N", b"k").map(b"S", b"j").swap(b"y", b"z");
+///     assert_eq!(&map.apply(b"NSy\x1b[A")[..], b"kjz\x1b[A");
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct KeyMap {
+    keys: HashMap<Vec<u8>, Vec<u8>>,
+}
+
+impl KeyMap {
+    pub fn new() -> KeyMap {
+        KeyMap::default()
+    }
+    /// Send ```to``` instead of key ```from```(a character, an escape sequence,
+    /// or a character with Meta as ```ESC```).
+    pub fn map(mut self, from: &[u8], to: &[u8]) -> KeyMap {
+        self.keys.insert(from.to_vec(), to.to_vec());
+        self
+    }
+    /// Send ```a``` for ```b``` and vice versa.
+    pub fn swap(self, a: &[u8], b: &[u8]) -> KeyMap {
+        self.map(a, b).map(b, a)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+    /// Translate ```bytes``` sent by the AI.
+    pub fn apply<'a>(&self, bytes: &'a [u8]) -> Cow<'a, [u8]> {
+        if self.keys.is_empty() {
+            return Cow::Borrowed(bytes);
+        }
+        let mut res = Vec::with_capacity(bytes.len());
+        for key in split_keys(bytes) {
+            match self.keys.get(key) {
+                Some(to) => res.extend_from_slice(to),
+                None => res.extend_from_slice(key),
+            }
+        }
+        Cow::Owned(res)
+    }
+}
+
+/// Checks keys sent by the AI before they're written to the game, so that a
+/// buggy AI can't leave the game in a weird state during long unattended runs.
+/// Register it by ```GameSetting::sanitize_input```.
+///
+/// By default it only strips NUL bytes.
+/// # Example
+/// ```
+/// extern crate curses_game_wrapper as cgw;
+/// use cgw::InputSanitizer;
+/// fn main() {
+///     let sanitizer = InputSanitizer::new().max_len(3).reject_esc(true);
+///     assert_eq!(&sanitizer.apply(b"h\0jkl").unwrap()[..], b"hjk");
+///     assert_eq!(sanitizer.apply(b"\x1b"), Some(b"\x1b"[..].into()));
+///     assert_eq!(sanitizer.apply(b"\x1b[A"), None);
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InputSanitizer {
+    strip_nul: bool,
+    max_len: Option<usize>,
+    reject_esc: bool,
+}
+
+impl Default for InputSanitizer {
+    fn default() -> InputSanitizer {
+        InputSanitizer {
+            strip_nul: true,
+            max_len: None,
+            reject_esc: false,
+        }
+    }
+}
+
+impl InputSanitizer {
+    pub fn new() -> InputSanitizer {
+        InputSanitizer::default()
+    }
+    /// Remove NUL bytes(Default: true).
+    pub fn strip_nul(mut self, on: bool) -> InputSanitizer {
+        self.strip_nul = on;
+        self
+    }
+    /// Send at most ```len``` bytes per turn, dropping keys after them.
+    /// Keys aren't cut in the middle of escape sequences.
+    pub fn max_len(mut self, len: usize) -> InputSanitizer {
+        self.max_len = Some(len);
+        self
+    }
+    /// Reject whole keys of the turn if they contain escape sequences, including
+    /// arrow keys and Meta keys as ```ESC```(Default: false).
+    /// A lone ```ESC``` is allowed, to cancel menus.
+    pub fn reject_esc(mut self, on: bool) -> InputSanitizer {
+        self.reject_esc = on;
+        self
+    }
+    /// Returns ```bytes``` to send after the checks, or ```None``` if they're rejected.
+    pub fn apply<'a>(&self, bytes: &'a [u8]) -> Option<Cow<'a, [u8]>> {
+        let stripped: Vec<u8>;
+        let keys = if self.strip_nul && bytes.contains(&0) {
+            stripped = bytes.iter().cloned().filter(|&b| b != 0).collect();
+            split_keys(&stripped)
+        } else {
+            split_keys(bytes)
+        };
+        let mut res = Vec::with_capacity(bytes.len());
+        for key in keys {
+            if self.reject_esc && key.len() > 1 && key[0] == 0x1b {
+                return None;
+            }
+            if self.max_len.map_or(false, |max| res.len() + key.len() > max) {
+                break;
+            }
+            res.extend_from_slice(key);
+        }
+        // only removed bytes
+        Some(if res.len() == bytes.len() {
+            Cow::Borrowed(bytes)
+        } else {
+            Cow::Owned(res)
+        })
+    }
+}
+
+/// Parse key sequence written like ```"2h<Esc>i<C-r><M-p><Enter>"```.
+///
+/// In angle brackets you can write names of ```Key```, ```C-x``` for ```ctrl('x')```,
+/// ```M-x``` for Alt + ```x``` encoded by ```encoding```, and ```lt``` for ```<```.
+/// Other characters are sent as they are.
+/// Returns the part which couldn't be parsed as ```Err```.
+pub fn parse_keys(spec: &str, encoding: MetaEncoding) -> Result<Vec<u8>, String> {
+    let mut res = Vec::new();
+    let mut rest = spec;
+    while let Some(start) = rest.find('<') {
+        res.extend_from_slice(rest[..start].as_bytes());
+        rest = &rest[start..];
+        let end = rest.find('>').ok_or_else(|| rest.to_owned())?;
+        let name = &rest[1..end];
+        match *name.as_bytes() {
+            [m, b'-', c] if (m == b'C' || m == b'c')
+                && (c == b'?' || (b'@'..=b'_').contains(&c.to_ascii_uppercase())) =>
+            {
+                res.push(ctrl(char::from(c)))
+            }
+            [m, b'-', c] if (m == b'M' || m == b'm') && c.is_ascii() => {
+                res.extend(meta(c, encoding))
+            }
+            _ if name.eq_ignore_ascii_case("lt") => res.push(b'<'),
+            _ => match Key::from_name(name) {
+                Some(key) => res.extend_from_slice(key.as_bytes()),
+                None => return Err(rest[..=end].to_owned()),
+            },
+        }
+        rest = &rest[end + 1..];
+    }
+    res.extend_from_slice(rest.as_bytes());
+    Ok(res)
+}
+
+/// Make key sequence as ```Vec<u8>``` from strings and names of ```Key```.
+///
+/// Other expressions which are ```AsRef<[u8]>``` can be used in parentheses.
+/// # Example
+/// ```
+/// #[macro_use]
+/// extern crate curses_game_wrapper as cgw;
+/// fn main() {
+///     assert_eq!(keys!("2h" Esc "i" Enter), b"2h\x1bi\r".to_vec());
+///     let name = "AI";
+///     assert_eq!(keys!("Call " (name) Enter), b"Call AI\r".to_vec());
+/// }
+/// ```
+#[macro_export]
+macro_rules! keys {
+    (@push $v:ident, $key:ident) => {
+        $v.extend_from_slice($crate::Key::$key.as_bytes())
+    };
+    (@push $v:ident, $bytes:expr) => {
+        $v.extend_from_slice(::std::convert::AsRef::<[u8]>::as_ref(&$bytes))
+    };
+    () => {
+        Vec::<u8>::new()
+    };
+    ($($k:tt)+) => {{
+        let mut v: Vec<u8> = Vec::new();
+        $(keys!(@push v, $k);)*
+        v
+    }};
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_keys() {
+        assert_eq!(keys!(), Vec::<u8>::new());
+        assert_eq!(keys!("2h" Esc "i" Enter), b"2h\x1bi\r".to_vec());
+        assert_eq!(keys!(Up Left (b"yes")), b"\x1b[A\x1b[Dyes".to_vec());
+    }
+    #[test]
+    fn test_ctrl() {
+        assert_eq!(ctrl('r'), CTRL_R);
+        assert_eq!(ctrl('P'), CTRL_P);
+        assert_eq!(ctrl('['), Key::Esc.as_bytes()[0]);
+        assert_eq!(ctrl('?'), 0x7f);
+        assert_eq!(keys!("a" [ctrl('d')] "h"), b"a\x04h".to_vec());
+    }
+    #[test]
+    fn test_meta() {
+        assert_eq!(meta(b'p', MetaEncoding::Esc), b"\x1bp");
+        assert_eq!(meta(b'p', MetaEncoding::HighBit), vec![0xf0]);
+    }
+    #[test]
+    fn test_key_name() {
+        for k in &Key::ALL {
+            assert_eq!(Key::from_name(&format!("{:?}", k)), Some(*k));
+        }
+        assert_eq!(Key::from_name("pageup"), Some(Key::PageUp));
+        assert_eq!(Key::from_name("Pg"), None);
+    }
+    #[test]
+    fn test_parse_keys() {
+        let enc = MetaEncoding::Esc;
+        assert_eq!(parse_keys("2h<Esc>i<enter>", enc), Ok(b"2h\x1bi\r".to_vec()));
+        assert_eq!(parse_keys("<C-r><c-?><M-p>", enc), Ok(b"\x12\x7f\x1bp".to_vec()));
+        assert_eq!(parse_keys("<lt>a>", enc), Ok(b"<a>".to_vec()));
+        assert_eq!(parse_keys("<M-p>", MetaEncoding::HighBit), Ok(vec![0xf0]));
+        assert_eq!(parse_keys("a<Foo>b", enc), Err("<Foo>".to_owned()));
+        assert_eq!(parse_keys("a<C-1>", enc), Err("<C-1>".to_owned()));
+        assert_eq!(parse_keys("a<Up", enc), Err("<Up".to_owned()));
+    }
+    #[test]
+    fn test_wrap_paste() {
+        assert_eq!(wrap_paste(b"abc", false), b"abc");
+        assert_eq!(wrap_paste(b"abc", true), b"\x1b[200~abc\x1b[201~");
+        assert_eq!(
+            wrap_paste(b"a\x1b[201~b", true),
+            b"\x1b[200~ab\x1b[201~"
+        );
+    }
+    #[test]
+    fn test_split_keys() {
+        let keys = split_keys(b"ab\x1b[A\x1bOP\x1b[1;5C\x1bp\xc3\xa9\x1b");
+        let expected: Vec<&[u8]> = vec![
+            b"a", b"b", b"\x1b[A", b"\x1bOP", b"\x1b[1;5C", b"\x1bp", b"\xc3\xa9", b"\x1b"
+        ];
+        assert_eq!(keys, expected);
+        assert_eq!(split_keys(b"\x1b[1"), vec![&b"\x1b[1"[..]]);
+    }
+    #[test]
+    fn test_key_map() {
+        let map = KeyMap::new().map(b"W", b"\x1b[D").map(b"\x1bOP", b"?").swap(b"y", b"z");
+        assert_eq!(&map.apply(b"Wyz\x1bOPz\x1b[1;5y")[..], b"\x1b[Dzy?y\x1b[1;5y");
+        assert_eq!(&KeyMap::new().apply(b"Wy")[..], b"Wy");
+    }
+    #[test]
+    fn test_input_sanitizer() {
+        let s = InputSanitizer::new();
+        assert_eq!(s.apply(b"ab\x1b[A"), Some(Cow::Borrowed(&b"ab\x1b[A"[..])));
+        assert_eq!(&s.apply(b"\0a\0").unwrap()[..], b"a");
+        assert_eq!(&s.clone().strip_nul(false).apply(b"\0a").unwrap()[..], b"\0a");
+        let s = s.max_len(4);
+        assert_eq!(&s.apply(b"a\x1b[1;5Ab").unwrap()[..], b"a");
+        assert_eq!(&s.apply(b"ab\x1bOPc").unwrap()[..], b"ab");
+        let s = s.reject_esc(true);
+        assert_eq!(s.apply(b"a\x1bq"), None);
+        assert_eq!(&s.apply(b"\x1b\0").unwrap()[..], b"\x1b");
+    }
+    #[test]
+    fn test_direction() {
+        for d in &Direction::ALL {
+            assert_eq!(Direction::from_vi_byte(d.as_vi_byte()), Some(*d));
+            let (dx, dy) = d.delta();
+            assert_eq!(Direction::from_delta(dx * 3, dy * 3), Some(*d));
+        }
+        assert_eq!(Direction::from_delta(0, 0), None);
+        assert_eq!(Direction::N.as_arrow_seq(CursorKeyMode::Normal), b"\x1b[A");
+        assert_eq!(Direction::N.as_arrow_seq(CursorKeyMode::Application), b"\x1bOA");
+        assert_eq!(Direction::NE.as_arrow_seq(CursorKeyMode::Application), b"\x1b[5~");
+    }
+}
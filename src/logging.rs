@@ -0,0 +1,65 @@
+// Logging backend of this crate.
+//
+// With feature "slog-logger"(default), we use slog + sloggers.
+// Without it, trace!/debug!/warn! below forward messages to "log" crate
+// if feature "log" is enabled, or just discard them.
+
+#[cfg(feature = "slog-logger")]
+pub use slog::Logger;
+
+#[cfg(feature = "slog-logger")]
+pub use sloggers::types::Severity;
+
+#[cfg(not(feature = "slog-logger"))]
+#[derive(Clone, Debug)]
+pub struct Logger;
+
+/// Logging level used by ```GameSetting::debug_level```.
+#[cfg(not(feature = "slog-logger"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Trace,
+    Debug,
+    Info,
+    Warning,
+    Error,
+    Critical,
+}
+
+#[cfg(all(not(feature = "slog-logger"), feature = "log"))]
+macro_rules! log_facade {
+    ($lvl:ident, $($args:tt)+) => {
+        log!(target: "curses_game_wrapper", ::log::Level::$lvl, $($args)+)
+    }
+}
+
+#[cfg(all(not(feature = "slog-logger"), not(feature = "log")))]
+macro_rules! log_facade {
+    ($lvl:ident, $($args:tt)+) => {{
+        let _ = format_args!($($args)+);
+    }}
+}
+
+#[cfg(not(feature = "slog-logger"))]
+macro_rules! trace {
+    ($logger:expr, $($args:tt)+) => {{
+        let _ = &$logger;
+        log_facade!(Trace, $($args)+)
+    }}
+}
+
+#[cfg(not(feature = "slog-logger"))]
+macro_rules! debug {
+    ($logger:expr, $($args:tt)+) => {{
+        let _ = &$logger;
+        log_facade!(Debug, $($args)+)
+    }}
+}
+
+#[cfg(not(feature = "slog-logger"))]
+macro_rules! warn {
+    ($logger:expr, $($args:tt)+) => {{
+        let _ = &$logger;
+        log_facade!(Warn, $($args)+)
+    }}
+}
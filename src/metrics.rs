@@ -0,0 +1,93 @@
+use stop::{GameResult, Outcome};
+use std::time::Duration;
+
+/// Runtime statistics of one game.
+///
+/// ```GameEnv::play``` returns it when the game ends.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Metrics {
+    /// Number of turns(calls of ```Reactor::action```)
+    pub turns: usize,
+    /// Total bytes read from the game
+    pub bytes_read: usize,
+    /// Total bytes written to the game
+    pub bytes_written: usize,
+    /// Number of ```ActionResult::Changed``` delivered to the AI
+    pub frames: usize,
+    /// Time spent in vt100 emulation
+    pub parse_time: Duration,
+    /// Time spent in ```Reactor::action```
+    pub action_time: Duration,
+    /// Time spent waiting for the game output
+    pub wait_time: Duration,
+    /// How many times the game was restarted after crash.
+    /// See ```GameSetting::restart_on_crash```.
+    pub restarts: usize,
+    /// The game was killed by watchdog.
+    /// See ```GameSetting::watchdog```.
+    pub hung: bool,
+    /// Number of actions not sent, since ```GameSetting::sanitize_input``` rejected them.
+    pub rejected: usize,
+    /// Sum of rewards of delivered frames.
+    /// See ```GameSetting::reward```.
+    pub reward: f64,
+    /// Reason of the condition which ended the game.
+    /// See ```GameSetting::stop_when```.
+    pub stop_reason: Option<String>,
+    /// How the game ended, if found by ```GameSetting::game_over```.
+    pub outcome: Option<Outcome>,
+    /// Result parsed from the final screens, if ```GameOver::result_parser``` is set.
+    pub result: Option<GameResult>,
+}
+
+impl Metrics {
+    // statistics from ```prev``` to now
+    #[cfg(feature = "process")]
+    pub(crate) fn turn_stats(
+        &self,
+        prev: &Metrics,
+        bells: usize,
+        latency: Option<Duration>,
+    ) -> TurnStats {
+        TurnStats {
+            turn: self.turns,
+            bytes_read: self.bytes_read - prev.bytes_read,
+            bytes_written: self.bytes_written - prev.bytes_written,
+            parse_time: self.parse_time - prev.parse_time,
+            action_time: self.action_time - prev.action_time,
+            wait_time: self.wait_time - prev.wait_time,
+            bells: bells,
+            latency: latency,
+        }
+    }
+}
+
+/// Statistics of one turn, passed to ```Reactor::on_turn_stats```.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TurnStats {
+    pub turn: usize,
+    /// Bytes read from the game since last turn
+    pub bytes_read: usize,
+    /// Bytes written to the game in this turn
+    pub bytes_written: usize,
+    /// Time spent in vt100 emulation since last turn
+    pub parse_time: Duration,
+    /// Time spent in ```Reactor::action``` in this turn
+    pub action_time: Duration,
+    /// Time spent waiting for the game output since last turn
+    pub wait_time: Duration,
+    /// How many times the bell rang since last turn
+    pub bells: usize,
+    /// Time from writing keys to the first output of the game after them, if the
+    /// game responded since last turn. Useful to tune ```GameSetting::timeout```.
+    pub latency: Option<Duration>,
+}
+
+/// How long the screen hasn't changed, passed to ```Reactor::on_idle```.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct IdleStreak {
+    /// Number of consecutive ```NotChanged``` turns, including this turn
+    pub turns: usize,
+    /// Time since the last ```Changed``` was delivered(or the game started)
+    pub since_change: Duration,
+}
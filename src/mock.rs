@@ -0,0 +1,128 @@
+use action_log::escape_bytes;
+use std::fmt::{self, Debug, Formatter};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Fake game defined by a closure, to test your Reactor quickly and
+/// deterministically without spawning real games. Use it by ```GameSetting::mock```.
+///
+/// The closure receives keys sent to the game(empty when the game starts) and
+/// returns bytes written to the terminal(with vt100 sequences), or ```None```
+/// to exit the game.
+/// # Example
+/// ```
+/// extern crate curses_game_wrapper as cgw;
+/// use cgw::MockGame;
+/// fn main() {
+///     // echo keys, and exit by 'q'
+///     let game = MockGame::new(|keys: &[u8]| match keys {
+///         b"q" => None,
+///         _ => Some(keys.to_vec()),
+///     });
+///     assert_eq!(game.respond(b"hj"), Some(b"hj".to_vec()));
+///     assert_eq!(game.respond(b"q"), None);
+/// }
+/// ```
+#[derive(Clone)]
+pub struct MockGame(Arc<Mutex<dyn FnMut(&[u8]) -> Option<Vec<u8>> + Send>>);
+
+impl MockGame {
+    pub fn new<F: FnMut(&[u8]) -> Option<Vec<u8>> + Send + 'static>(f: F) -> MockGame {
+        MockGame(Arc::new(Mutex::new(f)))
+    }
+    /// Call the closure with ```keys```.
+    pub fn respond(&self, keys: &[u8]) -> Option<Vec<u8>> {
+        let mut f = self.0.lock().unwrap();
+        (&mut *f)(keys)
+    }
+}
+
+impl Debug for MockGame {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "MockGame")
+    }
+}
+
+/// Fake game playing back canned vt100 output, to test the whole play loop
+/// end to end. Use it by ```GameSetting::mock(script.to_mock())```.
+///
+/// The game prints ```start``` when it starts, and then expects keys of each
+/// step in order, printing the output of the step.
+/// It panics if the AI sends other keys than expected, or sends keys after
+/// the end of the script.
+/// # Example
+/// ```
+/// extern crate curses_game_wrapper as cgw;
+/// use cgw::ScriptedGame;
+/// fn main() {
+///     let script = ScriptedGame::new(b"\x1b[2J@")
+///         .step(b"l", b"\x1b[1;1H.@")
+///         .exit(b"q");
+///     let game = script.to_mock();
+///     assert_eq!(game.respond(b""), Some(b"\x1b[2J@".to_vec()));
+///     assert_eq!(game.respond(b"l"), Some(b"\x1b[1;1H.@".to_vec()));
+///     assert_eq!(script.remaining(), 1);
+///     assert_eq!(game.respond(b"q"), None);
+///     assert_eq!(script.remaining(), 0);
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ScriptedGame {
+    start: Vec<u8>,
+    // expected keys and output(None to exit)
+    steps: Vec<(Vec<u8>, Option<Vec<u8>>)>,
+    // index of the next step, shared with mocks
+    next: Arc<AtomicUsize>,
+}
+
+impl ScriptedGame {
+    pub fn new(start: &[u8]) -> ScriptedGame {
+        ScriptedGame {
+            start: start.to_vec(),
+            steps: Vec::new(),
+            next: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+    /// Print ```output``` when the AI sends ```keys```.
+    pub fn step(mut self, keys: &[u8], output: &[u8]) -> ScriptedGame {
+        self.steps.push((keys.to_vec(), Some(output.to_vec())));
+        self
+    }
+    /// Exit the game when the AI sends ```keys```.
+    pub fn exit(mut self, keys: &[u8]) -> ScriptedGame {
+        self.steps.push((keys.to_vec(), None));
+        self
+    }
+    /// Returns the number of steps not played yet in the current game,
+    /// to check that the AI went through the script.
+    pub fn remaining(&self) -> usize {
+        self.steps.len() - self.next.load(Ordering::SeqCst)
+    }
+    /// Make ```MockGame``` playing the script from the start in each game.
+    pub fn to_mock(&self) -> MockGame {
+        let script = self.clone();
+        MockGame::new(move |keys: &[u8]| {
+            if keys.is_empty() {
+                script.next.store(0, Ordering::SeqCst);
+                return Some(script.start.clone());
+            }
+            let i = script.next.load(Ordering::SeqCst);
+            let &(ref expected, ref output) = script.steps.get(i).unwrap_or_else(|| {
+                panic!(
+                    "ScriptedGame: got \"{}\" after the end of the script",
+                    escape_bytes(keys)
+                )
+            });
+            if &expected[..] != keys {
+                panic!(
+                    "ScriptedGame: step {} expected \"{}\", but got \"{}\"",
+                    i,
+                    escape_bytes(expected),
+                    escape_bytes(keys)
+                );
+            }
+            script.next.store(i + 1, Ordering::SeqCst);
+            output.clone()
+        })
+    }
+}
@@ -0,0 +1,117 @@
+/// Mouse buttons for ```MouseEvent```.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
+    WheelUp,
+    WheelDown,
+}
+
+impl MouseButton {
+    fn code(&self) -> u8 {
+        match *self {
+            MouseButton::Left => 0,
+            MouseButton::Middle => 1,
+            MouseButton::Right => 2,
+            MouseButton::WheelUp => 64,
+            MouseButton::WheelDown => 65,
+        }
+    }
+}
+
+/// Synthetic mouse event sent to the game.
+///
+/// ```x``` and ```y``` are 0-origin cell positions, same as ```Screen```.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum MouseEvent {
+    Press { button: MouseButton, x: usize, y: usize },
+    Release { button: MouseButton, x: usize, y: usize },
+    /// Move with the button held(drag)
+    Motion { button: MouseButton, x: usize, y: usize },
+}
+
+/// Mouse reporting modes enabled by the game.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MouseMode {
+    /// Report clicks(mode 1000)
+    pub click: bool,
+    /// Report clicks and drags(mode 1002)
+    pub motion: bool,
+    /// Use SGR extended encoding(mode 1006) instead of X10 encoding
+    pub sgr: bool,
+}
+
+impl MouseEvent {
+    /// Encode the event for the mode.
+    ///
+    /// Returns ```None``` if the game doesn't accept the event, or the position
+    /// can't be encoded(X10 encoding supports only up to 223 columns and lines).
+    pub fn encode(&self, mode: MouseMode) -> Option<Vec<u8>> {
+        let (code, x, y, release) = match *self {
+            MouseEvent::Press { button, x, y } => (button.code(), x, y, false),
+            MouseEvent::Release { button, x, y } => (button.code(), x, y, true),
+            MouseEvent::Motion { button, x, y } => (button.code() + 32, x, y, false),
+        };
+        let is_motion = code & 32 != 0;
+        if !(mode.motion || (mode.click && !is_motion)) {
+            return None;
+        }
+        if mode.sgr {
+            let end = if release { 'm' } else { 'M' };
+            return Some(format!("\x1b[<{};{};{}{}", code, x + 1, y + 1, end).into_bytes());
+        }
+        // X10 encoding can't tell which button is released
+        let code = if release { 3 } else { code };
+        if x + 1 > 223 || y + 1 > 223 {
+            return None;
+        }
+        Some(vec![
+            0x1b,
+            b'[',
+            b'M',
+            32 + code,
+            32 + x as u8 + 1,
+            32 + y as u8 + 1,
+        ])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_encode() {
+        let click = MouseEvent::Press {
+            button: MouseButton::Left,
+            x: 2,
+            y: 0,
+        };
+        let release = MouseEvent::Release {
+            button: MouseButton::Right,
+            x: 2,
+            y: 0,
+        };
+        let drag = MouseEvent::Motion {
+            button: MouseButton::Left,
+            x: 3,
+            y: 1,
+        };
+        let x10 = MouseMode {
+            click: true,
+            ..MouseMode::default()
+        };
+        assert_eq!(click.encode(MouseMode::default()), None);
+        assert_eq!(click.encode(x10), Some(b"\x1b[M #!".to_vec()));
+        assert_eq!(release.encode(x10), Some(b"\x1b[M##!".to_vec()));
+        assert_eq!(drag.encode(x10), None);
+        let sgr = MouseMode {
+            motion: true,
+            sgr: true,
+            ..MouseMode::default()
+        };
+        assert_eq!(click.encode(sgr), Some(b"\x1b[<0;3;1M".to_vec()));
+        assert_eq!(release.encode(sgr), Some(b"\x1b[<2;3;1m".to_vec()));
+        assert_eq!(drag.encode(sgr), Some(b"\x1b[<32;4;2M".to_vec()));
+    }
+}
@@ -0,0 +1,296 @@
+use dataset::DatasetRecord;
+use screen::Screen;
+use std::f64;
+use std::io::{self, Write};
+use super::ActionResult;
+
+/// Write ```records``` of one game(an element of ```read_dataset```) as NumPy
+/// ```.npz``` archive, so that Python can load it by ```numpy.load```.
+///
+/// The archive has arrays with one element per turn:
+///
+/// - ```frames```: ```uint8``` of shape ```(turns, lines, columns)```, the screen the AI
+///   saw in the turn(the last screen for ```NotChanged```), padded with spaces.
+/// - ```changed```, ```ended```: ```bool```, whether the AI received ```Changed``` or
+///   ```GameEnded```.
+/// - ```actions```: ```bytes```(```S``` dtype) the AI returned, and ```has_action```:
+///   ```bool```, which is false if the AI returned ```None```.
+/// - ```rewards```: ```float64```, ```NaN``` if not recorded.
+/// - ```times```: ```float64```, seconds from the start of the game.
+/// - ```turns```: ```int64```.
+/// # Example
+/// ```no_run
+/// extern crate curses_game_wrapper as cgw;
+/// use cgw::{read_dataset, write_npz};
+/// use std::fs::File;
+/// fn main() {
+///     let games = read_dataset(File::open("rogue.dataset").unwrap()).unwrap();
+///     for (i, game) in games.iter().enumerate() {
+///         let file = File::create(format!("rogue-{}.npz", i)).unwrap();
+///         write_npz(file, game).unwrap();
+///     }
+/// }
+/// ```
+pub fn write_npz<W: Write>(writer: W, records: &[DatasetRecord]) -> io::Result<()> {
+    let screens: Vec<_> = records
+        .iter()
+        .filter_map(|r| match r.result {
+            ActionResult::Changed(ref s) => Some(s),
+            _ => None,
+        })
+        .collect();
+    let lines = screens.iter().map(|s| s.lines()).max().unwrap_or(0);
+    let columns = screens
+        .iter()
+        .flat_map(|s| s.iter().map(|l| l.len()))
+        .max()
+        .unwrap_or(0);
+    let mut frames = Vec::with_capacity(records.len() * lines * columns);
+    let mut current = None;
+    for rec in records {
+        if let ActionResult::Changed(ref s) = rec.result {
+            current = Some(s);
+        }
+        for y in 0..lines {
+            let line = current.and_then(|s: &Screen| (**s).get(y)).map_or(&[][..], |l| &l[..]);
+            frames.extend_from_slice(line);
+            frames.extend((line.len()..columns).map(|_| b' '));
+        }
+    }
+    let action_len = records
+        .iter()
+        .filter_map(|r| r.action.as_ref().map(|a| a.len()))
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let mut actions = Vec::with_capacity(records.len() * action_len);
+    for rec in records {
+        let action = rec.action.as_ref().map_or(&[][..], |a| &a[..]);
+        actions.extend_from_slice(action);
+        actions.extend((action.len()..action_len).map(|_| 0));
+    }
+    let bools = |f: &dyn Fn(&DatasetRecord) -> bool| -> Vec<u8> {
+        records.iter().map(|r| f(r) as u8).collect()
+    };
+    let floats = |f: &dyn Fn(&DatasetRecord) -> f64| {
+        let mut buf = Vec::with_capacity(records.len() * 8);
+        for r in records {
+            put_le(&mut buf, f(r).to_bits(), 8);
+        }
+        buf
+    };
+    let rewards = floats(&|r| r.reward.unwrap_or(f64::NAN));
+    let times = floats(&|r| {
+        r.time.as_secs() as f64 + f64::from(r.time.subsec_nanos()) / 1_000_000_000.0
+    });
+    let mut turns = Vec::with_capacity(records.len() * 8);
+    for r in records {
+        put_le(&mut turns, r.turn as u64, 8);
+    }
+    let n = records.len();
+    let arrays = vec![
+        ("frames", npy("|u1", &[n, lines, columns], &frames)),
+        ("changed", npy("|b1", &[n], &bools(&|r| r.result.name() == "Changed"))),
+        ("ended", npy("|b1", &[n], &bools(&|r| r.result.name() == "GameEnded"))),
+        ("actions", npy(&format!("|S{}", action_len), &[n], &actions)),
+        ("has_action", npy("|b1", &[n], &bools(&|r| r.action.is_some()))),
+        ("rewards", npy("<f8", &[n], &rewards)),
+        ("times", npy("<f8", &[n], &times)),
+        ("turns", npy("<i8", &[n], &turns)),
+    ];
+    write_zip(writer, &arrays)
+}
+
+// array in .npy format(version 1.0)
+fn npy(descr: &str, shape: &[usize], data: &[u8]) -> Vec<u8> {
+    let shape = match shape.len() {
+        1 => format!("{},", shape[0]),
+        _ => shape
+            .iter()
+            .map(|d| d.to_string())
+            .collect::<Vec<_>>()
+            .join(", "),
+    };
+    let mut header = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': ({}), }}",
+        descr, shape
+    );
+    // magic, version and header length take 10 bytes, and data is aligned to 64 bytes
+    while (10 + header.len() + 1) % 64 != 0 {
+        header.push(' ');
+    }
+    header.push('\n');
+    let mut res = b"\x93NUMPY\x01\x00".to_vec();
+    put_le(&mut res, header.len() as u64, 2);
+    res.extend_from_slice(header.as_bytes());
+    res.extend_from_slice(data);
+    res
+}
+
+// zip archive with uncompressed entries named NAME.npy
+fn write_zip<W: Write>(mut writer: W, arrays: &[(&str, Vec<u8>)]) -> io::Result<()> {
+    const VERSION: u64 = 20;
+    // 1980-01-01 00:00 in MS-DOS format
+    const DATE: u64 = 0x21;
+    let too_large = || io::Error::new(io::ErrorKind::InvalidInput, "too large array for npz");
+    let mut central = Vec::new();
+    let mut offset = 0;
+    for &(name, ref data) in arrays {
+        let name = format!("{}.npy", name);
+        if data.len() > u32::max_value() as usize || offset > u64::from(u32::max_value()) {
+            return Err(too_large());
+        }
+        let crc = u64::from(crc32(data));
+        let mut local = Vec::new();
+        put_le(&mut local, 0x0403_4b50, 4);
+        // version, flags, method(stored), time and date
+        for &(u, len) in &[(VERSION, 2), (0, 2), (0, 2), (0, 2), (DATE, 2)] {
+            put_le(&mut local, u, len);
+        }
+        // crc, sizes, name length and extra field length
+        for &(u, len) in &[(crc, 4), (data.len() as u64, 4), (data.len() as u64, 4)] {
+            put_le(&mut local, u, len);
+        }
+        put_le(&mut local, name.len() as u64, 2);
+        put_le(&mut local, 0, 2);
+        local.extend_from_slice(name.as_bytes());
+        put_le(&mut central, 0x0201_4b50, 4);
+        central.extend_from_slice(&[VERSION as u8, 0]);
+        // the rest is same as the local header until name length
+        central.extend_from_slice(&local[4..28]);
+        // extra field, comment, disk number, attributes and offset of the local header
+        for &(u, len) in &[(0, 2), (0, 2), (0, 2), (0, 2), (0, 4), (offset, 4)] {
+            put_le(&mut central, u, len);
+        }
+        central.extend_from_slice(name.as_bytes());
+        writer.write_all(&local)?;
+        writer.write_all(data)?;
+        offset += (local.len() + data.len()) as u64;
+    }
+    if offset > u64::from(u32::max_value()) {
+        return Err(too_large());
+    }
+    let mut end = Vec::new();
+    put_le(&mut end, 0x0605_4b50, 4);
+    // disk numbers, number of entries, size and offset of the central directory,
+    // and comment length
+    let n = arrays.len() as u64;
+    for &(u, len) in &[(0, 2), (0, 2), (n, 2), (n, 2), (central.len() as u64, 4)] {
+        put_le(&mut end, u, len);
+    }
+    put_le(&mut end, offset, 4);
+    put_le(&mut end, 0, 2);
+    writer.write_all(&central)?;
+    writer.write_all(&end)
+}
+
+// CRC-32 of zip(reversed polynomial 0xedb88320)
+fn crc32(data: &[u8]) -> u32 {
+    let crc = data.iter().fold(!0u32, |crc, &b| {
+        (0..8).fold(crc ^ u32::from(b), |c, _| {
+            if c & 1 == 1 {
+                (c >> 1) ^ 0xedb8_8320
+            } else {
+                c >> 1
+            }
+        })
+    });
+    !crc
+}
+
+fn put_le(buf: &mut Vec<u8>, u: u64, len: usize) {
+    buf.extend((0..len).map(|i| (u >> (i * 8)) as u8));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+    fn record(turn: usize, result: ActionResult, action: Option<&[u8]>) -> DatasetRecord {
+        DatasetRecord {
+            time: Duration::from_millis(turn as u64 * 500),
+            turn: turn,
+            result: result,
+            action: action.map(|a| a.to_vec()),
+            reward: if turn == 2 { Some(1.5) } else { None },
+        }
+    }
+    // data of NAME.npy in the archive
+    fn entry<'a>(zip: &'a [u8], name: &str) -> &'a [u8] {
+        let name = format!("{}.npy", name);
+        let pos = (0..zip.len())
+            .position(|i| {
+                zip[i..].starts_with(b"PK\x03\x04") && zip[i + 30..].starts_with(name.as_bytes())
+            })
+            .unwrap();
+        let le = |i: usize, len: usize| {
+            (0..len).fold(0, |acc, j| acc | (usize::from(zip[pos + i + j]) << (j * 8)))
+        };
+        let size = le(18, 4);
+        let start = pos + 30 + le(26, 2);
+        let data = &zip[start..start + size];
+        assert_eq!(u64::from(crc32(data)), le(14, 4) as u64);
+        data
+    }
+    // header and data of .npy
+    fn parse(npy: &[u8]) -> (&str, &[u8]) {
+        assert!(npy.starts_with(b"\x93NUMPY\x01\x00"));
+        let len = usize::from(npy[8]) | usize::from(npy[9]) << 8;
+        assert_eq!((10 + len) % 64, 0);
+        let header = ::std::str::from_utf8(&npy[10..10 + len]).unwrap();
+        (header.trim_end(), &npy[10 + len..])
+    }
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+    #[test]
+    fn test_npz() {
+        let screen = |lines: &[&[u8]]| Screen::new(lines.iter().map(|l| l.to_vec()).collect());
+        let records = vec![
+            record(1, ActionResult::NotChanged, None),
+            record(2, ActionResult::Changed(screen(&[b"ab", b"c"])), Some(b"jj")),
+            record(3, ActionResult::NotChanged, Some(b"")),
+            record(4, ActionResult::Changed(screen(&[b"xyz"])), Some(b"\x1b")),
+            record(5, ActionResult::GameEnded, None),
+        ];
+        let mut zip = Vec::new();
+        write_npz(&mut zip, &records).unwrap();
+        // end of central directory with 8 entries
+        let end = &zip[zip.len() - 22..];
+        assert!(end.starts_with(b"PK\x05\x06"));
+        assert_eq!(end[8..12], [8, 0, 8, 0]);
+        let (header, frames) = parse(entry(&zip, "frames"));
+        assert_eq!(
+            header,
+            "{'descr': '|u1', 'fortran_order': False, 'shape': (5, 2, 3), }"
+        );
+        assert_eq!(
+            frames,
+            &b"      ab c  ab c  xyz   xyz   "[..]
+        );
+        let (header, actions) = parse(entry(&zip, "actions"));
+        assert_eq!(
+            header,
+            "{'descr': '|S2', 'fortran_order': False, 'shape': (5,), }"
+        );
+        assert_eq!(actions, b"\0\0jj\0\0\x1b\0\0\0");
+        assert_eq!(parse(entry(&zip, "has_action")).1, [0, 1, 1, 1, 0]);
+        assert_eq!(parse(entry(&zip, "changed")).1, [0, 1, 0, 1, 0]);
+        assert_eq!(parse(entry(&zip, "ended")).1, [0, 0, 0, 0, 1]);
+        let floats = |name: &str| -> Vec<f64> {
+            parse(entry(&zip, name))
+                .1
+                .chunks(8)
+                .map(|c| c.iter().rev().fold(0, |acc, &b| acc << 8 | u64::from(b)))
+                .map(f64::from_bits)
+                .collect()
+        };
+        let rewards = floats("rewards");
+        assert_eq!(rewards[1], 1.5);
+        assert!(rewards[0].is_nan() && rewards[4].is_nan());
+        assert_eq!(floats("times"), vec![0.5, 1.0, 1.5, 2.0, 2.5]);
+        assert_eq!(parse(entry(&zip, "turns")).1[8..16], [2, 0, 0, 0, 0, 0, 0, 0]);
+    }
+}
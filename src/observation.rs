@@ -0,0 +1,101 @@
+use metrics::{IdleStreak, TurnStats};
+use screen::Screen;
+use term_data::{TermMode, TerminalEvent, UnhandledSeq};
+use super::{ActionResult, Reactor};
+
+/// Converts screens into the observation your AI works on(e.g. feature tensors
+/// or parsed game state), used by ```GameEnv::play_mapped```.
+///
+/// Closures ```FnMut(&Screen) -> O``` implement it.
+pub trait ObservationMapper {
+    type Observation;
+    fn map(&mut self, screen: &Screen) -> Self::Observation;
+}
+
+impl<O, F: FnMut(&Screen) -> O> ObservationMapper for F {
+    type Observation = O;
+    fn map(&mut self, screen: &Screen) -> O {
+        self(screen)
+    }
+}
+
+// Reactor for screens, passing observations to the inner AI
+pub struct MappedReactor<'a, M: 'a, R: 'a> {
+    mapper: &'a mut M,
+    ai: &'a mut R,
+}
+
+impl<'a, M, R> MappedReactor<'a, M, R>
+where
+    M: ObservationMapper,
+    R: Reactor<M::Observation>,
+{
+    pub fn new(mapper: &'a mut M, ai: &'a mut R) -> Self {
+        MappedReactor {
+            mapper: mapper,
+            ai: ai,
+        }
+    }
+}
+
+impl<'a, M, R> Reactor for MappedReactor<'a, M, R>
+where
+    M: ObservationMapper,
+    R: Reactor<M::Observation>,
+{
+    fn action(&mut self, action_result: ActionResult, turn: usize) -> Option<Vec<u8>> {
+        let mapper = &mut self.mapper;
+        let res = action_result.map(|screen| mapper.map(&screen));
+        self.ai.action(res, turn)
+    }
+    fn unhandled(&mut self, seq: &UnhandledSeq) {
+        self.ai.unhandled(seq)
+    }
+    fn on_turn_stats(&mut self, stats: TurnStats) {
+        self.ai.on_turn_stats(stats)
+    }
+    fn on_reward(&mut self, reward: f64) {
+        self.ai.on_reward(reward)
+    }
+    fn on_idle(&mut self, idle: IdleStreak) {
+        self.ai.on_idle(idle)
+    }
+    fn on_term_mode(&mut self, mode: TermMode) {
+        self.ai.on_term_mode(mode)
+    }
+    fn on_scrollback(&mut self, lines: &[Vec<u8>]) {
+        self.ai.on_scrollback(lines)
+    }
+    fn on_terminal_event(&mut self, event: &TerminalEvent) {
+        self.ai.on_terminal_event(event)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    struct CountAI {
+        counts: Vec<Option<usize>>,
+    }
+    impl Reactor<usize> for CountAI {
+        fn action(&mut self, res: ActionResult<usize>, _turn: usize) -> Option<Vec<u8>> {
+            self.counts.push(match res {
+                ActionResult::Changed(n) => Some(n),
+                _ => None,
+            });
+            None
+        }
+    }
+    #[test]
+    fn test_mapped_reactor() {
+        let mut count_a = |s: &Screen| s.iter_cells().filter(|&(_, _, c)| c == b'a').count();
+        let mut ai = CountAI { counts: Vec::new() };
+        {
+            let mut mapped = MappedReactor::new(&mut count_a, &mut ai);
+            let screen = Screen::new(vec![b"aba".to_vec(), b"cca".to_vec()]);
+            mapped.action(ActionResult::Changed(screen), 1);
+            mapped.action(ActionResult::NotChanged, 2);
+        }
+        assert_eq!(ai.counts, vec![Some(3), None]);
+    }
+}
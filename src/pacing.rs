@@ -0,0 +1,135 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How fast games are played. See ```GameSetting::pacing```.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Pacing {
+    /// Wait for the game output by ```timeout``` and ```settle```, and draw the
+    /// game with the delay given to ```draw_on``` or ```debug_console```
+    Normal,
+    /// Play as fast as possible, for training.
+    ///
+    /// Drawing and the backoff of ```restart_on_crash``` are disabled.
+    /// If ```adaptive``` is true, waits for the game output shrink to a few times
+    /// the longest delay of the game observed recently, up to ```timeout``` and
+    /// ```settle```. Then a game slower than usual can be seen as
+    /// ```NotChanged``` or a half-drawn screen, until the wait catches up.
+    FastForward { adaptive: bool },
+    /// Play at human speed, for demos and watching games.
+    ///
+    /// Turns are delivered at most ```fps``` times per second(0 for no limit),
+    /// and keys of each action are sent one by one with ```key_delay```
+    /// between them. Escape sequences(e.g. arrow keys) are sent at once.
+    RealTime { fps: u32, key_delay: Duration },
+}
+
+impl Default for Pacing {
+    fn default() -> Pacing {
+        Pacing::Normal
+    }
+}
+
+impl Pacing {
+    pub fn is_fast_forward(&self) -> bool {
+        match *self {
+            Pacing::FastForward { .. } => true,
+            _ => false,
+        }
+    }
+}
+
+// Sleeps to deliver turns at the frame rate of Pacing::RealTime
+#[derive(Clone, Debug)]
+pub(crate) struct FrameLimiter {
+    interval: Duration,
+    next: Option<Instant>,
+}
+
+impl FrameLimiter {
+    pub fn new(fps: u32) -> FrameLimiter {
+        let interval = if fps == 0 {
+            Duration::new(0, 0)
+        } else {
+            Duration::new(1, 0) / fps
+        };
+        FrameLimiter {
+            interval: interval,
+            next: None,
+        }
+    }
+    pub fn wait(&mut self) {
+        let now = Instant::now();
+        let start = match self.next {
+            Some(next) if next > now => {
+                thread::sleep(next - now);
+                next
+            }
+            _ => now,
+        };
+        self.next = Some(start + self.interval);
+    }
+}
+
+// waits are this times the estimated delay of the game
+const WAIT_FACTOR: u32 = 4;
+const MIN_WAIT_MS: u64 = 1;
+
+// Estimates how long the game can keep silent while it's still working
+#[derive(Clone, Debug, Default)]
+pub(crate) struct AdaptiveWait {
+    delay: Option<Duration>,
+}
+
+impl AdaptiveWait {
+    // the game wrote something after keeping silent for delay
+    pub fn observe(&mut self, delay: Duration) {
+        // decay the estimate, so that a few slow responses don't slow down the rest
+        self.delay = Some(match self.delay {
+            Some(d) => delay.max(d - d / 8),
+            None => delay,
+        });
+    }
+    pub fn wait(&self, limit: Duration) -> Duration {
+        match self.delay {
+            Some(d) => (d * WAIT_FACTOR)
+                .max(Duration::from_millis(MIN_WAIT_MS))
+                .min(limit),
+            None => limit,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_frame_limiter() {
+        let mut limiter = FrameLimiter::new(50);
+        let start = Instant::now();
+        for _ in 0..4 {
+            limiter.wait();
+        }
+        assert!(start.elapsed() >= Duration::from_millis(60));
+        let mut limiter = FrameLimiter::new(0);
+        let start = Instant::now();
+        for _ in 0..4 {
+            limiter.wait();
+        }
+        assert!(start.elapsed() < Duration::from_millis(20));
+    }
+    #[test]
+    fn test_adaptive_wait() {
+        let ms = Duration::from_millis;
+        let mut w = AdaptiveWait::default();
+        assert_eq!(w.wait(ms(100)), ms(100));
+        w.observe(ms(5));
+        assert_eq!(w.wait(ms(100)), ms(20));
+        assert_eq!(w.wait(ms(10)), ms(10));
+        w.observe(Duration::from_micros(10));
+        assert!(w.wait(ms(100)) < ms(20));
+        w.observe(ms(40));
+        assert_eq!(w.wait(ms(100)), ms(100));
+        w.observe(Duration::new(0, 0));
+        assert_eq!(w.wait(ms(200)), ms(140));
+    }
+}
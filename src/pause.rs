@@ -0,0 +1,59 @@
+use libc;
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// Pauses and resumes the game from any thread, made by ```GameEnv::pause_handle```.
+///
+/// The game process group is stopped by ```SIGSTOP``` and continued by ```SIGCONT```,
+/// and ```GameEnv``` doesn't call the AI while it's paused.
+/// The state is kept across games, so the next game also starts paused.
+#[derive(Clone, Debug, Default)]
+pub struct PauseHandle {
+    // pid of the current game process, or 0
+    pid: Arc<AtomicUsize>,
+    paused: Arc<AtomicBool>,
+}
+
+impl PauseHandle {
+    /// Stop the game.
+    pub fn pause(&self) -> io::Result<()> {
+        self.paused.store(true, Ordering::SeqCst);
+        self.signal(libc::SIGSTOP)
+    }
+    /// Continue the game.
+    pub fn resume(&self) -> io::Result<()> {
+        self.paused.store(false, Ordering::SeqCst);
+        self.signal(libc::SIGCONT)
+    }
+    /// Returns true if the game is paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+    // called when new game process is spawned
+    pub(crate) fn attach(&self, pid: u32) -> io::Result<()> {
+        self.pid.store(pid as usize, Ordering::SeqCst);
+        if self.is_paused() {
+            self.signal(libc::SIGSTOP)
+        } else {
+            Ok(())
+        }
+    }
+    // called when the game process is dropped
+    pub(crate) fn detach(&self, pid: u32) {
+        self.pid
+            .compare_exchange(pid as usize, 0, Ordering::SeqCst, Ordering::SeqCst)
+            .ok();
+    }
+    fn signal(&self, sig: libc::c_int) -> io::Result<()> {
+        let pid = self.pid.load(Ordering::SeqCst);
+        if pid == 0 {
+            return Ok(());
+        }
+        // the game is the leader of its own process group
+        if unsafe { libc::kill(-(pid as libc::pid_t), sig) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,320 @@
+use emulator::Emulator;
+use keylog::{read_varint, write_varint};
+use rle::{decode_line, encode_line};
+use screen::Screen;
+use transcript::{IoKind, TranscriptRecord};
+use std::io::{self, BufRead, Read, Write};
+use std::thread;
+use std::time::Duration;
+
+const FRAME_HEADER: &[u8] = b"=== frame ";
+const COMPRESSED_MAGIC: &[u8; 5] = b"CGWG\x01";
+
+/// Replay transcript through the emulator and returns the screen after each read.
+pub fn replay_screens(
+    records: &[TranscriptRecord],
+    lines: usize,
+    columns: usize,
+) -> Vec<Screen> {
+    let mut emulator = Emulator::new(lines, columns);
+    let mut res = Vec::new();
+    for rec in records.iter().filter(|r| r.kind == IoKind::Read) {
+        emulator.feed(&rec.bytes);
+        res.push(emulator.screen());
+    }
+    res
+}
+
+/// Plays back outputs of the game in a transcript with the recorded timing,
+/// emulating the screen after each output(frame).
+///
+/// The speed can be changed, long idle gaps can be skipped and frames can be
+/// stepped one by one, since recordings of slow AIs are tedious to watch in real time.
+/// # Example
+/// ```no_run
+/// extern crate curses_game_wrapper as cgw;
+/// use cgw::{read_transcript, Replay};
+/// use std::fs::File;
+/// use std::time::Duration;
+/// fn main() {
+///     let records = read_transcript(File::open("rogue.transcript").unwrap()).unwrap();
+///     let mut replay = Replay::new(records, 24, 80);
+///     replay.set_speed(4.0);
+///     replay.set_skip_idle(Some(Duration::from_secs(2)));
+///     while let Some(screen) = replay.next_frame() {
+///         println!("{}\n", screen);
+///     }
+/// }
+/// ```
+pub struct Replay {
+    frames: Vec<TranscriptRecord>,
+    emulator: Emulator,
+    lines: usize,
+    columns: usize,
+    // number of frames fed to the emulator
+    pos: usize,
+    speed: f64,
+    skip_idle: Option<Duration>,
+}
+
+impl Replay {
+    /// Make replay of game outputs in ```records```, with the screen of given size.
+    pub fn new(records: Vec<TranscriptRecord>, lines: usize, columns: usize) -> Replay {
+        Replay {
+            frames: records
+                .into_iter()
+                .filter(|r| r.kind == IoKind::Read)
+                .collect(),
+            emulator: Emulator::new(lines, columns),
+            lines: lines,
+            columns: columns,
+            pos: 0,
+            speed: 1.0,
+            skip_idle: None,
+        }
+    }
+    /// Number of frames
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+    /// Number of frames already played
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+    /// Multiply the speed of playback by ```speed```(Default: 1.0).
+    /// # Panics
+    /// Panics if ```speed``` is not positive.
+    pub fn set_speed(&mut self, speed: f64) {
+        assert!(speed > 0.0, "speed must be positive, but got {}", speed);
+        self.speed = speed;
+    }
+    /// Returns the current speed of playback.
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+    /// Show the next frame immediately when the game was idle longer than
+    /// ```gap``` before it(Default: ```None```).
+    pub fn set_skip_idle(&mut self, gap: Option<Duration>) {
+        self.skip_idle = gap;
+    }
+    /// Returns the current screen.
+    pub fn screen(&self) -> Screen {
+        self.emulator.screen()
+    }
+    /// Returns how long ```next_frame``` waits before the next frame.
+    pub fn delay(&self) -> Duration {
+        if self.pos == 0 || self.pos >= self.frames.len() {
+            return Duration::from_millis(0);
+        }
+        // timestamps can go backwards in ttyrec files, by adjusted clocks
+        let gap = self.frames[self.pos]
+            .time
+            .checked_sub(self.frames[self.pos - 1].time)
+            .unwrap_or_default();
+        if self.skip_idle.map_or(false, |max| gap > max) {
+            return Duration::from_millis(0);
+        }
+        let nanos = gap.as_secs() as f64 * 1e9 + f64::from(gap.subsec_nanos());
+        Duration::from_nanos((nanos / self.speed) as u64)
+    }
+    /// Wait for the timing of the next frame and returns its screen.
+    /// Returns ```None``` after the last frame.
+    pub fn next_frame(&mut self) -> Option<Screen> {
+        if self.pos >= self.frames.len() {
+            return None;
+        }
+        thread::sleep(self.delay());
+        self.step()
+    }
+    /// Go to the next frame without waiting, and returns its screen.
+    /// Returns ```None``` after the last frame.
+    pub fn step(&mut self) -> Option<Screen> {
+        let frame = self.frames.get(self.pos)?;
+        self.emulator.feed(&frame.bytes);
+        self.pos += 1;
+        Some(self.emulator.screen())
+    }
+    /// Go back to the previous frame, and returns its screen.
+    /// Returns ```None``` at the start.
+    pub fn step_back(&mut self) -> Option<Screen> {
+        if self.pos == 0 {
+            return None;
+        }
+        let pos = self.pos - 1;
+        Some(self.seek(pos))
+    }
+    /// Go to the screen after ```pos``` frames are played, and returns it.
+    pub fn seek(&mut self, pos: usize) -> Screen {
+        let pos = pos.min(self.frames.len());
+        if pos < self.pos {
+            self.emulator = Emulator::new(self.lines, self.columns);
+            self.pos = 0;
+        }
+        for frame in &self.frames[self.pos..pos] {
+            self.emulator.feed(&frame.bytes);
+        }
+        self.pos = pos;
+        self.emulator.screen()
+    }
+}
+
+/// Write screens as golden frames, which can be read by ```read_golden```.
+pub fn write_golden<W: Write>(mut writer: W, screens: &[Screen]) -> io::Result<()> {
+    for (i, screen) in screens.iter().enumerate() {
+        writer.write_all(FRAME_HEADER)?;
+        writeln!(writer, "{}", i)?;
+        for line in screen.iter() {
+            writer.write_all(line)?;
+            writer.write_all(b"\n")?;
+        }
+    }
+    Ok(())
+}
+
+// Compressed golden frames are written as
+// COMPRESSED_MAGIC | (number of lines(varint) | lines)...
+// where lines are run-length encoded as deltas from the last frame(see rle.rs).
+/// Same as ```write_golden```, but screens are run-length encoded in binary,
+/// as differences from the last screen.
+///
+/// It's much smaller for many frames, though you can't read the file by text editors.
+/// ```read_golden``` reads both formats.
+pub fn write_golden_compressed<W: Write>(mut writer: W, screens: &[Screen]) -> io::Result<()> {
+    let mut buf = COMPRESSED_MAGIC.to_vec();
+    let mut prev: &[Vec<u8>] = &[];
+    for screen in screens {
+        write_varint(&mut buf, screen.lines() as u64);
+        for (y, line) in screen.iter().enumerate() {
+            encode_line(&mut buf, line, prev.get(y).map(|l| &l[..]));
+        }
+        prev = screen;
+    }
+    writer.write_all(&buf)
+}
+
+/// Read golden frames written by ```write_golden``` or ```write_golden_compressed```.
+pub fn read_golden<R: BufRead>(mut reader: R) -> io::Result<Vec<Screen>> {
+    if reader.fill_buf()?.starts_with(COMPRESSED_MAGIC) {
+        reader.consume(COMPRESSED_MAGIC.len());
+        return read_golden_compressed(reader);
+    }
+    let mut res: Vec<Vec<Vec<u8>>> = Vec::new();
+    for line in reader.split(b'\n') {
+        let line = line?;
+        if line.starts_with(FRAME_HEADER) {
+            res.push(Vec::new());
+            continue;
+        }
+        match res.last_mut() {
+            Some(screen) => screen.push(line),
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "golden file doesn't start with frame header",
+                ))
+            }
+        }
+    }
+    Ok(res.into_iter().map(Screen::new).collect())
+}
+
+fn read_golden_compressed<R: Read>(reader: R) -> io::Result<Vec<Screen>> {
+    let mut bytes = reader.bytes().peekable();
+    let mut res: Vec<Vec<Vec<u8>>> = Vec::new();
+    while bytes.peek().is_some() {
+        let lines = read_varint(&mut bytes)?;
+        let mut screen = Vec::new();
+        for y in 0..lines as usize {
+            let prev = res.last().and_then(|p| p.get(y)).map(|l| &l[..]);
+            screen.push(decode_line(&mut bytes, prev)?);
+        }
+        res.push(screen);
+    }
+    Ok(res.into_iter().map(Screen::new).collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use transcript::read_ttyrec;
+    #[test]
+    fn test_replay() {
+        let records = (0..5)
+            .map(|i| TranscriptRecord {
+                time: Duration::from_secs(if i < 3 { i } else { i + 60 }),
+                kind: IoKind::Read,
+                bytes: format!("\x1b[{}H{}", i + 1, i).into_bytes(),
+            })
+            .collect();
+        let mut replay = Replay::new(records, 5, 2);
+        assert_eq!(replay.len(), 5);
+        assert_eq!(replay.delay(), Duration::from_secs(0));
+        assert_eq!(replay.step().unwrap().trimmed_line(0), "0");
+        assert_eq!(replay.delay(), Duration::from_secs(1));
+        replay.set_speed(4.0);
+        assert_eq!(replay.delay(), Duration::from_millis(250));
+        replay.step();
+        assert_eq!(replay.delay(), Duration::from_millis(250));
+        replay.step();
+        assert_eq!(replay.delay(), Duration::from_millis(15_250));
+        replay.set_skip_idle(Some(Duration::from_secs(10)));
+        assert_eq!(replay.delay(), Duration::from_secs(0));
+        assert_eq!(replay.next_frame().unwrap().trimmed_line(3), "3");
+        let screen = replay.step_back().unwrap();
+        assert_eq!(replay.pos(), 3);
+        assert_eq!(screen.trimmed_line(3), "");
+        assert_eq!(screen.trimmed_line(2), "2");
+        assert_eq!(replay.seek(100).trimmed_line(4), "4");
+        assert_eq!(replay.next_frame(), None);
+        assert_eq!(replay.seek(0), Replay::new(Vec::new(), 5, 2).screen());
+    }
+    #[test]
+    fn test_clock_skew() {
+        let le = |n: u32| (0..4).map(move |i| (n >> (i * 8)) as u8);
+        let mut ttyrec = Vec::new();
+        for &(sec, bytes) in &[(10, &b"a"[..]), (15, b"b"), (12, b"c"), (13, b"d")] {
+            ttyrec.extend(le(sec).chain(le(0)).chain(le(bytes.len() as u32)));
+            ttyrec.extend_from_slice(bytes);
+        }
+        let records = read_ttyrec(&ttyrec[..]).unwrap();
+        let mut replay = Replay::new(records, 1, 4);
+        replay.seek(2);
+        assert_eq!(replay.delay(), Duration::from_secs(0));
+        assert_eq!(replay.next_frame().unwrap().trimmed_line(0), "abc");
+        assert_eq!(replay.delay(), Duration::from_secs(1));
+    }
+    #[test]
+    fn test_golden_roundtrip() {
+        let records = vec![
+            TranscriptRecord {
+                time: Duration::from_millis(0),
+                kind: IoKind::Read,
+                bytes: b"\x1b[2Jab".to_vec(),
+            },
+            TranscriptRecord {
+                time: Duration::from_millis(1),
+                kind: IoKind::Write,
+                bytes: b"j".to_vec(),
+            },
+            TranscriptRecord {
+                time: Duration::from_millis(2),
+                kind: IoKind::Read,
+                bytes: b"\x1b[2;3Hc".to_vec(),
+            },
+        ];
+        let screens = replay_screens(&records, 3, 4);
+        assert_eq!(screens.len(), 2);
+        assert_eq!(screens[1][0], b"ab  ".to_vec());
+        assert_eq!(screens[1][1], b"  c ".to_vec());
+        let mut golden = Vec::new();
+        write_golden(&mut golden, &screens).unwrap();
+        assert_eq!(read_golden(&golden[..]).unwrap(), screens);
+        let mut compressed = Vec::new();
+        write_golden_compressed(&mut compressed, &screens).unwrap();
+        assert_eq!(read_golden(&compressed[..]).unwrap(), screens);
+        assert!(read_golden(&compressed[..compressed.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,230 @@
+use action_log::{json_quote, millis};
+use metrics::Metrics;
+#[cfg(feature = "sqlite")]
+use rusqlite::{self, Connection};
+use std::fmt::Display;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Format of the results file. See ```GameSetting::results_file```.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ResultFormat {
+    /// One JSON object per line
+    Jsonl,
+    /// Table ```episodes``` of SQLite database
+    #[cfg(feature = "sqlite")]
+    Sqlite,
+}
+
+// Summary of one game
+pub struct EpisodeRecord<'a> {
+    // seconds since UNIX epoch when the game ended
+    pub time: u64,
+    pub episode: usize,
+    pub config_hash: &'a str,
+    pub metrics: &'a Metrics,
+}
+
+impl<'a> EpisodeRecord<'a> {
+    pub fn new(episode: usize, config_hash: &'a str, metrics: &'a Metrics) -> Self {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        EpisodeRecord {
+            time: time,
+            episode: episode,
+            config_hash: config_hash,
+            metrics: metrics,
+        }
+    }
+}
+
+// Appends summaries of games to the results file
+pub enum ResultSink {
+    Jsonl(File),
+    #[cfg(feature = "sqlite")]
+    Sqlite(Connection),
+}
+
+#[cfg(feature = "sqlite")]
+const CREATE_TABLE: &str = "CREATE TABLE IF NOT EXISTS episodes (
+    time INTEGER, episode INTEGER, config_hash TEXT,
+    outcome TEXT, stop_reason TEXT, cause TEXT, score INTEGER, gold INTEGER, level INTEGER,
+    turns INTEGER, frames INTEGER, bytes_read INTEGER, bytes_written INTEGER, reward REAL,
+    restarts INTEGER, hung INTEGER, parse_ms REAL, action_ms REAL, wait_ms REAL)";
+
+// how long to wait for the database locked by other games
+#[cfg(feature = "sqlite")]
+const BUSY_TIMEOUT: ::std::time::Duration = ::std::time::Duration::from_secs(5);
+
+#[cfg(feature = "sqlite")]
+fn sql_error(e: rusqlite::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, e)
+}
+
+impl ResultSink {
+    pub fn open(path: &str, format: ResultFormat) -> io::Result<ResultSink> {
+        match format {
+            ResultFormat::Jsonl => {
+                let file = OpenOptions::new().append(true).create(true).open(path)?;
+                Ok(ResultSink::Jsonl(file))
+            }
+            #[cfg(feature = "sqlite")]
+            ResultFormat::Sqlite => {
+                let conn = Connection::open(path).map_err(sql_error)?;
+                conn.busy_timeout(BUSY_TIMEOUT).map_err(sql_error)?;
+                conn.execute(CREATE_TABLE, rusqlite::NO_PARAMS)
+                    .map_err(sql_error)?;
+                Ok(ResultSink::Sqlite(conn))
+            }
+        }
+    }
+    pub fn record(&mut self, rec: &EpisodeRecord) -> io::Result<()> {
+        match *self {
+            // write at once, so that parallel games can share the file
+            ResultSink::Jsonl(ref mut file) => file.write_all(to_json(rec).as_bytes()),
+            #[cfg(feature = "sqlite")]
+            ResultSink::Sqlite(ref conn) => insert(conn, rec).map_err(sql_error),
+        }
+    }
+}
+
+fn json_or_null<T: Display>(v: Option<T>) -> String {
+    v.map_or_else(|| "null".to_owned(), |v| v.to_string())
+}
+
+fn to_json(rec: &EpisodeRecord) -> String {
+    let m = rec.metrics;
+    let result = m.result.clone().unwrap_or_default();
+    let quote = |s: Option<String>| json_or_null(s.map(|s| json_quote(&s)));
+    format!(
+        "{{\"time\":{},\"episode\":{},\"config_hash\":{},\"outcome\":{},\
+         \"stop_reason\":{},\"cause\":{},\"score\":{},\"gold\":{},\"level\":{},\
+         \"turns\":{},\"frames\":{},\"bytes_read\":{},\"bytes_written\":{},\"reward\":{},\
+         \"restarts\":{},\"hung\":{},\"parse_ms\":{:.3},\"action_ms\":{:.3},\"wait_ms\":{:.3}}}\n",
+        rec.time,
+        rec.episode,
+        json_quote(rec.config_hash),
+        quote(m.outcome.map(|o| o.to_string())),
+        quote(m.stop_reason.clone()),
+        quote(result.cause),
+        json_or_null(result.score),
+        json_or_null(result.gold),
+        json_or_null(result.level),
+        m.turns,
+        m.frames,
+        m.bytes_read,
+        m.bytes_written,
+        // JSON has no NaN or infinity
+        json_or_null(Some(m.reward).filter(|r| r.is_finite())),
+        m.restarts,
+        m.hung,
+        millis(m.parse_time),
+        millis(m.action_time),
+        millis(m.wait_time)
+    )
+}
+
+#[cfg(feature = "sqlite")]
+fn insert(conn: &Connection, rec: &EpisodeRecord) -> rusqlite::Result<()> {
+    use rusqlite::types::ToSql;
+    let m = rec.metrics;
+    let result = m.result.clone().unwrap_or_default();
+    let int = |n: usize| n as i64;
+    let params: &[&dyn ToSql] = &[
+        &(rec.time as i64),
+        &int(rec.episode),
+        &rec.config_hash,
+        &m.outcome.map(|o| o.to_string()),
+        &m.stop_reason,
+        &result.cause,
+        &result.score.map(|n| n as i64),
+        &result.gold.map(|n| n as i64),
+        &result.level,
+        &int(m.turns),
+        &int(m.frames),
+        &int(m.bytes_read),
+        &int(m.bytes_written),
+        &m.reward,
+        &int(m.restarts),
+        &m.hung,
+        &millis(m.parse_time),
+        &millis(m.action_time),
+        &millis(m.wait_time),
+    ];
+    conn.execute(
+        "INSERT INTO episodes VALUES \
+         (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        params,
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+    use stop::{GameResult, Outcome};
+    fn metrics() -> Metrics {
+        Metrics {
+            turns: 10,
+            frames: 4,
+            reward: 1.5,
+            wait_time: Duration::from_millis(30),
+            stop_reason: Some("died".to_owned()),
+            outcome: Some(Outcome::Died),
+            result: Some(GameResult {
+                cause: Some("killed by a \"bat\"".to_owned()),
+                gold: Some(3),
+                level: None,
+                score: Some(3),
+            }),
+            ..Metrics::default()
+        }
+    }
+    #[test]
+    fn test_json() {
+        let m = metrics();
+        let rec = EpisodeRecord {
+            time: 100,
+            episode: 2,
+            config_hash: "0123",
+            metrics: &m,
+        };
+        assert_eq!(
+            to_json(&rec),
+            "{\"time\":100,\"episode\":2,\"config_hash\":\"0123\",\"outcome\":\"died\",\
+             \"stop_reason\":\"died\",\"cause\":\"killed by a \\\"bat\\\"\",\"score\":3,\
+             \"gold\":3,\"level\":null,\"turns\":10,\"frames\":4,\"bytes_read\":0,\
+             \"bytes_written\":0,\"reward\":1.5,\"restarts\":0,\"hung\":false,\
+             \"parse_ms\":0.000,\"action_ms\":0.000,\"wait_ms\":30.000}\n"
+        );
+        let m = Metrics {
+            reward: ::std::f64::NAN,
+            ..metrics()
+        };
+        let rec = EpisodeRecord { metrics: &m, ..rec };
+        assert!(to_json(&rec).contains("\"reward\":null,"));
+    }
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_sqlite() {
+        let path = ::std::env::temp_dir().join(format!("cgw-results-{}.db", ::std::process::id()));
+        let path = path.to_str().unwrap();
+        let m = metrics();
+        for episode in 0..2 {
+            let mut sink = ResultSink::open(path, ResultFormat::Sqlite).unwrap();
+            sink.record(&EpisodeRecord::new(episode, "0123", &m)).unwrap();
+        }
+        let conn = Connection::open(path).unwrap();
+        let (count, turns): (i64, i64) = conn.query_row(
+            "SELECT COUNT(*), SUM(turns) FROM episodes WHERE outcome = 'died'",
+            rusqlite::NO_PARAMS,
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).unwrap();
+        assert_eq!((count, turns), (2, 20));
+        ::std::fs::remove_file(path).unwrap();
+    }
+}
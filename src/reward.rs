@@ -0,0 +1,86 @@
+use screen::Screen;
+
+/// Extracts the reward of each frame delivered to the AI, from the previous
+/// frame(```None``` for the first frame of the game) and the current one.
+///
+/// Register it by ```GameSetting::reward```. Rewards are summed into
+/// ```Metrics::reward``` and passed to ```Reactor::on_reward```.
+/// Closures ```Fn(Option<&Screen>, &Screen) -> f64``` implement it.
+pub trait RewardFn: Send + Sync {
+    fn reward(&self, prev: Option<&Screen>, current: &Screen) -> f64;
+}
+
+impl<F: Fn(Option<&Screen>, &Screen) -> f64 + Send + Sync> RewardFn for F {
+    fn reward(&self, prev: Option<&Screen>, current: &Screen) -> f64 {
+        self(prev, current)
+    }
+}
+
+/// Rewards the increase of the number after ```label```, like ```Gold:``` in
+/// the status line of rogue or ```Exp:``` of NetHack.
+///
+/// The reward is 0 when the number isn't found in either frame.
+#[derive(Clone, Debug)]
+pub struct CounterDelta {
+    label: String,
+    scale: f64,
+}
+
+impl CounterDelta {
+    pub fn new(label: &str) -> CounterDelta {
+        CounterDelta {
+            label: label.to_owned(),
+            scale: 1.0,
+        }
+    }
+    /// Multiply the reward by ```scale```(Default: 1.0).
+    pub fn scale(mut self, scale: f64) -> CounterDelta {
+        self.scale = scale;
+        self
+    }
+    // the first number after the label
+    fn counter(&self, screen: &Screen) -> Option<i64> {
+        screen
+            .line_strs()
+            .filter_map(|line| {
+                let start = line.find(&self.label[..])? + self.label.len();
+                let rest = line[start..].trim_start();
+                let end = rest
+                    .char_indices()
+                    .find(|&(i, c)| !(c.is_ascii_digit() || (i == 0 && c == '-')))
+                    .map_or(rest.len(), |(i, _)| i);
+                rest[..end].parse().ok()
+            })
+            .next()
+    }
+}
+
+impl RewardFn for CounterDelta {
+    fn reward(&self, prev: Option<&Screen>, current: &Screen) -> f64 {
+        match (prev.and_then(|s| self.counter(s)), self.counter(current)) {
+            (Some(p), Some(c)) => (c - p) as f64 * self.scale,
+            _ => 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    fn screen(lines: &[&[u8]]) -> Screen {
+        Screen::new(lines.iter().map(|l| l.to_vec()).collect())
+    }
+    #[test]
+    fn test_counter_delta() {
+        let gold = CounterDelta::new("Gold:");
+        let s1 = screen(&[b"@  ", b"Level: 1  Gold: 12    Hp: 12(12)"]);
+        let s2 = screen(&[b"  @", b"Level: 1  Gold: 40    Hp: 10(12)"]);
+        assert_eq!(gold.reward(None, &s1), 0.0);
+        assert_eq!(gold.reward(Some(&s1), &s2), 28.0);
+        assert_eq!(gold.clone().scale(0.5).reward(Some(&s2), &s1), -14.0);
+        let menu = screen(&[b"a - a food ration", b"--More--"]);
+        assert_eq!(gold.reward(Some(&s1), &menu), 0.0);
+        let hp = |_: Option<&Screen>, s: &Screen| if s.trimmed_line(0) == "@" { 1.0 } else { 0.0 };
+        assert_eq!(hp.reward(None, &s1), 1.0);
+    }
+}
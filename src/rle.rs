@@ -0,0 +1,135 @@
+use keylog::{next_byte, read_varint, write_varint};
+use std::io;
+
+// longest runs in one chunk
+const MAX_LITERAL: usize = 128;
+const MAX_REPEAT: usize = 129;
+
+// Run-length codec for lines of stored screens, which are mostly blanks.
+// Each line is written as length of the line(varint) | chunks, where each chunk is
+// n(< 128) | n + 1 literal bytes
+// or n(>= 128) | one byte repeated n - 126 times.
+// If the line has the same length as ```prev```(the same line of the last screen),
+// it's XORed with ```prev``` first, so that unchanged cells become runs of 0.
+pub(crate) fn encode_line(buf: &mut Vec<u8>, line: &[u8], prev: Option<&[u8]>) {
+    write_varint(buf, line.len() as u64);
+    let delta: Vec<u8>;
+    let line = match prev {
+        Some(prev) if prev.len() == line.len() => {
+            delta = line.iter().zip(prev).map(|(a, b)| a ^ b).collect();
+            &delta[..]
+        }
+        _ => line,
+    };
+    let mut literal_start = 0;
+    let mut i = 0;
+    while i < line.len() {
+        let run = line[i..]
+            .iter()
+            .take(MAX_REPEAT)
+            .take_while(|&&b| b == line[i])
+            .count();
+        // runs shorter than 3 are cheaper as literals
+        if run < 3 {
+            i += run;
+            continue;
+        }
+        write_literals(buf, &line[literal_start..i]);
+        buf.push((run + 126) as u8);
+        buf.push(line[i]);
+        i += run;
+        literal_start = i;
+    }
+    write_literals(buf, &line[literal_start..]);
+}
+
+fn write_literals(buf: &mut Vec<u8>, bytes: &[u8]) {
+    for chunk in bytes.chunks(MAX_LITERAL) {
+        buf.push((chunk.len() - 1) as u8);
+        buf.extend_from_slice(chunk);
+    }
+}
+
+pub(crate) fn decode_line<I: Iterator<Item = io::Result<u8>>>(
+    bytes: &mut I,
+    prev: Option<&[u8]>,
+) -> io::Result<Vec<u8>> {
+    // not reserved in advance, as the length in broken files can be anything
+    let len = read_varint(bytes)?;
+    let mut res = Vec::new();
+    while (res.len() as u64) < len {
+        let n = usize::from(next_chunk_byte(bytes)?);
+        if n < MAX_LITERAL {
+            for _ in 0..n + 1 {
+                res.push(next_chunk_byte(bytes)?);
+            }
+        } else {
+            let b = next_chunk_byte(bytes)?;
+            res.extend((0..n - 126).map(|_| b));
+        }
+    }
+    if res.len() as u64 != len {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "too long run"));
+    }
+    if let Some(prev) = prev.filter(|p| p.len() == res.len()) {
+        for (a, b) in res.iter_mut().zip(prev) {
+            *a ^= *b;
+        }
+    }
+    Ok(res)
+}
+
+// running out of bytes in a line means its length is broken
+fn next_chunk_byte<I: Iterator<Item = io::Result<u8>>>(bytes: &mut I) -> io::Result<u8> {
+    next_byte(bytes).map_err(|e| match e.kind() {
+        io::ErrorKind::UnexpectedEof => {
+            io::Error::new(io::ErrorKind::InvalidData, "line longer than the rest of input")
+        }
+        _ => e,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    fn roundtrip(line: &[u8], prev: Option<&[u8]>) -> usize {
+        let mut buf = Vec::new();
+        encode_line(&mut buf, line, prev);
+        let len = buf.len();
+        let mut bytes = buf.into_iter().map(Ok);
+        assert_eq!(decode_line(&mut bytes, prev).unwrap(), line.to_vec());
+        assert!(bytes.next().is_none());
+        len
+    }
+    #[test]
+    fn test_rle() {
+        assert_eq!(roundtrip(b"", None), 1);
+        assert_eq!(roundtrip(&[b' '; 80], None), 3);
+        assert_eq!(roundtrip(&[b' '; 300], None), 8);
+        assert_eq!(roundtrip(b"ab", None), 4);
+        roundtrip(b"  |..@...|  Gold: 10   ", None);
+        roundtrip(&(0..=255).collect::<Vec<u8>>(), None);
+        let mut line = vec![b' '; 80];
+        line[10..20].copy_from_slice(b"|.......@|");
+        let mut moved = line.clone();
+        moved.swap(17, 18);
+        assert!(roundtrip(&moved, Some(&line)) < 10);
+        roundtrip(&moved, Some(b"short"));
+        let mut bytes = vec![3, 0x83, b'a'].into_iter().map(Ok);
+        assert!(decode_line(&mut bytes, None).is_err());
+        // lengths beyond the input aren't allocated
+        let mut huge = vec![0xff; 8];
+        huge.extend_from_slice(&[0x7f, 0x80, b' ']);
+        let err = decode_line(&mut huge.into_iter().map(Ok), None).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let mut truncated = vec![0xff, 0xff].into_iter().map(Ok);
+        assert!(decode_line(&mut truncated, None).is_err());
+    }
+    quickcheck! {
+        fn prop_roundtrip(line: Vec<u8>, prev: Vec<u8>) -> bool {
+            let mut buf = Vec::new();
+            encode_line(&mut buf, &line, Some(&prev));
+            decode_line(&mut buf.into_iter().map(Ok), Some(&prev)).ok() == Some(line)
+        }
+    }
+}
@@ -0,0 +1,505 @@
+use std::borrow::Cow;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::ops::{Deref, Index, IndexMut};
+
+/// DEC line attribute set by ```ESC # 3/4/5/6```.
+///
+/// On double-width lines each character occupies 2 columns on a real terminal,
+/// so only the left half of the line is visible.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum LineAttr {
+    /// Single-width and single-height(```ESC # 5```)
+    Normal,
+    /// Double-width and single-height(```ESC # 6```)
+    DoubleWidth,
+    /// Top half of double-height line(```ESC # 3```)
+    DoubleHeightTop,
+    /// Bottom half of double-height line(```ESC # 4```)
+    DoubleHeightBottom,
+}
+
+impl LineAttr {
+    /// Returns true if characters on the line are shown in double width.
+    pub fn is_double_width(&self) -> bool {
+        *self != LineAttr::Normal
+    }
+}
+
+impl Default for LineAttr {
+    fn default() -> LineAttr {
+        LineAttr::Normal
+    }
+}
+
+/// Virtual terminal buffer, which AI receives as ```ActionResult::Changed(Screen)```.
+///
+/// It derefs to ```[Vec<u8>]```, so you can access it like ```screen[y][x]```.
+/// It also keeps where the cursor was and whether it was visible, which are
+/// ignored when comparing screens.
+#[derive(Clone, Default)]
+pub struct Screen {
+    buf: Vec<Vec<u8>>,
+    attrs: Vec<LineAttr>,
+    reverse: Vec<Vec<bool>>,
+    cursor: (usize, usize),
+    cursor_visible: bool,
+}
+
+impl Screen {
+    /// Make screen from lines.
+    pub fn new(buf: Vec<Vec<u8>>) -> Screen {
+        let attrs = vec![LineAttr::Normal; buf.len()];
+        let reverse = buf.iter().map(|l| vec![false; l.len()]).collect();
+        Screen {
+            buf: buf,
+            attrs: attrs,
+            reverse: reverse,
+            cursor: (0, 0),
+            cursor_visible: true,
+        }
+    }
+    pub(crate) fn with_line_attrs(mut self, attrs: Vec<LineAttr>) -> Screen {
+        assert_eq!(attrs.len(), self.buf.len());
+        self.attrs = attrs;
+        self
+    }
+    pub(crate) fn with_reverse(mut self, reverse: Vec<Vec<bool>>) -> Screen {
+        assert_eq!(reverse.len(), self.buf.len());
+        self.reverse = reverse;
+        self
+    }
+    pub(crate) fn with_cursor(mut self, cursor: (usize, usize), visible: bool) -> Screen {
+        self.cursor = cursor;
+        self.cursor_visible = visible;
+        self
+    }
+    /// Height of the screen
+    pub fn lines(&self) -> usize {
+        self.buf.len()
+    }
+    /// Width of the screen
+    pub fn columns(&self) -> usize {
+        self.buf.first().map_or(0, |l| l.len())
+    }
+    /// Returns i-th line as string.
+    /// Invalid UTF-8 sequences are replaced with ```U+FFFD```.
+    /// # Panics
+    /// Panics if ```i >= self.lines()```
+    pub fn line(&self, i: usize) -> Cow<str> {
+        String::from_utf8_lossy(&self.buf[i])
+    }
+    /// Returns iterator of all lines as string.
+    pub fn line_strs<'a>(&'a self) -> impl Iterator<Item = Cow<'a, str>> {
+        self.buf.iter().map(|l| String::from_utf8_lossy(l))
+    }
+    /// Returns i-th line as string, without trailing blanks(spaces and NULs).
+    /// # Panics
+    /// Panics if ```i >= self.lines()```
+    pub fn trimmed_line(&self, i: usize) -> Cow<str> {
+        String::from_utf8_lossy(trim_blank(&self.buf[i]))
+    }
+    /// Returns iterator of all lines without trailing blanks.
+    pub fn trimmed_lines<'a>(&'a self) -> impl Iterator<Item = Cow<'a, str>> {
+        self.buf
+            .iter()
+            .map(|l| String::from_utf8_lossy(trim_blank(l)))
+    }
+    /// Returns whole screen as string, with trailing blanks of each line
+    /// and trailing empty lines removed.
+    pub fn to_trimmed_string(&self) -> String {
+        let lines: Vec<_> = self.trimmed_lines().collect();
+        let len = lines
+            .iter()
+            .rposition(|l| !l.is_empty())
+            .map_or(0, |i| i + 1);
+        lines[..len].join("\n")
+    }
+    /// Returns whole screen as ANSI text, which shows reverse video cells and
+    /// line attributes as the game displayed when printed to a terminal.
+    ///
+    /// Colors are not tracked by the emulator, so they aren't reproduced.
+    /// # Example
+    /// ```no_run
+    /// # extern crate curses_game_wrapper as cgw;
+    /// # use cgw::Emulator;
+    /// # fn main() {
+    /// let mut emu = Emulator::new(24, 80);
+    /// emu.feed(b"\x1b[7mInventory\x1b[m");
+    /// println!("{}", emu.screen().to_ansi_string());
+    /// # }
+    /// ```
+    pub fn to_ansi_string(&self) -> String {
+        let mut res = String::new();
+        for (y, line) in self.buf.iter().enumerate() {
+            if y > 0 {
+                res.push('\n');
+            }
+            match self.attrs[y] {
+                LineAttr::Normal => {}
+                LineAttr::DoubleWidth => res.push_str("\x1b#6"),
+                LineAttr::DoubleHeightTop => res.push_str("\x1b#3"),
+                LineAttr::DoubleHeightBottom => res.push_str("\x1b#4"),
+            }
+            let mut reverse = false;
+            for (x, &c) in line.iter().enumerate() {
+                let r = self.is_reverse(y, x);
+                if r != reverse {
+                    res.push_str(if r { "\x1b[7m" } else { "\x1b[27m" });
+                    reverse = r;
+                }
+                // control characters would break the output
+                res.push(if c < 0x20 || c == 0x7f { ' ' } else { char::from(c) });
+            }
+            if reverse {
+                res.push_str("\x1b[27m");
+            }
+        }
+        res
+    }
+    /// Returns whole screen with row and column rulers, for debugging.
+    ///
+    /// If ```cursor``` is given as ```(y, x)```, its row and column are marked
+    /// by ```<``` and ```^``` outside of the frame.
+    /// # Example
+    /// ```
+    /// # extern crate curses_game_wrapper as cgw;
+    /// # use cgw::Screen;
+    /// # fn main() {
+    /// let screen = Screen::new(vec![b"ab".to_vec(), b"c ".to_vec()]);
+    /// let expected = "   01
+    ///   +--+
+    /// 0 |ab|
+    /// 1 |c |<
+    ///   +--+
+    ///     ^";
+    /// assert_eq!(screen.to_ruled_string(Some((1, 1))), expected);
+    /// # }
+    /// ```
+    pub fn to_ruled_string(&self, cursor: Option<(usize, usize)>) -> String {
+        let columns = self.columns();
+        let width = format!("{}", self.lines().saturating_sub(1)).len();
+        let indent = " ".repeat(width + 2);
+        let mut res = String::new();
+        if columns > 10 {
+            res.push_str(&indent);
+            for x in 0..columns {
+                res.push(if x % 10 == 0 {
+                    char::from(b'0' + (x / 10 % 10) as u8)
+                } else {
+                    ' '
+                });
+            }
+            res.push('\n');
+        }
+        res.push_str(&indent);
+        for x in 0..columns {
+            res.push(char::from(b'0' + (x % 10) as u8));
+        }
+        let border = format!("\n{}+{}+", " ".repeat(width + 1), "-".repeat(columns));
+        res.push_str(&border);
+        for (y, line) in self.buf.iter().enumerate() {
+            res.push_str(&format!("\n{:>w$} |", y, w = width));
+            for &c in line {
+                res.push(if c < 0x20 || c == 0x7f { ' ' } else { char::from(c) });
+            }
+            res.push('|');
+            if cursor.map_or(false, |(cy, _)| cy == y) {
+                res.push('<');
+            }
+        }
+        res.push_str(&border);
+        if let Some((_, cx)) = cursor {
+            res.push('\n');
+            res.push_str(&" ".repeat(width + 2 + cx));
+            res.push('^');
+        }
+        res
+    }
+    /// Returns iterator of all cells as ```(y, x, byte)```, in row-major order.
+    /// # Example
+    /// ```
+    /// # extern crate curses_game_wrapper as cgw;
+    /// # use cgw::Screen;
+    /// # fn main() {
+    /// let screen = Screen::new(vec![b"..@".to_vec(), b"D..".to_vec()]);
+    /// let player = screen.iter_cells().find(|&(_, _, c)| c == b'@');
+    /// assert_eq!(player, Some((0, 2, b'@')));
+    /// # }
+    /// ```
+    pub fn iter_cells<'a>(&'a self) -> impl Iterator<Item = (usize, usize, u8)> + 'a {
+        self.buf.iter().enumerate().flat_map(|(y, line)| {
+            line.iter().enumerate().map(move |(x, c)| (y, x, *c))
+        })
+    }
+    /// Same as ```iter_cells```, but returns cells as ```char```.
+    pub fn iter_chars<'a>(&'a self) -> impl Iterator<Item = (usize, usize, char)> + 'a {
+        self.iter_cells().map(|(y, x, c)| (y, x, char::from(c)))
+    }
+    /// Returns the byte at (y, x), or ```None``` if it's out of the screen.
+    pub fn get(&self, y: usize, x: usize) -> Option<u8> {
+        self.buf.get(y).and_then(|l| l.get(x)).cloned()
+    }
+    /// Returns the byte at (y + dy, x + dx), or ```None``` if it's out of the screen.
+    ///
+    /// Useful to look around the player without checking bounds by hand.
+    pub fn get_offset(&self, y: usize, x: usize, dy: isize, dx: isize) -> Option<u8> {
+        let ny = y as isize + dy;
+        let nx = x as isize + dx;
+        if ny < 0 || nx < 0 {
+            return None;
+        }
+        self.get(ny as usize, nx as usize)
+    }
+    /// Returns the byte at (y, x), or ```default``` if it's out of the screen.
+    pub fn get_or(&self, y: usize, x: usize, default: u8) -> u8 {
+        self.get(y, x).unwrap_or(default)
+    }
+    /// Returns the line attribute of line y.
+    /// # Panics
+    /// Panics if ```y >= self.lines()```
+    pub fn line_attr(&self, y: usize) -> LineAttr {
+        self.attrs[y]
+    }
+    /// Returns the number of visible columns of line y, which is halved
+    /// on double-width lines.
+    /// # Panics
+    /// Panics if ```y >= self.lines()```
+    pub fn line_columns(&self, y: usize) -> usize {
+        if self.attrs[y].is_double_width() {
+            self.columns() / 2
+        } else {
+            self.columns()
+        }
+    }
+    /// Returns true if the cell at (y, x) is shown in reverse video(```SGR 7```),
+    /// as curses does for selected items of menus.
+    ///
+    /// Returns false if it's out of the screen.
+    pub fn is_reverse(&self, y: usize, x: usize) -> bool {
+        self.reverse
+            .get(y)
+            .and_then(|l| l.get(x))
+            .cloned()
+            .unwrap_or(false)
+    }
+    /// Returns cursor position as ```(y, x)``` when the screen was taken.
+    ///
+    /// It's ```(0, 0)``` for screens made by ```Screen::new```.
+    pub fn cursor(&self) -> (usize, usize) {
+        self.cursor
+    }
+    /// Returns true if the cursor was visible(```DECTCEM```) when the screen was taken.
+    ///
+    /// Games usually hide the cursor while showing menus or prompts.
+    /// # Example
+    /// ```
+    /// # extern crate curses_game_wrapper as cgw;
+    /// # use cgw::Emulator;
+    /// # fn main() {
+    /// let mut emu = Emulator::new(3, 10);
+    /// emu.feed(b"\x1b[?25lWhich item?\x1b[2;3H");
+    /// let screen = emu.screen();
+    /// assert_eq!(screen.cursor(), (1, 2));
+    /// assert!(!screen.is_cursor_visible());
+    /// # }
+    /// ```
+    pub fn is_cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+    /// Returns iterator of lines which have any reverse video cell.
+    /// # Example
+    /// ```
+    /// # extern crate curses_game_wrapper as cgw;
+    /// # use cgw::Emulator;
+    /// # fn main() {
+    /// let mut emu = Emulator::new(3, 10);
+    /// emu.feed(b"a - apple\r\n\x1b[7mb - bread\x1b[m\r\nc - cake");
+    /// let selected: Vec<_> = emu.screen().highlighted_lines().collect();
+    /// assert_eq!(selected, vec![1]);
+    /// # }
+    /// ```
+    pub fn highlighted_lines<'a>(&'a self) -> impl Iterator<Item = usize> + 'a {
+        self.reverse
+            .iter()
+            .enumerate()
+            .filter(|&(_, l)| l.iter().any(|r| *r))
+            .map(|(y, _)| y)
+    }
+    /// Clears reverse video and line attributes.
+    pub fn clear_attributes(&mut self) {
+        for line in &mut self.reverse {
+            for r in line.iter_mut() {
+                *r = false;
+            }
+        }
+        for a in &mut self.attrs {
+            *a = LineAttr::Normal;
+        }
+    }
+    /// Consume self and returns inner buffer.
+    pub fn into_inner(self) -> Vec<Vec<u8>> {
+        self.buf
+    }
+}
+
+impl PartialEq for Screen {
+    fn eq(&self, other: &Screen) -> bool {
+        self.buf == other.buf && self.attrs == other.attrs && self.reverse == other.reverse
+    }
+}
+
+impl Eq for Screen {}
+
+impl Hash for Screen {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.buf.hash(state);
+        self.attrs.hash(state);
+        self.reverse.hash(state);
+    }
+}
+
+impl Deref for Screen {
+    type Target = [Vec<u8>];
+    fn deref(&self) -> &[Vec<u8>] {
+        &self.buf
+    }
+}
+
+fn trim_blank(line: &[u8]) -> &[u8] {
+    let len = line
+        .iter()
+        .rposition(|&c| c != b' ' && c != 0)
+        .map_or(0, |i| i + 1);
+    &line[..len]
+}
+
+/// ```screen[y]``` returns line y.
+impl Index<usize> for Screen {
+    type Output = Vec<u8>;
+    fn index(&self, y: usize) -> &Vec<u8> {
+        &self.buf[y]
+    }
+}
+
+impl IndexMut<usize> for Screen {
+    fn index_mut(&mut self, y: usize) -> &mut Vec<u8> {
+        &mut self.buf[y]
+    }
+}
+
+/// ```screen[(y, x)]``` returns the byte at line y and column x.
+impl Index<(usize, usize)> for Screen {
+    type Output = u8;
+    fn index(&self, (y, x): (usize, usize)) -> &u8 {
+        &self.buf[y][x]
+    }
+}
+
+impl IndexMut<(usize, usize)> for Screen {
+    fn index_mut(&mut self, (y, x): (usize, usize)) -> &mut u8 {
+        &mut self.buf[y][x]
+    }
+}
+
+impl From<Vec<Vec<u8>>> for Screen {
+    fn from(buf: Vec<Vec<u8>>) -> Screen {
+        Screen::new(buf)
+    }
+}
+
+impl Display for Screen {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        for (i, line) in self.line_strs().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+impl Debug for Screen {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "Screen")?;
+        write!(f, "{}", self.to_ruled_string(None))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_display() {
+        let screen = Screen::new(vec![b"ab ".to_vec(), b"c\xffd".to_vec()]);
+        assert_eq!(format!("{}", screen), "ab \nc\u{fffd}d");
+        assert_eq!(screen.line(1), "c\u{fffd}d");
+        assert_eq!(screen.lines(), 2);
+        assert_eq!(screen.columns(), 3);
+        assert_eq!(screen[0][1], b'b');
+    }
+    #[test]
+    fn test_index() {
+        let mut screen = Screen::new(vec![b"ab".to_vec(), b"cd".to_vec()]);
+        assert_eq!(screen[(1, 0)], b'c');
+        screen[(1, 0)] = b'@';
+        assert_eq!(screen[1][0], b'@');
+        assert_eq!(screen.get(0, 1), Some(b'b'));
+        assert_eq!(screen.get(2, 0), None);
+        assert_eq!(screen.get_or(0, 2, b' '), b' ');
+        assert_eq!(screen.get_offset(1, 0, -1, 1), Some(b'b'));
+        assert_eq!(screen.get_offset(1, 0, 0, -1), None);
+    }
+    #[test]
+    fn test_trimmed() {
+        let screen = Screen::new(vec![
+            b" ab  ".to_vec(),
+            b"c\0\0 \0".to_vec(),
+            b"     ".to_vec(),
+            b"\0\0\0\0\0".to_vec(),
+        ]);
+        assert_eq!(screen.trimmed_line(0), " ab");
+        let lines: Vec<_> = screen.trimmed_lines().collect();
+        assert_eq!(lines, vec![" ab", "c", "", ""]);
+        assert_eq!(screen.to_trimmed_string(), " ab\nc");
+    }
+    #[test]
+    fn test_ansi_string() {
+        let screen = Screen::new(vec![b"ab\0".to_vec(), b"cde".to_vec()])
+            .with_line_attrs(vec![LineAttr::Normal, LineAttr::DoubleWidth])
+            .with_reverse(vec![vec![false, true, false], vec![false, true, true]]);
+        assert_eq!(
+            screen.to_ansi_string(),
+            "a\x1b[7mb\x1b[27m \n\x1b#6c\x1b[7mde\x1b[27m"
+        );
+    }
+    #[test]
+    fn test_ruled_string() {
+        let line = b"x          y".to_vec();
+        let screen = Screen::new(vec![line; 11]);
+        let ruled = screen.to_ruled_string(Some((10, 11)));
+        let lines: Vec<_> = ruled.lines().collect();
+        assert_eq!(lines[0], "    0         1 ");
+        assert_eq!(lines[1], "    012345678901");
+        assert_eq!(lines[2], "   +------------+");
+        assert_eq!(lines[3], " 0 |x          y|");
+        assert_eq!(lines[13], "10 |x          y|<");
+        assert_eq!(lines[15], "               ^");
+        assert_eq!(lines.len(), 16);
+    }
+    #[test]
+    fn test_iter_cells() {
+        let screen = Screen::new(vec![b"ab".to_vec(), b"cd".to_vec()]);
+        let cells: Vec<_> = screen.iter_cells().collect();
+        assert_eq!(
+            cells,
+            vec![(0, 0, b'a'), (0, 1, b'b'), (1, 0, b'c'), (1, 1, b'd')]
+        );
+        let monsters: Vec<_> = screen
+            .iter_chars()
+            .filter(|&(_, _, c)| c == 'd')
+            .collect();
+        assert_eq!(monsters, vec![(1, 1, 'd')]);
+    }
+}
@@ -0,0 +1,158 @@
+use emulator::Emulator;
+use screen::Screen;
+use std::cmp;
+use std::io::{self, Read, Write};
+
+use game_env::{GameHandle, Handle};
+
+/// Wraps a session spawned by other crates(e.g. ```rexpect``` or ```expectrl```),
+/// and emulates all bytes read from it.
+///
+/// Reading and writing are passed through to the inner session, so you can keep
+/// using ```expect```-style matching on the output and see the screen at the same time.
+/// # Example
+/// ```ignore
+/// extern crate curses_game_wrapper as cgw;
+/// extern crate expectrl;
+/// use cgw::EmulatedSession;
+/// use std::io::Read;
+/// fn main() {
+///     let session = expectrl::spawn("rogue").unwrap();
+///     let mut session = EmulatedSession::new(session, 24, 80);
+///     let mut buf = [0u8; 4096];
+///     session.read(&mut buf).unwrap();
+///     println!("{}", session.screen());
+/// }
+/// ```
+/// For ```rexpect```, wrap the file handle of ```PtyProcess``` instead:
+/// ```ignore
+/// let file = session.process.get_file_handle();
+/// let mut session = EmulatedSession::new(file, 24, 80);
+/// ```
+pub struct EmulatedSession<S> {
+    inner: S,
+    emulator: Emulator,
+}
+
+impl<S> EmulatedSession<S> {
+    /// Wrap session with an emulator of given size.
+    pub fn new(inner: S, lines: usize, columns: usize) -> EmulatedSession<S> {
+        EmulatedSession::with_emulator(inner, Emulator::new(lines, columns))
+    }
+    /// Wrap session with your own emulator.
+    pub fn with_emulator(inner: S, emulator: Emulator) -> EmulatedSession<S> {
+        EmulatedSession {
+            inner: inner,
+            emulator: emulator,
+        }
+    }
+    /// Returns current screen.
+    pub fn screen(&self) -> Screen {
+        self.emulator.screen()
+    }
+    /// Gets a reference to the inner session.
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+    /// Gets a mutable reference to the inner session.
+    ///
+    /// Bytes read directly from it are not emulated.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+    /// Consume self and returns the inner session.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: Read> Read for EmulatedSession<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.emulator.feed(&buf[..n]);
+        Ok(n)
+    }
+}
+
+impl<S: Write> Write for EmulatedSession<S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Reads raw outputs of the game, which are also emulated.
+///
+/// It blocks until the game writes something, and returns 0 when the game exits.
+/// It's useful to pass the game to code written for other session types.
+impl<'a> Read for GameHandle<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.unread.is_empty() {
+            if self.ended {
+                return Ok(0);
+            }
+            let received = self.process.as_ref().unwrap().rx.recv();
+            match received {
+                Ok(Handle::Valid(bytes)) => {
+                    self.feed(&bytes);
+                    self.unread = bytes;
+                }
+                Ok(Handle::Zero) => {
+                    self.ended = true;
+                    return Ok(0);
+                }
+                Ok(Handle::Error(why)) => return Err(why),
+                Err(_) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::BrokenPipe,
+                        "reader thread stopped",
+                    ))
+                }
+            }
+        }
+        let n = cmp::min(buf.len(), self.unread.len());
+        buf[..n].copy_from_slice(&self.unread[..n]);
+        self.unread.drain(..n);
+        Ok(n)
+    }
+}
+
+/// Same as ```GameHandle::send```.
+impl<'a> Write for GameHandle<'a> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.send(buf)?;
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+    use GameSetting;
+    #[test]
+    fn test_emulated_session() {
+        let mut session = EmulatedSession::new(Cursor::new(b"\x1b[2;3Hhello".to_vec()), 3, 10);
+        let mut out = String::new();
+        session.read_to_string(&mut out).unwrap();
+        assert_eq!(out, "\x1b[2;3Hhello");
+        assert_eq!(session.screen().trimmed_line(1), "  hello");
+    }
+    #[test]
+    fn test_handle_read_write() {
+        let mut game = GameSetting::new("cat").build().unwrap();
+        let mut handle = game.handle();
+        handle.write_all(b"hello").unwrap();
+        let mut buf = [0u8; 3];
+        handle.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"hel");
+        handle.read_exact(&mut buf[..2]).unwrap();
+        assert_eq!(&buf[..2], b"lo");
+        assert_eq!(handle.screen().trimmed_line(0), "hello");
+    }
+}
@@ -0,0 +1,165 @@
+use replay::{read_golden, write_golden};
+use screen::Screen;
+use std::borrow::Cow;
+use std::env;
+use std::fmt::Write;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// Compare ```screen``` with ```expected``` lines, and returns a line-by-line
+/// diff if they differ.
+///
+/// Trailing blanks of each line and trailing empty lines are ignored.
+/// Usually you want ```assert_screen!``` instead.
+pub fn screen_diff<S: AsRef<str>>(screen: &Screen, expected: &[S]) -> Option<String> {
+    let expected: Vec<Cow<str>> = expected
+        .iter()
+        .map(|l| Cow::Borrowed(l.as_ref().trim_end()))
+        .collect();
+    diff_lines(&expected, &screen.trimmed_lines().collect::<Vec<_>>())
+}
+
+/// Compare ```screen``` with the golden frame in ```path```, written by
+/// ```write_golden```(or ```write_golden_compressed```), and returns a line-by-line
+/// diff if they differ.
+///
+/// If environment variable ```CGW_BLESS``` is set, ```screen``` is written to
+/// ```path``` instead, to create or update the golden file.
+pub fn check_golden<P: AsRef<Path>>(screen: &Screen, path: P) -> Result<(), String> {
+    let path = path.as_ref();
+    if env::var_os("CGW_BLESS").is_some() {
+        return File::create(path)
+            .and_then(|f| write_golden(f, &[screen.clone()]))
+            .map_err(|e| format!("failed to write golden file {:?}: {}", path, e));
+    }
+    let golden = File::open(path)
+        .and_then(|f| read_golden(BufReader::new(f)))
+        .map_err(|e| {
+            format!(
+                "failed to read golden file {:?}: {}\n(run with CGW_BLESS=1 to create it)",
+                path, e
+            )
+        })?;
+    let expected = match golden.first() {
+        Some(s) => s.trimmed_lines().collect::<Vec<_>>(),
+        None => Vec::new(),
+    };
+    match diff_lines(&expected, &screen.trimmed_lines().collect::<Vec<_>>()) {
+        Some(diff) => Err(format!("screen differs from {:?}\n{}", path, diff)),
+        None => Ok(()),
+    }
+}
+
+// lines are already trimmed
+fn diff_lines(expected: &[Cow<str>], got: &[Cow<str>]) -> Option<String> {
+    let len = |lines: &[Cow<str>]| {
+        lines
+            .iter()
+            .rposition(|l| !l.is_empty())
+            .map_or(0, |i| i + 1)
+    };
+    let (elen, glen) = (len(expected), len(got));
+    if expected[..elen] == got[..glen] {
+        return None;
+    }
+    let mut res = String::from("- expected\n+ got\n");
+    for y in 0..elen.max(glen) {
+        let e = expected.get(y).map_or("", |l| l);
+        let g = got.get(y).map_or("", |l| l);
+        if e == g {
+            writeln!(res, "  {:>3} |{}", y, e).unwrap();
+        } else {
+            writeln!(res, "- {:>3} |{}", y, e).unwrap();
+            writeln!(res, "+ {:>3} |{}", y, g).unwrap();
+        }
+    }
+    Some(res)
+}
+
+/// Assert that the screen shows the expected text, printing a line-by-line
+/// diff on failure.
+///
+/// The expected screen is given as an array of lines, a multi-line string, or
+/// a golden file path relative to the crate root(```file = "path"```).
+/// A newline at the start of the string is ignored, so that raw string
+/// literals can start on the next line.
+/// See ```screen_diff``` and ```check_golden``` for how they are compared.
+/// # Example
+/// ```
+/// #[macro_use]
+/// extern crate curses_game_wrapper as cgw;
+/// use cgw::Emulator;
+/// fn main() {
+///     let mut emu = Emulator::new(4, 10);
+///     emu.feed(b"\x1b[2J|..@.|\x1b[3;1HHP: 12");
+///     assert_screen!(emu.screen(), r"
+/// |..@.|
+///
+/// HP: 12");
+///     assert_screen!(emu.screen(), ["|..@.|", "", "HP: 12"]);
+/// }
+/// ```
+#[macro_export]
+macro_rules! assert_screen {
+    ($screen:expr, file = $path:expr) => {{
+        let path = ::std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join($path);
+        if let Err(msg) = $crate::check_golden(&$screen, path) {
+            panic!("assertion failed: {}", msg);
+        }
+    }};
+    ($screen:expr, [$($line:expr),* $(,)*]) => {{
+        let lines: &[&str] = &[$($line),*];
+        if let Some(diff) = $crate::screen_diff(&$screen, lines) {
+            panic!("assertion failed: screen differs from expected\n{}", diff);
+        }
+    }};
+    ($screen:expr, $expected:expr) => {{
+        let expected: &str = $expected;
+        let expected = if expected.starts_with('\n') { &expected[1..] } else { expected };
+        let lines: Vec<&str> = expected.split('\n').collect();
+        if let Some(diff) = $crate::screen_diff(&$screen, &lines) {
+            panic!("assertion failed: screen differs from expected\n{}", diff);
+        }
+    }};
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    fn screen(lines: &[&[u8]]) -> Screen {
+        Screen::new(lines.iter().map(|l| l.to_vec()).collect())
+    }
+    #[test]
+    fn test_screen_diff() {
+        let s = screen(&[b"|.@|  ", b"      ", b"HP: 9\0", b"      "]);
+        assert_eq!(screen_diff(&s, &["|.@|", "", "HP: 9", ""]), None);
+        assert_eq!(screen_diff(&s, &["|.@|  ", "", "HP: 9"]), None);
+        assert_eq!(
+            screen_diff(&s, &["|@.|", "", "HP: 9", ">"]),
+            Some(
+                "- expected\n+ got\n\
+                 -   0 ||@.|\n+   0 ||.@|\n    1 |\n    2 |HP: 9\n-   3 |>\n+   3 |\n"
+                    .to_owned()
+            )
+        );
+        assert_screen!(s, ["|.@|", "", "HP: 9"]);
+        assert_screen!(s, "\n|.@|\n\nHP: 9\n");
+    }
+    #[test]
+    #[should_panic(expected = "+   0 |ab")]
+    fn test_assert_screen() {
+        assert_screen!(screen(&[b"ab"]), "ba");
+    }
+    #[test]
+    fn test_check_golden() {
+        let path = env::temp_dir().join(format!("cgw-snapshot-{}.golden", ::std::process::id()));
+        let s = screen(&[b"ab ", b"c  "]);
+        write_golden(File::create(&path).unwrap(), &[s.clone()]).unwrap();
+        assert_eq!(check_golden(&s, &path), Ok(()));
+        let err = check_golden(&screen(&[b"ab ", b"d  "]), &path).unwrap_err();
+        assert!(err.ends_with("-   1 |c\n+   1 |d\n"));
+        ::std::fs::remove_file(&path).unwrap();
+        assert!(check_golden(&s, &path).unwrap_err().contains("CGW_BLESS"));
+    }
+}
@@ -0,0 +1,143 @@
+use regex::Regex;
+use screen::Screen;
+use std::fmt;
+
+/// Condition to end the game early, like the tombstone of rogue.
+///
+/// Register it by ```GameSetting::stop_when``` or ```GameSetting::stop_on```.
+/// Closures ```Fn(&Screen) -> bool``` and ```Regex```(matched against
+/// ```Screen::to_trimmed_string```) implement it.
+pub trait StopCondition: Send + Sync {
+    fn is_met(&self, screen: &Screen) -> bool;
+}
+
+impl<F: Fn(&Screen) -> bool + Send + Sync> StopCondition for F {
+    fn is_met(&self, screen: &Screen) -> bool {
+        self(screen)
+    }
+}
+
+impl StopCondition for Regex {
+    fn is_met(&self, screen: &Screen) -> bool {
+        self.is_match(&screen.to_trimmed_string())
+    }
+}
+
+/// How the game ended, reported as ```Metrics::outcome```.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    Died,
+    Quit,
+    /// Left the dungeon without winning, like NetHack without the Amulet
+    Escaped,
+    /// Won the game, like ascension of NetHack
+    Won,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match *self {
+            Outcome::Died => "died",
+            Outcome::Quit => "quit",
+            Outcome::Escaped => "escaped",
+            Outcome::Won => "won",
+        };
+        f.write_str(s)
+    }
+}
+
+/// Result of the game parsed from the final screens(e.g. the tombstone),
+/// reported as ```Metrics::result```.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GameResult {
+    /// Cause of the end, like ```killed by a kestrel``` or ```quit```
+    pub cause: Option<String>,
+    pub gold: Option<u64>,
+    /// Deepest level shown before the end
+    pub level: Option<u32>,
+    pub score: Option<u64>,
+}
+
+/// Detects the end of the game and its ```Outcome``` by regex patterns, which
+/// are matched against ```Screen::to_trimmed_string``` in the order they are added.
+///
+/// Register it by ```GameSetting::game_over```. Detectors of rogue and NetHack are
+/// ```games::rogue::game_over``` and ```games::nethack::game_over```.
+#[derive(Clone, Debug, Default)]
+pub struct GameOver {
+    patterns: Vec<(Regex, Outcome)>,
+    parser: Option<fn(&[Screen]) -> GameResult>,
+}
+
+impl GameOver {
+    pub fn new() -> GameOver {
+        GameOver::default()
+    }
+    /// Add ```pattern``` meaning ```outcome```.
+    /// # Panics
+    /// Panics if ```pattern``` is not a valid regex.
+    pub fn on(mut self, pattern: &str, outcome: Outcome) -> GameOver {
+        let re = Regex::new(pattern)
+            .unwrap_or_else(|e| panic!("invalid pattern {}: {}", pattern, e));
+        self.patterns.push((re, outcome));
+        self
+    }
+    /// Set the function to parse ```GameResult``` from the last frames
+    /// delivered to the AI(oldest first), called when the end is detected.
+    pub fn result_parser(mut self, parser: fn(&[Screen]) -> GameResult) -> GameOver {
+        self.parser = Some(parser);
+        self
+    }
+    /// Parse the result from ```screens```, if the parser is set.
+    pub fn parse_result(&self, screens: &[Screen]) -> Option<GameResult> {
+        self.parser.map(|parse| parse(screens))
+    }
+    /// Returns the outcome if the screen shows the end of the game.
+    pub fn detect(&self, screen: &Screen) -> Option<Outcome> {
+        let text = screen.to_trimmed_string();
+        self.patterns
+            .iter()
+            .find(|&&(ref re, _)| re.is_match(&text))
+            .map(|&(_, outcome)| outcome)
+    }
+}
+
+impl StopCondition for GameOver {
+    fn is_met(&self, screen: &Screen) -> bool {
+        self.detect(screen).is_some()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    fn screen(lines: &[&[u8]]) -> Screen {
+        Screen::new(lines.iter().map(|l| l.to_vec()).collect())
+    }
+    #[test]
+    fn test_stop_condition() {
+        let s = screen(&[b"You die...--More--  ", b"  @   "]);
+        assert!(Regex::new("You die").unwrap().is_met(&s));
+        assert!(Regex::new(r"(?m)^You die\.\.\.--More--$").unwrap().is_met(&s));
+        assert!(!Regex::new("possessions identified").unwrap().is_met(&s));
+        let at_home = |s: &Screen| s.get(1, 2) == Some(b'@');
+        assert!(at_home.is_met(&s));
+    }
+    #[test]
+    fn test_game_over() {
+        let go = GameOver::new()
+            .on("You die", Outcome::Died)
+            .on("You (quit|die)", Outcome::Quit);
+        assert_eq!(go.detect(&screen(&[b"You die...--More--"])), Some(Outcome::Died));
+        assert_eq!(go.detect(&screen(&[b"", b"You quit"])), Some(Outcome::Quit));
+        assert_eq!(go.detect(&screen(&[b"You see here a die."])), None);
+        assert_eq!(Outcome::Escaped.to_string(), "escaped");
+        assert_eq!(go.parse_result(&[]), None);
+        let lines = |screens: &[Screen]| GameResult {
+            level: Some(screens.len() as u32),
+            ..GameResult::default()
+        };
+        let go = go.result_parser(lines);
+        assert_eq!(go.parse_result(&[]).unwrap().level, Some(0));
+    }
+}
@@ -0,0 +1,196 @@
+use futures::{Async, AsyncSink, Poll, Sink, StartSend, Stream};
+use futures::sync::mpsc::{self as futures_mpsc, UnboundedReceiver};
+use std::io::{self, Write};
+use std::mem;
+use std::process::ChildStdin;
+use std::sync::{mpsc, Arc, Mutex, MutexGuard};
+use std::thread;
+
+use game_env::{GameEnv, GameHandle, Handle};
+use super::ActionResult;
+
+impl GameEnv {
+    /// Start process and returns it as ```Stream``` of screen updates
+    /// and ```Sink``` of keys(needs feature "futures").
+    ///
+    /// Keys sent via ```GameSink``` are not recorded in the transcript.
+    /// Responses to queries of the game(e.g. ```DECRQSS```) are sent while
+    /// the stream is polled.
+    /// It panics with ```GameSetting::mock```, ```GameSetting::stdin_from``` or
+    /// ```GameSetting::connect```.
+    /// # Example
+    /// ```no_run
+    /// extern crate curses_game_wrapper as cgw;
+    /// extern crate futures;
+    /// use cgw::{ActionResult, GameSetting};
+    /// use futures::{Future, Sink, Stream};
+    /// fn main() {
+    ///     let mut game = GameSetting::new("rogue").build().unwrap();
+    ///     let (stream, sink) = game.stream();
+    ///     let _sink = sink.send(b"j".to_vec()).wait().unwrap();
+    ///     for res in stream.wait() {
+    ///         if let ActionResult::Changed(screen) = res.unwrap() {
+    ///             println!("{}", screen);
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn stream(&mut self) -> (GameStream, GameSink) {
+        let mut handle = self.handle();
+        let (tx, rx) = futures_mpsc::unbounded();
+        // the stream ends at once if the game couldn't be spawned
+        let stdin = handle.process.as_mut().map(|process| {
+            // forward outputs from reader thread to futures' channel, to wake up the task
+            let (_, dummy) = mpsc::channel();
+            let proc_rx = mem::replace(&mut process.rx, dummy);
+            thread::spawn(move || {
+                while let Ok(h) = proc_rx.recv() {
+                    let end = match h {
+                        Handle::Valid(_) => false,
+                        _ => true,
+                    };
+                    if tx.unbounded_send(h).is_err() || end {
+                        break;
+                    }
+                }
+            });
+            let stdin = process
+                .my_proc
+                .as_mut()
+                .and_then(|p| p.stdin.take())
+                .expect("stream needs stdin of the game, which MockGame doesn't have");
+            // shared, so that the handle can send responses and held keys
+            let stdin = Arc::new(Mutex::new(stdin));
+            process.stdin = Some(stdin.clone());
+            stdin
+        });
+        let stream = GameStream {
+            handle: handle,
+            rx: rx,
+            finished: false,
+            done: false,
+        };
+        (stream, GameSink { stdin: stdin })
+    }
+}
+
+/// ```Stream``` of screen updates, made by ```GameEnv::stream```.
+///
+/// It yields ```Changed``` each time the game writes something and ```GameEnded```
+/// when the game exits. It never yields ```NotChanged```, so use timers of
+/// your runtime to know the game is waiting for input.
+/// The game is killed when it's dropped.
+pub struct GameStream<'a> {
+    handle: GameHandle<'a>,
+    rx: UnboundedReceiver<Handle<Vec<u8>>>,
+    // the game exited
+    finished: bool,
+    // GameEnded is already yielded
+    done: bool,
+}
+
+impl<'a> Stream for GameStream<'a> {
+    type Item = ActionResult;
+    type Error = io::Error;
+    fn poll(&mut self) -> Poll<Option<ActionResult>, io::Error> {
+        if self.done {
+            return Ok(Async::Ready(None));
+        }
+        let mut changed = false;
+        while !self.finished {
+            match self.rx.poll() {
+                Ok(Async::Ready(Some(Handle::Valid(bytes)))) => {
+                    self.handle.feed(&bytes);
+                    changed = true;
+                }
+                Ok(Async::Ready(Some(Handle::Error(why)))) => return Err(why),
+                Ok(Async::Ready(Some(Handle::Zero))) | Ok(Async::Ready(None)) | Err(()) => {
+                    self.finished = true;
+                    self.handle.ended = true;
+                }
+                Ok(Async::NotReady) => break,
+            }
+        }
+        if changed {
+            self.handle.metrics.frames += 1;
+            Ok(Async::Ready(Some(ActionResult::Changed(self.handle.screen()))))
+        } else if self.finished {
+            self.done = true;
+            Ok(Async::Ready(Some(ActionResult::GameEnded)))
+        } else {
+            Ok(Async::NotReady)
+        }
+    }
+}
+
+/// ```Sink``` of keys sent to the game, made by ```GameEnv::stream```.
+pub struct GameSink {
+    // None if the game couldn't be spawned
+    stdin: Option<Arc<Mutex<ChildStdin>>>,
+}
+
+impl GameSink {
+    fn stdin(&mut self) -> io::Result<MutexGuard<ChildStdin>> {
+        self.stdin
+            .as_ref()
+            .map(|stdin| stdin.lock().unwrap_or_else(|e| e.into_inner()))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::BrokenPipe, "game isn't running"))
+    }
+}
+
+impl Sink for GameSink {
+    type SinkItem = Vec<u8>;
+    type SinkError = io::Error;
+    fn start_send(&mut self, item: Vec<u8>) -> StartSend<Vec<u8>, io::Error> {
+        self.stdin()?.write_all(&item)?;
+        Ok(AsyncSink::Ready)
+    }
+    fn poll_complete(&mut self) -> Poll<(), io::Error> {
+        self.stdin()?.flush()?;
+        Ok(Async::Ready(()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use futures::Future;
+    use GameSetting;
+    #[test]
+    fn test_stream() {
+        let mut game = GameSetting::new("cat").build().unwrap();
+        {
+            let (stream, sink) = game.stream();
+            let _sink = sink.send(b"hello".to_vec()).wait().unwrap();
+            let mut results = stream.wait();
+            match results.next() {
+                Some(Ok(ActionResult::Changed(screen))) => {
+                    assert_eq!(screen.trimmed_line(0), "hello")
+                }
+                res => panic!("unexpected result: {:?}", res),
+            }
+        }
+        assert_eq!(game.episode(), 1);
+    }
+    #[test]
+    fn test_stream_responses() {
+        use TermType;
+        // the game shows the reply to DECRQSS, without ESC
+        let script = "printf '\\033P$qm\\033\\\\'; timeout 5 head -c 9 | tr -d '\\033'";
+        let mut game = GameSetting::new("sh")
+            .args(vec!["-c", script])
+            .term(TermType::Xterm)
+            .build()
+            .unwrap();
+        let (stream, _sink) = game.stream();
+        let last = stream
+            .wait()
+            .filter_map(|res| match res {
+                Ok(ActionResult::Changed(screen)) => Some(screen),
+                _ => None,
+            })
+            .last()
+            .unwrap();
+        assert_eq!(last.trimmed_line(0), "P1$r0m\\");
+    }
+}
@@ -0,0 +1,257 @@
+use error::CgwError;
+use metrics::Metrics;
+use std::fmt::Debug;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use super::{GameSetting, Reactor};
+
+/// Runs games with variations of ```GameSetting```, to tune settings like
+/// timeouts which change the behavior of your AI.
+///
+/// Each ```axis``` multiplies the configurations, making a grid of them.
+/// ```add``` adds a configuration to the list instead.
+/// # Example
+/// ```no_run
+/// extern crate curses_game_wrapper as cgw;
+/// use cgw::{ActionResult, GameSetting, Reactor, Sweep};
+/// use std::time::Duration;
+/// fn main() {
+///     struct EmptyAI;
+///     impl Reactor for EmptyAI {
+///         fn action(&mut self, _screen: ActionResult, _turn: usize) -> Option<Vec<u8>> {
+///              None
+///         }
+///     }
+///     let base = GameSetting::new("rogue").env("ROGUEUSER", "EmptyAI");
+///     let ms = Duration::from_millis;
+///     let results = Sweep::new(base)
+///         .timeouts(vec![ms(50), ms(100), ms(200)])
+///         .seeds(0..4)
+///         .episodes(3)
+///         .jobs(4)
+///         .run(|| EmptyAI);
+///     for res in results {
+///         println!("{}: {:?}", res.label, res.mean_turns());
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Sweep<'a> {
+    // labels and settings
+    configs: Vec<(Vec<String>, GameSetting<'a>)>,
+    episodes: usize,
+    jobs: usize,
+}
+
+impl<'a> Sweep<'a> {
+    /// Start from ```base``` setting.
+    pub fn new(base: GameSetting<'a>) -> Sweep<'a> {
+        Sweep {
+            configs: vec![(Vec::new(), base)],
+            episodes: 1,
+            jobs: 1,
+        }
+    }
+    /// Vary all configurations by each of ```values```, which are labeled as
+    /// ```name=value``` in ```SweepResult::label```.
+    pub fn axis<T, F>(mut self, name: &str, values: Vec<T>, f: F) -> Sweep<'a>
+    where
+        T: Debug,
+        F: Fn(GameSetting<'a>, &T) -> GameSetting<'a>,
+    {
+        let configs = self.configs
+            .drain(..)
+            .flat_map(|(labels, setting)| {
+                values
+                    .iter()
+                    .map(|v| {
+                        let mut labels = labels.clone();
+                        labels.push(format!("{}={:?}", name, v));
+                        (labels, f(setting.clone(), v))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        self.configs = configs;
+        self
+    }
+    /// Vary screen size by ```(lines, columns)```.
+    pub fn sizes(self, sizes: Vec<(usize, usize)>) -> Sweep<'a> {
+        self.axis("size", sizes, |s, &(l, c)| s.lines(l).columns(c))
+    }
+    pub fn timeouts(self, timeouts: Vec<Duration>) -> Sweep<'a> {
+        self.axis("timeout", timeouts, |s, &t| s.timeout(t))
+    }
+    pub fn seeds<I: IntoIterator<Item = u64>>(self, seeds: I) -> Sweep<'a> {
+        self.axis("seed", seeds.into_iter().collect(), |s, &seed| s.seed(seed))
+    }
+    /// Vary environment variable ```name```.
+    pub fn env(self, name: &'a str, values: Vec<&'a str>) -> Sweep<'a> {
+        self.axis(name, values, move |s, v| s.env(name, v))
+    }
+    /// Add ```setting``` to the list of configurations.
+    pub fn add(mut self, label: &str, setting: GameSetting<'a>) -> Sweep<'a> {
+        self.configs.push((vec![label.to_owned()], setting));
+        self
+    }
+    /// Number of games per configuration(Default: 1).
+    pub fn episodes(mut self, n: usize) -> Sweep<'a> {
+        self.episodes = n;
+        self
+    }
+    /// Number of configurations run in parallel(Default: 1).
+    pub fn jobs(mut self, n: usize) -> Sweep<'a> {
+        self.jobs = n.max(1);
+        self
+    }
+    /// Returns the number of configurations.
+    pub fn len(&self) -> usize {
+        self.configs.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.configs.is_empty()
+    }
+    /// Play games of all configurations, each with a new AI made by ```make_ai```.
+    ///
+    /// Games are spawned in this thread and played in worker threads.
+    /// Results are in the order of configurations.
+    /// If the AI panics, the panic is propagated to the caller.
+    pub fn run<R, F>(self, make_ai: F) -> Vec<SweepResult>
+    where
+        R: Reactor,
+        F: Fn() -> R + Send + Sync + 'static,
+    {
+        let make_ai = Arc::new(make_ai);
+        let episodes = self.episodes;
+        let mut results: Vec<Option<SweepResult>> =
+            (0..self.configs.len()).map(|_| None).collect();
+        let (tx, rx) = mpsc::channel::<(usize, thread::Result<SweepResult>)>();
+        let mut running = 0;
+        for (i, (labels, setting)) in self.configs.into_iter().enumerate() {
+            if running == self.jobs {
+                let (j, res) = rx.recv().unwrap();
+                results[j] = Some(res.unwrap_or_else(|e| panic::resume_unwind(e)));
+                running -= 1;
+            }
+            let label = labels.join(", ");
+            let config_hash = setting.config_hash();
+            let mut game = match setting.build() {
+                Ok(game) => game,
+                Err(e) => {
+                    results[i] = Some(SweepResult {
+                        label: label,
+                        config_hash: config_hash,
+                        metrics: Err(e),
+                    });
+                    continue;
+                }
+            };
+            let (tx, make_ai) = (tx.clone(), Arc::clone(&make_ai));
+            thread::spawn(move || {
+                // pass the panic to the caller, which otherwise waits forever
+                let res = panic::catch_unwind(AssertUnwindSafe(|| {
+                    let mut ai = make_ai();
+                    let metrics = (0..episodes).map(|_| game.play(&mut ai)).collect();
+                    SweepResult {
+                        label: label,
+                        config_hash: config_hash,
+                        metrics: Ok(metrics),
+                    }
+                }));
+                tx.send((i, res)).ok();
+            });
+            running += 1;
+        }
+        for _ in 0..running {
+            let (j, res) = rx.recv().unwrap();
+            results[j] = Some(res.unwrap_or_else(|e| panic::resume_unwind(e)));
+        }
+        results.into_iter().map(|r| r.unwrap()).collect()
+    }
+}
+
+/// Summary of games with one configuration of ```Sweep```.
+#[derive(Debug)]
+pub struct SweepResult {
+    /// Values of axes(like ```timeout=100ms, seed=3```) or the label of ```Sweep::add```
+    pub label: String,
+    /// See ```GameSetting::config_hash```
+    pub config_hash: String,
+    /// Metrics of each game, or the error of ```GameSetting::build```
+    pub metrics: Result<Vec<Metrics>, CgwError>,
+}
+
+impl SweepResult {
+    fn mean<F: Fn(&Metrics) -> f64>(&self, f: F) -> Option<f64> {
+        match self.metrics {
+            Ok(ref ms) if !ms.is_empty() => {
+                Some(ms.iter().map(f).sum::<f64>() / ms.len() as f64)
+            }
+            _ => None,
+        }
+    }
+    pub fn mean_turns(&self) -> Option<f64> {
+        self.mean(|m| m.turns as f64)
+    }
+    pub fn mean_reward(&self) -> Option<f64> {
+        self.mean(|m| m.reward)
+    }
+    /// Mean of ```GameResult::score```, treating games without score as 0.
+    pub fn mean_score(&self) -> Option<f64> {
+        self.mean(|m| m.result.as_ref().and_then(|r| r.score).unwrap_or(0) as f64)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::ActionResult;
+    use test_utils::EmptyAI;
+    #[test]
+    fn test_grid() {
+        let sweep = Sweep::new(GameSetting::new("cat"))
+            .sizes(vec![(24, 80), (30, 100)])
+            .seeds(0..3)
+            .env("A", vec!["x"]);
+        assert_eq!(sweep.len(), 6);
+        let labels: Vec<_> = sweep.configs.iter().map(|c| c.0.join(", ")).collect();
+        assert_eq!(labels[0], "size=(24, 80), seed=0, A=\"x\"");
+        assert_eq!(labels[5], "size=(30, 100), seed=2, A=\"x\"");
+    }
+    #[test]
+    fn test_run() {
+        let base = GameSetting::new("sh").args(vec!["-c", "printf a; exec sleep 10"]);
+        let results = Sweep::new(base)
+            .axis("max_loop", vec![2, 3, 4], |s, &n| s.max_loop(n))
+            .add("broken", GameSetting::new("cgw-no-such-command"))
+            .episodes(2)
+            .jobs(2)
+            .run(|| EmptyAI);
+        assert_eq!(results.len(), 4);
+        for (res, turns) in results.iter().zip(2..5) {
+            assert_eq!(res.label, format!("max_loop={}", turns));
+            assert_eq!(res.metrics.as_ref().unwrap().len(), 2);
+            assert_eq!(res.mean_turns(), Some(turns as f64));
+        }
+        assert_ne!(results[0].config_hash, results[1].config_hash);
+        assert!(results[3].metrics.is_err());
+        assert_eq!(results[3].mean_reward(), None);
+    }
+    #[test]
+    #[should_panic(expected = "AI is broken")]
+    fn test_panic() {
+        struct PanicAI;
+        impl Reactor for PanicAI {
+            fn action(&mut self, _screen: ActionResult, _turn: usize) -> Option<Vec<u8>> {
+                panic!("AI is broken")
+            }
+        }
+        Sweep::new(GameSetting::new("cat"))
+            .seeds(0..3)
+            .jobs(2)
+            .run(|| PanicAI);
+    }
+}
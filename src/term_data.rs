@@ -1,23 +1,27 @@
-use super::GameSetting;
-use slog::Logger;
+#[cfg(feature = "process")]
+use game_env::GameSetting;
+use keys::CursorKeyMode;
+use term_type::TermType;
+use mouse::MouseMode;
+use screen::{LineAttr, Screen};
+use logging::Logger;
+#[cfg(feature = "slog-logger")]
 use sloggers::Build;
+#[cfg(all(feature = "slog-logger", feature = "process"))]
 use sloggers::file::FileLoggerBuilder;
+#[cfg(feature = "slog-logger")]
 use sloggers::null::NullLoggerBuilder;
 use vte::Perform;
 use std::str;
 use std::default::Default;
-use std::cmp::min;
+use std::cmp::{max, min};
+use std::collections::VecDeque;
 
 #[derive(Copy, Clone, Debug, Default)]
 struct Cursor {
     x: usize,
     y: usize,
 }
-impl Cursor {
-    fn new(x: usize, y: usize) -> Cursor {
-        Cursor { x: x, y: y }
-    }
-}
 
 #[derive(Clone, Copy, Debug, Default)]
 struct LineRange(usize, usize);
@@ -31,54 +35,324 @@ impl LineRange {
 #[derive(Debug)]
 pub struct TermData {
     buf: Vec<Vec<u8>>,
+    // DEC line attributes of each line
+    line_attrs: Vec<LineAttr>,
+    // reverse[y][x] is true if the cell is shown in reverse video
+    reverse: Vec<Vec<bool>>,
+    // SGR 7 is set
+    pen_reverse: bool,
     cur: Cursor,
     height: usize,
     width: usize,
     mode: TermMode,
     scroll_range: LineRange,
     saved_cur: Cursor,
+    // the last column was written and next character goes to the next line
+    wrap_pending: bool,
+    // tabs[x] is true if column x is a tab stop
+    tabs: Vec<bool>,
     pub logger: Logger,
     preceeding: Option<u8>,
+    unhandled: Vec<UnhandledSeq>,
+    // DCS query being received, and its payload
+    dcs: Option<(DcsQuery, Vec<u8>)>,
+    // replies to queries, which should be sent back to the game
+    responses: Vec<u8>,
+    // honor XON/XOFF, and whether the game stopped us by XOFF
+    flow_control: bool,
+    xoff: bool,
+    bells: usize,
+    // byte to fill cleared cells
+    blank: u8,
+    // sequences we accept depend on it
+    term: TermType,
+    // lines scrolled off the top of the screen, oldest first
+    scrollback: VecDeque<Vec<u8>>,
+    scrollback_depth: usize,
+    // lines added to scrollback since last take_scrolled
+    scrolled: usize,
+    // interpreted events since last take_events, if recording
+    record_events: bool,
+    events: Vec<TerminalEvent>,
+    // the byte being parsed printed a character
+    printed: bool,
 }
 
 impl TermData {
+    #[cfg(feature = "process")]
     pub fn from_setting(s: &GameSetting) -> TermData {
         TermData {
-            buf: vec![vec![b' '; s.columns]; s.lines],
+            buf: vec![vec![s.blank; s.columns]; s.lines],
+            line_attrs: vec![LineAttr::Normal; s.lines],
+            reverse: vec![vec![false; s.columns]; s.lines],
+            pen_reverse: false,
             cur: Cursor::default(),
             height: s.lines,
             width: s.columns,
             mode: TermMode::default(),
             scroll_range: LineRange(0, s.lines),
             saved_cur: Cursor::default(),
-            logger: if !s.log_info.fname.is_empty() {
-                let mut builder = FileLoggerBuilder::new(&s.log_info.fname);
-                builder.level(s.log_info.sev);
-                builder.truncate();
-                builder.build()
-            } else {
-                NullLoggerBuilder {}.build()
-            }.ok()
-                .unwrap(),
+            wrap_pending: false,
+            tabs: default_tabs(s.columns),
+            logger: TermData::make_logger(s),
             preceeding: None,
+            unhandled: Vec::new(),
+            dcs: None,
+            responses: Vec::new(),
+            bells: 0,
+            blank: s.blank,
+            term: s.term,
+            flow_control: s.flow_control,
+            xoff: false,
+            scrollback: VecDeque::new(),
+            scrollback_depth: s.scrollback,
+            scrolled: 0,
+            record_events: s.terminal_events,
+            events: Vec::new(),
+            printed: false,
+        }
+    }
+    #[cfg(all(feature = "slog-logger", feature = "process"))]
+    fn make_logger(s: &GameSetting) -> Logger {
+        if let Some(ref l) = s.log_info.logger {
+            l.clone()
+        } else if !s.log_info.fname.is_empty() {
+            let mut builder = FileLoggerBuilder::new(&s.log_info.fname);
+            builder.level(s.log_info.sev);
+            builder.truncate();
+            builder.build().ok().unwrap()
+        } else {
+            null_logger()
         }
     }
+    #[cfg(all(not(feature = "slog-logger"), feature = "process"))]
+    fn make_logger(_s: &GameSetting) -> Logger {
+        Logger
+    }
+    pub fn with_size(lines: usize, columns: usize, blank: u8) -> TermData {
+        assert!(lines > 0 && columns > 0, "screen size must be positive");
+        let mut res = TermData::from_buf(vec![vec![blank; columns]; lines]);
+        res.blank = blank;
+        res
+    }
+    pub fn set_term(&mut self, term: TermType) {
+        self.term = term;
+    }
     // For debug usage only
     fn from_buf(buf: Vec<Vec<u8>>) -> TermData {
         TermData {
+            line_attrs: vec![LineAttr::Normal; buf.len()],
+            reverse: vec![vec![false; buf[0].len()]; buf.len()],
+            pen_reverse: false,
             cur: Cursor::default(),
             height: buf.len(),
             width: buf[0].len(),
             mode: TermMode::default(),
             scroll_range: LineRange(0, buf.len()),
             saved_cur: Cursor::default(),
-            logger: NullLoggerBuilder {}.build().ok().unwrap(),
+            wrap_pending: false,
+            tabs: default_tabs(buf[0].len()),
+            logger: null_logger(),
             preceeding: None,
+            unhandled: Vec::new(),
+            dcs: None,
+            responses: Vec::new(),
+            bells: 0,
+            blank: b' ',
+            term: TermType::Xterm,
+            flow_control: false,
+            xoff: false,
+            scrollback: VecDeque::new(),
+            scrollback_depth: 0,
+            scrolled: 0,
+            record_events: false,
+            events: Vec::new(),
+            printed: false,
             buf: buf,
         }
     }
-    pub fn ret_screen(&self) -> Vec<Vec<u8>> {
-        self.buf.clone()
+    // clear screen and states for next game
+    #[cfg(feature = "process")]
+    pub fn reset(&mut self) {
+        self.full_reset();
+        self.unhandled.clear();
+        self.events.clear();
+        self.responses.clear();
+        self.xoff = false;
+        self.bells = 0;
+        self.clear_scrollback();
+    }
+    // RIS
+    fn full_reset(&mut self) {
+        debug!(self.logger, "(full_reset)");
+        self.record_clear(ClearKind::Screen);
+        self.buf = vec![vec![self.blank; self.width]; self.height];
+        self.line_attrs = vec![LineAttr::Normal; self.height];
+        self.reverse = vec![vec![false; self.width]; self.height];
+        self.pen_reverse = false;
+        self.cur = Cursor::default();
+        self.mode = TermMode::default();
+        self.scroll_range = LineRange(0, self.height);
+        self.saved_cur = Cursor::default();
+        self.wrap_pending = false;
+        self.tabs = default_tabs(self.width);
+        self.preceeding = None;
+    }
+    // change screen size, keeping the top-left of the screen
+    pub fn resize(&mut self, lines: usize, columns: usize) {
+        assert!(lines > 0 && columns > 0, "screen size must be positive");
+        debug!(self.logger, "(resize) {}x{}", lines, columns);
+        let blank = self.blank;
+        self.buf.resize(lines, vec![blank; columns]);
+        for line in &mut self.buf {
+            line.resize(columns, blank);
+        }
+        self.line_attrs.resize(lines, LineAttr::Normal);
+        self.reverse.resize(lines, vec![false; columns]);
+        for line in &mut self.reverse {
+            line.resize(columns, false);
+        }
+        let defaults = default_tabs(columns);
+        self.tabs.resize(columns, false);
+        if columns > self.width {
+            let old = self.width;
+            self.tabs[old..].copy_from_slice(&defaults[old..]);
+        }
+        self.height = lines;
+        self.width = columns;
+        self.scroll_range = LineRange(0, lines);
+        for c in &mut [&mut self.cur, &mut self.saved_cur] {
+            c.x = min(c.x, columns - 1);
+            c.y = min(c.y, lines - 1);
+        }
+        self.wrap_pending = false;
+    }
+    pub fn ret_screen(&self) -> Screen {
+        Screen::new(self.buf.clone())
+            .with_line_attrs(self.line_attrs.clone())
+            .with_reverse(self.reverse.clone())
+            .with_cursor(self.cursor(), self.cursor_visible())
+    }
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.cur.y, self.cur.x)
+    }
+    pub fn cursor_key_mode(&self) -> CursorKeyMode {
+        if self.mode.contains(TermMode::APP_CURSOR) {
+            CursorKeyMode::Application
+        } else {
+            CursorKeyMode::Normal
+        }
+    }
+    pub fn mode(&self) -> TermMode {
+        self.mode
+    }
+    pub fn cursor_visible(&self) -> bool {
+        self.mode.contains(TermMode::SHOW_CURSOR)
+    }
+    pub fn bracketed_paste(&self) -> bool {
+        self.mode.contains(TermMode::BRACKETED_PASTE)
+    }
+    pub fn mouse_mode(&self) -> MouseMode {
+        MouseMode {
+            click: self.mode.contains(TermMode::MOUSE_REPORT_CLICK),
+            motion: self.mode.contains(TermMode::MOUSE_MOTION),
+            sgr: self.mode.contains(TermMode::SGR_MOUSE),
+        }
+    }
+    // keep at most depth lines scrolled off the screen(0 to disable)
+    pub fn set_scrollback(&mut self, depth: usize) {
+        self.scrollback_depth = depth;
+        while self.scrollback.len() > depth {
+            self.scrollback.pop_front();
+        }
+        self.scrolled = min(self.scrolled, depth);
+    }
+    pub fn scrollback(&self) -> Vec<Vec<u8>> {
+        self.scrollback.iter().cloned().collect()
+    }
+    // returns lines scrolled off the screen since last call, oldest first
+    #[cfg(any(test, feature = "process"))]
+    pub fn take_scrolled(&mut self) -> Vec<Vec<u8>> {
+        let start = self.scrollback.len() - ::std::mem::replace(&mut self.scrolled, 0);
+        self.scrollback.iter().skip(start).cloned().collect()
+    }
+    fn clear_scrollback(&mut self) {
+        self.scrollback.clear();
+        self.scrolled = 0;
+    }
+    // true if the game sent XOFF and we must not write until XON
+    #[cfg(feature = "process")]
+    pub fn xoff(&self) -> bool {
+        self.xoff
+    }
+    // returns replies to queries of the game since last call
+    pub fn take_responses(&mut self) -> Vec<u8> {
+        ::std::mem::replace(&mut self.responses, Vec::new())
+    }
+    // returns how many times bell rang since last call
+    #[cfg(feature = "process")]
+    pub fn take_bells(&mut self) -> usize {
+        ::std::mem::replace(&mut self.bells, 0)
+    }
+    // returns sequences we couldn't handle since last call
+    #[cfg(any(test, feature = "process"))]
+    pub fn take_unhandled(&mut self) -> Vec<UnhandledSeq> {
+        ::std::mem::replace(&mut self.unhandled, Vec::new())
+    }
+    // record TerminalEvents from now on, or stop and drop recorded ones
+    pub fn set_record_events(&mut self, on: bool) {
+        self.record_events = on;
+        if !on {
+            self.events.clear();
+        }
+    }
+    // returns events interpreted since last call, if recording
+    pub fn take_events(&mut self) -> Vec<TerminalEvent> {
+        ::std::mem::replace(&mut self.events, Vec::new())
+    }
+    // extend the last print run, if c follows it on the same line
+    fn record_print(&mut self, c: u8) {
+        let (y, x) = (self.cur.y, self.cur.x);
+        self.printed = true;
+        if let Some(&mut TerminalEvent::Print {
+            y: py,
+            x: px,
+            ref mut text,
+        }) = self.events.last_mut()
+        {
+            if py == y && px + text.len() == x {
+                text.push(c);
+                return;
+            }
+        }
+        self.events.push(TerminalEvent::Print {
+            y: y,
+            x: x,
+            text: vec![c],
+        });
+    }
+    fn record_clear(&mut self, kind: ClearKind) {
+        if self.record_events {
+            let (y, x) = (self.cur.y, self.cur.x);
+            self.events.push(TerminalEvent::Clear {
+                kind: kind,
+                y: y,
+                x: x,
+            });
+        }
+    }
+    // cursor moves and mode changes are found by comparing states before and
+    // after each byte, since many control functions cause them
+    fn record_changes(&mut self, cur: (usize, usize), mode: TermMode) {
+        if mode != self.mode {
+            let mode = self.mode;
+            self.events.push(TerminalEvent::ModeChange(mode));
+        }
+        if cur != self.cursor() && !self.printed {
+            let (y, x) = self.cursor();
+            self.events.push(TerminalEvent::CursorMove { y: y, x: x });
+        }
+        self.printed = false;
     }
     fn is_cursor_valid(&self) -> bool {
         self.cur.y < self.height && self.cur.x < self.width
@@ -92,27 +366,36 @@ impl TermData {
     }
     fn input(&mut self, c: u8) {
         trace!(self.logger, "(input) c: {}", c);
-        while self.cur.x >= self.width {
-            if !self.mode.contains(TermMode::LINE_WRAP) {
-                return;
-            }
-            self.cur.x -= self.width;
+        // wrap lazily, as real terminals do
+        if self.wrap_pending && self.mode.contains(TermMode::LINE_WRAP) {
+            self.carriage_return();
             self.linefeed();
         }
         self.assert_cursor();
+        if self.record_events {
+            self.record_print(c);
+        }
         self.buf[self.cur.y][self.cur.x] = c;
+        self.reverse[self.cur.y][self.cur.x] = self.pen_reverse;
         self.preceeding = Some(c);
-        self.cur.x += 1;
+        if self.cur.x + 1 < self.width {
+            self.cur.x += 1;
+            self.wrap_pending = false;
+        } else {
+            self.wrap_pending = true;
+        }
     }
     fn carriage_return(&mut self) {
         debug!(self.logger, "(carriage_return)");
         self.cur.x = 0;
+        self.wrap_pending = false;
     }
     fn linefeed(&mut self) {
         debug!(
             self.logger,
             "line_feed, cur: {:?}, range: {:?}", self.cur, self.scroll_range
         );
+        self.wrap_pending = false;
         let nxt = self.cur.y + 1;
         if nxt == self.scroll_range.1 {
             self.scroll_up(1);
@@ -122,89 +405,175 @@ impl TermData {
     }
     fn backspace(&mut self) {
         trace!(self.logger, "(backspace)");
+        self.wrap_pending = false;
         if self.cur.x > 0 {
             self.cur.x -= 1;
         }
     }
-    fn newline(&mut self) {
-        self.linefeed();
-        if self.mode.contains(TermMode::LINE_FEED_NEW_LINE) {
-            self.carriage_return();
+    fn forward_tab(&mut self, num: usize) {
+        trace!(self.logger, "(forward_tab) {}", num);
+        self.wrap_pending = false;
+        for _ in 0..min(num, self.width) {
+            let last = self.width - 1;
+            if self.cur.x >= last {
+                break;
+            }
+            self.cur.x = (self.cur.x + 1..last)
+                .find(|&x| self.tabs[x])
+                .unwrap_or(last);
+        }
+    }
+    fn backward_tab(&mut self, num: usize) {
+        trace!(self.logger, "(backward_tab) {}", num);
+        self.wrap_pending = false;
+        for _ in 0..min(num, self.width) {
+            let cur = min(self.cur.x, self.width);
+            self.cur.x = (0..cur).rev().find(|&x| self.tabs[x]).unwrap_or(0);
+        }
+    }
+    fn set_tab(&mut self) {
+        if self.cur.x < self.width {
+            self.tabs[self.cur.x] = true;
+        }
+    }
+    fn clear_tabs(&mut self, mode: TabClearMode) {
+        debug!(self.logger, "(clear_tabs): {:?}", mode);
+        match mode {
+            TabClearMode::Current => if self.cur.x < self.width {
+                self.tabs[self.cur.x] = false;
+            },
+            TabClearMode::All => self.tabs.iter_mut().for_each(|t| *t = false),
         }
     }
     fn add_x(&mut self, num: usize) {
-        self.cur.x += num;
+        self.cur.x = min(self.cur.x.saturating_add(num), self.width - 1);
+        self.wrap_pending = false;
     }
+    // stops at the bottom margin, if the cursor is above it
     fn add_y(&mut self, num: usize) {
-        self.cur.y += num;
-        assert!(self.cur.y < self.height);
+        let bottom = if self.cur.y < self.scroll_range.1 {
+            self.scroll_range.1
+        } else {
+            self.height
+        };
+        self.cur.y = min(self.cur.y.saturating_add(num), bottom - 1);
+        self.wrap_pending = false;
     }
     fn sub_x(&mut self, num: usize) {
-        assert!(self.cur.x >= num);
-        self.cur.x -= num;
+        self.cur.x = self.cur.x.saturating_sub(num);
+        self.wrap_pending = false;
     }
+    // stops at the top margin, if the cursor is below it
     fn sub_y(&mut self, num: usize) {
-        assert!(self.cur.y >= num);
-        self.cur.y -= num;
+        let top = if self.cur.y >= self.scroll_range.0 {
+            self.scroll_range.0
+        } else {
+            0
+        };
+        self.cur.y = max(self.cur.y.saturating_sub(num), top);
+        self.wrap_pending = false;
     }
     fn goto_x(&mut self, num: usize) {
-        self.cur.x = num;
+        self.cur.x = min(num, self.width - 1);
+        self.wrap_pending = false;
     }
     fn goto_y(&mut self, num: usize) {
-        self.cur.y = num;
-        assert!(self.cur.y < self.height);
+        self.cur.y = min(num, self.height - 1);
+        self.wrap_pending = false;
+    }
+    // lines the cursor can be moved to by CUP, which depend on DECOM
+    fn origin_range(&self) -> LineRange {
+        if self.mode.contains(TermMode::ORIGIN) {
+            self.scroll_range
+        } else {
+            LineRange(0, self.height)
+        }
     }
-    fn goto(&mut self, c: Cursor) {
-        self.cur = c;
+    // move cursor relative to the origin
+    fn goto_origin(&mut self, x: usize, y: usize) {
+        let range = self.origin_range();
+        let width = self.width;
+        self.goto_x(min(x, width - 1));
+        self.goto_y(min(range.0 + y, range.1 - 1));
+    }
+    fn set_scroll_range(&mut self, top: usize, bottom: usize) {
+        let bottom = min(bottom, self.height);
+        if top + 1 >= bottom {
+            warn!(self.logger, "invalid scroll range: {}..{}", top, bottom);
+            return;
+        }
+        self.scroll_range = LineRange(top, bottom);
+        self.goto_origin(0, 0);
     }
     fn clear_scr(&mut self, mode: ClearMode) {
         debug!(self.logger, "(clear_scr): {:?}", mode);
+        self.record_clear(match mode {
+            ClearMode::All => ClearKind::Screen,
+            ClearMode::Above => ClearKind::Above,
+            ClearMode::Below => ClearKind::Below,
+            ClearMode::Saved => ClearKind::Scrollback,
+        });
         match mode {
             ClearMode::All => for i in 0..self.height {
-                for j in 0..self.width {
-                    self.buf[i][j] = b' ';
-                }
+                let w = self.width;
+                self.erase_cells(i, 0, w);
+                self.line_attrs[i] = LineAttr::Normal;
             },
             ClearMode::Above => {
-                for i in 0..self.cur.y {
-                    for j in 0..self.width {
-                        self.buf[i][j] = b' ';
-                    }
-                }
-                for j in 0..(self.cur.x + 1) {
-                    self.buf[self.cur.y][j] = b' ';
+                let (x, y) = (self.cur.x, self.cur.y);
+                for i in 0..y {
+                    let w = self.width;
+                    self.erase_cells(i, 0, w);
+                    self.line_attrs[i] = LineAttr::Normal;
                 }
+                self.erase_cells(y, 0, x + 1);
             }
             ClearMode::Below => {
-                for i in (self.cur.y + 1)..self.height {
-                    for j in 0..self.width {
-                        self.buf[i][j] = b' ';
-                    }
-                }
-                for j in self.cur.x..self.width {
-                    self.buf[self.cur.y][j] = b' ';
+                let (x, y) = (self.cur.x, self.cur.y);
+                for i in (y + 1)..self.height {
+                    let w = self.width;
+                    self.erase_cells(i, 0, w);
+                    self.line_attrs[i] = LineAttr::Normal;
                 }
+                let w = self.width;
+                self.erase_cells(y, x, w);
             }
-            // Oh my god tell me what should I do
-            ClearMode::Saved => {}
+            ClearMode::Saved => self.clear_scrollback(),
         }
     }
     fn clear_line(&mut self, mode: LineClearMode) {
         debug!(self.logger, "(clear_line): {:?}", mode);
+        self.record_clear(match mode {
+            LineClearMode::Right => ClearKind::LineRight,
+            LineClearMode::Left => ClearKind::LineLeft,
+            LineClearMode::All => ClearKind::Line,
+        });
+        let (x, y, w) = (self.cur.x, self.cur.y, self.width);
         match mode {
-            LineClearMode::Right => for i in self.cur.x..self.width {
-                self.buf[self.cur.y][i] = b' ';
-            },
-            LineClearMode::Left => for i in 0..self.cur.x + 1 {
-                self.buf[self.cur.y][i] = b' ';
-            },
-            LineClearMode::All => for i in 0..self.width {
-                self.buf[self.cur.y][i] = b' ';
-            },
+            LineClearMode::Right => self.erase_cells(y, x, w),
+            LineClearMode::Left => self.erase_cells(y, 0, x + 1),
+            LineClearMode::All => self.erase_cells(y, 0, w),
         }
     }
+    // fill cells of line y in [start, end) with blank
+    fn erase_cells(&mut self, y: usize, start: usize, end: usize) {
+        let blank = self.blank;
+        self.buf[y][start..end].iter_mut().for_each(|c| *c = blank);
+        self.reverse[y][start..end].iter_mut().for_each(|r| *r = false);
+    }
     fn scroll_up(&mut self, num: usize) {
         let origin = self.scroll_range.0;
+        // like xterm, keep lines only when they go off the top of the screen
+        if origin == 0 && self.scrollback_depth > 0 {
+            let num = min(num, self.scroll_range.1);
+            for y in 0..num {
+                if self.scrollback.len() == self.scrollback_depth {
+                    self.scrollback.pop_front();
+                }
+                self.scrollback.push_back(self.buf[y].clone());
+            }
+            self.scrolled = min(self.scrolled + num, self.scrollback.len());
+        }
         self.scroll_up_relative(origin, num);
     }
     fn scroll_up_relative(&mut self, origin: usize, num: usize) {
@@ -218,6 +587,9 @@ impl TermData {
             self.logger,
             "scroll_down_relative: origin={}, num={}", origin, num
         );
+        let num = min(num, self.scroll_range.1 - origin);
+        self.record_scroll(origin, num as isize);
+        let blank = self.blank;
         let mut tmp = self.buf.clone();
         {
             let buf = &self.buf[origin..self.scroll_range.1];
@@ -227,11 +599,30 @@ impl TermData {
                         *tmp_v = buf_v.clone();
                     }
                 } else {
-                    tmp_v.iter_mut().for_each(|x| *x = b' ');
+                    tmp_v.iter_mut().for_each(|x| *x = blank);
                 }
             }
         }
         self.buf = tmp;
+        let attrs = &mut self.line_attrs[origin..self.scroll_range.1];
+        attrs.rotate_left(num);
+        let len = attrs.len();
+        attrs[len - num..].iter_mut().for_each(|a| *a = LineAttr::Normal);
+        let reverse = &mut self.reverse[origin..self.scroll_range.1];
+        reverse.rotate_left(num);
+        for line in &mut reverse[len - num..] {
+            line.iter_mut().for_each(|r| *r = false);
+        }
+    }
+    fn record_scroll(&mut self, top: usize, lines: isize) {
+        if self.record_events {
+            let bottom = self.scroll_range.1;
+            self.events.push(TerminalEvent::Scroll {
+                top: top,
+                bottom: bottom,
+                lines: lines,
+            });
+        }
     }
     fn scroll_down(&mut self, num: usize) {
         let origin = self.scroll_range.0;
@@ -248,6 +639,9 @@ impl TermData {
             self.logger,
             "scroll_up_relative: origin={}, num={}", origin, num
         );
+        let num = min(num, self.scroll_range.1 - origin);
+        self.record_scroll(origin, -(num as isize));
+        let blank = self.blank;
         let mut tmp = self.buf.clone();
         {
             let buf = &self.buf[origin..self.scroll_range.1];
@@ -257,11 +651,19 @@ impl TermData {
                         *tmp_v = buf_v.clone();
                     }
                 } else {
-                    tmp_v.iter_mut().for_each(|x| *x = b' ');
+                    tmp_v.iter_mut().for_each(|x| *x = blank);
                 }
             }
         }
         self.buf = tmp;
+        let attrs = &mut self.line_attrs[origin..self.scroll_range.1];
+        attrs.rotate_right(num);
+        attrs[..num].iter_mut().for_each(|a| *a = LineAttr::Normal);
+        let reverse = &mut self.reverse[origin..self.scroll_range.1];
+        reverse.rotate_right(num);
+        for line in &mut reverse[..num] {
+            line.iter_mut().for_each(|r| *r = false);
+        }
     }
     fn insert_blank_lines(&mut self, num: usize) {
         trace!(self.logger, "insert_blank_lines, {}", num);
@@ -279,7 +681,8 @@ impl TermData {
     }
     fn insert_blank_chars(&mut self, num: usize) {
         trace!(self.logger, "insert_blank_chars, {}", num);
-        let mut tmp = vec![b' '; self.width];
+        let num = min(num, self.width);
+        let mut tmp = vec![self.blank; self.width];
         for j in 0..self.width {
             if j < self.cur.x {
                 tmp[j] = self.buf[self.cur.y][j];
@@ -288,16 +691,22 @@ impl TermData {
             }
         }
         self.buf[self.cur.y] = tmp;
+        let reverse = &mut self.reverse[self.cur.y][self.cur.x..];
+        let num = min(num, reverse.len());
+        reverse.rotate_right(num);
+        reverse[..num].iter_mut().for_each(|r| *r = false);
     }
     fn erase_chars(&mut self, num: usize) {
         trace!(self.logger, "erase_chars, {}", num);
-        for j in self.cur.x..min(self.cur.x + num, self.width) {
-            self.buf[self.cur.y][j] = b' ';
-        }
+        self.record_clear(ClearKind::Chars(num));
+        let (x, y) = (self.cur.x, self.cur.y);
+        let end = min(x.saturating_add(num), self.width);
+        self.erase_cells(y, x, end);
     }
     fn delete_chars(&mut self, num: usize) {
         trace!(self.logger, "delete_chars, {}", num);
-        let mut tmp = vec![b' '; self.width];
+        let num = min(num, self.width);
+        let mut tmp = vec![self.blank; self.width];
         for j in 0..self.width {
             if j < self.cur.x {
                 tmp[j] = self.buf[self.cur.y][j];
@@ -306,8 +715,20 @@ impl TermData {
             }
         }
         self.buf[self.cur.y] = tmp;
+        let reverse = &mut self.reverse[self.cur.y][self.cur.x..];
+        let num = min(num, reverse.len());
+        reverse.rotate_left(num);
+        let len = reverse.len();
+        reverse[len - num..].iter_mut().for_each(|r| *r = false);
+    }
+    // We can't change the width since the game's window size is fixed, so
+    // only clear the screen and reset margins as DECCOLM does.
+    fn deccolm(&mut self) {
+        self.clear_scr(ClearMode::All);
+        self.scroll_range = LineRange(0, self.height);
+        self.cur = Cursor::default();
+        self.wrap_pending = false;
     }
-    fn deccolm(&self) {}
     fn unset_mode(&mut self, mode: ModeInt) {
         debug!(self.logger, "unset_mode: {:?}", mode);
         match mode {
@@ -321,7 +742,10 @@ impl TermData {
             ModeInt::SgrMouse => self.mode.remove(TermMode::SGR_MOUSE),
             ModeInt::LineWrap => self.mode.remove(TermMode::LINE_WRAP),
             ModeInt::LineFeedNewLine => self.mode.remove(TermMode::LINE_FEED_NEW_LINE),
-            ModeInt::Origin => self.mode.remove(TermMode::ORIGIN),
+            ModeInt::Origin => {
+                self.mode.remove(TermMode::ORIGIN);
+                self.goto_origin(0, 0);
+            }
             ModeInt::DECCOLM => self.deccolm(),
             ModeInt::Insert => self.mode.remove(TermMode::INSERT),
             _ => trace!(self.logger, "ignoring unset_mode"),
@@ -340,12 +764,73 @@ impl TermData {
             ModeInt::SgrMouse => self.mode.insert(TermMode::SGR_MOUSE),
             ModeInt::LineWrap => self.mode.insert(TermMode::LINE_WRAP),
             ModeInt::LineFeedNewLine => self.mode.insert(TermMode::LINE_FEED_NEW_LINE),
-            ModeInt::Origin => self.mode.insert(TermMode::ORIGIN),
+            ModeInt::Origin => {
+                self.mode.insert(TermMode::ORIGIN);
+                self.goto_origin(0, 0);
+            }
             ModeInt::DECCOLM => self.deccolm(),
             ModeInt::Insert => self.mode.insert(TermMode::INSERT),
             _ => trace!(self.logger, "ignoring set_mode"),
         }
     }
+    // SGR. Only reverse video is tracked for now.
+    fn set_graphic_rendition(&mut self, args: &[i64]) {
+        trace!(self.logger, "(sgr) {:?}", args);
+        if args.is_empty() {
+            self.pen_reverse = false;
+        }
+        let mut i = 0;
+        while i < args.len() {
+            match args[i] {
+                0 | 27 => self.pen_reverse = false,
+                7 => self.pen_reverse = true,
+                // skip the color of 38;5;n and 38;2;r;g;b
+                38 | 48 => match args.get(i + 1) {
+                    Some(&5) => i += 2,
+                    Some(&2) => i += 4,
+                    _ => {}
+                },
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+    // DECRQSS: reply current setting of the control function
+    fn request_status(&mut self, query: &[u8]) {
+        let status = match query {
+            b"m" => Some(if self.pen_reverse { "0;7m".to_owned() } else { "0m".to_owned() }),
+            b"r" => {
+                let LineRange(top, bottom) = self.scroll_range;
+                Some(format!("{};{}r", top + 1, bottom))
+            }
+            _ => None,
+        };
+        debug!(self.logger, "(DECRQSS) {:?} => {:?}", query, status);
+        let reply = match status {
+            Some(st) => format!("\x1bP1$r{}\x1b\\", st),
+            None => "\x1bP0$r\x1b\\".to_owned(),
+        };
+        self.responses.extend_from_slice(reply.as_bytes());
+    }
+    // XTGETTCAP: reply terminfo capabilities, names and values in hex
+    fn request_termcap(&mut self, query: &[u8]) {
+        for name in query.split(|&b| b == b';') {
+            let value = match &hex_decode(name)[..] {
+                b"TN" | b"name" => Some(self.term.name()),
+                _ => None,
+            };
+            debug!(self.logger, "(XTGETTCAP) {:?} => {:?}", name, value);
+            let reply = match value {
+                Some(v) => format!(
+                    "\x1bP1+r{}={}\x1b\\",
+                    str::from_utf8(name).unwrap_or(""),
+                    hex_encode(v.as_bytes())
+                ),
+                None => format!("\x1bP0+r{}\x1b\\", str::from_utf8(name).unwrap_or("")),
+            };
+            self.responses.extend_from_slice(reply.as_bytes());
+        }
+    }
     fn set_keyboard_app_mode(&mut self) {
         self.mode.insert(TermMode::APP_KEYPAD);
     }
@@ -359,6 +844,7 @@ impl TermData {
     fn restore_cursor(&mut self) {
         trace!(self.logger, "restore_cursor");
         self.cur = self.saved_cur;
+        self.wrap_pending = false;
     }
     fn reverse_index(&mut self) {
         trace!(self.logger, "reverse_index");
@@ -368,8 +854,83 @@ impl TermData {
             self.sub_y(1);
         }
     }
+    // DECALN: fill the screen with 'E'
     fn dectest(&mut self) {
-        unimplemented!("dectest");
+        trace!(self.logger, "dectest");
+        for line in &mut self.buf {
+            line.iter_mut().for_each(|x| *x = b'E');
+        }
+        for line in &mut self.reverse {
+            line.iter_mut().for_each(|r| *r = false);
+        }
+        self.line_attrs.iter_mut().for_each(|a| *a = LineAttr::Normal);
+        self.scroll_range = LineRange(0, self.height);
+        self.cur = Cursor::default();
+        self.wrap_pending = false;
+    }
+}
+
+// tab stops at every 8 columns
+fn default_tabs(columns: usize) -> Vec<bool> {
+    (0..columns).map(|x| x > 0 && x % 8 == 0).collect()
+}
+
+#[cfg(feature = "slog-logger")]
+fn null_logger() -> Logger {
+    NullLoggerBuilder {}.build().ok().unwrap()
+}
+
+#[cfg(not(feature = "slog-logger"))]
+fn null_logger() -> Logger {
+    Logger
+}
+
+/// vte parser which also accepts 8-bit C1 controls.
+///
+/// vte drops C1 controls introducing sequences or strings(CSI, DCS, OSC, etc.),
+/// so they are converted into ```ESC``` + 7-bit form. Bytes inside UTF-8 characters
+/// are passed as is.
+pub struct Parser {
+    inner: ::vte::Parser,
+    // number of UTF-8 continuation bytes we're waiting for
+    utf8_rest: u8,
+}
+
+impl Parser {
+    pub fn new() -> Parser {
+        Parser {
+            inner: ::vte::Parser::new(),
+            utf8_rest: 0,
+        }
+    }
+    pub fn advance(&mut self, term: &mut TermData, byte: u8) {
+        if term.record_events {
+            let (cur, mode) = (term.cursor(), term.mode);
+            self.advance_byte(term, byte);
+            term.record_changes(cur, mode);
+        } else {
+            self.advance_byte(term, byte);
+        }
+    }
+    fn advance_byte(&mut self, term: &mut TermData, byte: u8) {
+        if self.utf8_rest > 0 && byte & 0xc0 == 0x80 {
+            self.utf8_rest -= 1;
+            self.inner.advance(term, byte);
+            return;
+        }
+        self.utf8_rest = match byte {
+            0xc2..=0xdf => 1,
+            0xe0..=0xef => 2,
+            0xf0..=0xf4 => 3,
+            _ => 0,
+        };
+        match byte {
+            C1::DCS | C1::SOS | C1::CSI | C1::OSC | C1::PM | C1::APC => {
+                self.inner.advance(term, C0::ESC);
+                self.inner.advance(term, byte - 0x40);
+            }
+            _ => self.inner.advance(term, byte),
+        }
     }
 }
 
@@ -391,11 +952,21 @@ impl Perform for TermData {
             byte
         );
         match byte {
+            C0::BEL => self.bells += 1,
             C0::BS => self.backspace(), // backspace
+            C0::HT => self.forward_tab(1),
             C0::CR => self.carriage_return(),
             C0::LF | C0::VT | C0::FF => self.linefeed(),
-            C1::NEL => self.newline(),
-            _ => warn!(self.logger, "[unhandled!(execute)] byte={:02x}", byte),
+            C0::XON if self.flow_control => self.xoff = false,
+            C0::XOFF if self.flow_control => self.xoff = true,
+            // same as ESC + 7-bit form
+            C1::IND..=C1::SS3 | C1::DECID | C1::ST => {
+                self.esc_dispatch(&[], &[], false, byte - 0x40)
+            }
+            _ => {
+                warn!(self.logger, "[unhandled!(execute)] byte={:02x}", byte);
+                self.unhandled.push(UnhandledSeq::Execute(byte));
+            }
         }
     }
 
@@ -405,6 +976,11 @@ impl Perform for TermData {
             () => {{
                 warn!(self.logger, "[unhandled! (CSI)] action={:?}, args={:?}, intermediates={:?}",
                              action, args, intermediates);
+                self.unhandled.push(UnhandledSeq::Csi {
+                    action: action,
+                    args: args.to_owned(),
+                    intermediates: intermediates.to_owned(),
+                });
                 return;
             }}
         }
@@ -415,6 +991,10 @@ impl Perform for TermData {
                 args[id]
             }
         };
+        // number of times, where 0 means 1
+        let count = |id: usize| -> usize { max(args_or(id, 1), 1) as usize };
+        // 1-origin position, where 0 means 1
+        let pos = |id: usize| -> usize { max(args_or(id, 1), 1) as usize - 1 };
         trace!(
             self.logger,
             "(CSI) private = {:?}, action={:?}, args={:?}, intermediates={:?}",
@@ -424,32 +1004,42 @@ impl Perform for TermData {
             intermediates
         );
         match action {
-            '@' => self.insert_blank_chars(args_or(0, 1) as _),
-            'A' => self.sub_y(args_or(0, 1) as _),
+            '@' => self.insert_blank_chars(count(0)),
+            'A' => self.sub_y(count(0)),
             'b' => match self.preceeding {
-                Some(c) => for _ in 0..args_or(0, 1) {
+                Some(c) => for _ in 0..min(count(0), self.width * self.height) {
                     self.input(c);
                 },
                 None => warn!(self.logger, "Try repeating with No Precceding Char!"),
             },
-            'B' | 'e' => self.add_y(args_or(0, 1) as _), // move down
-            'C' | 'a' => self.add_x(args_or(0, 1) as _), // move forward
-            'D' => self.sub_x(args_or(0, 1) as _),       // move backward
+            'B' | 'e' => self.add_y(count(0)), // move down
+            'C' | 'a' => self.add_x(count(0)), // move forward
+            'D' => self.sub_x(count(0)),       // move backward
             'E' => {
                 // move down and CR
-                self.add_y(args_or(0, 1) as _);
+                self.add_y(count(0));
                 self.carriage_return();
             }
             'F' => {
                 // move up and CR
-                self.sub_y(args_or(0, 1) as _);
+                self.sub_y(count(0));
                 self.carriage_return();
             }
-            'G' | '`' => self.goto_x(args_or(0, 1) as usize - 1),
+            'g' => {
+                let mode = match args_or(0, 0) {
+                    0 => TabClearMode::Current,
+                    3 => TabClearMode::All,
+                    _ => unhandled!(),
+                };
+                self.clear_tabs(mode);
+            }
+            'I' => self.forward_tab(count(0)),
+            'Z' => self.backward_tab(count(0)),
+            'G' | '`' => self.goto_x(pos(0)),
             'H' | 'f' => {
-                let y = args_or(0, 1) as usize - 1;
-                let x = args_or(1, 1) as usize - 1;
-                self.goto(Cursor::new(x, y));
+                let y = pos(0);
+                let x = pos(1);
+                self.goto_origin(x, y);
             }
             'J' => {
                 let mode = match args_or(0, 0) {
@@ -470,23 +1060,28 @@ impl Perform for TermData {
                 };
                 self.clear_line(mode);
             }
-            'S' => self.scroll_up(args_or(0, 1) as _),
-            'T' => self.scroll_down(args_or(0, 1) as _),
-            'L' => self.insert_blank_lines(args_or(0, 1) as _),
+            'S' => self.scroll_up(count(0)),
+            'T' => self.scroll_down(count(0)),
+            'L' => self.insert_blank_lines(count(0)),
             'l' => {
-                let mode = ModeInt::from_primitive(private, args_or(0, 0));
+                let mode = ModeInt::from_primitive(private, args_or(0, 0))
+                    .filter(|m| self.term.is_xterm() || !m.is_xterm_ext());
                 trace!(self.logger, "unset mode {:?}", mode);
                 match mode {
                     Some(m) => self.unset_mode(m),
                     None => unhandled!(),
                 }
             }
-            'M' => self.delete_lines(args_or(0, 1) as _),
-            'X' => self.erase_chars(args_or(0, 1) as _),
-            'P' => self.delete_chars(args_or(0, 1) as _),
-            'd' => self.goto_y(args_or(0, 1) as usize - 1),
+            'M' => self.delete_lines(count(0)),
+            'X' => self.erase_chars(count(0)),
+            'P' => self.delete_chars(count(0)),
+            'd' => {
+                let x = self.cur.x;
+                self.goto_origin(x, pos(0));
+            }
             'h' => {
-                let mode = ModeInt::from_primitive(private, args_or(0, 0));
+                let mode = ModeInt::from_primitive(private, args_or(0, 0))
+                    .filter(|m| self.term.is_xterm() || !m.is_xterm_ext());
                 trace!(self.logger, "mode {:?}", mode);
                 match mode {
                     Some(m) => self.set_mode(m),
@@ -498,13 +1093,17 @@ impl Perform for TermData {
                 if private {
                     unhandled!();
                 }
-                let top = args_or(0, 1) as usize - 1;
-                let bottom = args_or(1, self.height as _) as usize;
-                self.scroll_range = LineRange(top, bottom);
+                let top = pos(0);
+                let bottom = match args_or(1, 0) {
+                    0 => self.height,
+                    b => b as usize,
+                };
+                self.set_scroll_range(top, bottom);
             }
+            'm' => self.set_graphic_rendition(args),
             's' => self.save_cursor(),
             'u' => self.restore_cursor(),
-            _ => {}
+            _ => unhandled!(),
         }
     }
     fn esc_dispatch(&mut self, params: &[i64], intermediates: &[u8], _ignore: bool, byte: u8) {
@@ -512,6 +1111,11 @@ impl Perform for TermData {
             () => {{
                 warn!(self.logger, "[unhandled! (ESC)]  params={:?}, ints={:?}, byte={:?} ({:x})",
                        params, intermediates, byte as char, byte);
+                self.unhandled.push(UnhandledSeq::Esc {
+                    byte: byte,
+                    params: params.to_owned(),
+                    intermediates: intermediates.to_owned(),
+                });
                 return;
             }}
         }
@@ -524,13 +1128,23 @@ impl Perform for TermData {
             byte
         );
         match byte {
-            b'D' => self.add_y(1),
+            b'D' => self.linefeed(),
             b'E' => {
-                self.add_y(1);
-                self.goto_x(0);
+                self.linefeed();
+                self.carriage_return();
             }
+            b'H' => self.set_tab(),
+            b'c' => self.full_reset(),
             b'M' => self.reverse_index(),
             b'7' => self.save_cursor(),
+            b'3'..=b'6' if intermediates.first() == Some(&b'#') => {
+                self.line_attrs[self.cur.y] = match byte {
+                    b'3' => LineAttr::DoubleHeightTop,
+                    b'4' => LineAttr::DoubleHeightBottom,
+                    b'5' => LineAttr::Normal,
+                    _ => LineAttr::DoubleWidth,
+                };
+            }
             b'8' => {
                 if !intermediates.is_empty() && intermediates[0] == b'#' {
                     self.dectest();
@@ -550,10 +1164,27 @@ impl Perform for TermData {
         debug!(
             self.logger,
             "[ignored! (osc_dispatch)]: {}",
-            str::from_utf8(params[0]).unwrap()
+            String::from_utf8_lossy(params[0])
         );
+        self.unhandled
+            .push(UnhandledSeq::Osc(params.iter().map(|p| p.to_vec()).collect()));
     }
     fn hook(&mut self, params: &[i64], intermediates: &[u8], ignore: bool) {
+        // vte doesn't give the final byte, so we assume 'q' of DECRQSS(DCS $ q)
+        // and XTGETTCAP(DCS + q), which are what programs send.
+        // Neither has parameters, unlike DECRSPS(DCS 1 $ t) or DECRSTS(DCS 1 $ p),
+        // though vte drops the last parameter of DCS and so they are told apart by
+        // the payload in unhook.
+        let query = match intermediates {
+            _ if ignore || !params.is_empty() || !self.term.is_xterm() => None,
+            b"$" => Some(DcsQuery::Status),
+            b"+" => Some(DcsQuery::Termcap),
+            _ => None,
+        };
+        if let Some(q) = query {
+            self.dcs = Some((q, Vec::new()));
+            return;
+        }
         debug!(
             self.logger,
             "[unhandled! (hook)] params={:?}, ints: {:?}, ignore: {:?}",
@@ -561,29 +1192,177 @@ impl Perform for TermData {
             intermediates,
             ignore
         );
+        self.unhandled.push(UnhandledSeq::Dcs {
+            params: params.to_owned(),
+            intermediates: intermediates.to_owned(),
+        });
     }
     fn put(&mut self, byte: u8) {
-        debug!(self.logger, "[unhandled! (put)] byte={:?}", byte);
+        match self.dcs {
+            Some((_, ref mut payload)) => if payload.len() < MAX_DCS_LEN {
+                payload.push(byte);
+            },
+            None => debug!(self.logger, "[unhandled! (put)] byte={:?}", byte),
+        }
     }
     fn unhook(&mut self) {
-        debug!(self.logger, "[unhandled! (unhook)]");
+        match self.dcs.take() {
+            Some((DcsQuery::Status, ref payload)) if is_setting_name(payload) => {
+                self.request_status(payload)
+            }
+            Some((DcsQuery::Status, payload)) => {
+                debug!(self.logger, "[unhandled! (unhook)] DCS $ {:?}", payload);
+                self.unhandled.push(UnhandledSeq::Dcs {
+                    params: Vec::new(),
+                    intermediates: b"$".to_vec(),
+                });
+            }
+            Some((DcsQuery::Termcap, payload)) => self.request_termcap(&payload),
+            None => debug!(self.logger, "[unhandled! (unhook)]"),
+        }
     }
 }
 
+// DCS sequences we reply to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DcsQuery {
+    // DECRQSS
+    Status,
+    // XTGETTCAP
+    Termcap,
+}
+
+// longer payloads of DCS queries are truncated
+const MAX_DCS_LEN: usize = 1024;
+
+// DECRQSS names a setting by intermediates and the final byte of the control
+// function(e.g. "m" for SGR, " q" for DECSCUSR)
+fn is_setting_name(payload: &[u8]) -> bool {
+    match payload.split_last() {
+        Some((&last, ints)) => {
+            (0x40..0x7f).contains(&last) && ints.iter().all(|b| (0x20..0x30).contains(b))
+        }
+        None => false,
+    }
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+// invalid digits are decoded as 0
+fn hex_decode(hex: &[u8]) -> Vec<u8> {
+    let digit = |b: u8| (b as char).to_digit(16).unwrap_or(0) as u8;
+    hex.chunks(2)
+        .map(|c| c.iter().fold(0, |acc, &b| acc * 16 + digit(b)))
+        .collect()
+}
+
+/// Control sequence which the emulator doesn't handle.
+///
+/// You can receive them by ```Reactor::unhandled```.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum UnhandledSeq {
+    /// C0 or C1 control character
+    Execute(u8),
+    /// CSI sequence
+    Csi {
+        action: char,
+        args: Vec<i64>,
+        intermediates: Vec<u8>,
+    },
+    /// Escape sequence
+    Esc {
+        byte: u8,
+        params: Vec<i64>,
+        intermediates: Vec<u8>,
+    },
+    /// Operating System Command
+    Osc(Vec<Vec<u8>>),
+    /// Device Control String
+    Dcs {
+        params: Vec<i64>,
+        intermediates: Vec<u8>,
+    },
+}
+
+/// What the emulator did for the game output, in the order it happened.
+///
+/// You can receive them by ```Reactor::on_terminal_event``` or
+/// ```Emulator::take_events```, to follow how the screen is drawn rather than
+/// only comparing screens. Positions are ```(y, x)``` after the event happened,
+/// except ```Print```. Inserting or deleting characters in a line and DECALN
+/// aren't reported.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TerminalEvent {
+    /// Characters printed in a row on line ```y```, from column ```x```
+    Print { y: usize, x: usize, text: Vec<u8> },
+    /// Cells are erased around the cursor at ```(y, x)```
+    Clear { kind: ClearKind, y: usize, x: usize },
+    /// Lines ```top..bottom``` are scrolled up by ```lines```, or down if negative.
+    ///
+    /// Inserting or deleting lines is reported as scrolling lines below the cursor.
+    Scroll {
+        top: usize,
+        bottom: usize,
+        lines: isize,
+    },
+    /// The cursor is moved to ```(y, x)``` by control characters or sequences
+    CursorMove { y: usize, x: usize },
+    /// Terminal modes are changed to this
+    ModeChange(TermMode),
+}
+
+/// Which cells are erased by ```TerminalEvent::Clear```.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClearKind {
+    /// Whole screen(ED 2, or reset by RIS)
+    Screen,
+    /// From the top of the screen to the cursor(ED 1)
+    Above,
+    /// From the cursor to the bottom of the screen(ED 0)
+    Below,
+    /// Lines kept by ```GameSetting::scrollback```(ED 3)
+    Scrollback,
+    /// Line of the cursor(EL 2)
+    Line,
+    /// From the start of the line to the cursor(EL 1)
+    LineLeft,
+    /// From the cursor to the end of the line(EL 0)
+    LineRight,
+    /// This number of characters from the cursor(ECH)
+    Chars(usize),
+}
+
 // below, from awesome https://github.com/jwilm/alacritty. Many thanks!
 bitflags! {
+    /// Terminal modes set by the game, which the AI may need to encode keys
+    /// correctly or to guess the state of the game.
+    /// See ```Reactor::on_term_mode```.
     pub struct TermMode: u16 {
+        /// Cursor is visible(DECTCEM)
         const SHOW_CURSOR         = 0b000000000001;
+        /// Application cursor keys(DECCKM), see ```CursorKeyMode```
         const APP_CURSOR          = 0b000000000010;
+        /// Application keypad(DECKPAM)
         const APP_KEYPAD          = 0b000000000100;
+        /// Mouse clicks are reported
         const MOUSE_REPORT_CLICK  = 0b000000001000;
+        /// Bracketed paste, see ```wrap_paste```
         const BRACKETED_PASTE     = 0b000000010000;
+        /// Mouse events are reported in SGR format
         const SGR_MOUSE           = 0b000000100000;
+        /// Mouse motions are reported
         const MOUSE_MOTION        = 0b000001000000;
+        /// Auto wrap(DECAWM)
         const LINE_WRAP           = 0b000010000000;
+        /// Line feed also returns the cursor(LNM)
         const LINE_FEED_NEW_LINE  = 0b000100000000;
+        /// Cursor is relative to the scroll region(DECOM)
         const ORIGIN              = 0b001000000000;
+        /// Insert mode(IRM)
         const INSERT              = 0b010000000000;
+        /// Focus in/out events are reported
         const FOCUS_IN_OUT        = 0b100000000000;
         const ANY                 = 0b111111111111;
         const NONE                = 0;
@@ -677,6 +1456,18 @@ impl ModeInt {
             })
         }
     }
+    // modes not supported by vt100
+    fn is_xterm_ext(&self) -> bool {
+        match *self {
+            ModeInt::ReportMouseClicks
+            | ModeInt::ReportMouseMotion
+            | ModeInt::ReportFocusInOut
+            | ModeInt::SgrMouse
+            | ModeInt::SwapScreenAndSetRestoreCursor
+            | ModeInt::BracketedPaste => true,
+            _ => false,
+        }
+    }
 }
 
 /// Mode for clearing line
@@ -692,6 +1483,15 @@ enum LineClearMode {
     All,
 }
 
+/// Mode for clearing tab stops
+#[derive(Debug, Clone, Copy)]
+enum TabClearMode {
+    /// Clear tab stop at cursor
+    Current,
+    /// Clear all tab stops
+    All,
+}
+
 /// Mode for clearing terminal
 ///
 /// Relative to cursor
@@ -854,7 +1654,7 @@ mod C1 {
 mod test {
     use super::*;
     use std::io::{BufRead, BufReader};
-    use str::from_utf8;
+    use std::str::from_utf8;
     const MAP1: &str = "
         ----------------
         |.....@........|
@@ -931,6 +1731,32 @@ mod test {
         assert_eq!(initial.buf, str_to_buf(&MAP2));
     }
     #[test]
+    fn test_scrollback() {
+        let mut term = TermData::with_size(2, 3, b' ');
+        term.set_scrollback(3);
+        let mut parser = Parser::new();
+        for c in b"a\r\nb\r\nc\r\nd" {
+            parser.advance(&mut term, *c);
+        }
+        assert_eq!(term.take_scrolled(), vec![b"a  ".to_vec(), b"b  ".to_vec()]);
+        assert!(term.take_scrolled().is_empty());
+        for c in b"\r\ne\r\nf" {
+            parser.advance(&mut term, *c);
+        }
+        assert_eq!(term.take_scrolled(), vec![b"c  ".to_vec(), b"d  ".to_vec()]);
+        assert_eq!(term.scrollback(), vec![b"b  ".to_vec(), b"c  ".to_vec(), b"d  ".to_vec()]);
+        // deleted lines aren't saved
+        for c in b"\x1b[1;1H\x1b[M" {
+            parser.advance(&mut term, *c);
+        }
+        assert!(term.take_scrolled().is_empty());
+        for c in b"\x1b[3J" {
+            parser.advance(&mut term, *c);
+        }
+        assert!(term.scrollback().is_empty());
+        assert_eq!(term.ret_screen()[0], b"f  ".to_vec());
+    }
+    #[test]
     fn test_scroll_down() {
         let mut initial = TermData::from_buf(str_to_buf(MAP1));
         initial.scroll_down(2);
@@ -980,6 +1806,294 @@ mod test {
         println!("\n{}", &buf_to_str(&initial.buf));
         assert_eq!(initial.buf, str_to_buf(&MAP8));
     }
+    #[test]
+    fn test_unhandled() {
+        use vte::Parser;
+        let mut term = TermData::from_buf(str_to_buf(MAP1));
+        let mut parser = Parser::new();
+        for c in b"\x1b[5y\x1b]0;title\x07\x1b[2J" {
+            parser.advance(&mut term, *c);
+        }
+        let unhandled = term.take_unhandled();
+        assert_eq!(
+            unhandled,
+            vec![
+                UnhandledSeq::Csi {
+                    action: 'y',
+                    args: vec![5],
+                    intermediates: vec![],
+                },
+                UnhandledSeq::Osc(vec![b"0".to_vec(), b"title".to_vec()]),
+            ]
+        );
+        assert!(term.take_unhandled().is_empty());
+    }
+    #[test]
+    fn test_events() {
+        let mut term = TermData::with_size(3, 6, b' ');
+        let mut parser = Parser::new();
+        for c in b"ab" {
+            parser.advance(&mut term, *c);
+        }
+        assert!(term.take_events().is_empty());
+        term.set_record_events(true);
+        for c in b"\x1b[?25lcd\x1b[3;5Hxyz\x1b[1K\nw\x1b[2J" {
+            parser.advance(&mut term, *c);
+        }
+        assert_eq!(
+            term.take_events(),
+            vec![
+                TerminalEvent::ModeChange(TermMode::LINE_WRAP),
+                TerminalEvent::Print { y: 0, x: 2, text: b"cd".to_vec() },
+                TerminalEvent::CursorMove { y: 2, x: 4 },
+                TerminalEvent::Print { y: 2, x: 4, text: b"xy".to_vec() },
+                TerminalEvent::Scroll { top: 0, bottom: 3, lines: 1 },
+                TerminalEvent::Print { y: 2, x: 0, text: b"z".to_vec() },
+                TerminalEvent::Clear { kind: ClearKind::LineLeft, y: 2, x: 1 },
+                TerminalEvent::Scroll { top: 0, bottom: 3, lines: 1 },
+                TerminalEvent::Print { y: 2, x: 1, text: b"w".to_vec() },
+                TerminalEvent::Clear { kind: ClearKind::Screen, y: 2, x: 2 },
+            ]
+        );
+        assert_eq!(term.buf[2], b"      ".to_vec());
+        assert!(term.take_events().is_empty());
+    }
+    #[test]
+    #[should_panic(expected = "screen size must be positive")]
+    fn test_zero_size() {
+        ::emulator::Emulator::new(0, 80);
+    }
+    #[test]
+    #[should_panic(expected = "screen size must be positive")]
+    fn test_resize_to_zero() {
+        ::emulator::Emulator::new(24, 80).resize(24, 0);
+    }
+    #[test]
+    fn test_resize() {
+        let mut term = TermData::with_size(6, 10, b' ');
+        let mut parser = Parser::new();
+        for c in b"\x1b[2;6r\x1b[6;10H\x1b7" {
+            parser.advance(&mut term, *c);
+        }
+        term.resize(3, 4);
+        assert_eq!(term.scroll_range.1, 3);
+        for c in b"\x1b8X\n" {
+            parser.advance(&mut term, *c);
+        }
+        assert_eq!(term.buf, vec![b"    ".to_vec(), b"   X".to_vec(), b"    ".to_vec()]);
+        term.resize(3, 20);
+        for c in b"\r\t\tZ" {
+            parser.advance(&mut term, *c);
+        }
+        assert_eq!(term.buf[2][16], b'Z');
+    }
+    // bytes of CSI sequence, with parameters from 0 to very large ones
+    fn csi_bytes(action: u8, params: &[u32]) -> Vec<u8> {
+        const ACTIONS: &[u8] = b"@ABCDEFGHIJKLMPSTXZ`abdefghlrsu";
+        let params: Vec<String> = params
+            .iter()
+            .map(|&p| match p % 4 {
+                0 => 0,
+                1 => p,
+                2 => p.saturating_mul(1000),
+                _ => u32::max_value(),
+            })
+            .map(|p| p.to_string())
+            .collect();
+        let mut res = b"\x1b[".to_vec();
+        res.extend_from_slice(params.join(";").as_bytes());
+        res.push(ACTIONS[action as usize % ACTIONS.len()]);
+        res
+    }
+    quickcheck! {
+        fn prop_cursor_in_range(seqs: Vec<(u8, Vec<u32>)>, text: Vec<u8>) -> bool {
+            let mut term = TermData::with_size(5, 7, b' ');
+            let mut parser = Parser::new();
+            // CSI sequences and arbitrary bytes, alternately
+            for (i, &(action, ref params)) in seqs.iter().enumerate() {
+                for c in csi_bytes(action, params).iter().chain(text.get(i)) {
+                    parser.advance(&mut term, *c);
+                }
+            }
+            for c in text.iter().skip(seqs.len()) {
+                parser.advance(&mut term, *c);
+            }
+            let LineRange(top, bottom) = term.scroll_range;
+            term.buf.len() == 5 && term.buf.iter().all(|l| l.len() == 7)
+                && term.cur.y < 5 && term.cur.x < 7
+                && term.saved_cur.y < 5 && term.saved_cur.x < 7
+                && top < bottom && bottom <= 5 && term.tabs.len() == 7
+                && term.line_attrs.len() == 5
+                && term.reverse.len() == 5 && term.reverse.iter().all(|l| l.len() == 7)
+        }
+    }
+    #[test]
+    fn test_line_attrs() {
+        let mut term = TermData::with_size(4, 4, b' ');
+        let mut parser = Parser::new();
+        for c in b"\x1b#3A\r\n\x1b#4A\r\n\x1b#6B\x1b#5\x1b#6" {
+            parser.advance(&mut term, *c);
+        }
+        let screen = term.ret_screen();
+        assert_eq!(screen.line_attr(0), LineAttr::DoubleHeightTop);
+        assert_eq!(screen.line_attr(1), LineAttr::DoubleHeightBottom);
+        assert_eq!(screen.line_attr(2), LineAttr::DoubleWidth);
+        assert_eq!(screen.line_columns(2), 2);
+        assert_eq!(screen.line_attr(3), LineAttr::Normal);
+        // attributes move with lines
+        for c in b"\x1b[1;1H\x1b[M" {
+            parser.advance(&mut term, *c);
+        }
+        assert_eq!(term.line_attrs[0], LineAttr::DoubleHeightBottom);
+        assert_eq!(term.line_attrs[3], LineAttr::Normal);
+        for c in b"\x1b[2J" {
+            parser.advance(&mut term, *c);
+        }
+        assert!(term.line_attrs.iter().all(|a| *a == LineAttr::Normal));
+    }
+    #[test]
+    fn test_reverse() {
+        let mut term = TermData::with_size(3, 4, b' ');
+        let mut parser = Parser::new();
+        for c in b"a\x1b[7mbc\x1b[27md\x1b[1;7;38;5;7mef\x1b[0mg" {
+            parser.advance(&mut term, *c);
+        }
+        let screen = term.ret_screen();
+        let rev: Vec<_> = (0..4).map(|x| screen.is_reverse(0, x)).collect();
+        assert_eq!(rev, vec![false, true, true, false]);
+        assert!(screen.is_reverse(1, 0));
+        assert!(screen.is_reverse(1, 1));
+        assert!(!screen.is_reverse(1, 2));
+        // erased and shifted cells
+        for c in b"\x1b[1;2H\x1b[P\x1b[2;1H\x1b[K" {
+            parser.advance(&mut term, *c);
+        }
+        assert_eq!(term.reverse[0], vec![false, true, false, false]);
+        assert_eq!(term.reverse[1], vec![false; 4]);
+    }
+    #[test]
+    fn test_huge_params() {
+        let mut term = TermData::with_size(3, 4, b' ');
+        let mut parser = Parser::new();
+        for c in b"\x1b[0;0Ha\x1b[9999C\x1b[0@\x1b[99999999999P\x1b[9999Bb\x1b[9999S" {
+            parser.advance(&mut term, *c);
+        }
+        assert_eq!(term.buf, vec![b"    ".to_vec(); 3]);
+        assert_eq!((term.cur.x, term.cur.y), (3, 2));
+    }
+    #[test]
+    fn test_c1_in_utf8() {
+        let mut term = TermData::with_size(2, 4, b' ');
+        let mut parser = Parser::new();
+        // U+00DB is encoded as c3 9b, which contains 8-bit CSI
+        for c in b"\xc3\x9bA\x9b2;2HB" {
+            parser.advance(&mut term, *c);
+        }
+        assert_eq!(term.buf, vec![b"\xdbA  ".to_vec(), b" B  ".to_vec()]);
+    }
+    #[test]
+    fn test_blank() {
+        use vte::Parser;
+        let mut term = TermData::with_size(2, 4, b'\0');
+        let mut parser = Parser::new();
+        for c in b"a b\x1b[2;2Hc\x1b[1;2H\x1b[K" {
+            parser.advance(&mut term, *c);
+        }
+        assert_eq!(term.buf, vec![b"a\0\0\0".to_vec(), b"\0c\0\0".to_vec()]);
+    }
+    #[test]
+    fn test_cursor_key_mode() {
+        use vte::Parser;
+        let mut term = TermData::with_size(2, 4, b' ');
+        let mut parser = Parser::new();
+        assert_eq!(term.cursor_key_mode(), CursorKeyMode::Normal);
+        for c in b"\x1b[?1h" {
+            parser.advance(&mut term, *c);
+        }
+        assert_eq!(term.cursor_key_mode(), CursorKeyMode::Application);
+    }
+    #[test]
+    fn test_mouse_mode() {
+        use vte::Parser;
+        let mut term = TermData::with_size(2, 4, b' ');
+        let mut parser = Parser::new();
+        assert_eq!(term.mouse_mode(), MouseMode::default());
+        for c in b"\x1b[?1000h\x1b[?1006h" {
+            parser.advance(&mut term, *c);
+        }
+        let mode = MouseMode {
+            click: true,
+            motion: false,
+            sgr: true,
+        };
+        assert_eq!(term.mouse_mode(), mode);
+    }
+    #[test]
+    fn test_bracketed_paste() {
+        use vte::Parser;
+        let mut term = TermData::with_size(2, 4, b' ');
+        let mut parser = Parser::new();
+        for c in b"\x1b[?2004h" {
+            parser.advance(&mut term, *c);
+        }
+        assert!(term.bracketed_paste());
+        for c in b"\x1b[?2004l" {
+            parser.advance(&mut term, *c);
+        }
+        assert!(!term.bracketed_paste());
+    }
+    #[test]
+    fn test_dcs_queries() {
+        let mut term = TermData::with_size(4, 4, b' ');
+        let mut parser = Parser::new();
+        for c in b"\x1b[2;3r\x1b[7m\x1bP$qm\x1b\\\x1bP$qr\x1b\\\x1bP$q q\x1b\\" {
+            parser.advance(&mut term, *c);
+        }
+        assert_eq!(
+            term.take_responses(),
+            b"\x1bP1$r0;7m\x1b\\\x1bP1$r2;3r\x1b\\\x1bP0$r\x1b\\".to_vec()
+        );
+        // "TN" and "Co"
+        for c in b"\x1bP+q544E;436F\x1b\\" {
+            parser.advance(&mut term, *c);
+        }
+        assert_eq!(
+            term.take_responses(),
+            b"\x1bP1+r544E=787465726D\x1b\\\x1bP0+r436F\x1b\\".to_vec()
+        );
+        assert!(term.take_responses().is_empty());
+        assert!(term.take_unhandled().is_empty());
+        // DECRSPS and DECRSTS aren't queries
+        for c in b"\x1bP1$t1;1;1;@;@;@;0;2;@;BBBB\x1b\\\x1bP1$p0;1;2\x1b\\" {
+            parser.advance(&mut term, *c);
+        }
+        assert!(term.take_responses().is_empty());
+        assert_eq!(term.take_unhandled().len(), 2);
+        term.hook(&[], b"$", true);
+        term.put(b'm');
+        term.unhook();
+        assert!(term.take_responses().is_empty());
+        assert_eq!(term.take_unhandled().len(), 1);
+        term.set_term(TermType::Vt100);
+        for c in b"\x1bP$qm\x1b\\" {
+            parser.advance(&mut term, *c);
+        }
+        assert!(term.take_responses().is_empty());
+        assert_eq!(term.take_unhandled().len(), 1);
+    }
+    #[test]
+    fn test_vt100_ignores_xterm_modes() {
+        use vte::Parser;
+        let mut term = TermData::with_size(2, 4, b' ');
+        term.set_term(TermType::Vt100);
+        let mut parser = Parser::new();
+        for c in b"\x1b[?2004h\x1b[?1h" {
+            parser.advance(&mut term, *c);
+        }
+        assert!(!term.bracketed_paste());
+        assert_eq!(term.cursor_key_mode(), CursorKeyMode::Application);
+        assert_eq!(term.take_unhandled().len(), 1);
+    }
     fn buf_to_str(buf: &Vec<Vec<u8>>) -> String {
         let mut res = String::new();
         let len = buf.len();
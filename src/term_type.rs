@@ -0,0 +1,42 @@
+/// Terminal type of the game, which decides both ```TERM``` of the game and
+/// the sequences the emulator accepts.
+///
+/// With ```Vt100```, xterm extensions(mouse reporting, bracketed paste, alternate
+/// screen, replies to DCS queries, etc.) are ignored and reported as ```UnhandledSeq```,
+/// like a real vt100.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TermType {
+    Vt100,
+    Xterm,
+    Xterm256Color,
+}
+
+impl TermType {
+    /// Returns the value of ```TERM```.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            TermType::Vt100 => "vt100",
+            TermType::Xterm => "xterm",
+            TermType::Xterm256Color => "xterm-256color",
+        }
+    }
+    /// Parse the value of ```TERM```.
+    pub fn from_name(name: &str) -> Option<TermType> {
+        match name {
+            "vt100" => Some(TermType::Vt100),
+            "xterm" => Some(TermType::Xterm),
+            "xterm-256color" => Some(TermType::Xterm256Color),
+            _ => None,
+        }
+    }
+    /// Returns true if it supports xterm extensions.
+    pub fn is_xterm(&self) -> bool {
+        *self != TermType::Vt100
+    }
+}
+
+impl Default for TermType {
+    fn default() -> TermType {
+        TermType::Vt100
+    }
+}
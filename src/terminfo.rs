@@ -0,0 +1,83 @@
+//! Key encoding loaded from the terminfo database.
+use keys::Key;
+use term::terminfo::TermInfo;
+use term_type::TermType;
+use std::collections::HashMap;
+use std::io;
+
+/// Special keys encoded as the terminfo entry of the game's ```TERM``` says.
+///
+/// Curses libraries decode input by the terminfo entry, so these bytes are
+/// what the game actually expects(e.g. ```\x1bOA``` for ```Key::Up``` on xterm).
+/// Keys without entry are encoded by ```Key::as_bytes```.
+#[derive(Clone, Debug, Default)]
+pub struct TermKeys {
+    map: HashMap<Key, Vec<u8>>,
+}
+
+impl TermKeys {
+    /// Load the terminfo entry of ```term```.
+    pub fn load(term: TermType) -> io::Result<TermKeys> {
+        TermKeys::from_name(term.name())
+    }
+    /// Load the terminfo entry named ```name```.
+    pub fn from_name(name: &str) -> io::Result<TermKeys> {
+        let info = TermInfo::from_name(name)
+            .map_err(|e| io::Error::new(io::ErrorKind::NotFound, e.to_string()))?;
+        let mut map = HashMap::new();
+        for &(key, cap) in CAPNAMES {
+            if let Some(bytes) = info.strings.get(cap) {
+                if !bytes.is_empty() {
+                    map.insert(key, bytes.clone());
+                }
+            }
+        }
+        Ok(TermKeys { map: map })
+    }
+    /// Returns the bytes sent to the game.
+    pub fn encode(&self, key: Key) -> &[u8] {
+        self.map
+            .get(&key)
+            .map(|v| v.as_slice())
+            .unwrap_or_else(|| key.as_bytes())
+    }
+}
+
+const CAPNAMES: &[(Key, &str)] = &[
+    (Key::Backspace, "kbs"),
+    (Key::Up, "kcuu1"),
+    (Key::Down, "kcud1"),
+    (Key::Right, "kcuf1"),
+    (Key::Left, "kcub1"),
+    (Key::Home, "khome"),
+    (Key::End, "kend"),
+    (Key::Insert, "kich1"),
+    (Key::Delete, "kdch1"),
+    (Key::PageUp, "kpp"),
+    (Key::PageDown, "knp"),
+    (Key::F1, "kf1"),
+    (Key::F2, "kf2"),
+    (Key::F3, "kf3"),
+    (Key::F4, "kf4"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_xterm_keys() {
+        let keys = match TermKeys::from_name("xterm") {
+            Ok(k) => k,
+            // no terminfo database
+            Err(_) => return,
+        };
+        assert_eq!(keys.encode(Key::Up), b"\x1bOA");
+        assert_eq!(keys.encode(Key::F1), b"\x1bOP");
+        assert_eq!(keys.encode(Key::Enter), b"\r");
+    }
+    #[test]
+    fn test_unknown_term() {
+        assert!(TermKeys::from_name("no-such-terminal").is_err());
+        assert_eq!(TermKeys::default().encode(Key::Up), b"\x1b[A");
+    }
+}
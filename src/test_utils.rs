@@ -0,0 +1,23 @@
+// Reactors shared by tests which play games
+use super::{ActionResult, Reactor};
+
+/// Does nothing.
+pub struct EmptyAI;
+impl Reactor for EmptyAI {
+    fn action(&mut self, _screen: ActionResult, _turn: usize) -> Option<Vec<u8>> {
+        None
+    }
+}
+
+/// Records the first line of each changed screen, and does nothing.
+pub struct RecordAI {
+    pub lines: Vec<String>,
+}
+impl Reactor for RecordAI {
+    fn action(&mut self, screen: ActionResult, _turn: usize) -> Option<Vec<u8>> {
+        if let ActionResult::Changed(screen) = screen {
+            self.lines.push(screen.trimmed_line(0).into_owned());
+        }
+        None
+    }
+}
@@ -0,0 +1,197 @@
+use std::io::{self, Read};
+#[cfg(any(test, feature = "process"))]
+use std::io::Write;
+use std::time::Duration;
+#[cfg(any(test, feature = "process"))]
+use std::time::Instant;
+
+const MAGIC: &[u8; 5] = b"CGWT\x01";
+
+/// Direction of bytes recorded in transcript.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum IoKind {
+    /// Bytes read from the game
+    Read,
+    /// Bytes written to the game
+    Write,
+}
+
+impl IoKind {
+    #[cfg(any(test, feature = "process"))]
+    fn to_byte(self) -> u8 {
+        match self {
+            IoKind::Read => b'<',
+            IoKind::Write => b'>',
+        }
+    }
+    fn from_byte(b: u8) -> Option<IoKind> {
+        match b {
+            b'<' => Some(IoKind::Read),
+            b'>' => Some(IoKind::Write),
+            _ => None,
+        }
+    }
+}
+
+/// One chunk of I/O in transcript.
+/// ```time``` is elapsed time from the start of the game.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TranscriptRecord {
+    pub time: Duration,
+    pub kind: IoKind,
+    pub bytes: Vec<u8>,
+}
+
+// Each record is written as
+// kind(1 byte) | elapsed micro secs(u64 LE) | length(u32 LE) | bytes
+#[cfg(any(test, feature = "process"))]
+pub struct TranscriptWriter<W: Write> {
+    inner: W,
+    start: Instant,
+}
+
+#[cfg(any(test, feature = "process"))]
+impl<W: Write> TranscriptWriter<W> {
+    pub fn new(mut inner: W) -> io::Result<TranscriptWriter<W>> {
+        inner.write_all(MAGIC)?;
+        Ok(TranscriptWriter {
+            inner: inner,
+            start: Instant::now(),
+        })
+    }
+    pub fn record(&mut self, kind: IoKind, bytes: &[u8]) -> io::Result<()> {
+        let elapsed = self.start.elapsed();
+        let micros = elapsed.as_secs() * 1_000_000 + u64::from(elapsed.subsec_nanos() / 1000);
+        self.inner.write_all(&[kind.to_byte()])?;
+        self.inner.write_all(&u64_to_le(micros))?;
+        self.inner.write_all(&u32_to_le(bytes.len() as u32))?;
+        self.inner.write_all(bytes)
+    }
+}
+
+/// Read all records from transcript made by ```GameSetting::transcript_file```.
+pub fn read_transcript<R: Read>(mut reader: R) -> io::Result<Vec<TranscriptRecord>> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_owned());
+    let mut magic = [0u8; 5];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid("not a transcript file"));
+    }
+    let mut res = Vec::new();
+    loop {
+        let mut kind = [0u8; 1];
+        if reader.read(&mut kind)? == 0 {
+            break;
+        }
+        let kind = IoKind::from_byte(kind[0]).ok_or_else(|| invalid("invalid record kind"))?;
+        let mut micros = [0u8; 8];
+        reader.read_exact(&mut micros)?;
+        let micros = le_to_u64(&micros);
+        let mut len = [0u8; 4];
+        reader.read_exact(&mut len)?;
+        let mut bytes = vec![0u8; le_to_u64(&len) as usize];
+        reader.read_exact(&mut bytes)?;
+        res.push(TranscriptRecord {
+            time: Duration::new(micros / 1_000_000, (micros % 1_000_000) as u32 * 1000),
+            kind: kind,
+            bytes: bytes,
+        });
+    }
+    Ok(res)
+}
+
+/// Read all records from ttyrec file(e.g. recorded by ```ttyrec``` or NAO),
+/// as ```IoKind::Read``` records.
+/// ```time``` is elapsed time from the first record.
+pub fn read_ttyrec<R: Read>(mut reader: R) -> io::Result<Vec<TranscriptRecord>> {
+    let mut res = Vec::new();
+    let mut start = None;
+    // sec(u32 LE) | usec(u32 LE) | length(u32 LE) | bytes
+    let mut header = [0u8; 12];
+    loop {
+        match reader.read(&mut header[..1])? {
+            0 => break,
+            _ => reader.read_exact(&mut header[1..])?,
+        }
+        let micros = le_to_u64(&header[..4]) * 1_000_000 + le_to_u64(&header[4..8]);
+        let start = *start.get_or_insert(micros);
+        let micros = micros.saturating_sub(start);
+        let mut bytes = vec![0u8; le_to_u64(&header[8..]) as usize];
+        reader.read_exact(&mut bytes)?;
+        res.push(TranscriptRecord {
+            time: Duration::new(micros / 1_000_000, (micros % 1_000_000) as u32 * 1000),
+            kind: IoKind::Read,
+            bytes: bytes,
+        });
+    }
+    Ok(res)
+}
+
+#[cfg(any(test, feature = "process"))]
+fn u64_to_le(u: u64) -> [u8; 8] {
+    let mut res = [0u8; 8];
+    for (i, b) in res.iter_mut().enumerate() {
+        *b = (u >> (i * 8)) as u8;
+    }
+    res
+}
+
+#[cfg(any(test, feature = "process"))]
+fn u32_to_le(u: u32) -> [u8; 4] {
+    let mut res = [0u8; 4];
+    for (i, b) in res.iter_mut().enumerate() {
+        *b = (u >> (i * 8)) as u8;
+    }
+    res
+}
+
+fn le_to_u64(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .enumerate()
+        .fold(0, |acc, (i, b)| acc | (u64::from(*b) << (i * 8)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_roundtrip() {
+        let mut buf = Vec::new();
+        {
+            let mut writer = TranscriptWriter::new(&mut buf).unwrap();
+            writer.record(IoKind::Read, b"\x1b[2Jhello").unwrap();
+            writer.record(IoKind::Write, b"j").unwrap();
+            writer.record(IoKind::Read, b"").unwrap();
+        }
+        let records = read_transcript(&buf[..]).unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].kind, IoKind::Read);
+        assert_eq!(records[0].bytes, b"\x1b[2Jhello".to_vec());
+        assert_eq!(records[1].kind, IoKind::Write);
+        assert_eq!(records[1].bytes, b"j".to_vec());
+        assert!(records[1].time >= records[0].time);
+        assert!(records[2].bytes.is_empty());
+    }
+    #[test]
+    fn test_ttyrec() {
+        let mut buf = Vec::new();
+        for &(sec, usec, bytes) in &[(10u32, 900_000u32, &b"ab"[..]), (12, 100_000, b"c")] {
+            buf.extend_from_slice(&u32_to_le(sec));
+            buf.extend_from_slice(&u32_to_le(usec));
+            buf.extend_from_slice(&u32_to_le(bytes.len() as u32));
+            buf.extend_from_slice(bytes);
+        }
+        let records = read_ttyrec(&buf[..]).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].time, Duration::from_secs(0));
+        assert_eq!(records[1].time, Duration::from_millis(1200));
+        assert_eq!(records[1].kind, IoKind::Read);
+        assert_eq!(records[1].bytes, b"c".to_vec());
+        assert!(read_ttyrec(&buf[..buf.len() - 1]).is_err());
+    }
+    #[test]
+    fn test_invalid_magic() {
+        assert!(read_transcript(&b"hello"[..]).is_err());
+    }
+}
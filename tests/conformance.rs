@@ -0,0 +1,327 @@
+//! vt100 conformance tests, derived from screens of vttest.
+//!
+//! Each test feeds a control sequence to the emulator and checks the screen.
+//! Tests marked as ```#[ignore]``` are features not supported yet, so
+//! ```cargo test --test conformance -- --ignored``` shows what's missing.
+extern crate curses_game_wrapper as cgw;
+
+use cgw::Emulator;
+
+const LINES: usize = 8;
+const COLUMNS: usize = 20;
+
+fn check(input: &[u8], expected: &[&str]) {
+    assert_eq!(expected.len(), LINES, "expected screen must have {} lines", LINES);
+    let mut emu = Emulator::new(LINES, COLUMNS);
+    emu.feed(input);
+    let screen = emu.screen();
+    if let Some(diff) = cgw::screen_diff(&screen, expected) {
+        panic!(
+            "\n{}\n{}",
+            diff,
+            screen.to_ruled_string(Some(emu.cursor()))
+        );
+    }
+}
+
+macro_rules! conformance {
+    ($(#[$attr:meta])* $name:ident, $input:expr, [$($line:expr),* $(,)*]) => {
+        #[test]
+        $(#[$attr])*
+        fn $name() {
+            check($input, &[$($line),*]);
+        }
+    }
+}
+
+// vttest 1: cursor movements
+conformance!(
+    cursor_frame,
+    b"\x1b[2J\x1b[1;1H********************\
+      \x1b[8;1H********************\
+      \x1b[2;1H*\x1b[3;1H*\x1b[4;1H*\x1b[5;1H*\x1b[6;1H*\x1b[7;1H*\
+      \x1b[2;20H*\x1b[3;20H*\x1b[4;20H*\x1b[5;20H*\x1b[6;20H*\x1b[7;20H*",
+    [
+        "********************",
+        "*                  *",
+        "*                  *",
+        "*                  *",
+        "*                  *",
+        "*                  *",
+        "*                  *",
+        "********************",
+    ]
+);
+
+conformance!(
+    cursor_home_default,
+    b"\x1b[5;5H\x1b[HA\x1b[3;3H\x1b[fB",
+    ["B", "", "", "", "", "", "", ""]
+);
+
+conformance!(
+    cursor_position_zero_params,
+    b"\x1b[3;3H\x1b[;HA\x1b[3;3H\x1b[0;0HB",
+    ["B", "", "", "", "", "", "", ""]
+);
+
+conformance!(
+    cursor_relative,
+    b"\x1b[4;10HX\x1b[2AU\x1b[2DD\x1b[3B\x1b[2CR\x1b[4;1H\x1b[CL",
+    ["", "         DU", "", " L       X", "            R", "", "", ""]
+);
+
+conformance!(
+    cursor_column_and_line,
+    b"\x1b[3GA\x1b[5dB\x1b[2;7fC\x1b[GD",
+    ["  A", "D     C", "", "", "   B", "", "", ""]
+);
+
+conformance!(
+    next_and_previous_line,
+    b"\x1b[3;5HA\x1b[2EB\x1b[FC",
+    ["", "", "    A", "C", "B", "", "", ""]
+);
+
+conformance!(
+    save_restore_cursor,
+    b"\x1b[3;4H\x1b7\x1b[6;10HA\x1b8B\x1b[5;5H\x1b[sC\x1b[1;1H\x1b[uD",
+    ["", "", "   B", "", "    D", "         A", "", ""]
+);
+
+// vttest 1: autowrap
+conformance!(
+    autowrap,
+    b"\x1b[2;1Habcdefghijklmnopqrstuvwxyz",
+    ["", "abcdefghijklmnopqrst", "uvwxyz", "", "", "", "", ""]
+);
+
+conformance!(
+    no_autowrap,
+    b"\x1b[?7l\x1b[2;1Habcdefghijklmnopqrstuvwxyz",
+    ["", "abcdefghijklmnopqrsz", "", "", "", "", "", ""]
+);
+
+conformance!(
+    pending_wrap_cursor_left,
+    b"\x1b[1;1Habcdefghijklmnopqrst\x1b[DX",
+    ["abcdefghijklmnopqrXt", "", "", "", "", "", "", ""]
+);
+
+conformance!(
+    pending_wrap_after_sgr,
+    b"\x1b[1;20HA\x1b[mB",
+    ["                   A", "B", "", "", "", "", "", ""]
+);
+
+conformance!(
+    pending_wrap_cancelled_by_cup,
+    b"\x1b[1;20HA\x1b[3;1HB",
+    ["                   A", "", "B", "", "", "", "", ""]
+);
+
+// vttest 2: screen features
+conformance!(
+    screen_alignment,
+    b"\x1b#8",
+    [
+        "EEEEEEEEEEEEEEEEEEEE",
+        "EEEEEEEEEEEEEEEEEEEE",
+        "EEEEEEEEEEEEEEEEEEEE",
+        "EEEEEEEEEEEEEEEEEEEE",
+        "EEEEEEEEEEEEEEEEEEEE",
+        "EEEEEEEEEEEEEEEEEEEE",
+        "EEEEEEEEEEEEEEEEEEEE",
+        "EEEEEEEEEEEEEEEEEEEE",
+    ]
+);
+
+conformance!(
+    tab_stops,
+    b"a\tb\tc",
+    ["a       b       c", "", "", "", "", "", "", ""]
+);
+
+conformance!(
+    set_and_clear_tab_stops,
+    b"\x1b[3g\x1b[1;4H\x1bH\x1b[1;11H\x1bH\r\tA\tB\tC\x1b[1;4H\x1b[g\r\tD",
+    ["   A      D        C", "", "", "", "", "", "", ""]
+);
+
+conformance!(
+    cursor_tabulation,
+    b"\x1b[2IA\x1b[2ZB\x1b[3ZC\x1b[9ID",
+    ["C       B       A  D", "", "", "", "", "", "", ""]
+);
+
+conformance!(
+    scroll_region_linefeed,
+    b"\x1b[1;1H1\x1b[2;1H2\x1b[3;1H3\x1b[4;1H4\x1b[5;1H5\x1b[6;1H6\
+      \x1b[2;4r\x1b[4;1H\r\nA\r\nB",
+    ["1", "4", "A", "B", "5", "6", "", ""]
+);
+
+conformance!(
+    scroll_region_reverse_index,
+    b"\x1b[1;1H1\x1b[2;1H2\x1b[3;1H3\x1b[4;1H4\x1b[5;1H5\
+      \x1b[2;4r\x1b[2;1H\x1bMA",
+    ["1", "A", "2", "3", "5", "", "", ""]
+);
+
+conformance!(
+    index_scrolls_at_bottom,
+    b"\x1b[1;1H1\x1b[8;1H8\x1bDA",
+    ["", "", "", "", "", "", "8", " A"]
+);
+
+conformance!(
+    scroll_region_homes_cursor,
+    b"\x1b[5;5H\x1b[2;4rA",
+    ["A", "", "", "", "", "", "", ""]
+);
+
+conformance!(
+    scroll_region_invalid_is_ignored,
+    b"\x1b[5;5H\x1b[4;2rA\x1b[3;3rB",
+    ["", "", "", "", "    AB", "", "", ""]
+);
+
+conformance!(
+    scroll_region_bottom_clamped,
+    b"\x1b[1;1H1\x1b[2;99r\x1b[8;1H8\nX",
+    ["1", "", "", "", "", "", "8", " X"]
+);
+
+conformance!(
+    origin_mode,
+    b"\x1b[2;4r\x1b[?6h\x1b[1;1HA\x1b[9;3HB\x1b[?6l\x1b[1;1HC",
+    ["C", "A", "", "  B", "", "", "", ""]
+);
+
+conformance!(
+    origin_mode_scroll_region_homes_cursor,
+    b"\x1b[?6h\x1b[3;5rA\x1b[3;1HB\nC\x1bDD",
+    ["", "", "B", " C", "  D", "", "", ""]
+);
+
+conformance!(
+    next_line,
+    b"\x1b[2;5HA\x1bEB",
+    ["", "    A", "B", "", "", "", "", ""]
+);
+
+conformance!(
+    scroll_up_down,
+    b"\x1b[1;1H1\x1b[2;1H2\x1b[3;1H3\x1b[2S\x1b[8;1H8\x1b[1T",
+    ["", "3", "", "", "", "", "", ""]
+);
+
+conformance!(
+    column_mode_resets_margins,
+    b"\x1b[2;4rA\x1b[?3hB\x1b[4;1H\nC",
+    ["B", "", "", "", "C", "", "", ""]
+);
+
+conformance!(
+    full_reset,
+    b"\x1b[2;4r\x1b[?6h\x1b[3gA\x1bcB\tC\x1b[4;1H\nD",
+    ["B       C", "", "", "", "D", "", "", ""]
+);
+
+// vttest 2: erasing
+conformance!(
+    erase_in_line,
+    b"\x1b[1;1Habcdef\x1b[1;3H\x1b[K\
+      \x1b[2;1Habcdef\x1b[2;3H\x1b[1K\
+      \x1b[3;1Habcdef\x1b[3;3H\x1b[2K",
+    ["ab", "   def", "", "", "", "", "", ""]
+);
+
+conformance!(
+    erase_below,
+    b"\x1b[1;1Habc\x1b[2;1Habc\x1b[3;1Habc\x1b[2;2H\x1b[J",
+    ["abc", "a", "", "", "", "", "", ""]
+);
+
+conformance!(
+    erase_above,
+    b"\x1b[1;1Habc\x1b[2;1Habc\x1b[3;1Habc\x1b[2;2H\x1b[1J",
+    ["", "  c", "abc", "", "", "", "", ""]
+);
+
+conformance!(
+    erase_all,
+    b"\x1b[1;1Habc\x1b[2;1Habc\x1b[3;1Habc\x1b[2;2H\x1b[2JX",
+    ["", " X", "", "", "", "", "", ""]
+);
+
+conformance!(
+    erase_chars,
+    b"\x1b[1;1Habcdef\x1b[1;2H\x1b[3X",
+    ["a   ef", "", "", "", "", "", "", ""]
+);
+
+// vttest 8: VT102 features
+conformance!(
+    insert_chars,
+    b"\x1b[1;1Habcdef\x1b[1;3H\x1b[2@",
+    ["ab  cdef", "", "", "", "", "", "", ""]
+);
+
+conformance!(
+    delete_chars,
+    b"\x1b[1;1Habcdef\x1b[1;2H\x1b[2P",
+    ["adef", "", "", "", "", "", "", ""]
+);
+
+conformance!(
+    insert_lines,
+    b"\x1b[1;1H1\x1b[2;1H2\x1b[3;1H3\x1b[2;1H\x1b[2L",
+    ["1", "", "", "2", "3", "", "", ""]
+);
+
+conformance!(
+    delete_lines,
+    b"\x1b[1;1H1\x1b[2;1H2\x1b[3;1H3\x1b[4;1H4\x1b[2;1H\x1b[2M",
+    ["1", "4", "", "", "", "", "", ""]
+);
+
+conformance!(
+    insert_lines_in_scroll_region,
+    b"\x1b[1;1H1\x1b[2;1H2\x1b[3;1H3\x1b[4;1H4\x1b[5;1H5\
+      \x1b[2;4r\x1b[3;1H\x1b[L",
+    ["1", "2", "", "3", "5", "", "", ""]
+);
+
+conformance!(
+    #[ignore] // insert mode(IRM) is recorded but not applied to printing
+    insert_mode,
+    b"\x1b[1;1Habcdef\x1b[1;3H\x1b[4hXY",
+    ["abXYcdef", "", "", "", "", "", "", ""]
+);
+
+conformance!(
+    repeat_preceding,
+    b"\x1b[1;1Ha\x1b[3b",
+    ["aaaa", "", "", "", "", "", "", ""]
+);
+
+// vttest 11: 8-bit controls
+conformance!(
+    c1_control_sequence,
+    b"\x9b3;3HA\x9b1;1HB",
+    ["B", "", "  A", "", "", "", "", ""]
+);
+
+conformance!(
+    c1_index_and_next_line,
+    b"\x9b2;5HA\x85B\x8dC\x84D",
+    ["", " C  A", "B D", "", "", "", "", ""]
+);
+
+// OSC strings aren't always UTF-8(e.g. titles in legacy encodings)
+conformance!(
+    osc_not_utf8,
+    b"\x1b]0;\xc4\xe3\xba\xc3\x07A\x1b]\xff\xfe\x1b\\B",
+    ["AB", "", "", "", "", "", "", ""]
+);
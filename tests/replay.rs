@@ -0,0 +1,55 @@
+//! Replays transcripts in tests/transcripts and compares screens with golden frames.
+//!
+//! To update golden frames after changing the emulator, run
+//! ```CGW_BLESS=1 cargo test --test replay``` and check the diff.
+extern crate curses_game_wrapper as cgw;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::Path;
+
+const LINES: usize = 24;
+const COLUMNS: usize = 80;
+
+#[test]
+fn replay_transcripts() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/transcripts");
+    let bless = env::var_os("CGW_BLESS").is_some();
+    let mut checked = 0;
+    for entry in fs::read_dir(&dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().map_or(true, |e| e != "cgwt") {
+            continue;
+        }
+        let records = cgw::read_transcript(BufReader::new(File::open(&path).unwrap())).unwrap();
+        let screens = cgw::replay_screens(&records, LINES, COLUMNS);
+        let golden_path = path.with_extension("golden");
+        if bless {
+            cgw::write_golden(File::create(&golden_path).unwrap(), &screens).unwrap();
+        }
+        let golden = cgw::read_golden(BufReader::new(File::open(&golden_path).unwrap())).unwrap();
+        assert_eq!(
+            golden.len(),
+            screens.len(),
+            "{:?}: number of frames differs",
+            path
+        );
+        for (i, (g, s)) in golden.iter().zip(screens.iter()).enumerate() {
+            assert_eq!(g.len(), s.len(), "{:?}: frame {} has wrong height", path, i);
+            for (y, (gl, sl)) in g.iter().zip(s.iter()).enumerate() {
+                assert!(
+                    gl == sl,
+                    "{:?}: frame {}, line {} differs\nexpected: {:?}\n     got: {:?}",
+                    path,
+                    i,
+                    y,
+                    String::from_utf8_lossy(gl),
+                    String::from_utf8_lossy(sl)
+                );
+            }
+        }
+        checked += 1;
+    }
+    assert!(checked > 0, "no transcripts found in {:?}", dir);
+}